@@ -0,0 +1,386 @@
+//! Computed INFO fields written back onto records.
+//!
+//! An annotation definition is `ID=EXPR`: the expression is evaluated for
+//! every row and its value stored under the new INFO key `ID`. The header
+//! declaration is derived from the expression:
+//!
+//! | Expression                              | Number      | Type        |
+//! |-----------------------------------------|-------------|-------------|
+//! | Arithmetic or a number (`AD[1] / DP`)   | `1`         | Float       |
+//! | Comparison, logic or `exists()`         | `0`         | Flag        |
+//! | A declared INFO field (`AF`, `INFO.AF`) | as declared | as declared |
+//! | Anything else                           | `.`         | String      |
+//!
+//! Expressions using `[alt]` are evaluated once per ALT allele and declared
+//! `Number=A`. Missing values leave the key out, as does a false flag.
+//! Adjust the declaration through [`Annotations::fields_mut`] before writing
+//! the header if the inferred one does not fit.
+//!
+//! ```rust
+//! use vcf_filter::FilterEngine;
+//!
+//! let header = r#"##INFO=<ID=DP,Number=1,Type=Integer,Description="Depth">
+//! ##INFO=<ID=AD,Number=R,Type=Integer,Description="Allele depths">"#;
+//! let engine = FilterEngine::new(header).unwrap();
+//! let annotations = engine.annotations(&["VAF=AD[1] / DP", "DEEP=DP >= 30"]).unwrap();
+//!
+//! assert_eq!(
+//!     annotations.header_lines(),
+//!     [
+//!         r#"##INFO=<ID=VAF,Number=1,Type=Float,Description="Computed by vcf-filter: AD[1] / DP">"#,
+//!         r#"##INFO=<ID=DEEP,Number=0,Type=Flag,Description="Computed by vcf-filter: DP >= 30">"#,
+//!     ]
+//! );
+//!
+//! let row = "chr1\t100\t.\tA\tG\t50\tPASS\tDP=40;AD=30,10";
+//! assert_eq!(
+//!     engine.annotate_line(&annotations, row).unwrap(),
+//!     "chr1\t100\t.\tA\tG\t50\tPASS\tDP=40;AD=30,10;VAF=0.25;DEEP"
+//! );
+//! ```
+
+use crate::FilterEngine;
+use crate::error::{Result, VcfFilterError};
+use crate::eval::uses_alt_access;
+use crate::filter::{AccessPart, BinaryOp, Expr};
+use crate::header::{InfoMap, InfoNumber, InfoType, escape_description};
+use crate::row::{VcfRow, encode_info_value, set_info};
+use crate::value::Value;
+
+/// One INFO field computed from an expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComputedField {
+    /// The new INFO key.
+    pub id: String,
+    /// The expression computing the value.
+    pub expr: Expr,
+    /// The header `Number=` attribute.
+    pub number: InfoNumber,
+    /// The header `Type=` attribute.
+    pub field_type: InfoType,
+    /// The header description, unescaped.
+    pub description: String,
+}
+
+impl ComputedField {
+    /// The `##INFO` header line declaring this field.
+    pub fn header_line(&self) -> String {
+        format!(
+            "##INFO=<ID={},Number={},Type={},Description=\"{}\">",
+            self.id,
+            self.number,
+            self.field_type,
+            escape_description(&self.description)
+        )
+    }
+
+    /// The value as written in an INFO column, `Some("")` for a set flag
+    /// and `None` when the key is left out.
+    fn encode(&self, value: &Value) -> Option<String> {
+        if self.field_type == InfoType::Flag {
+            return is_set(value).then(String::new);
+        }
+        match value {
+            Value::Missing => None,
+            Value::Array(values) if values.iter().all(|v| *v == Value::Missing) => None,
+            value => Some(encode_info_value(value)),
+        }
+    }
+}
+
+/// Computed INFO fields, created with [`FilterEngine::annotations`].
+#[derive(Debug, Clone)]
+pub struct Annotations {
+    fields: Vec<ComputedField>,
+}
+
+impl Annotations {
+    /// The computed fields, in definition order.
+    pub fn fields(&self) -> &[ComputedField] {
+        &self.fields
+    }
+
+    /// The computed fields, to override inferred types or descriptions.
+    pub fn fields_mut(&mut self) -> &mut [ComputedField] {
+        &mut self.fields
+    }
+
+    /// The `##INFO` lines declaring the computed fields, to insert before `#CHROM`.
+    pub fn header_lines(&self) -> Vec<String> {
+        self.fields.iter().map(ComputedField::header_line).collect()
+    }
+}
+
+impl FilterEngine {
+    /// Parse `ID=EXPR` definitions of computed INFO fields.
+    ///
+    /// Fails with `ConfigError` on a definition without `=`, an ID that is
+    /// not a valid INFO key, an ID defined twice or already declared in the
+    /// header, and with `FilterParseError` on an invalid expression. See
+    /// [`annotate`](crate::annotate) for how the header declaration is inferred.
+    pub fn annotations(&self, definitions: &[&str]) -> Result<Annotations> {
+        let mut fields: Vec<ComputedField> = Vec::with_capacity(definitions.len());
+        for definition in definitions {
+            let (id, source) = definition.split_once('=').ok_or_else(|| {
+                VcfFilterError::ConfigError(format!(
+                    "annotation '{}' is not of the form ID=EXPR",
+                    definition
+                ))
+            })?;
+            let (id, source) = (id.trim(), source.trim());
+            if !is_valid_id(id) {
+                return Err(VcfFilterError::ConfigError(format!(
+                    "'{}' is not a valid INFO key",
+                    id
+                )));
+            }
+            if self.info_map().contains_key(id) || fields.iter().any(|field| field.id == id) {
+                return Err(VcfFilterError::ConfigError(format!(
+                    "INFO field '{}' is already defined",
+                    id
+                )));
+            }
+
+            let expr = self.parse_filter(source)?;
+            let (number, field_type) = infer_declaration(&expr, self.info_map());
+            fields.push(ComputedField {
+                id: id.to_string(),
+                expr,
+                number,
+                field_type,
+                description: format!("Computed by vcf-filter: {}", source),
+            });
+        }
+        Ok(Annotations { fields })
+    }
+
+    /// Evaluate the computed fields of `annotations` and set them on `row`.
+    ///
+    /// Missing values and false flags remove the key instead. The fields are
+    /// written by [`VcfRow::to_vcf_line`].
+    pub fn annotate(&self, annotations: &Annotations, row: &mut VcfRow) -> Result<()> {
+        let values = self.computed_values(annotations, row)?;
+        for (field, value) in annotations.fields.iter().zip(values) {
+            let value = match field.field_type {
+                InfoType::Flag => is_set(&value).then_some(Value::Bool(true)),
+                _ => field.encode(&value).map(|_| value),
+            };
+            match value {
+                Some(value) => row.set_info(&field.id, value),
+                None => {
+                    row.remove_info(&field.id);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Add the computed fields of `annotations` to a VCF data line's INFO column.
+    ///
+    /// Existing entries with the same keys are replaced. String values are
+    /// percent-encoded.
+    pub fn annotate_line(&self, annotations: &Annotations, line: &str) -> Result<String> {
+        let row = self.parse_row(line)?;
+        self.annotate_parsed_line(annotations, line, &row)
+    }
+
+    /// Like [`annotate_line`](Self::annotate_line), evaluating against an
+    /// already parsed `row` of `line`.
+    pub fn annotate_parsed_line(
+        &self,
+        annotations: &Annotations,
+        line: &str,
+        row: &VcfRow,
+    ) -> Result<String> {
+        let values = self.computed_values(annotations, row)?;
+        let mut line = line.to_string();
+        for (field, value) in annotations.fields.iter().zip(values) {
+            line = set_info(&line, &field.id, field.encode(&value).as_deref());
+        }
+        Ok(line)
+    }
+
+    /// Evaluate every computed field against a row.
+    fn computed_values(&self, annotations: &Annotations, row: &VcfRow) -> Result<Vec<Value>> {
+        annotations
+            .fields
+            .iter()
+            .map(|field| self.evaluate_parsed_value(&field.expr, row))
+            .collect()
+    }
+}
+
+/// Whether `id` is a valid INFO key (`^[A-Za-z_][0-9A-Za-z_.]*$`).
+fn is_valid_id(id: &str) -> bool {
+    let mut chars = id.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+/// Infer the header `Number` and `Type` of an expression's value.
+fn infer_declaration(expr: &Expr, info_map: &InfoMap) -> (InfoNumber, InfoType) {
+    let (number, field_type) = match expr {
+        Expr::Number(_)
+        | Expr::Binary(_, BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div, _) => {
+            (InfoNumber::Count(1), InfoType::Float)
+        }
+        Expr::Bool(_) | Expr::Binary(..) | Expr::Unary(..) | Expr::Exists(_) | Expr::All(_) => {
+            (InfoNumber::Flag, InfoType::Flag)
+        }
+        Expr::Let(_, _, body) => infer_declaration(body, info_map),
+        Expr::Var(parts) => match declared_field(parts, info_map) {
+            Some(declaration) => declaration,
+            None => (InfoNumber::Variable, InfoType::String),
+        },
+        _ => (InfoNumber::Variable, InfoType::String),
+    };
+    if field_type != InfoType::Flag && uses_alt_access(expr) {
+        (InfoNumber::PerAltAllele, field_type)
+    } else {
+        (number, field_type)
+    }
+}
+
+/// The declaration of a plain INFO field reference (`AF` or `INFO.AF`).
+fn declared_field(parts: &[AccessPart], info_map: &InfoMap) -> Option<(InfoNumber, InfoType)> {
+    let id = match parts {
+        [AccessPart::Field(id)] => id,
+        [AccessPart::Field(info), AccessPart::Field(id)] if info == "INFO" => id,
+        _ => return None,
+    };
+    info_map
+        .get(id)
+        .map(|field| (field.number.clone(), field.field_type.clone()))
+}
+
+/// Whether a flag value is set; per-allele values are set if any allele is.
+fn is_set(value: &Value) -> bool {
+    match value {
+        Value::Bool(b) => *b,
+        Value::Array(values) => values.iter().any(is_set),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "##INFO=<ID=DP,Number=1,Type=Integer,Description=\"Depth\">\n\
+                          ##INFO=<ID=AF,Number=A,Type=Float,Description=\"Allele frequency\">\n\
+                          ##INFO=<ID=AD,Number=R,Type=Integer,Description=\"Allele depths\">";
+
+    #[test]
+    fn test_inferred_declarations() {
+        let engine = FilterEngine::new(HEADER).unwrap();
+        let annotations = engine
+            .annotations(&[
+                "VAF=AD[1] / DP",
+                "RARE=AF < 0.01",
+                "AF_COPY=INFO.AF",
+                "HIGH_AF=AF[alt] * 2",
+                "LABEL=CHROM",
+            ])
+            .unwrap();
+        let declarations: Vec<(String, String)> = annotations
+            .fields()
+            .iter()
+            .map(|f| (f.number.to_string(), f.field_type.to_string()))
+            .collect();
+        assert_eq!(
+            declarations,
+            [
+                ("1".into(), "Float".into()),
+                ("0".into(), "Flag".into()),
+                ("A".into(), "Float".into()),
+                ("A".into(), "Float".into()),
+                (".".into(), "String".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_annotate_line() {
+        let engine = FilterEngine::new(HEADER).unwrap();
+        let annotations = engine
+            .annotations(&[
+                "VAF=AD[1] / DP",
+                "LOW=DP < 10",
+                r#"NOTE="a;b""#,
+                "ALT2=AF[alt] * 2",
+            ])
+            .unwrap();
+
+        let line = "chr1\t100\t.\tA\tG,T\t50\tq10;s50\tDP=40;AD=30,10,0;AF=0.1,0.2;VAF=9";
+        assert_eq!(
+            engine.annotate_line(&annotations, line).unwrap(),
+            "chr1\t100\t.\tA\tG,T\t50\tq10;s50\tDP=40;AD=30,10,0;AF=0.1,0.2;VAF=0.25;NOTE=a%3Bb;ALT2=0.2,0.4"
+        );
+
+        // Missing values leave the keys out
+        let line = "chr1\t100\t.\tA\tG\t50\tPASS\t.";
+        assert_eq!(
+            engine.annotate_line(&annotations, line).unwrap(),
+            "chr1\t100\t.\tA\tG\t50\tPASS\tNOTE=a%3Bb"
+        );
+    }
+
+    #[test]
+    fn test_annotate_row() {
+        let engine = FilterEngine::new(HEADER).unwrap();
+        let annotations = engine
+            .annotations(&["VAF=AD[1] / DP", "DEEP=DP > 30"])
+            .unwrap();
+
+        let mut row = engine
+            .parse_row("chr1\t100\t.\tA\tG\t50\tPASS\tDP=40;AD=30,10")
+            .unwrap();
+        engine.annotate(&annotations, &mut row).unwrap();
+        assert_eq!(row.info.get("VAF"), Some(&Value::Number(0.25)));
+        assert_eq!(row.info.get("DEEP"), Some(&Value::Bool(true)));
+        assert_eq!(
+            row.to_vcf_line(),
+            "chr1\t100\t.\tA\tG\t50\tPASS\tDP=40;AD=30,10;VAF=0.25;DEEP"
+        );
+        assert!(
+            engine
+                .evaluate_parsed(&engine.parse_filter("VAF > 0.2").unwrap(), &row)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_invalid_definitions() {
+        let engine = FilterEngine::new(HEADER).unwrap();
+        for definition in ["VAF", "1X=DP", "DP=DP * 2"] {
+            assert!(matches!(
+                engine.annotations(&[definition]),
+                Err(VcfFilterError::ConfigError(_))
+            ));
+        }
+        assert!(matches!(
+            engine.annotations(&["X=DP", "X=AF"]),
+            Err(VcfFilterError::ConfigError(_))
+        ));
+        assert!(matches!(
+            engine.annotations(&["X=DP >"]),
+            Err(VcfFilterError::FilterParseError(_))
+        ));
+    }
+
+    #[test]
+    fn test_header_line_escapes_description() {
+        let engine = FilterEngine::new(HEADER).unwrap();
+        let annotations = engine
+            .annotations(&[r#"PASSING=FILTER == "PASS""#])
+            .unwrap();
+        assert_eq!(
+            annotations.header_lines(),
+            [
+                r#"##INFO=<ID=PASSING,Number=0,Type=Flag,Description="Computed by vcf-filter: FILTER == \"PASS\"">"#
+            ]
+        );
+    }
+}
@@ -0,0 +1,394 @@
+//! Batch filtering driven by a job manifest.
+//!
+//! A manifest is a tab-separated file with one job per line:
+//!
+//! ```text
+//! # input            filter                        output
+//! patient1.vcf       QUAL >= 30 && DP >= 10        patient1.pass.vcf
+//! patient1.vcf       @high_impact                  patient1.high.vcf
+//! patient2.vcf       FILTER == "PASS"              patient2.pass.vcf
+//! ```
+//!
+//! A filter may refer to presets in a [`FilterConfig`] as `@name`, either as
+//! the whole filter or inside a larger expression (`@high_impact && DP > 10`).
+//! Jobs sharing an input are grouped so the input is read and its header
+//! parsed once, with each row parsed once and evaluated against every
+//! filter in the group. Groups run in parallel on a fixed number of threads.
+//!
+//! Inputs may be gzip- or bgzip-compressed, and outputs named `*.gz` are
+//! written as bgzip; see [`compression`](crate::compression).
+
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::FilterEngine;
+use crate::compiled::CompiledFilter;
+use crate::compression::{create_output, io_error, open_input, resolve_path};
+use crate::config::FilterConfig;
+use crate::error::{Result, VcfFilterError};
+use crate::filter::{Expr, parse_error, parse_filter};
+use crate::lists::ListLibrary;
+use crate::presets::PresetLibrary;
+
+/// A single filter job from a manifest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchJob {
+    /// Path of the VCF to read.
+    pub input: PathBuf,
+    /// Filter expression, or `@name` to use a preset.
+    pub filter: String,
+    /// Path the passing records are written to.
+    pub output: PathBuf,
+}
+
+/// Outcome of a single job.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobSummary {
+    /// The job that was run.
+    pub job: BatchJob,
+    /// Number of data rows that passed the filter.
+    pub passed: u64,
+    /// Number of data rows read from the input.
+    pub total: u64,
+}
+
+/// Parse a tab-separated job manifest.
+///
+/// Blank lines and lines starting with `#` are ignored. Every other line
+/// must have exactly three columns: input, filter, output.
+pub fn parse_manifest(text: &str) -> Result<Vec<BatchJob>> {
+    let mut jobs = Vec::new();
+
+    for (line_no, line) in text.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let columns: Vec<&str> = line.split('\t').map(str::trim).collect();
+        let [input, filter, output] = columns.as_slice() else {
+            return Err(VcfFilterError::BatchError(format!(
+                "line {}: expected 3 tab-separated columns (input, filter, output), found {}",
+                line_no + 1,
+                columns.len()
+            )));
+        };
+        if input.is_empty() || filter.is_empty() || output.is_empty() {
+            return Err(VcfFilterError::BatchError(format!(
+                "line {}: empty column",
+                line_no + 1
+            )));
+        }
+
+        jobs.push(BatchJob {
+            input: PathBuf::from(input),
+            filter: filter.to_string(),
+            output: PathBuf::from(output),
+        });
+    }
+
+    Ok(jobs)
+}
+
+/// Resolve a job's filter text to an expression.
+fn compile_job_filter(filter: &str, config: Option<&FilterConfig>) -> Result<Expr> {
+    if let Some(name) = filter.strip_prefix('@')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        let config = config.ok_or_else(|| {
+            VcfFilterError::BatchError(format!("preset @{} used without a config", name))
+        })?;
+        let expr = config
+            .preset(name)
+            .ok_or_else(|| VcfFilterError::ConfigError(format!("unknown preset: {}", name)))?;
+        return ListLibrary::new().expand(&expr);
+    }
+
+    let expr = match config {
+        Some(config) => config.compile(filter)?,
+        // Without a config, any `@name` reference is unknown
+        None => PresetLibrary::new().expand(&parse_filter(filter).map_err(parse_error)?)?,
+    };
+    ListLibrary::new().expand(&expr)
+}
+
+/// Filter one VCF stream against several filters at once.
+///
+/// The header is copied to every writer. Each data row is parsed once and
+/// written to the writers whose filter it passes; rows every filter rejects
+/// at byte level are not parsed at all. Returns the number of
+/// passing rows per filter and the total number of data rows.
+pub fn filter_stream<R: BufRead, W: Write>(
+    mut reader: R,
+    filters: &[Expr],
+    writers: &mut [W],
+) -> Result<(Vec<u64>, u64)> {
+    let (engine, header_lines) = FilterEngine::from_reader(&mut reader)?;
+    for writer in writers.iter_mut() {
+        for line in &header_lines {
+            writeln!(writer, "{}", line).map_err(io_error("<output>"))?;
+        }
+    }
+    let compiled: Vec<CompiledFilter> = filters
+        .iter()
+        .map(|expr| engine.compile_expr(expr.clone()))
+        .collect();
+    let mut passed = vec![0u64; filters.len()];
+    let mut total = 0u64;
+
+    for line in reader.lines() {
+        let line = line.map_err(io_error("<input>"))?;
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        total += 1;
+        let candidates: Vec<usize> = (0..compiled.len())
+            .filter(|&i| engine.quick_reject(&compiled[i], line.as_bytes()) != Some(false))
+            .collect();
+        if candidates.is_empty() {
+            continue;
+        }
+        let row = engine.parse_row(&line)?;
+        for i in candidates {
+            if engine.evaluate_compiled(&compiled[i], &row)? {
+                passed[i] += 1;
+                writeln!(writers[i], "{}", line).map_err(io_error("<output>"))?;
+            }
+        }
+    }
+
+    for writer in writers.iter_mut() {
+        writer.flush().map_err(io_error("<output>"))?;
+    }
+
+    Ok((passed, total))
+}
+
+/// Run every job in a manifest.
+///
+/// Filters are compiled before any file is opened, so a typo in one job
+/// fails the whole batch up front. So does a job whose output would
+/// overwrite an input, or an output shared by two jobs. Jobs are grouped by input and the groups
+/// are processed on up to `threads` worker threads. Summaries are returned
+/// in manifest order.
+pub fn run(
+    jobs: &[BatchJob],
+    config: Option<&FilterConfig>,
+    threads: usize,
+) -> Result<Vec<JobSummary>> {
+    let filters = jobs
+        .iter()
+        .map(|job| compile_job_filter(&job.filter, config))
+        .collect::<Result<Vec<_>>>()?;
+
+    let inputs: Vec<PathBuf> = jobs.iter().map(|job| resolve_path(&job.input)).collect();
+    let mut outputs: Vec<PathBuf> = Vec::with_capacity(jobs.len());
+    for job in jobs {
+        let output = resolve_path(&job.output);
+        if inputs.contains(&output) {
+            return Err(VcfFilterError::BatchError(format!(
+                "{}: output would overwrite an input",
+                job.output.display()
+            )));
+        }
+        if outputs.contains(&output) {
+            return Err(VcfFilterError::BatchError(format!(
+                "{}: output is written by more than one job",
+                job.output.display()
+            )));
+        }
+        outputs.push(output);
+    }
+
+    // Group job indices by input, keeping first-seen order
+    let mut groups: Vec<(&Path, Vec<usize>)> = Vec::new();
+    for (i, job) in jobs.iter().enumerate() {
+        match groups.iter_mut().find(|(input, _)| *input == job.input) {
+            Some((_, members)) => members.push(i),
+            None => groups.push((&job.input, vec![i])),
+        }
+    }
+
+    let next_group = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<(u64, u64)>>> = Mutex::new(vec![None; jobs.len()]);
+    let first_error: Mutex<Option<VcfFilterError>> = Mutex::new(None);
+
+    std::thread::scope(|scope| {
+        for _ in 0..threads.clamp(1, groups.len().max(1)) {
+            scope.spawn(|| {
+                loop {
+                    let g = next_group.fetch_add(1, Ordering::Relaxed);
+                    let Some((input, members)) = groups.get(g) else {
+                        break;
+                    };
+                    let group_filters: Vec<Expr> =
+                        members.iter().map(|&i| filters[i].clone()).collect();
+
+                    match run_group(input, &group_filters, members, jobs) {
+                        Ok((passed, total)) => {
+                            let mut results = results.lock().unwrap();
+                            for (&i, passed) in members.iter().zip(passed) {
+                                results[i] = Some((passed, total));
+                            }
+                        }
+                        Err(e) => {
+                            first_error.lock().unwrap().get_or_insert(e);
+                            break;
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    if let Some(e) = first_error.into_inner().unwrap() {
+        return Err(e);
+    }
+
+    Ok(jobs
+        .iter()
+        .zip(results.into_inner().unwrap())
+        .map(|(job, result)| {
+            let (passed, total) = result.unwrap_or_default();
+            JobSummary {
+                job: job.clone(),
+                passed,
+                total,
+            }
+        })
+        .collect())
+}
+
+/// Run all jobs reading the same input.
+fn run_group(
+    input: &Path,
+    filters: &[Expr],
+    members: &[usize],
+    jobs: &[BatchJob],
+) -> Result<(Vec<u64>, u64)> {
+    let reader = open_input(input)?;
+    let mut writers = members
+        .iter()
+        .map(|&i| create_output(&jobs[i].output))
+        .collect::<Result<Vec<_>>>()?;
+
+    filter_stream(reader, filters, &mut writers)
+        .map_err(|e| VcfFilterError::BatchError(format!("{}: {}", input.display(), e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VCF: &str = "##fileformat=VCFv4.2
+##INFO=<ID=DP,Number=1,Type=Integer,Description=\"Depth\">
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO
+chr1\t100\t.\tA\tG\t50\tPASS\tDP=30
+chr1\t200\t.\tC\tT\t10\tLowQual\tDP=5
+";
+
+    #[test]
+    fn test_parse_manifest() {
+        let jobs = parse_manifest(
+            "# input\tfilter\toutput\n\na.vcf\tQUAL > 30\ta.out.vcf\nb.vcf\t@strict\tb.out.vcf\n",
+        )
+        .unwrap();
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(jobs[0].input, PathBuf::from("a.vcf"));
+        assert_eq!(jobs[0].filter, "QUAL > 30");
+        assert_eq!(jobs[1].output, PathBuf::from("b.out.vcf"));
+
+        assert!(parse_manifest("a.vcf\tQUAL > 30\n").is_err());
+    }
+
+    #[test]
+    fn test_filter_stream_multiple_filters() {
+        let filters = vec![
+            parse_filter("QUAL > 30").unwrap(),
+            parse_filter("DP > 0").unwrap(),
+        ];
+        let mut writers = vec![Vec::new(), Vec::new()];
+
+        let (passed, total) = filter_stream(VCF.as_bytes(), &filters, &mut writers).unwrap();
+        assert_eq!(passed, vec![1, 2]);
+        assert_eq!(total, 2);
+
+        let first = String::from_utf8(writers.remove(0)).unwrap();
+        assert!(first.starts_with("##fileformat"));
+        assert!(first.contains("chr1\t100"));
+        assert!(!first.contains("chr1\t200"));
+    }
+
+    #[test]
+    fn test_preset_requires_config() {
+        assert!(compile_job_filter("@strict", None).is_err());
+
+        let config = FilterConfig::parse("[presets]\nstrict = QUAL >= 50\n").unwrap();
+        assert!(compile_job_filter("@strict", Some(&config)).is_ok());
+        assert!(compile_job_filter("@missing", Some(&config)).is_err());
+        assert!(compile_job_filter("@strict && DP > 1", Some(&config)).is_ok());
+        assert!(compile_job_filter("@strict && DP > 1", None).is_err());
+    }
+
+    #[test]
+    fn test_run_groups_jobs_by_input() {
+        let dir = std::env::temp_dir().join(format!("vcf-filter-batch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("in.vcf");
+        std::fs::write(&input, VCF).unwrap();
+
+        let jobs = vec![
+            BatchJob {
+                input: input.clone(),
+                filter: "QUAL > 30".to_string(),
+                output: dir.join("qual.vcf"),
+            },
+            BatchJob {
+                input: input.clone(),
+                filter: r#"FILTER == "LowQual""#.to_string(),
+                output: dir.join("low.vcf"),
+            },
+        ];
+
+        let summaries = run(&jobs, None, 2).unwrap();
+        assert_eq!(summaries[0].passed, 1);
+        assert_eq!(summaries[1].passed, 1);
+        assert_eq!(summaries[1].total, 2);
+
+        let low = std::fs::read_to_string(dir.join("low.vcf")).unwrap();
+        assert!(low.contains("LowQual"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_run_rejects_clashing_outputs() {
+        let dir = std::env::temp_dir().join(format!("vcf-filter-clash-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("in.vcf");
+        std::fs::write(&input, VCF).unwrap();
+        let job = |output: PathBuf| BatchJob {
+            input: input.clone(),
+            filter: "QUAL > 30".to_string(),
+            output,
+        };
+
+        // Output is the input, spelled differently
+        let error = run(&[job(dir.join(".").join("in.vcf"))], None, 1).unwrap_err();
+        assert!(error.to_string().contains("overwrite an input"));
+        assert_eq!(std::fs::read_to_string(&input).unwrap(), VCF);
+
+        let error = run(
+            &[job(dir.join("out.vcf")), job(dir.join("./out.vcf"))],
+            None,
+            1,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("more than one job"));
+        assert!(!dir.join("out.vcf").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,345 @@
+//! Fluent construction of filter expressions.
+//!
+//! Applications that build filters from user input should construct the
+//! [`Expr`] directly rather than formatting a filter string: values are
+//! never parsed, so a gene name such as `BRCA1" || QUAL > 0 || "` stays a
+//! string literal instead of changing the filter.
+//!
+//! ```rust
+//! use vcf_filter::{Expr, FilterEngine};
+//!
+//! let gene = "BRCA1";
+//! let expr = Expr::field("DP")
+//!     .gt(30)
+//!     .and(Expr::field("FILTER").eq("PASS"))
+//!     .and(Expr::field("ANN").wildcard().subfield("Gene_Name").eq(gene));
+//! assert_eq!(
+//!     expr.to_string(),
+//!     r#"DP > 30 && FILTER == "PASS" && ANN[*].Gene_Name == "BRCA1""#
+//! );
+//!
+//! let engine = FilterEngine::new("").unwrap();
+//! let row = engine.parse_row("chr1\t100\t.\tA\tG\t50\tPASS\tDP=40").unwrap();
+//! assert!(!engine.evaluate_parsed(&expr, &row).unwrap());
+//! ```
+//!
+//! Path methods ([`Expr::at`], [`Expr::wildcard`], [`Expr::subfield`], ...)
+//! extend a field reference and panic on any other expression.
+
+use std::ops::{Add, Div, Mul, Not, Sub};
+
+use crate::filter::{AccessPart, BinaryOp, Expr, UnaryOp};
+
+impl Expr {
+    /// Reference a field by name (`DP`, `QUAL`, `ANN`).
+    pub fn field(name: &str) -> Self {
+        Expr::var(name)
+    }
+
+    /// Reference an INFO field explicitly (`INFO.DP`).
+    pub fn info(name: &str) -> Self {
+        Expr::field("INFO").subfield(name)
+    }
+
+    /// Reference a FORMAT field explicitly (`FORMAT.DP`).
+    pub fn format(name: &str) -> Self {
+        Expr::field("FORMAT").subfield(name)
+    }
+
+    /// A list literal (`["Pathogenic", "Likely_pathogenic"]`).
+    pub fn list<T: Into<Expr>>(items: impl IntoIterator<Item = T>) -> Self {
+        Expr::List(items.into_iter().map(Into::into).collect())
+    }
+
+    /// A function call (`default(AF, 0)`).
+    pub fn call<T: Into<Expr>>(name: &str, args: impl IntoIterator<Item = T>) -> Self {
+        Expr::Call(name.to_string(), args.into_iter().map(Into::into).collect())
+    }
+
+    /// A reference to a named filter (`@rare`).
+    pub fn preset(name: &str) -> Self {
+        Expr::Preset(name.to_string())
+    }
+
+    /// Bind `name` to `value` within `body` (`let name = value in body`).
+    pub fn let_in(name: &str, value: impl Into<Expr>, body: impl Into<Expr>) -> Self {
+        Expr::Let(
+            name.to_string(),
+            Box::new(value.into()),
+            Box::new(body.into()),
+        )
+    }
+
+    /// Index into an array field (`ANN[0]`).
+    pub fn at(self, index: usize) -> Self {
+        self.push_part(AccessPart::Index(index))
+    }
+
+    /// Match any element of an array field (`ANN[*]`).
+    pub fn wildcard(self) -> Self {
+        self.push_part(AccessPart::Wildcard)
+    }
+
+    /// Require every element of an array field to match (`ANN[all]`).
+    pub fn every(self) -> Self {
+        self.push_part(AccessPart::All)
+    }
+
+    /// Select the value of the ALT allele being evaluated (`AF[alt]`).
+    pub fn alt(self) -> Self {
+        self.push_part(AccessPart::Alt)
+    }
+
+    /// Keep the annotations for which `predicate` holds (`ANN[?(predicate)]`).
+    pub fn matching(self, predicate: Expr) -> Self {
+        self.push_part(AccessPart::Predicate(Box::new(predicate)))
+    }
+
+    /// Select a sample by name (`SAMPLE["NA12878"]`, `FORMAT.DP["tumor"]`).
+    pub fn sample(self, name: &str) -> Self {
+        self.push_part(AccessPart::Sample(name.to_string()))
+    }
+
+    /// Access a subfield or namespaced field (`.Gene_Name`).
+    pub fn subfield(self, name: &str) -> Self {
+        self.push_part(AccessPart::Field(name.to_string()))
+    }
+
+    /// Test whether a field is present (`exists(field)`).
+    pub fn exists(self) -> Self {
+        match self {
+            Expr::Var(parts) => Expr::Exists(parts),
+            other => panic!("exists() needs a field reference, got `{}`", other),
+        }
+    }
+
+    /// Require wildcard comparisons to hold for every element (`all(expr)`).
+    pub fn for_all(self) -> Self {
+        Expr::All(Box::new(self))
+    }
+
+    /// `self == value`
+    pub fn eq(self, value: impl Into<Expr>) -> Self {
+        self.binary(BinaryOp::Eq, value)
+    }
+
+    /// `self != value`
+    pub fn ne(self, value: impl Into<Expr>) -> Self {
+        self.binary(BinaryOp::NotEq, value)
+    }
+
+    /// `self < value`
+    pub fn lt(self, value: impl Into<Expr>) -> Self {
+        self.binary(BinaryOp::Lt, value)
+    }
+
+    /// `self <= value`
+    pub fn le(self, value: impl Into<Expr>) -> Self {
+        self.binary(BinaryOp::LtEq, value)
+    }
+
+    /// `self > value`
+    pub fn gt(self, value: impl Into<Expr>) -> Self {
+        self.binary(BinaryOp::Gt, value)
+    }
+
+    /// `self >= value`
+    pub fn ge(self, value: impl Into<Expr>) -> Self {
+        self.binary(BinaryOp::GtEq, value)
+    }
+
+    /// `self contains value`
+    pub fn contains(self, value: impl Into<Expr>) -> Self {
+        self.binary(BinaryOp::Contains, value)
+    }
+
+    /// `self in values`
+    pub fn is_in(self, values: impl Into<Expr>) -> Self {
+        self.binary(BinaryOp::In, values)
+    }
+
+    /// `self severity_at_least term`
+    pub fn severity_at_least(self, term: &str) -> Self {
+        self.binary(BinaryOp::SeverityAtLeast, term)
+    }
+
+    /// `self && other`
+    pub fn and(self, other: impl Into<Expr>) -> Self {
+        self.binary(BinaryOp::And, other)
+    }
+
+    /// `self || other`
+    pub fn or(self, other: impl Into<Expr>) -> Self {
+        self.binary(BinaryOp::Or, other)
+    }
+
+    fn binary(self, op: BinaryOp, right: impl Into<Expr>) -> Self {
+        Expr::Binary(Box::new(self), op, Box::new(right.into()))
+    }
+
+    fn push_part(self, part: AccessPart) -> Self {
+        match self {
+            Expr::Var(mut parts) => {
+                parts.push(part);
+                Expr::Var(parts)
+            }
+            other => panic!("field access needs a field reference, got `{}`", other),
+        }
+    }
+}
+
+impl Not for Expr {
+    type Output = Expr;
+
+    /// `!self`
+    fn not(self) -> Expr {
+        Expr::Unary(UnaryOp::Not, Box::new(self))
+    }
+}
+
+macro_rules! arithmetic_ops {
+    ($($trait:ident :: $method:ident => $op:ident),*) => {$(
+        impl<T: Into<Expr>> $trait<T> for Expr {
+            type Output = Expr;
+
+            fn $method(self, right: T) -> Expr {
+                self.binary(BinaryOp::$op, right)
+            }
+        }
+    )*};
+}
+
+arithmetic_ops!(Add::add => Add, Sub::sub => Sub, Mul::mul => Mul, Div::div => Div);
+
+macro_rules! number_literals {
+    ($($ty:ty),*) => {$(
+        impl From<$ty> for Expr {
+            fn from(n: $ty) -> Self {
+                Expr::Number(n as f64)
+            }
+        }
+    )*};
+}
+
+number_literals!(f64, f32, i32, i64, u32, u64, usize);
+
+impl From<&str> for Expr {
+    fn from(s: &str) -> Self {
+        Expr::String(s.to_string())
+    }
+}
+
+impl From<String> for Expr {
+    fn from(s: String) -> Self {
+        Expr::String(s)
+    }
+}
+
+impl From<bool> for Expr {
+    fn from(b: bool) -> Self {
+        Expr::Bool(b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::parse_filter;
+
+    #[test]
+    fn test_builder_matches_parser() {
+        let cases = [
+            (
+                Expr::field("DP")
+                    .gt(30)
+                    .and(Expr::field("FILTER").eq("PASS")),
+                r#"DP > 30 && FILTER == "PASS""#,
+            ),
+            (
+                Expr::field("ANN")
+                    .matching(
+                        Expr::field("Annotation_Impact").is_in(Expr::list(["HIGH", "MODERATE"])),
+                    )
+                    .subfield("Gene_Name")
+                    .eq("BRCA1"),
+                r#"ANN[?(Annotation_Impact in ["HIGH", "MODERATE"])].Gene_Name == "BRCA1""#,
+            ),
+            (
+                !Expr::info("DB").or(Expr::format("DP").at(0).le(10.5)),
+                "!(INFO.DB || FORMAT.DP[0] <= 10.5)",
+            ),
+            (
+                Expr::field("LOF")
+                    .every()
+                    .subfield("Perc")
+                    .ge(0.5)
+                    .for_all(),
+                "all(LOF[all].Perc >= 0.5)",
+            ),
+            (
+                Expr::let_in(
+                    "ab",
+                    Expr::field("AD").at(1) / Expr::field("DP"),
+                    Expr::field("ab").gt(0.2).and(Expr::field("ab").lt(0.8)),
+                ),
+                "let ab = AD[1] / DP in ab > 0.2 && ab < 0.8",
+            ),
+            (
+                Expr::preset("rare")
+                    .and(Expr::call("default", [Expr::field("AF"), Expr::from(0)]).ne(1)),
+                "@rare && default(AF, 0) != 1",
+            ),
+            (
+                Expr::field("CLNSIG")
+                    .exists()
+                    .and(Expr::field("CLNDN").contains("cancer")),
+                r#"exists(CLNSIG) && CLNDN contains "cancer""#,
+            ),
+            (
+                Expr::field("SAMPLE")
+                    .sample("NA12878")
+                    .subfield("GT")
+                    .eq("0/1")
+                    .and(Expr::format("DP").sample("tumor").gt(50)),
+                r#"SAMPLE["NA12878"].GT == "0/1" && FORMAT.DP["tumor"] > 50"#,
+            ),
+            (
+                Expr::field("AF")
+                    .alt()
+                    .gt(0.01)
+                    .and(Expr::field("AC").alt().gt(2)),
+                "AF[alt] > 0.01 && AC[alt] > 2",
+            ),
+            (
+                Expr::field("ANN")
+                    .wildcard()
+                    .subfield("Annotation")
+                    .severity_at_least("missense_variant"),
+                r#"ANN[*].Annotation severity_at_least "missense_variant""#,
+            ),
+        ];
+        for (built, filter) in cases {
+            assert_eq!(built, parse_filter(filter).unwrap(), "{}", filter);
+        }
+    }
+
+    #[test]
+    fn test_values_are_never_parsed() {
+        let hostile = r#"BRCA1" || QUAL > 0 || ""#;
+        let expr = Expr::field("GENE").eq(hostile);
+        assert_eq!(
+            expr,
+            Expr::Binary(
+                Box::new(Expr::var("GENE")),
+                BinaryOp::Eq,
+                Box::new(Expr::String(hostile.to_string()))
+            )
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "field reference")]
+    fn test_path_on_non_field_panics() {
+        let _ = Expr::from(1).at(0);
+    }
+}
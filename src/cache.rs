@@ -0,0 +1,171 @@
+//! Small LRU cache of parsed filters for the string-based evaluation API.
+//!
+//! [`FilterEngine::evaluate`](crate::FilterEngine::evaluate) and friends take
+//! the filter as text on every call. Parsing dominates the cost of a single
+//! evaluation, so the most recently used filters are kept parsed. Entries
+//! depend on the engine's presets, lists, limits and configuration, and the
+//! cache is cleared whenever those change, including when a watched
+//! configuration is reloaded. A cloned engine starts with an empty cache.
+
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use crate::config::FilterConfig;
+use crate::error::Result;
+use crate::filter::Expr;
+
+/// Number of distinct filters kept parsed.
+const CAPACITY: usize = 32;
+
+/// Parsed filters keyed by their text, most recently used first.
+#[derive(Default)]
+pub(crate) struct ExprCache {
+    entries: Mutex<Entries>,
+}
+
+/// Cached filters and the configuration snapshot they were expanded with.
+#[derive(Default)]
+struct Entries {
+    config: Option<Arc<FilterConfig>>,
+    exprs: Vec<(String, Arc<Expr>)>,
+}
+
+impl Entries {
+    /// Whether the entries were expanded with `config`.
+    fn expanded_with(&self, config: &Arc<FilterConfig>) -> bool {
+        self.config
+            .as_ref()
+            .is_some_and(|current| Arc::ptr_eq(current, config))
+    }
+}
+
+impl ExprCache {
+    /// Get the parsed filter for `filter` expanded with `config`, parsing and
+    /// caching it on a miss.
+    ///
+    /// A different `config` snapshot than the cached filters were expanded
+    /// with (after a reload) empties the cache. Parse errors are returned and
+    /// not cached.
+    pub(crate) fn get_or_parse(
+        &self,
+        filter: &str,
+        config: &Arc<FilterConfig>,
+        parse: impl FnOnce(&str) -> Result<Expr>,
+    ) -> Result<Arc<Expr>> {
+        {
+            let mut entries = self.lock();
+            if !entries.expanded_with(config) {
+                entries.config = Some(Arc::clone(config));
+                entries.exprs.clear();
+            }
+            if let Some(i) = entries.exprs.iter().position(|(text, _)| text == filter) {
+                let entry = entries.exprs.remove(i);
+                let expr = Arc::clone(&entry.1);
+                entries.exprs.insert(0, entry);
+                return Ok(expr);
+            }
+        }
+
+        // Parse without holding the lock, so other filters stay available
+        let expr = Arc::new(parse(filter)?);
+        let mut entries = self.lock();
+        // Skip caching if a reload happened while parsing
+        if entries.expanded_with(config) && !entries.exprs.iter().any(|(text, _)| text == filter) {
+            entries.exprs.truncate(CAPACITY - 1);
+            entries
+                .exprs
+                .insert(0, (filter.to_string(), Arc::clone(&expr)));
+        }
+        Ok(expr)
+    }
+
+    /// Forget every cached filter.
+    pub(crate) fn clear(&mut self) {
+        self.entries
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .exprs
+            .clear();
+    }
+
+    /// Lock the entries; they stay consistent even if a holder panicked.
+    fn lock(&self) -> MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Clone for ExprCache {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl fmt::Debug for ExprCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExprCache")
+            .field("len", &self.lock().exprs.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::parse_filter;
+
+    #[test]
+    fn test_least_recently_used_eviction() {
+        let mut cache = ExprCache::default();
+        let config = Arc::new(FilterConfig::default());
+        let parses = std::cell::Cell::new(0);
+        let get = |cache: &ExprCache, filter: &str| {
+            cache
+                .get_or_parse(filter, &config, |text| {
+                    parses.set(parses.get() + 1);
+                    Ok(parse_filter(text).unwrap())
+                })
+                .unwrap()
+        };
+
+        let first = get(&cache, "DP > 0");
+        assert!(Arc::ptr_eq(&first, &get(&cache, "DP > 0")));
+        assert_eq!(parses.get(), 1);
+
+        // Fill the cache, touching the first filter so it survives
+        for i in 1..CAPACITY {
+            get(&cache, &format!("DP > {}", i));
+        }
+        get(&cache, "DP > 0");
+        get(&cache, "QUAL > 0");
+        assert_eq!(parses.get(), CAPACITY + 1);
+        get(&cache, "DP > 0");
+        assert_eq!(parses.get(), CAPACITY + 1);
+        get(&cache, "DP > 1");
+        assert_eq!(parses.get(), CAPACITY + 2);
+
+        cache.clear();
+        get(&cache, "DP > 0");
+        assert_eq!(parses.get(), CAPACITY + 3);
+    }
+
+    #[test]
+    fn test_reloaded_config_empties_cache() {
+        let cache = ExprCache::default();
+        let parse = |text: &str| Ok(parse_filter(text).unwrap());
+        let config = Arc::new(FilterConfig::default());
+        let first = cache.get_or_parse("DP > 0", &config, parse).unwrap();
+        assert!(Arc::ptr_eq(
+            &first,
+            &cache.get_or_parse("DP > 0", &config, parse).unwrap()
+        ));
+
+        // An equal but newly loaded snapshot still counts as a reload
+        let reloaded = Arc::new(FilterConfig::default());
+        let second = cache.get_or_parse("DP > 0", &reloaded, parse).unwrap();
+        assert!(!Arc::ptr_eq(&first, &second));
+        assert!(Arc::ptr_eq(
+            &second,
+            &cache.get_or_parse("DP > 0", &reloaded, parse).unwrap()
+        ));
+    }
+}
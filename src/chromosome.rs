@@ -0,0 +1,133 @@
+//! Natural chromosome ordering: `CHROM <= "chr9"`, `chrom_rank()`.
+//!
+//! Contig names sort badly as strings (`"chr10" < "chr2"`), so ordering
+//! comparisons against `CHROM` use karyotypic order instead: numbered
+//! chromosomes by number, then X, Y, XY (pseudo-autosomal) and the
+//! mitochondrial genome, following PLINK's numeric codes (X = 23, Y = 24,
+//! XY = 25, M/MT = 26). A `chr` prefix is optional and case is ignored.
+//!
+//! Other contigs (`chrUn_gl000220`, `HLA-A*01:01`, decoys) have no rank, so
+//! ordering comparisons involving them are false.
+//!
+//! When the header declares `##contig` lines, their order is the
+//! chromosome order instead ([`ContigOrder`]): every declared contig is
+//! ranked by its position, and undeclared names have no rank.
+//!
+//! ```rust
+//! use vcf_filter::chromosome::rank;
+//!
+//! assert!(rank("chr2") < rank("chr10"));
+//! assert_eq!(rank("chrX"), Some(23));
+//! assert_eq!(rank("MT"), rank("chrM"));
+//! assert_eq!(rank("chrUn_gl000220"), None);
+//! ```
+
+use std::collections::HashMap;
+
+use crate::header::Contig;
+
+/// Named chromosomes and their ranks, after any numbered chromosome of a human genome.
+const NAMED: &[(&str, u32)] = &[("X", 23), ("Y", 24), ("XY", 25), ("M", 26), ("MT", 26)];
+
+/// Get the natural rank of a chromosome name, or `None` for other contigs.
+pub fn rank(chrom: &str) -> Option<u32> {
+    let name = match chrom.get(..3) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("chr") => &chrom[3..],
+        _ => chrom,
+    };
+    if !name.is_empty() && name.bytes().all(|b| b.is_ascii_digit()) {
+        return name.parse().ok().filter(|&n| n > 0);
+    }
+    NAMED
+        .iter()
+        .find(|(named, _)| named.eq_ignore_ascii_case(name))
+        .map(|&(_, rank)| rank)
+}
+
+/// The contigs of a VCF header, in header order.
+#[derive(Debug, Clone, Default)]
+pub struct ContigOrder {
+    contigs: Vec<Contig>,
+    /// Position of each contig in `contigs`, by name.
+    positions: HashMap<String, usize>,
+}
+
+impl ContigOrder {
+    /// Index contigs in header order; a repeated name keeps its first position.
+    pub fn new(contigs: Vec<Contig>) -> Self {
+        let mut positions = HashMap::with_capacity(contigs.len());
+        for (i, contig) in contigs.iter().enumerate() {
+            positions.entry(contig.id.clone()).or_insert(i);
+        }
+        Self { contigs, positions }
+    }
+
+    /// The declared contigs, in header order.
+    pub fn contigs(&self) -> &[Contig] {
+        &self.contigs
+    }
+
+    /// Whether the header declares no contigs.
+    pub fn is_empty(&self) -> bool {
+        self.contigs.is_empty()
+    }
+
+    /// The declared contig named `chrom`, if any.
+    pub fn get(&self, chrom: &str) -> Option<&Contig> {
+        self.positions.get(chrom).map(|&i| &self.contigs[i])
+    }
+
+    /// Get the rank of a chromosome: its one-based header position, or its
+    /// natural [`rank`] when no contigs are declared.
+    pub fn rank(&self, chrom: &str) -> Option<u32> {
+        if self.is_empty() {
+            return rank(chrom);
+        }
+        self.positions
+            .get(chrom)
+            .and_then(|&i| u32::try_from(i + 1).ok())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_natural_order() {
+        let order = [
+            "chr1", "chr2", "chr9", "chr10", "chr22", "chrX", "chrY", "chrM",
+        ];
+        let ranks: Vec<_> = order.iter().map(|c| rank(c).unwrap()).collect();
+        assert!(ranks.windows(2).all(|w| w[0] < w[1]));
+
+        assert_eq!(rank("1"), Some(1));
+        assert_eq!(rank("CHR7"), Some(7));
+        assert_eq!(rank("x"), Some(23));
+        assert_eq!(rank("chr0"), None);
+        assert_eq!(rank("chr"), None);
+        assert_eq!(rank("chr1_KI270706v1_random"), None);
+        assert_eq!(rank("GL000220.1"), None);
+    }
+
+    #[test]
+    fn test_contig_order() {
+        let contig = |id: &str, length| Contig {
+            id: id.to_string(),
+            length,
+        };
+        let order = ContigOrder::new(vec![
+            contig("chrM", Some(16569)),
+            contig("chr1", None),
+            contig("chrUn_gl000220", None),
+        ]);
+        assert!(order.rank("chrM") < order.rank("chr1"));
+        assert_eq!(order.rank("chrUn_gl000220"), Some(3));
+        assert_eq!(order.rank("chr2"), None);
+        assert_eq!(order.get("chrM").unwrap().length, Some(16569));
+
+        let natural = ContigOrder::default();
+        assert_eq!(natural.rank("chr2"), Some(2));
+        assert!(natural.get("chr2").is_none());
+    }
+}
@@ -0,0 +1,571 @@
+//! Compiled filters with a byte-level pre-screen.
+//!
+//! Many filters can only pass records whose raw text contains certain
+//! literals. `CLNSIG == "Pathogenic"` needs both `CLNSIG` and `Pathogenic`
+//! somewhere on the line, so a line lacking either can be skipped without
+//! being parsed. A [`CompiledFilter`] pairs the parsed expression with these
+//! requirements, derived conservatively: a line is only screened out when
+//! the filter is guaranteed to evaluate to `false` on it.
+//!
+//! Requirements are kept in conjunctive form. Each clause lists alternative
+//! literals, at least one of which must occur in the line:
+//!
+//! | Filter                                   | Requirements                      |
+//! |------------------------------------------|-----------------------------------|
+//! | `DP > 10`                                | `[DP]`                            |
+//! | `ANN[*].Gene_Name == "BRCA1"`            | `[ANN]`, `[BRCA1]`                |
+//! | `CLNSIG in ["Pathogenic", "Benign"]`     | `[CLNSIG]`, `[Pathogenic, Benign]`|
+//! | `DP > 10 \|\| MQ > 40`                   | `[DP, MQ]`                        |
+//! | `!exists(DP)`, `default(DP, 0) >= 0`     | none                              |
+//!
+//! `==`, `in` and `contains` only require the key when compared with a string
+//! or number literal: missing equals missing, so `DP == NOPE` passes a line
+//! with neither key. Comparisons on a Flag never require it, since an absent
+//! flag reads as `false` (`DB == false`).
+//!
+//! Built-in columns only contribute the literals they are compared with.
+//! Virtual fields (interval annotations, `INHERITANCE`) contribute nothing,
+//! since their values do not come from the text of the line.
+//!
+//! Compiling also resolves field paths once, up front: literals are built,
+//! annotation subfield names are mapped to column indexes, and fields are
+//! read from parsed rows in place rather than cloned. Evaluating a
+//! [`CompiledFilter`] with
+//! [`FilterEngine::evaluate_compiled`](crate::FilterEngine::evaluate_compiled)
+//! gives the same results as evaluating its expression, typically several
+//! times faster.
+//!
+//! Rows tested with [`CompiledFilter::matches`] are parsed for the filter:
+//! only the INFO keys it reads are parsed into values, so `DP > 10` skips
+//! a long ANN or CSQ entry. The remaining keys are kept as
+//! written and can be parsed on demand with
+//! [`FilterEngine::parse_info_key`](crate::FilterEngine::parse_info_key).
+//! Filters calling functions or matching INFO keys by pattern parse the
+//! whole column, as do engines with strict missing-value handling, which
+//! reports malformed values in any key.
+//!
+//! A [`CompiledFilter`] keeps a handle to the engine that compiled it, so it
+//! can test raw lines on its own with [`CompiledFilter::matches`] and cannot
+//! be paired with the wrong header. It is `Send + Sync`; share one across
+//! threads behind an `Arc` or a reference. When the engine's
+//! [`ConfigWatcher`](crate::config::ConfigWatcher) reloads, a filter compiled
+//! from text is swapped for one compiled against the new configuration by
+//! [`CompiledFilter::refresh`], typically between batches.
+
+use std::sync::Arc;
+
+use crate::FilterEngine;
+use crate::config::FilterConfig;
+use crate::error::Result;
+use crate::eval::{AlleleMode, EvalContext, each_allele, evaluate_with, uses_alt_access};
+use crate::filter::{AccessPart, BinaryOp, Expr};
+use crate::functions::BUILTIN_FIELDS;
+use crate::header::{InfoMap, InfoType};
+use crate::plan::Plan;
+use crate::row::{MissingValueMode, VcfRow, has_encoded_chars};
+
+/// Upper bound on clauses produced when distributing `||` over `&&`.
+const MAX_CLAUSES: usize = 16;
+
+/// Upper bound on the alternatives of one clause; a long `in` list (a gene
+/// panel, say) costs more to scan for than it saves.
+const MAX_ALTERNATIVES: usize = 16;
+
+/// A parsed filter with the literals a line must contain to pass it.
+///
+/// Build one with [`FilterEngine::compile`](crate::FilterEngine::compile) and
+/// test lines with [`matches`](Self::matches), or screen them with
+/// [`FilterEngine::quick_reject`](crate::FilterEngine::quick_reject).
+#[derive(Debug, Clone)]
+pub struct CompiledFilter {
+    /// The parsed filter expression.
+    expr: Expr,
+    /// Clauses of alternative literals; every clause must be satisfied.
+    required: Vec<Vec<String>>,
+    /// The expression with field paths resolved against the header.
+    plan: Plan,
+    /// Whether the filter uses `[alt]` and is evaluated once per ALT allele.
+    per_allele: bool,
+    /// The INFO keys the filter reads, or `None` when every key is parsed.
+    info_keys: Option<Vec<String>>,
+    /// The engine that compiled the filter, with its header and options.
+    engine: Arc<FilterEngine>,
+    /// The filter text and the configuration snapshot it was expanded with,
+    /// when compiled from text.
+    source: Option<(String, Arc<FilterConfig>)>,
+}
+
+impl CompiledFilter {
+    /// Compile an expression against an engine's header and virtual fields.
+    pub(crate) fn new(expr: Expr, engine: Arc<FilterEngine>) -> Self {
+        let mut required = requirements(&expr, &engine.virtual_fields, &engine.info_map);
+        for clause in &mut required {
+            clause.sort();
+            clause.dedup();
+        }
+        required.sort();
+        required.dedup();
+        let plan = Plan::new(&expr, &engine.info_map);
+        let per_allele = uses_alt_access(&expr);
+        let mut info_keys = Vec::new();
+        let info_keys = (engine.parse_options.missing_values != MissingValueMode::Strict
+            && collect_info_keys(&expr, &mut info_keys))
+        .then(|| {
+            // Interval annotations read a record's span from END or SVLEN
+            if !engine.intervals.is_empty() {
+                info_keys.extend(["END".to_string(), "SVLEN".to_string()]);
+            }
+            info_keys.sort();
+            info_keys.dedup();
+            info_keys
+        });
+        Self {
+            expr,
+            required,
+            plan,
+            per_allele,
+            info_keys,
+            engine,
+            source: None,
+        }
+    }
+
+    /// Compile filter text against the engine's current configuration.
+    pub(crate) fn from_source(filter: &str, engine: Arc<FilterEngine>) -> Result<Self> {
+        let config = engine.config();
+        let expr = engine.parse_filter_with(filter, &config)?;
+        let mut compiled = Self::new(expr, engine);
+        compiled.source = Some((filter.to_string(), config));
+        Ok(compiled)
+    }
+
+    /// Recompile the filter if the engine's configuration was reloaded since
+    /// it was compiled.
+    ///
+    /// Returns `true` if the filter was recompiled. Filters compiled from an
+    /// expression with
+    /// [`FilterEngine::compile_expr`](crate::FilterEngine::compile_expr) have
+    /// no text to recompile and are left as they are. If the filter no
+    /// longer compiles (it uses a preset the reload removed, say), the error
+    /// is returned and the filter is unchanged.
+    pub fn refresh(&mut self) -> Result<bool> {
+        let Some((filter, config)) = &self.source else {
+            return Ok(false);
+        };
+        if Arc::ptr_eq(config, &self.engine.config()) {
+            return Ok(false);
+        }
+        *self = Self::from_source(filter, Arc::clone(&self.engine))?;
+        Ok(true)
+    }
+
+    /// Get the INFO keys parsed for [`matches`](Self::matches), sorted.
+    ///
+    /// `None` when the filter may read any key, so the whole INFO column is
+    /// parsed.
+    pub fn info_keys(&self) -> Option<&[String]> {
+        self.info_keys.as_deref()
+    }
+
+    /// Test a raw data line against the filter.
+    ///
+    /// Parses the line with the compiling engine's header and options; lines
+    /// screened out by the byte-level pre-screen are rejected unparsed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use vcf_filter::FilterEngine;
+    ///
+    /// let header = r#"##INFO=<ID=DP,Number=1,Type=Integer,Description="Depth">"#;
+    /// let filter = FilterEngine::new(header).unwrap().compile("DP >= 10").unwrap();
+    ///
+    /// std::thread::scope(|s| {
+    ///     s.spawn(|| assert!(filter.matches("chr1\t100\t.\tA\tG\t50\tPASS\tDP=30").unwrap()));
+    ///     s.spawn(|| assert!(!filter.matches("chr1\t200\t.\tA\tG\t50\tPASS\tDP=3").unwrap()));
+    /// });
+    /// ```
+    pub fn matches(&self, line: &str) -> Result<bool> {
+        self.engine.evaluate_line(self, line)
+    }
+
+    /// Test a row parsed by the compiling engine against the filter.
+    pub fn matches_row(&self, row: &VcfRow) -> Result<bool> {
+        self.engine.evaluate_compiled(self, row)
+    }
+
+    /// Get the engine the filter was compiled with.
+    pub fn engine(&self) -> &FilterEngine {
+        &self.engine
+    }
+
+    /// Evaluate the filter against a parsed row.
+    pub(crate) fn evaluate(&self, row: &VcfRow, ctx: &EvalContext) -> Result<bool> {
+        if (self.per_allele || ctx.allele_mode != AlleleMode::Site) && ctx.alt.is_none() {
+            return each_allele(row, ctx, |ctx| self.evaluate(row, ctx));
+        }
+        match &self.plan {
+            // Strict mode's checks and three-valued logic live in the tree-walker
+            plan if !ctx.strict && !ctx.three_valued && !matches!(plan, Plan::Truthy(_)) => {
+                plan.evaluate(row, ctx)
+            }
+            // A non-boolean filter only passes on a literal `true` value
+            _ => Ok(evaluate_with(&self.expr, row, ctx)?
+                .as_bool()
+                .unwrap_or(false)),
+        }
+    }
+
+    /// Get the parsed filter expression.
+    pub fn expr(&self) -> &Expr {
+        &self.expr
+    }
+
+    /// Get the literal requirements: each clause needs one of its literals.
+    pub fn required_literals(&self) -> &[Vec<String>] {
+        &self.required
+    }
+
+    /// Check whether a raw line contains every required literal.
+    ///
+    /// `false` means the filter cannot pass the line; `true` means the line
+    /// must be parsed and evaluated to know.
+    pub fn may_match(&self, line: &[u8]) -> bool {
+        self.required.iter().all(|clause| {
+            clause
+                .iter()
+                .any(|literal| contains_bytes(line, literal.as_bytes()))
+        })
+    }
+}
+
+/// Collect the INFO keys an expression reads into `keys`.
+///
+/// Returns `false` when the keys cannot be known up front: functions may
+/// read any key (`af_consistent()` reads AF, AC and AN), as do `INFO.*`
+/// patterns. Names that turn out not to be INFO keys, such as built-in
+/// columns or `let` bindings, are harmless extras.
+fn collect_info_keys(expr: &Expr, keys: &mut Vec<String>) -> bool {
+    match expr {
+        Expr::Call(..) => return false,
+        Expr::Var(parts) | Expr::Exists(parts) => {
+            if parts.iter().any(|part| matches!(part, AccessPart::Glob(_))) {
+                return false;
+            }
+            let name = match parts.as_slice() {
+                [AccessPart::Field(ns), AccessPart::Field(name), ..]
+                    if ns == "INFO" || ns == "FORMAT" =>
+                {
+                    Some(name)
+                }
+                [AccessPart::Field(ns), ..] if ns == "SAMPLE" => None,
+                [AccessPart::Field(name), ..] if !BUILTIN_FIELDS.contains(&name.as_str()) => {
+                    Some(name)
+                }
+                _ => None,
+            };
+            keys.extend(name.cloned());
+        }
+        _ => {}
+    }
+    expr.children()
+        .into_iter()
+        .all(|child| collect_info_keys(child, keys))
+}
+
+/// Derive the literal clauses an expression needs to evaluate to true.
+fn requirements(expr: &Expr, virtual_fields: &[String], info_map: &InfoMap) -> Vec<Vec<String>> {
+    match expr {
+        Expr::Binary(left, BinaryOp::And, right) => {
+            let mut clauses = requirements(left, virtual_fields, info_map);
+            clauses.extend(requirements(right, virtual_fields, info_map));
+            clauses
+        }
+        Expr::Binary(left, BinaryOp::Or, right) => {
+            let left = requirements(left, virtual_fields, info_map);
+            let right = requirements(right, virtual_fields, info_map);
+            // (a && b) || (c && d) needs one of a/c, a/d, b/c and b/d
+            left.iter()
+                .flat_map(|l| right.iter().map(move |r| [l.as_slice(), r].concat()))
+                .take(MAX_CLAUSES)
+                .collect()
+        }
+        Expr::Binary(left, op, right) => {
+            let Expr::Var(parts) = left.as_ref() else {
+                return Vec::new();
+            };
+            let Some(name) = line_field(parts, virtual_fields) else {
+                return Vec::new();
+            };
+            // An absent flag reads as `false` rather than missing
+            if name.as_ref().is_some_and(|name| {
+                info_map
+                    .get(name)
+                    .is_some_and(|field| field.field_type == InfoType::Flag)
+            }) {
+                return Vec::new();
+            }
+            let mut clauses: Vec<Vec<String>> = name.into_iter().map(|n| vec![n]).collect();
+            match op {
+                // Each of these is false when the field is absent and the
+                // right side is a present literal; `!=` is not, and neither
+                // is `DP == NOPE`, as missing equals missing
+                BinaryOp::Eq | BinaryOp::In | BinaryOp::Contains if is_present_literal(right) => {
+                    clauses.extend(value_literals(op, right));
+                    clauses
+                }
+                BinaryOp::Lt
+                | BinaryOp::Gt
+                | BinaryOp::LtEq
+                | BinaryOp::GtEq
+                | BinaryOp::SeverityAtLeast => clauses,
+                _ => Vec::new(),
+            }
+        }
+        Expr::Var(parts) | Expr::Exists(parts) => field_requirement(parts, virtual_fields),
+        Expr::All(inner) => requirements(inner, virtual_fields, info_map),
+        // Bound names are not line fields
+        Expr::Let(name, _, body) => {
+            let mut scoped = virtual_fields.to_vec();
+            scoped.push(name.clone());
+            requirements(body, &scoped, info_map)
+        }
+        Expr::Number(_)
+        | Expr::String(_)
+        | Expr::Bool(_)
+        | Expr::Unary(..)
+        | Expr::List(_)
+        | Expr::Call(..)
+        | Expr::Preset(_) => Vec::new(),
+    }
+}
+
+/// Whether an expression is a string or number literal, or a list of them,
+/// which a missing value never equals.
+fn is_present_literal(expr: &Expr) -> bool {
+    match expr {
+        Expr::String(_) | Expr::Number(_) => true,
+        Expr::List(items) => items
+            .iter()
+            .all(|item| matches!(item, Expr::String(_) | Expr::Number(_))),
+        _ => false,
+    }
+}
+
+/// Find where a variable path reads its value from in the line text.
+///
+/// Returns `None` for values not taken verbatim from the line (virtual
+/// fields, INFO key globs), `Some(None)` for built-in columns and
+/// `Some(Some(name))` for an INFO/FORMAT key.
+fn line_field(parts: &[AccessPart], virtual_fields: &[String]) -> Option<Option<String>> {
+    let name = match parts {
+        [AccessPart::Field(ns), AccessPart::Field(name), ..] if ns == "INFO" || ns == "FORMAT" => {
+            name
+        }
+        [AccessPart::Field(ns), ..] if ns == "INFO" || ns == "FORMAT" || ns == "SAMPLE" => {
+            return None;
+        }
+        [AccessPart::Field(name), ..] if BUILTIN_FIELDS.contains(&name.as_str()) => {
+            return Some(None);
+        }
+        [AccessPart::Field(name), ..] => name,
+        _ => return None,
+    };
+    if virtual_fields.contains(name) {
+        return None;
+    }
+    Some(Some(name.clone()))
+}
+
+/// The key clause for a variable path read from INFO or FORMAT.
+fn field_requirement(parts: &[AccessPart], virtual_fields: &[String]) -> Vec<Vec<String>> {
+    line_field(parts, virtual_fields)
+        .flatten()
+        .map(|name| vec![vec![name]])
+        .unwrap_or_default()
+}
+
+/// Literals one of which a matching value must contain verbatim.
+///
+/// Only non-numeric strings qualify: a numeric string literal may equal a
+/// number written differently in the line (`"30"` vs `30.0`). Neither do
+/// strings with characters the line may percent-encode (`a;b` as `a%3Bb`).
+fn value_literals(op: &BinaryOp, right: &Expr) -> Option<Vec<String>> {
+    let literal = |expr: &Expr| match expr {
+        Expr::String(s)
+            if !s.is_empty()
+                && !has_encoded_chars(s)
+                && (op == &BinaryOp::Contains || s.parse::<f64>().is_err()) =>
+        {
+            Some(s.clone())
+        }
+        _ => None,
+    };
+    match (op, right) {
+        (BinaryOp::In, Expr::List(items)) if (1..=MAX_ALTERNATIVES).contains(&items.len()) => {
+            items.iter().map(literal).collect()
+        }
+        (BinaryOp::Eq | BinaryOp::Contains, expr) => literal(expr).map(|s| vec![s]),
+        _ => None,
+    }
+}
+
+/// Check whether `haystack` contains `needle` as a contiguous byte sequence.
+fn contains_bytes(haystack: &[u8], needle: &[u8]) -> bool {
+    needle.is_empty()
+        || haystack
+            .windows(needle.len())
+            .any(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::parse_filter;
+    use crate::intervals::IntervalAnnotation;
+    use crate::value::Value;
+
+    fn compile(filter: &str) -> CompiledFilter {
+        let mut engine = FilterEngine::new("").unwrap();
+        engine.virtual_fields = vec!["ROH_REGION".to_string()];
+        CompiledFilter::new(parse_filter(filter).unwrap(), Arc::new(engine))
+    }
+
+    fn required(filter: &str) -> Vec<Vec<String>> {
+        compile(filter).required_literals().to_vec()
+    }
+
+    fn clauses(expected: &[&[&str]]) -> Vec<Vec<String>> {
+        expected
+            .iter()
+            .map(|clause| clause.iter().map(|s| s.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_required_literals() {
+        assert_eq!(required("DP > 10"), clauses(&[&["DP"]]));
+        assert_eq!(
+            required(r#"INFO.CLNSIG == "Pathogenic" && QUAL > 30"#),
+            clauses(&[&["CLNSIG"], &["Pathogenic"]])
+        );
+        assert_eq!(
+            required(r#"ANN[*].Gene_Name contains "BRCA""#),
+            clauses(&[&["ANN"], &["BRCA"]])
+        );
+        assert_eq!(
+            required(r#"CLNSIG in ["Pathogenic", "Benign"]"#),
+            clauses(&[&["Benign", "Pathogenic"], &["CLNSIG"]])
+        );
+        assert_eq!(
+            required("let ab = AD[1] / DP in ab > 0.2 && DP > 10"),
+            clauses(&[&["DP"]])
+        );
+        assert_eq!(required("DP > 10 || MQ > 40"), clauses(&[&["DP", "MQ"]]));
+        assert_eq!(required(r#"SYMBOL == "123""#), clauses(&[&["SYMBOL"]]));
+        assert_eq!(required(r#"FILTER == "PASS""#), clauses(&[&["PASS"]]));
+        let panel: Vec<String> = (0..20).map(|i| format!("\"GENE{}\"", i)).collect();
+        assert_eq!(
+            required(&format!("GENE in [{}]", panel.join(", "))),
+            clauses(&[&["GENE"]])
+        );
+    }
+
+    #[test]
+    fn test_no_requirements_when_absence_can_pass() {
+        assert!(required("!exists(DP)").is_empty());
+        assert!(required("DP != 10").is_empty());
+        assert!(required("default(DP, 0) >= 0").is_empty());
+        assert!(required("DP > 10 || QUAL > 30").is_empty());
+        assert!(required(r#"ROH_REGION == "roh_1""#).is_empty());
+        assert!(required("INFO.gnomAD_* > 0.01").is_empty());
+        assert!(required("AD[1] / DP > 0.2").is_empty());
+        // The line may hold `a%3Bb`
+        assert_eq!(required(r#"CLNDN == "a;b""#), clauses(&[&["CLNDN"]]));
+    }
+
+    #[test]
+    fn test_shared_across_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<CompiledFilter>();
+
+        let filter = compile(r#"CLNSIG == "Pathogenic" && QUAL > 30"#);
+        let lines = [
+            ("chr1\t100\t.\tA\tG\t50\tPASS\tCLNSIG=Pathogenic", true),
+            ("chr1\t100\t.\tA\tG\t10\tPASS\tCLNSIG=Pathogenic", false),
+            ("chr1\t100\t.\tA\tG\t50\tPASS\tCLNSIG=Benign", false),
+        ];
+        std::thread::scope(|s| {
+            for (line, expected) in lines {
+                let filter = &filter;
+                s.spawn(move || assert_eq!(filter.matches(line).unwrap(), expected));
+            }
+        });
+        let row = filter.engine().parse_row(lines[0].0).unwrap();
+        assert!(filter.matches_row(&row).unwrap());
+    }
+
+    #[test]
+    fn test_may_match() {
+        let filter = compile(r#"CLNSIG == "Pathogenic" && DP > 10"#);
+        assert!(filter.may_match(b"chr1\t100\t.\tA\tG\t50\tPASS\tCLNSIG=Pathogenic;DP=30"));
+        assert!(!filter.may_match(b"chr1\t100\t.\tA\tG\t50\tPASS\tCLNSIG=Benign;DP=30"));
+        assert!(!filter.may_match(b"chr1\t100\t.\tA\tG\t50\tPASS\tDP=30"));
+    }
+
+    #[test]
+    fn test_info_keys() {
+        let keys = |filter: &str| compile(filter).info_keys().map(<[String]>::to_vec);
+        let names = |names: &[&str]| Some(names.iter().map(|s| s.to_string()).collect());
+
+        assert_eq!(keys("QUAL > 30 && DP > 10"), names(&["DP"]));
+        assert_eq!(
+            keys(r#"INFO.CLNSIG == "Pathogenic" || ANN[?(Gene_Name == "X")].Allele == "G""#),
+            names(&["ANN", "CLNSIG", "Gene_Name"])
+        );
+        assert_eq!(keys("SAMPLE[0].GT == \"0/1\""), names(&[]));
+        assert_eq!(keys("INFO.gnomAD_* > 0.01"), None);
+        assert_eq!(keys("af_consistent()"), None);
+
+        // Interval annotations need the record's span
+        let tsv = "#chrom\tstart\tend\tROH_REGION\nchr1\t0\t100\troh_1\n";
+        let engine = FilterEngine::new("")
+            .unwrap()
+            .with_intervals(IntervalAnnotation::parse_tsv(tsv).unwrap());
+        let filter = CompiledFilter::new(parse_filter("DP > 10").unwrap(), Arc::new(engine));
+        assert_eq!(
+            filter.info_keys().map(<[String]>::to_vec),
+            names(&["DP", "END", "SVLEN"])
+        );
+    }
+
+    #[test]
+    fn test_lazy_info_parsing() {
+        let header = r#"##INFO=<ID=DP,Number=1,Type=Integer,Description="Depth">"#;
+        let engine = FilterEngine::new(header).unwrap();
+        let filter = engine.compile("QUAL > 30 && DP > 10").unwrap();
+        let line = "chr1\t100\t.\tA\tG\t50\tPASS\tAF=0.5;DP=30;DB;MQ=x,y";
+        assert!(filter.matches(line).unwrap());
+
+        let mut row = engine
+            .parse_row_with_keys(line, filter.info_keys())
+            .unwrap();
+        assert_eq!(row.info.keys().collect::<Vec<_>>(), ["DP"]);
+        assert_eq!(row.unparsed_info().collect::<Vec<_>>(), ["AF", "DB", "MQ"]);
+        assert_eq!(row.to_vcf_line(), line);
+
+        assert_eq!(
+            engine.parse_info_key(&mut row, "AF").unwrap(),
+            Some(&Value::Number(0.5))
+        );
+        assert_eq!(row.unparsed_info().collect::<Vec<_>>(), ["DB", "MQ"]);
+        row.remove_info("MQ");
+        engine.parse_all_info(&mut row).unwrap();
+        assert_eq!(row.unparsed_info().count(), 0);
+        assert_eq!(row.info.get("DB"), Some(&Value::Bool(true)));
+        assert_eq!(
+            row.to_vcf_line(),
+            "chr1\t100\t.\tA\tG\t50\tPASS\tAF=0.5;DP=30;DB"
+        );
+    }
+}
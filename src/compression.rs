@@ -0,0 +1,619 @@
+//! Compressed VCF input and output.
+//!
+//! VCFs are usually distributed gzip- or bgzip-compressed. Inputs are
+//! recognised by their magic bytes rather than their extension: gzip starts
+//! with `1f 8b`, and BGZF (bgzip) is gzip whose members carry a `BC` extra
+//! subfield. Both decode as a series of gzip members.
+//!
+//! Outputs ending in `.gz` or `.bgz` are written as BGZF, which any gzip
+//! reader accepts and which `tabix` can index; [`create_output_as`] chooses
+//! the format regardless of the name. [`BgzfReader`] reads BGZF block by
+//! block and seeks to the virtual offsets such an index records.
+//! [`read_ahead`] moves decompression to a thread of its own.
+//!
+//! ```rust,no_run
+//! use std::io::BufRead;
+//! use vcf_filter::compression::open_input;
+//!
+//! let reader = open_input("calls.vcf.gz").unwrap();
+//! for line in reader.lines() {
+//!     println!("{}", line.unwrap());
+//! }
+//! ```
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, sync_channel};
+use std::thread;
+
+use flate2::Crc;
+use flate2::read::{DeflateDecoder, MultiGzDecoder};
+use flate2::write::DeflateEncoder;
+
+use crate::error::{Result, VcfFilterError};
+
+/// Largest amount of uncompressed data in one BGZF block, as in htslib.
+const BGZF_BLOCK_SIZE: usize = 0xff00;
+
+/// Size of the blocks [`read_ahead`] hands over, and how many may wait.
+const READ_AHEAD_SIZE: u64 = 1 << 20;
+const READ_AHEAD_BLOCKS: usize = 4;
+
+/// The empty block that marks the end of a BGZF file.
+const BGZF_EOF: [u8; 28] = [
+    0x1f, 0x8b, 0x08, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x06, 0x00, 0x42, 0x43, 0x02, 0x00,
+    0x1b, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+/// Compression format of a VCF stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// Uncompressed text.
+    Plain,
+    /// Plain gzip.
+    Gzip,
+    /// Blocked gzip, as written by `bgzip`.
+    Bgzip,
+}
+
+impl Compression {
+    /// Detect the compression format from the first bytes of a stream.
+    pub fn detect(bytes: &[u8]) -> Self {
+        match bytes {
+            // FEXTRA set and a `BC` subfield first in the extra field
+            [
+                0x1f,
+                0x8b,
+                0x08,
+                flags,
+                _,
+                _,
+                _,
+                _,
+                _,
+                _,
+                _,
+                _,
+                b'B',
+                b'C',
+                ..,
+            ] if flags & 0x04 != 0 => Compression::Bgzip,
+            [0x1f, 0x8b, ..] => Compression::Gzip,
+            _ => Compression::Plain,
+        }
+    }
+
+    /// The format an output file is written in by default: BGZF for names
+    /// ending in `.gz` or `.bgz`, plain text otherwise.
+    pub fn for_path(path: impl AsRef<Path>) -> Self {
+        match path.as_ref().extension() {
+            Some(ext) if ext == "gz" || ext == "bgz" => Compression::Bgzip,
+            _ => Compression::Plain,
+        }
+    }
+}
+
+/// Open a VCF file for reading, decompressing gzip and bgzip inputs.
+pub fn open_input(path: impl AsRef<Path>) -> Result<Box<dyn BufRead + Send>> {
+    let path = path.as_ref();
+    let reader = File::open(path)
+        .map(BufReader::new)
+        .map_err(io_error(path))?;
+    decompress(reader).map_err(io_error(path))
+}
+
+/// Wrap a VCF stream, such as stdin, decompressing it if it is gzip or bgzip.
+pub fn decompress<R: BufRead + Send + 'static>(
+    mut reader: R,
+) -> io::Result<Box<dyn BufRead + Send>> {
+    Ok(match Compression::detect(reader.fill_buf()?) {
+        Compression::Plain => Box::new(reader),
+        Compression::Gzip | Compression::Bgzip => {
+            Box::new(BufReader::new(MultiGzDecoder::new(reader)))
+        }
+    })
+}
+
+/// Create a VCF output file, BGZF-compressed if its name ends in `.gz` or `.bgz`.
+///
+/// Flush the writer before dropping it to see write errors; the BGZF
+/// end-of-file marker is appended on drop.
+pub fn create_output(path: impl AsRef<Path>) -> Result<Box<dyn Write + Send>> {
+    let path = path.as_ref();
+    create_output_as(path, Compression::for_path(path))
+}
+
+/// Create a VCF output file in the given format, whatever its name.
+///
+/// Gzip output is written as BGZF, which gzip readers accept too.
+pub fn create_output_as(
+    path: impl AsRef<Path>,
+    compression: Compression,
+) -> Result<Box<dyn Write + Send>> {
+    let path = path.as_ref();
+    let file = BufWriter::new(File::create(path).map_err(io_error(path))?);
+    Ok(match compression {
+        Compression::Plain => Box::new(file),
+        Compression::Gzip | Compression::Bgzip => Box::new(BgzfWriter::new(file)),
+    })
+}
+
+/// Resolve `path` to an absolute path with symlinks and `..` removed.
+///
+/// A path that does not exist yet, such as a new output, is resolved
+/// through its parent directory, so two spellings of the same file compare
+/// equal before either is created.
+pub fn resolve_path(path: impl AsRef<Path>) -> PathBuf {
+    let path = path.as_ref();
+    if let Ok(resolved) = path.canonicalize() {
+        return resolved;
+    }
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    match (parent.canonicalize(), path.file_name()) {
+        (Ok(parent), Some(name)) => parent.join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// A writer producing BGZF: independent gzip blocks of up to 64 KiB.
+///
+/// The end-of-file marker is written by [`finish`](BgzfWriter::finish), or
+/// on drop if `finish` was not called.
+pub struct BgzfWriter<W: Write> {
+    inner: Option<W>,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> BgzfWriter<W> {
+    /// Wrap a writer.
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner: Some(inner),
+            buffer: Vec::with_capacity(BGZF_BLOCK_SIZE),
+        }
+    }
+
+    /// Write any buffered data and the end-of-file marker, returning the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_eof()?;
+        Ok(self
+            .inner
+            .take()
+            .expect("inner writer present until finished"))
+    }
+
+    /// Compress the buffered data into one block.
+    fn write_block(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let mut encoder = DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&self.buffer)?;
+        let compressed = encoder.finish()?;
+        let mut crc = Crc::new();
+        crc.update(&self.buffer);
+
+        // BSIZE is the total block size minus one: 18 header + data + 8 trailer bytes
+        let block_size = u16::try_from(compressed.len() + 25)
+            .map_err(|_| io::Error::other("BGZF block exceeds 64 KiB"))?;
+        let inner = self
+            .inner
+            .as_mut()
+            .expect("inner writer present until finished");
+        inner.write_all(&[
+            0x1f, 0x8b, 0x08, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x06, 0x00, b'B', b'C',
+            0x02, 0x00,
+        ])?;
+        inner.write_all(&block_size.to_le_bytes())?;
+        inner.write_all(&compressed)?;
+        inner.write_all(&crc.sum().to_le_bytes())?;
+        inner.write_all(&(self.buffer.len() as u32).to_le_bytes())?;
+        self.buffer.clear();
+        Ok(())
+    }
+
+    /// Flush the last block and append the end-of-file marker.
+    fn write_eof(&mut self) -> io::Result<()> {
+        self.write_block()?;
+        let inner = self
+            .inner
+            .as_mut()
+            .expect("inner writer present until finished");
+        inner.write_all(&BGZF_EOF)?;
+        inner.flush()
+    }
+}
+
+impl<W: Write> Write for BgzfWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = buf.len().min(BGZF_BLOCK_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..n]);
+        if self.buffer.len() == BGZF_BLOCK_SIZE {
+            self.write_block()?;
+        }
+        Ok(n)
+    }
+
+    /// Complete the current block, so flushed data is readable.
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_block()?;
+        self.inner
+            .as_mut()
+            .expect("inner writer present until finished")
+            .flush()
+    }
+}
+
+impl<W: Write> Drop for BgzfWriter<W> {
+    fn drop(&mut self) {
+        if self.inner.is_some() {
+            let _ = self.write_eof();
+        }
+    }
+}
+
+/// Read a stream on a background thread, a few blocks ahead of the caller.
+///
+/// Decompressing gzip input is often the slowest single step of a filter
+/// run; on its own thread it overlaps with evaluation. An error reading the
+/// stream is returned by the read that reaches it.
+pub fn read_ahead<R: Read + Send + 'static>(mut reader: R) -> ReadAhead {
+    let (sender, receiver) = sync_channel(READ_AHEAD_BLOCKS);
+    thread::spawn(move || {
+        loop {
+            let mut block = Vec::new();
+            let result = match (&mut reader).take(READ_AHEAD_SIZE).read_to_end(&mut block) {
+                Ok(0) => break,
+                Ok(_) => Ok(block),
+                Err(e) => Err(e),
+            };
+            let failed = result.is_err();
+            // The reader was dropped, or the error is the last thing to send
+            if sender.send(result).is_err() || failed {
+                break;
+            }
+        }
+    });
+    ReadAhead {
+        receiver,
+        block: Vec::new(),
+        pos: 0,
+    }
+}
+
+/// The reading end of [`read_ahead`].
+pub struct ReadAhead {
+    receiver: Receiver<io::Result<Vec<u8>>>,
+    block: Vec<u8>,
+    pos: usize,
+}
+
+impl Read for ReadAhead {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl BufRead for ReadAhead {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos >= self.block.len() {
+            // A closed channel means the whole stream has been read
+            if let Ok(block) = self.receiver.recv() {
+                self.block = block?;
+                self.pos = 0;
+            }
+        }
+        Ok(&self.block[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.block.len());
+    }
+}
+
+/// A reader of BGZF that can seek to virtual offsets.
+///
+/// A virtual offset is the compressed offset of a block shifted left 16
+/// bits plus an offset into the block's uncompressed data, as stored in
+/// `.tbi` and `.csi` indexes.
+pub struct BgzfReader<R> {
+    inner: R,
+    /// Uncompressed data of the current block.
+    block: Vec<u8>,
+    /// Read position in `block`.
+    pos: usize,
+    /// Compressed offset of the current block.
+    block_offset: u64,
+    /// Compressed offset of the block after the current one.
+    next_offset: u64,
+}
+
+impl<R: Read> BgzfReader<R> {
+    /// Wrap a reader positioned at the start of a BGZF block.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            block: Vec::new(),
+            pos: 0,
+            block_offset: 0,
+            next_offset: 0,
+        }
+    }
+
+    /// Get the virtual offset of the next byte to be read.
+    pub fn virtual_offset(&self) -> u64 {
+        if self.pos < self.block.len() {
+            (self.block_offset << 16) | self.pos as u64
+        } else {
+            self.next_offset << 16
+        }
+    }
+
+    /// Read and decompress the next block, leaving `block` empty at end of input.
+    fn read_block(&mut self) -> io::Result<()> {
+        self.block.clear();
+        self.pos = 0;
+        self.block_offset = self.next_offset;
+
+        let mut header = [0u8; 12];
+        match self.inner.read_exact(&mut header) {
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            result => result?,
+        }
+        if header[..4] != [0x1f, 0x8b, 0x08, 0x04] {
+            return Err(invalid_data("not a BGZF block"));
+        }
+        let mut extra = vec![0u8; u16::from_le_bytes([header[10], header[11]]) as usize];
+        self.inner.read_exact(&mut extra)?;
+        let block_size = bgzf_block_size(&extra)
+            .ok_or_else(|| invalid_data("BGZF block without a BC subfield"))?;
+
+        // The rest of the block is the deflated data, CRC32 and ISIZE
+        let rest = (block_size + 1)
+            .checked_sub(header.len() + extra.len() + 8)
+            .ok_or_else(|| invalid_data("BGZF block size too small"))?;
+        let mut data = vec![0u8; rest + 8];
+        self.inner.read_exact(&mut data)?;
+        let (data, trailer) = data.split_at(rest);
+        DeflateDecoder::new(data).read_to_end(&mut self.block)?;
+
+        let mut crc = Crc::new();
+        crc.update(&self.block);
+        if trailer[..4] != crc.sum().to_le_bytes()
+            || trailer[4..] != (self.block.len() as u32).to_le_bytes()
+        {
+            return Err(invalid_data("BGZF block checksum mismatch"));
+        }
+        self.next_offset += (block_size + 1) as u64;
+        Ok(())
+    }
+}
+
+impl<R: Read + Seek> BgzfReader<R> {
+    /// Move to a virtual offset.
+    pub fn seek_virtual(&mut self, offset: u64) -> io::Result<()> {
+        let (block_offset, pos) = (offset >> 16, (offset & 0xffff) as usize);
+        if block_offset != self.block_offset || self.block.is_empty() {
+            self.inner.seek(SeekFrom::Start(block_offset))?;
+            self.next_offset = block_offset;
+            self.read_block()?;
+        }
+        if pos > self.block.len() {
+            return Err(invalid_data("virtual offset beyond the end of its block"));
+        }
+        self.pos = pos;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for BgzfReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: Read> BufRead for BgzfReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        // Skip empty blocks such as the end-of-file marker
+        while self.pos >= self.block.len() {
+            let offset = self.next_offset;
+            self.read_block()?;
+            if self.block.is_empty() && self.next_offset == offset {
+                break;
+            }
+        }
+        Ok(&self.block[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.block.len());
+    }
+}
+
+/// Find BSIZE, the total block size minus one, in a gzip extra field.
+fn bgzf_block_size(mut extra: &[u8]) -> Option<usize> {
+    while let [id1, id2, len_lo, len_hi, rest @ ..] = extra {
+        let len = u16::from_le_bytes([*len_lo, *len_hi]) as usize;
+        if (*id1, *id2, len) == (b'B', b'C', 2) {
+            return rest
+                .get(..2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]) as usize);
+        }
+        extra = rest.get(len..)?;
+    }
+    None
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Build a closure converting an I/O error into an `IoError` naming `path`.
+pub(crate) fn io_error(path: impl AsRef<Path>) -> impl Fn(io::Error) -> VcfFilterError {
+    move |e| VcfFilterError::IoError(format!("{}: {}", path.as_ref().display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(
+            Compression::detect(b"##fileformat=VCFv4.2"),
+            Compression::Plain
+        );
+        assert_eq!(Compression::detect(b""), Compression::Plain);
+        assert_eq!(Compression::detect(&BGZF_EOF), Compression::Bgzip);
+
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(b"##fileformat=VCFv4.2\n").unwrap();
+        assert_eq!(
+            Compression::detect(&gzip.finish().unwrap()),
+            Compression::Gzip
+        );
+    }
+
+    #[test]
+    fn test_bgzf_round_trip() {
+        // Enough data to span several blocks
+        let text: String = (0..20_000)
+            .map(|i| format!("chr1\t{}\t.\tA\tG\t50\tPASS\tDP={}\n", i, i % 97))
+            .collect();
+        let mut writer = BgzfWriter::new(Vec::new());
+        writer.write_all(text.as_bytes()).unwrap();
+        let compressed = writer.finish().unwrap();
+
+        assert_eq!(Compression::detect(&compressed), Compression::Bgzip);
+        assert!(compressed.ends_with(&BGZF_EOF));
+
+        let mut decoded = String::new();
+        MultiGzDecoder::new(compressed.as_slice())
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, text);
+    }
+
+    #[test]
+    fn test_decompress() {
+        let text = "##fileformat=VCFv4.2\nchr1\t1\t.\tA\tG\t50\tPASS\tDP=3\n";
+        let mut writer = BgzfWriter::new(Vec::new());
+        writer.write_all(text.as_bytes()).unwrap();
+        let compressed = writer.finish().unwrap();
+
+        for input in [compressed, text.as_bytes().to_vec()] {
+            let mut decoded = String::new();
+            decompress(io::Cursor::new(input))
+                .unwrap()
+                .read_to_string(&mut decoded)
+                .unwrap();
+            assert_eq!(decoded, text);
+        }
+    }
+
+    #[test]
+    fn test_output_format() {
+        assert_eq!(Compression::for_path("out.vcf.gz"), Compression::Bgzip);
+        assert_eq!(Compression::for_path("out.vcf.bgz"), Compression::Bgzip);
+        assert_eq!(Compression::for_path("out.vcf"), Compression::Plain);
+
+        let dir = std::env::temp_dir().join(format!("vcf-filter-output-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("out.vcf");
+        let mut writer = create_output_as(&path, Compression::Bgzip).unwrap();
+        writer.write_all(b"##fileformat=VCFv4.2\n").unwrap();
+        drop(writer);
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(Compression::detect(&bytes), Compression::Bgzip);
+        assert!(bytes.ends_with(&BGZF_EOF));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_path() {
+        let dir = std::env::temp_dir();
+        let existing = dir.join(format!("vcf-filter-resolve-{}.vcf", std::process::id()));
+        std::fs::write(&existing, "").unwrap();
+        let name = existing.file_name().unwrap();
+        assert_eq!(
+            resolve_path(dir.join(".").join(name)),
+            resolve_path(&existing)
+        );
+        std::fs::remove_file(&existing).unwrap();
+
+        // Not created yet
+        assert_eq!(
+            resolve_path(dir.join("missing.vcf")),
+            resolve_path(dir.join(".").join("missing.vcf"))
+        );
+        assert!(resolve_path("missing.vcf").is_absolute());
+    }
+
+    #[test]
+    fn test_bgzf_reader_seek() {
+        let mut writer = BgzfWriter::new(Vec::new());
+        writer.write_all(b"first\n").unwrap();
+        // Flushing ends the block, so the next line starts a new one
+        writer.flush().unwrap();
+        writer.write_all(b"second\nthird\n").unwrap();
+        let compressed = writer.finish().unwrap();
+
+        let mut reader = BgzfReader::new(io::Cursor::new(compressed));
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "first\n");
+        let second = reader.virtual_offset();
+        assert_eq!(second & 0xffff, 0);
+        reader.read_line(&mut String::new()).unwrap();
+        let third = reader.virtual_offset();
+        assert_eq!(third, second | 7);
+
+        let mut rest = String::new();
+        reader.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest, "third\n");
+
+        reader.seek_virtual(third).unwrap();
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "third\n");
+        reader.seek_virtual(0).unwrap();
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "first\n");
+
+        let mut plain = BgzfReader::new(io::Cursor::new(b"not bgzf at all".to_vec()));
+        assert!(plain.read_line(&mut String::new()).is_err());
+    }
+
+    #[test]
+    fn test_read_ahead() {
+        let text: String = (0..100_000).map(|i| format!("line {}\n", i)).collect();
+        let mut writer = BgzfWriter::new(Vec::new());
+        writer.write_all(text.as_bytes()).unwrap();
+        let compressed = writer.finish().unwrap();
+
+        let reader = read_ahead(decompress(io::Cursor::new(compressed)).unwrap());
+        assert_eq!(reader.lines().count(), 100_000);
+
+        let mut truncated = BgzfWriter::new(Vec::new());
+        truncated.write_all(text.as_bytes()).unwrap();
+        let mut truncated = truncated.finish().unwrap();
+        truncated.truncate(truncated.len() / 2);
+        let mut reader = read_ahead(decompress(io::Cursor::new(truncated)).unwrap());
+        assert!(reader.read_to_string(&mut String::new()).is_err());
+    }
+}
@@ -0,0 +1,623 @@
+//! Filter presets, field aliases and virtual fields loaded from a config source.
+//!
+//! A configuration is plain text with INI-style sections:
+//!
+//! ```text
+//! # Reusable whole filters
+//! [presets]
+//! high_impact = ANN[*].Annotation_Impact == "HIGH"
+//!
+//! # Short names for access paths
+//! [aliases]
+//! gene = ANN[0].Gene_Name
+//!
+//! # Named sub-expressions usable inside filters
+//! [fields]
+//! deep = DP >= 30
+//!
+//! # Indented lines continue the previous definition
+//! [presets]
+//! clinical = CLNSIG in ["Pathogenic", "Likely_pathogenic"]
+//!     && ANN[*].Annotation_Impact == "HIGH"
+//!     && QUAL >= 30
+//!
+//! # Presets may also be written as in a preset library
+//! @rare := AF < 0.01
+//! ```
+//!
+//! Presets are held in a [`PresetLibrary`], the same library
+//! [`FilterEngine::with_preset`](crate::FilterEngine::with_preset) fills.
+//! [`FilterEngine::with_config`](crate::FilterEngine::with_config) makes a
+//! configuration's presets, aliases and fields available to every filter the
+//! engine parses:
+//!
+//! ```rust
+//! use vcf_filter::FilterEngine;
+//! use vcf_filter::config::FilterConfig;
+//!
+//! let config = FilterConfig::parse("[presets]\ndeep = DP >= 30").unwrap();
+//! let engine = FilterEngine::new("").unwrap().with_config(config);
+//!
+//! let row = "chr1\t100\t.\tA\tG\t50\tPASS\tDP=40";
+//! assert!(engine.evaluate("@deep && QUAL > 30", row).unwrap());
+//! ```
+//!
+//! Long-running services can wrap a config file in a [`ConfigWatcher`], which
+//! reloads it when the file changes and swaps the parsed configuration
+//! atomically. Evaluations that already hold a snapshot keep using it. An
+//! engine built with
+//! [`FilterEngine::with_config_watcher`](crate::FilterEngine::with_config_watcher)
+//! takes a fresh snapshot for every filter it parses, so a reload takes effect
+//! without rebuilding the engine.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock};
+use std::time::SystemTime;
+
+use crate::error::{Result, VcfFilterError};
+use crate::filter::{AccessPart, Expr, parse_error, parse_filter};
+use crate::presets::PresetLibrary;
+
+/// Parsed presets, aliases and virtual field definitions.
+#[derive(Debug, Clone, Default)]
+pub struct FilterConfig {
+    /// Named whole filters, fully expanded.
+    presets: PresetLibrary,
+    /// Alias name to access path.
+    aliases: HashMap<String, Vec<AccessPart>>,
+    /// Virtual field name to its (expanded) expression.
+    fields: HashMap<String, Expr>,
+}
+
+/// Join indented continuation lines onto the definition they belong to.
+///
+/// Returns trimmed, non-empty, non-comment lines with their zero-based
+/// starting line number. Comment lines inside a continued definition are
+/// skipped.
+pub(crate) fn logical_lines(text: &str) -> Vec<(usize, String)> {
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    let mut continuable = false;
+
+    for (line_no, raw) in text.lines().enumerate() {
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let indented = raw.starts_with([' ', '\t']);
+        match lines.last_mut() {
+            Some((_, previous)) if indented && continuable => {
+                previous.push('\n');
+                previous.push_str(line);
+            }
+            _ => {
+                continuable = !line.starts_with('[');
+                lines.push((line_no, line.to_string()));
+            }
+        }
+    }
+
+    lines
+}
+
+/// Which section of the config file a line belongs to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Section {
+    Presets,
+    Aliases,
+    Fields,
+}
+
+impl FilterConfig {
+    /// Parse a configuration from text.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use vcf_filter::config::FilterConfig;
+    ///
+    /// let config = FilterConfig::parse("[aliases]\ngene = ANN[0].Gene_Name").unwrap();
+    /// let expr = config.compile(r#"gene == "BRCA1""#).unwrap();
+    /// assert_eq!(
+    ///     expr,
+    ///     vcf_filter::filter::parse_filter(r#"ANN[0].Gene_Name == "BRCA1""#).unwrap()
+    /// );
+    /// ```
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut section = None;
+        let mut raw_presets = Vec::new();
+        let mut raw_fields = Vec::new();
+        let mut aliases = HashMap::new();
+
+        for (line_no, line) in logical_lines(text) {
+            let line = line.as_str();
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = Some(match name.trim() {
+                    "presets" => Section::Presets,
+                    "aliases" => Section::Aliases,
+                    "fields" => Section::Fields,
+                    other => {
+                        return Err(VcfFilterError::ConfigError(format!(
+                            "line {}: unknown section [{}]",
+                            line_no + 1,
+                            other
+                        )));
+                    }
+                });
+                continue;
+            }
+
+            // Presets may use the `@name := expression` form of a preset library
+            let definition = match line.strip_prefix('@') {
+                Some(rest) if section == Some(Section::Presets) => rest.split_once(":="),
+                _ => line.split_once('='),
+            };
+            let (name, body) = definition.ok_or_else(|| {
+                VcfFilterError::ConfigError(format!(
+                    "line {}: expected `name = expression`",
+                    line_no + 1
+                ))
+            })?;
+            let name = name.trim().to_string();
+            let expr = parse_filter(body.trim()).map_err(parse_error)?;
+
+            match section {
+                Some(Section::Presets) => raw_presets.push((name, expr)),
+                Some(Section::Fields) => raw_fields.push((name, expr)),
+                Some(Section::Aliases) => match expr {
+                    Expr::Var(parts) => {
+                        aliases.insert(name, parts);
+                    }
+                    _ => {
+                        return Err(VcfFilterError::ConfigError(format!(
+                            "line {}: alias {} must be a field path",
+                            line_no + 1,
+                            name
+                        )));
+                    }
+                },
+                None => {
+                    return Err(VcfFilterError::ConfigError(format!(
+                        "line {}: definition outside of a section",
+                        line_no + 1
+                    )));
+                }
+            }
+        }
+
+        let mut config = FilterConfig {
+            aliases,
+            ..Default::default()
+        };
+
+        // Virtual fields may reference each other; expand them in dependency order.
+        let raw_fields: HashMap<String, Expr> = raw_fields.into_iter().collect();
+        for name in raw_fields.keys() {
+            let expanded = config.expand_field(name, &raw_fields, &mut Vec::new())?;
+            config.fields.insert(name.clone(), expanded);
+        }
+
+        for (name, expr) in raw_presets {
+            let expanded = config.expand(&expr)?;
+            config.presets.define_expr(&name, &expanded)?;
+        }
+
+        Ok(config)
+    }
+
+    /// Get an expanded preset filter by name.
+    pub fn preset(&self, name: &str) -> Option<Arc<Expr>> {
+        self.presets.get(name)
+    }
+
+    /// Iterate over the preset names.
+    pub fn preset_names(&self) -> impl Iterator<Item = &str> {
+        self.presets.names()
+    }
+
+    /// Get the library holding the configuration's presets.
+    pub fn presets(&self) -> &PresetLibrary {
+        &self.presets
+    }
+
+    /// Get the access path an alias refers to.
+    pub fn alias(&self, name: &str) -> Option<&[AccessPart]> {
+        self.aliases.get(name).map(Vec::as_slice)
+    }
+
+    /// Get the expanded expression behind a virtual field.
+    pub fn field(&self, name: &str) -> Option<&Expr> {
+        self.fields.get(name)
+    }
+
+    /// Parse a filter string and expand aliases and virtual fields in it.
+    pub fn compile(&self, filter: &str) -> Result<Expr> {
+        let expr = parse_filter(filter).map_err(parse_error)?;
+        self.expand(&expr)
+    }
+
+    /// Expand aliases and virtual fields in an already parsed expression.
+    pub fn expand(&self, expr: &Expr) -> Result<Expr> {
+        self.expand_with(expr, &PresetLibrary::new())
+    }
+
+    /// Expand an expression, resolving `@name` references the configuration
+    /// does not define from `fallback`.
+    pub(crate) fn expand_with(&self, expr: &Expr, fallback: &PresetLibrary) -> Result<Expr> {
+        let expand = |expr: &Expr| self.expand_with(expr, fallback);
+        Ok(match expr {
+            Expr::Var(parts) => {
+                if let [AccessPart::Field(name)] = parts.as_slice()
+                    && let Some(field) = self.fields.get(name)
+                {
+                    field.clone()
+                } else {
+                    Expr::Var(self.expand_path(parts))
+                }
+            }
+            Expr::Exists(parts) => Expr::Exists(self.expand_path(parts)),
+            Expr::Binary(left, op, right) => Expr::Binary(
+                Box::new(expand(left)?),
+                op.clone(),
+                Box::new(expand(right)?),
+            ),
+            Expr::Unary(op, inner) => Expr::Unary(op.clone(), Box::new(expand(inner)?)),
+            Expr::All(inner) => Expr::All(Box::new(expand(inner)?)),
+            Expr::List(items) => Expr::List(items.iter().map(expand).collect::<Result<_>>()?),
+            Expr::Call(name, args) => Expr::Call(
+                name.clone(),
+                args.iter().map(expand).collect::<Result<_>>()?,
+            ),
+            Expr::Let(name, value, body) => {
+                // The bound name shadows any alias or field of the same name
+                let mut scoped = self.clone();
+                scoped.aliases.remove(name);
+                scoped.fields.remove(name);
+                Expr::Let(
+                    name.clone(),
+                    Box::new(expand(value)?),
+                    Box::new(scoped.expand_with(body, fallback)?),
+                )
+            }
+            Expr::Preset(name) => self
+                .presets
+                .get(name)
+                .or_else(|| fallback.get(name))
+                .map(|preset| (*preset).clone())
+                .ok_or_else(|| VcfFilterError::ConfigError(format!("unknown preset: @{}", name)))?,
+            Expr::Number(_) | Expr::String(_) | Expr::Bool(_) => expr.clone(),
+        })
+    }
+
+    /// Replace a leading alias in an access path with its target path.
+    fn expand_path(&self, parts: &[AccessPart]) -> Vec<AccessPart> {
+        match parts.first() {
+            Some(AccessPart::Field(name)) if self.aliases.contains_key(name) => {
+                let mut expanded = self.aliases[name].clone();
+                expanded.extend(parts[1..].iter().cloned());
+                expanded
+            }
+            _ => parts.to_vec(),
+        }
+    }
+
+    /// Expand a virtual field definition, resolving references to other fields.
+    fn expand_field(
+        &self,
+        name: &str,
+        raw_fields: &HashMap<String, Expr>,
+        stack: &mut Vec<String>,
+    ) -> Result<Expr> {
+        if stack.iter().any(|s| s == name) {
+            stack.push(name.to_string());
+            return Err(VcfFilterError::ConfigError(format!(
+                "circular field definition: {}",
+                stack.join(" -> ")
+            )));
+        }
+
+        stack.push(name.to_string());
+        let mut scratch = self.clone();
+        for dep in referenced_names(&raw_fields[name]) {
+            if raw_fields.contains_key(&dep) {
+                let expanded = self.expand_field(&dep, raw_fields, stack)?;
+                scratch.fields.insert(dep, expanded);
+            }
+        }
+        stack.pop();
+
+        scratch.expand(&raw_fields[name])
+    }
+}
+
+/// Collect single-part variable names referenced by an expression.
+fn referenced_names(expr: &Expr) -> Vec<String> {
+    let mut names = Vec::new();
+    collect_names(expr, &mut names);
+    names
+}
+
+fn collect_names(expr: &Expr, names: &mut Vec<String>) {
+    if let Expr::Var(parts) = expr
+        && let [AccessPart::Field(name)] = parts.as_slice()
+    {
+        names.push(name.clone());
+    }
+    for child in expr.children() {
+        collect_names(child, names);
+    }
+}
+
+/// Reloads a [`FilterConfig`] from a file when it changes.
+///
+/// Readers call [`ConfigWatcher::config`] once per evaluation batch to get a
+/// snapshot; a reload swaps the shared configuration without disturbing
+/// snapshots that are still in use. Share a watcher with engines through
+/// [`FilterEngine::with_config_watcher`](crate::FilterEngine::with_config_watcher).
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use vcf_filter::FilterEngine;
+/// use vcf_filter::config::ConfigWatcher;
+///
+/// let path = std::env::temp_dir().join(format!("vcf_filter_watch_{}.ini", std::process::id()));
+/// std::fs::write(&path, "[presets]\ngood = QUAL > 30").unwrap();
+/// let watcher = Arc::new(ConfigWatcher::new(&path).unwrap());
+/// let engine = FilterEngine::new("").unwrap().with_config_watcher(Arc::clone(&watcher));
+///
+/// let row = "chr1\t100\t.\tA\tG\t50\tPASS\t.";
+/// assert!(engine.evaluate("@good", row).unwrap());
+///
+/// watcher.update("[presets]\ngood = QUAL > 60").unwrap();
+/// assert!(!engine.evaluate("@good", row).unwrap());
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct ConfigWatcher {
+    path: PathBuf,
+    current: RwLock<Arc<FilterConfig>>,
+    modified: Mutex<Option<SystemTime>>,
+}
+
+impl ConfigWatcher {
+    /// Load the configuration at `path` and start watching it.
+    pub fn new(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let modified = file_modified(&path);
+        let config = load_file(&path)?;
+        Ok(Self {
+            path,
+            current: RwLock::new(Arc::new(config)),
+            modified: Mutex::new(modified),
+        })
+    }
+
+    /// Get a snapshot of the current configuration.
+    pub fn config(&self) -> Arc<FilterConfig> {
+        Arc::clone(&self.current.read().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Get an expanded preset from the current configuration.
+    pub fn preset(&self, name: &str) -> Option<Arc<Expr>> {
+        self.config().preset(name)
+    }
+
+    /// Reload the configuration file unconditionally.
+    ///
+    /// If the new configuration fails to parse, the previous one stays active.
+    pub fn reload(&self) -> Result<()> {
+        let modified = file_modified(&self.path);
+        let config = load_file(&self.path)?;
+        self.swap(config);
+        *self.modified() = modified;
+        Ok(())
+    }
+
+    /// Reload the configuration if the file's modification time changed.
+    ///
+    /// Returns `true` if a new configuration was loaded.
+    pub fn reload_if_changed(&self) -> Result<bool> {
+        let modified = file_modified(&self.path);
+        if modified == *self.modified() {
+            return Ok(false);
+        }
+        self.reload()?;
+        Ok(true)
+    }
+
+    /// Replace the active configuration with one parsed from `text`.
+    ///
+    /// Useful when the configuration arrives from a source other than the
+    /// watched file (e.g., a control-plane message).
+    pub fn update(&self, text: &str) -> Result<()> {
+        let config = FilterConfig::parse(text)?;
+        self.swap(config);
+        Ok(())
+    }
+
+    /// Make `config` the active configuration.
+    ///
+    /// A writer that panicked cannot leave a half-written configuration
+    /// behind, so a poisoned lock is safe to reuse.
+    fn swap(&self, config: FilterConfig) {
+        *self.current.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(config);
+    }
+
+    fn modified(&self) -> MutexGuard<'_, Option<SystemTime>> {
+        self.modified.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn file_modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn load_file(path: &Path) -> Result<FilterConfig> {
+    let text = std::fs::read_to_string(path).map_err(|e| {
+        VcfFilterError::ConfigError(format!("cannot read {}: {}", path.display(), e))
+    })?;
+    FilterConfig::parse(&text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+# Example configuration
+[presets]
+high_impact = ANN[*].Annotation_Impact == "HIGH"
+deep_high = deep && high_impact_field
+
+[aliases]
+gene = ANN[0].Gene_Name
+
+[fields]
+deep = DP >= 30
+high_impact_field = ANN[*].Annotation_Impact == "HIGH"
+"#;
+
+    fn parse(filter: &str) -> Expr {
+        parse_filter(filter).unwrap()
+    }
+
+    #[test]
+    fn test_parse_sections() {
+        let config = FilterConfig::parse(CONFIG).unwrap();
+        assert!(config.preset("high_impact").is_some());
+        assert_eq!(
+            config.alias("gene").unwrap(),
+            &[
+                AccessPart::Field("ANN".to_string()),
+                AccessPart::Index(0),
+                AccessPart::Field("Gene_Name".to_string()),
+            ]
+        );
+        assert_eq!(config.field("deep"), Some(&parse("DP >= 30")));
+    }
+
+    #[test]
+    fn test_presets_expand_fields() {
+        let config = FilterConfig::parse(CONFIG).unwrap();
+        assert_eq!(
+            *config.preset("deep_high").unwrap(),
+            parse(r#"DP >= 30 && ANN[*].Annotation_Impact == "HIGH""#)
+        );
+    }
+
+    #[test]
+    fn test_compile_expands_aliases() {
+        let config = FilterConfig::parse(CONFIG).unwrap();
+        assert_eq!(
+            config.compile(r#"gene == "BRCA1" && deep"#).unwrap(),
+            parse(r#"ANN[0].Gene_Name == "BRCA1" && DP >= 30"#)
+        );
+        assert_eq!(
+            config.compile("exists(gene)").unwrap(),
+            parse("exists(ANN[0].Gene_Name)")
+        );
+    }
+
+    #[test]
+    fn test_let_shadows_aliases_and_fields() {
+        let config = FilterConfig::parse(CONFIG).unwrap();
+        assert_eq!(
+            config
+                .compile("let deep = 1 in deep == 1 && gene == 2")
+                .unwrap(),
+            parse("let deep = 1 in deep == 1 && ANN[0].Gene_Name == 2")
+        );
+        assert_eq!(
+            config.compile("let x = deep in x").unwrap(),
+            parse("let x = (DP >= 30) in x")
+        );
+    }
+
+    #[test]
+    fn test_preset_references() {
+        let config =
+            FilterConfig::parse("[presets]\nq = QUAL > 30\nq_deep = @q && DP > 10\n").unwrap();
+        assert_eq!(
+            *config.preset("q_deep").unwrap(),
+            parse("(QUAL > 30) && DP > 10")
+        );
+        assert_eq!(
+            config.compile("@q_deep || @q").unwrap(),
+            parse("(QUAL > 30 && DP > 10) || QUAL > 30")
+        );
+        assert!(config.compile("@nope").is_err());
+    }
+
+    #[test]
+    fn test_preset_library_syntax() {
+        let config =
+            FilterConfig::parse("[presets]\n@q := QUAL > 30\nq_deep = @q && DP == 10\n").unwrap();
+        assert_eq!(*config.preset("q").unwrap(), parse("QUAL > 30"));
+        assert_eq!(
+            *config.presets().get("q_deep").unwrap(),
+            parse("(QUAL > 30) && DP == 10")
+        );
+        // Preset names follow the preset library's rules
+        assert!(FilterConfig::parse("[presets]\n@bad name := QUAL > 1").is_err());
+    }
+
+    #[test]
+    fn test_nested_fields() {
+        let config = FilterConfig::parse("[fields]\na = b && QUAL > 1\nb = DP > 2").unwrap();
+        assert_eq!(config.field("a"), Some(&parse("DP > 2 && QUAL > 1")));
+    }
+
+    #[test]
+    fn test_circular_fields_rejected() {
+        let err = FilterConfig::parse("[fields]\na = b\nb = a").unwrap_err();
+        assert!(matches!(err, VcfFilterError::ConfigError(_)));
+    }
+
+    #[test]
+    fn test_multi_line_definitions() {
+        let text = "[presets]\nclinical = CLNSIG == \"Pathogenic\"\n    # high impact only\n    && ANN[*]\n\t.Annotation_Impact == \"HIGH\"\nq = QUAL > 30\n";
+        let config = FilterConfig::parse(text).unwrap();
+        assert_eq!(
+            *config.preset("clinical").unwrap(),
+            parse(r#"CLNSIG == "Pathogenic" && ANN[*].Annotation_Impact == "HIGH""#)
+        );
+        assert_eq!(*config.preset("q").unwrap(), parse("QUAL > 30"));
+    }
+
+    #[test]
+    fn test_invalid_config_lines() {
+        assert!(FilterConfig::parse("x = DP > 1").is_err());
+        assert!(FilterConfig::parse("[unknown]").is_err());
+        assert!(FilterConfig::parse("[aliases]\ng = DP > 1").is_err());
+        assert!(FilterConfig::parse("[presets]\nbroken").is_err());
+    }
+
+    #[test]
+    fn test_watcher_reload() {
+        let path =
+            std::env::temp_dir().join(format!("vcf_filter_config_test_{}.ini", std::process::id()));
+        std::fs::write(&path, "[presets]\nq = QUAL > 30").unwrap();
+
+        let watcher = ConfigWatcher::new(&path).unwrap();
+        let snapshot = watcher.config();
+        assert_eq!(*watcher.preset("q").unwrap(), parse("QUAL > 30"));
+
+        std::fs::write(&path, "[presets]\nq = QUAL > 50").unwrap();
+        watcher.reload().unwrap();
+        assert_eq!(*watcher.preset("q").unwrap(), parse("QUAL > 50"));
+        // Snapshots taken before the reload are unaffected
+        assert_eq!(*snapshot.preset("q").unwrap(), parse("QUAL > 30"));
+
+        // A broken config keeps the previous one active
+        std::fs::write(&path, "[presets]\nq = QUAL >").unwrap();
+        assert!(watcher.reload().is_err());
+        assert_eq!(*watcher.preset("q").unwrap(), parse("QUAL > 50"));
+
+        watcher.update("[presets]\nq = DP > 1").unwrap();
+        assert_eq!(*watcher.preset("q").unwrap(), parse("DP > 1"));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,315 @@
+//! Golden-dataset conformance corpus.
+//!
+//! Bundles miniature VCFs modelled on the output of commonly used tools
+//! (GATK, DeepVariant, SnpEff, VEP, ClinVar, Manta) together with filters and
+//! their expected per-row outcomes. Downstream packagers can call [`run`] to
+//! verify that a platform build evaluates filters exactly like upstream.
+//!
+//! # Example
+//!
+//! ```rust
+//! let report = vcf_filter::conformance::run();
+//! assert!(report.all_passed(), "{:?}", report.failures().collect::<Vec<_>>());
+//! ```
+
+use crate::FilterEngine;
+
+/// A bundled miniature VCF produced in the style of a specific tool.
+#[derive(Debug, Clone, Copy)]
+pub struct CorpusFile {
+    /// Short name of the corpus file (e.g., "gatk").
+    pub name: &'static str,
+    /// The tool whose output this file models.
+    pub tool: &'static str,
+    /// Full VCF text including header lines.
+    pub vcf: &'static str,
+}
+
+/// A single filter with its expected outcome for every data row of a corpus file.
+#[derive(Debug, Clone, Copy)]
+pub struct ConformanceCase {
+    /// Unique case name.
+    pub name: &'static str,
+    /// Name of the corpus file the case runs against.
+    pub corpus: &'static str,
+    /// The filter expression to evaluate.
+    pub filter: &'static str,
+    /// Expected result for each data row, in file order.
+    pub expected: &'static [bool],
+}
+
+/// Outcome of running one conformance case.
+#[derive(Debug, Clone)]
+pub struct CaseResult {
+    /// Name of the case that was run.
+    pub name: &'static str,
+    /// Expected result for each data row.
+    pub expected: Vec<bool>,
+    /// Actual result for each data row (empty if an error occurred).
+    pub actual: Vec<bool>,
+    /// Error message if the engine failed to parse or evaluate.
+    pub error: Option<String>,
+}
+
+impl CaseResult {
+    /// Returns true if the case produced exactly the expected outcomes.
+    pub fn passed(&self) -> bool {
+        self.error.is_none() && self.expected == self.actual
+    }
+}
+
+/// Results of running the full conformance corpus.
+#[derive(Debug, Clone)]
+pub struct ConformanceReport {
+    /// Per-case results, in corpus order.
+    pub results: Vec<CaseResult>,
+}
+
+impl ConformanceReport {
+    /// Returns true if every case passed.
+    pub fn all_passed(&self) -> bool {
+        self.results.iter().all(CaseResult::passed)
+    }
+
+    /// Iterate over the cases that did not pass.
+    pub fn failures(&self) -> impl Iterator<Item = &CaseResult> {
+        self.results.iter().filter(|r| !r.passed())
+    }
+}
+
+const CORPUS: &[CorpusFile] = &[
+    CorpusFile {
+        name: "gatk",
+        tool: "GATK HaplotypeCaller",
+        vcf: include_str!("../samples/conformance/gatk.vcf"),
+    },
+    CorpusFile {
+        name: "deepvariant",
+        tool: "DeepVariant",
+        vcf: include_str!("../samples/conformance/deepvariant.vcf"),
+    },
+    CorpusFile {
+        name: "snpeff",
+        tool: "SnpEff",
+        vcf: include_str!("../samples/conformance/snpeff.vcf"),
+    },
+    CorpusFile {
+        name: "vep",
+        tool: "Ensembl VEP",
+        vcf: include_str!("../samples/conformance/vep.vcf"),
+    },
+    CorpusFile {
+        name: "clinvar",
+        tool: "ClinVar",
+        vcf: include_str!("../samples/conformance/clinvar.vcf"),
+    },
+    CorpusFile {
+        name: "manta",
+        tool: "Manta",
+        vcf: include_str!("../samples/conformance/manta.vcf"),
+    },
+];
+
+const CASES: &[ConformanceCase] = &[
+    ConformanceCase {
+        name: "gatk_hard_filter",
+        corpus: "gatk",
+        filter: "QD >= 2 && FS <= 60 && MQ >= 40",
+        expected: &[true, true, true, false],
+    },
+    ConformanceCase {
+        name: "gatk_pass_in_dbsnp",
+        corpus: "gatk",
+        filter: r#"FILTER == "PASS" && exists(DB)"#,
+        expected: &[true, false, true, false],
+    },
+    ConformanceCase {
+        name: "gatk_sample_quality",
+        corpus: "gatk",
+        filter: "FORMAT.DP >= 30 && FORMAT.GQ >= 90",
+        expected: &[true, false, true, true],
+    },
+    ConformanceCase {
+        name: "gatk_hom_alt",
+        corpus: "gatk",
+        filter: r#"GT == "1/1""#,
+        expected: &[false, false, true, false],
+    },
+    ConformanceCase {
+        name: "gatk_af_consistent",
+        corpus: "gatk",
+        filter: "af_consistent()",
+        expected: &[true, true, true, true],
+    },
+    ConformanceCase {
+        name: "deepvariant_confident_pass",
+        corpus: "deepvariant",
+        filter: r#"FILTER == "PASS" && GQ >= 40"#,
+        expected: &[false, false, true],
+    },
+    ConformanceCase {
+        name: "deepvariant_not_refcall",
+        corpus: "deepvariant",
+        filter: r#"FILTER != "RefCall""#,
+        expected: &[false, true, true],
+    },
+    ConformanceCase {
+        name: "deepvariant_confident_genotype",
+        corpus: "deepvariant",
+        filter: "gt_confidence() >= 40",
+        expected: &[false, false, true],
+    },
+    ConformanceCase {
+        name: "snpeff_any_high_impact",
+        corpus: "snpeff",
+        filter: r#"ANN[*].Annotation_Impact == "HIGH""#,
+        expected: &[true, false, false],
+    },
+    ConformanceCase {
+        name: "snpeff_first_gene",
+        corpus: "snpeff",
+        filter: r#"ANN[0].Gene_Name == "TP53""#,
+        expected: &[false, true, false],
+    },
+    ConformanceCase {
+        name: "snpeff_lof_present",
+        corpus: "snpeff",
+        filter: r#"exists(LOF) && LOF[0].Gene_Name contains "BRCA1""#,
+        expected: &[true, false, false],
+    },
+    ConformanceCase {
+        name: "vep_csq_gene",
+        corpus: "vep",
+        filter: r#"FILTER == "PASS" && CSQ contains "BRAF""#,
+        expected: &[true, false, false],
+    },
+    ConformanceCase {
+        name: "clinvar_pathogenic",
+        corpus: "clinvar",
+        filter: r#"CLNSIG == "Pathogenic""#,
+        expected: &[true, false, false],
+    },
+    ConformanceCase {
+        name: "clinvar_cancer_disease",
+        corpus: "clinvar",
+        filter: r#"CLNDN contains "cancer""#,
+        expected: &[true, true, false],
+    },
+    ConformanceCase {
+        name: "clinvar_missing_qual",
+        corpus: "clinvar",
+        filter: "!(QUAL > 0)",
+        expected: &[true, true, true],
+    },
+    ConformanceCase {
+        name: "manta_deletion_end",
+        corpus: "manta",
+        filter: r#"SVTYPE == "DEL" && END > 1867000"#,
+        expected: &[true, false, false],
+    },
+    ConformanceCase {
+        name: "manta_precise_pass",
+        corpus: "manta",
+        filter: r#"FILTER == "PASS" && !exists(IMPRECISE)"#,
+        expected: &[true, false, true],
+    },
+];
+
+/// Get the bundled corpus files.
+pub fn corpus() -> &'static [CorpusFile] {
+    CORPUS
+}
+
+/// Get the bundled conformance cases.
+pub fn cases() -> &'static [ConformanceCase] {
+    CASES
+}
+
+/// Split VCF text into its header (all `#` lines) and data rows.
+fn split_vcf(vcf: &str) -> (String, Vec<&str>) {
+    let mut header_lines = Vec::new();
+    let mut rows = Vec::new();
+
+    for line in vcf.lines() {
+        if line.starts_with('#') {
+            header_lines.push(line);
+        } else if !line.is_empty() {
+            rows.push(line);
+        }
+    }
+
+    (header_lines.join("\n"), rows)
+}
+
+/// Run a single conformance case.
+pub fn run_case(case: &ConformanceCase) -> CaseResult {
+    let mut result = CaseResult {
+        name: case.name,
+        expected: case.expected.to_vec(),
+        actual: Vec::new(),
+        error: None,
+    };
+
+    let Some(file) = CORPUS.iter().find(|f| f.name == case.corpus) else {
+        result.error = Some(format!("Unknown corpus file: {}", case.corpus));
+        return result;
+    };
+
+    let (header, rows) = split_vcf(file.vcf);
+    let outcome = FilterEngine::new(&header).and_then(|engine| {
+        let expr = engine.parse_filter(case.filter)?;
+        rows.iter()
+            .map(|row| engine.evaluate_parsed(&expr, &engine.parse_row(row)?))
+            .collect::<crate::Result<Vec<bool>>>()
+    });
+
+    match outcome {
+        Ok(actual) => result.actual = actual,
+        Err(e) => result.error = Some(e.to_string()),
+    }
+
+    result
+}
+
+/// Run every bundled conformance case.
+pub fn run() -> ConformanceReport {
+    ConformanceReport {
+        results: CASES.iter().map(run_case).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conformance_corpus_passes() {
+        let report = run();
+        let failures: Vec<_> = report.failures().collect();
+        assert!(failures.is_empty(), "Conformance failures: {:#?}", failures);
+    }
+
+    #[test]
+    fn test_every_corpus_file_has_cases() {
+        for file in corpus() {
+            assert!(
+                cases().iter().any(|c| c.corpus == file.name),
+                "Corpus file {} has no cases",
+                file.name
+            );
+        }
+    }
+
+    #[test]
+    fn test_unknown_corpus_is_reported() {
+        let case = ConformanceCase {
+            name: "missing",
+            corpus: "nope",
+            filter: "QUAL > 0",
+            expected: &[],
+        };
+        let result = run_case(&case);
+        assert!(!result.passed());
+        assert!(result.error.is_some());
+    }
+}
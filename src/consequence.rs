@@ -0,0 +1,160 @@
+//! Sequence Ontology consequence severity: `ANN[*].Annotation severity_at_least "missense_variant"`.
+//!
+//! SnpEff (`ANN`) and VEP (`CSQ`) both describe effects with Sequence
+//! Ontology terms, but a filter that lists every term worse than a missense
+//! change is long and easy to get wrong. `x severity_at_least "term"` holds
+//! if `x` is as severe as `term` or more, using Ensembl VEP's ranking of
+//! consequence terms. SnpEff-specific names are ranked as their closest VEP
+//! term (`conservative_inframe_deletion` as `inframe_deletion`,
+//! `intergenic_region` as `intergenic_variant`, ...).
+//!
+//! Combined effects such as `missense_variant&splice_region_variant` rank as
+//! their most severe known term. Unknown terms rank below every known one,
+//! so they never satisfy a comparison.
+//!
+//! ```rust
+//! use vcf_filter::consequence::at_least;
+//!
+//! assert!(at_least("stop_gained", "missense_variant"));
+//! assert!(at_least("synonymous_variant&splice_region_variant", "splice_region_variant"));
+//! assert!(!at_least("intron_variant", "missense_variant"));
+//! ```
+
+/// Consequence terms from most to least severe (Ensembl VEP order).
+pub const SEVERITY_ORDER: &[&str] = &[
+    "transcript_ablation",
+    "splice_acceptor_variant",
+    "splice_donor_variant",
+    "stop_gained",
+    "frameshift_variant",
+    "stop_lost",
+    "start_lost",
+    "transcript_amplification",
+    "feature_elongation",
+    "feature_truncation",
+    "inframe_insertion",
+    "inframe_deletion",
+    "missense_variant",
+    "protein_altering_variant",
+    "splice_donor_5th_base_variant",
+    "splice_region_variant",
+    "splice_donor_region_variant",
+    "splice_polypyrimidine_tract_variant",
+    "incomplete_terminal_codon_variant",
+    "start_retained_variant",
+    "stop_retained_variant",
+    "synonymous_variant",
+    "coding_sequence_variant",
+    "mature_miRNA_variant",
+    "5_prime_UTR_variant",
+    "3_prime_UTR_variant",
+    "non_coding_transcript_exon_variant",
+    "intron_variant",
+    "NMD_transcript_variant",
+    "non_coding_transcript_variant",
+    "coding_transcript_variant",
+    "upstream_gene_variant",
+    "downstream_gene_variant",
+    "TFBS_ablation",
+    "TFBS_amplification",
+    "TF_binding_site_variant",
+    "regulatory_region_ablation",
+    "regulatory_region_amplification",
+    "regulatory_region_variant",
+    "intergenic_variant",
+    "sequence_variant",
+];
+
+/// SnpEff terms and the VEP term they rank as.
+const ALIASES: &[(&str, &str)] = &[
+    ("chromosome_number_variation", "transcript_ablation"),
+    ("exon_loss_variant", "transcript_ablation"),
+    ("feature_ablation", "transcript_ablation"),
+    ("gene_fusion", "transcript_ablation"),
+    ("bidirectional_gene_fusion", "transcript_ablation"),
+    ("rearranged_at_DNA_level", "transcript_ablation"),
+    ("rare_amino_acid_variant", "missense_variant"),
+    ("conservative_inframe_insertion", "inframe_insertion"),
+    ("disruptive_inframe_insertion", "inframe_insertion"),
+    ("conservative_inframe_deletion", "inframe_deletion"),
+    ("disruptive_inframe_deletion", "inframe_deletion"),
+    ("5_prime_UTR_truncation", "feature_truncation"),
+    ("3_prime_UTR_truncation", "feature_truncation"),
+    ("splice_branch_variant", "splice_region_variant"),
+    ("initiator_codon_variant", "start_retained_variant"),
+    (
+        "5_prime_UTR_premature_start_codon_gain_variant",
+        "5_prime_UTR_variant",
+    ),
+    ("miRNA", "mature_miRNA_variant"),
+    (
+        "non_coding_exon_variant",
+        "non_coding_transcript_exon_variant",
+    ),
+    ("intragenic_variant", "coding_transcript_variant"),
+    ("intergenic_region", "intergenic_variant"),
+];
+
+/// Get the rank of a single consequence term; 0 is the most severe.
+fn term_rank(term: &str) -> Option<usize> {
+    let term = ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == term)
+        .map_or(term, |(_, canonical)| canonical);
+    SEVERITY_ORDER.iter().position(|known| *known == term)
+}
+
+/// Get the rank of a consequence, the most severe of its `&`-joined terms.
+///
+/// Lower ranks are more severe; unknown terms have no rank.
+pub fn rank(consequence: &str) -> Option<usize> {
+    consequence
+        .split('&')
+        .filter_map(|term| term_rank(term.trim()))
+        .min()
+}
+
+/// Whether a term is known, directly or as a SnpEff alias.
+pub fn is_known(term: &str) -> bool {
+    rank(term).is_some()
+}
+
+/// Whether `consequence` is at least as severe as `threshold`.
+///
+/// False if either has no known term.
+pub fn at_least(consequence: &str, threshold: &str) -> bool {
+    match (rank(consequence), rank(threshold)) {
+        (Some(rank), Some(threshold)) => rank <= threshold,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_severity_ranking() {
+        assert!(at_least("frameshift_variant", "missense_variant"));
+        assert!(at_least("missense_variant", "missense_variant"));
+        assert!(!at_least("synonymous_variant", "missense_variant"));
+        assert!(!at_least("missense_variant", "unknown_term"));
+        assert!(!at_least("unknown_term", "intergenic_variant"));
+    }
+
+    #[test]
+    fn test_snpeff_terms() {
+        assert_eq!(
+            rank("disruptive_inframe_deletion"),
+            rank("inframe_deletion")
+        );
+        assert_eq!(rank("intergenic_region"), rank("intergenic_variant"));
+        assert!(at_least("exon_loss_variant", "stop_gained"));
+        assert_eq!(
+            rank("intron_variant&splice_region_variant&bogus"),
+            rank("splice_region_variant")
+        );
+        assert!(is_known("5_prime_UTR_premature_start_codon_gain_variant"));
+        assert!(!is_known("Missense_Variant"));
+    }
+}
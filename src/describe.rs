@@ -0,0 +1,213 @@
+//! Listing the fields a filter can reference, for autocomplete and validation.
+//!
+//! [`FilterEngine::describe_fields`] returns the built-in columns, the
+//! header's INFO fields (with structured subfields), engine virtual fields
+//! and the header's FORMAT keys, each with the path to write in a filter:
+//!
+//! ```rust
+//! use vcf_filter::FilterEngine;
+//! use vcf_filter::describe::FieldKind;
+//!
+//! let header = concat!(
+//!     "##INFO=<ID=ANN,Number=.,Type=String,Description=\"Functional annotations: 'Allele | Annotation | Gene_Name'\">\n",
+//!     "##FORMAT=<ID=GQ,Number=1,Type=Integer,Description=\"Genotype quality\">",
+//! );
+//! let engine = FilterEngine::new(header).unwrap();
+//! let fields = engine.describe_fields();
+//!
+//! let ann = fields.iter().find(|f| f.name == "ANN").unwrap();
+//! assert_eq!(ann.kind, FieldKind::Info);
+//! assert_eq!(ann.subfields, ["Allele", "Annotation", "Gene_Name"]);
+//!
+//! let gq = fields.iter().find(|f| f.kind == FieldKind::Format).unwrap();
+//! assert_eq!(gq.path(), "FORMAT.GQ");
+//! ```
+
+use crate::FilterEngine;
+use crate::header::{InfoField, InfoNumber, InfoType};
+
+/// Where a field's values come from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    /// A fixed VCF column (`CHROM`, `POS`, ...).
+    Builtin,
+    /// A field declared by a ##INFO line or registered on the engine.
+    Info,
+    /// A field the engine adds to rows (interval columns, `INHERITANCE`).
+    Virtual,
+    /// A per-sample field declared by a ##FORMAT line.
+    Format,
+}
+
+/// A field a filter can reference.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDescription {
+    /// The field name (e.g., "DP").
+    pub name: String,
+    /// Where the field's values come from.
+    pub kind: FieldKind,
+    /// The declared number of values; `None` for virtual fields.
+    pub number: Option<InfoNumber>,
+    /// The declared type; `None` for virtual fields.
+    pub field_type: Option<InfoType>,
+    /// The header description, or a fixed one for built-in columns.
+    pub description: String,
+    /// Subfield names of a structured field such as ANN; empty otherwise.
+    pub subfields: Vec<String>,
+}
+
+impl FieldDescription {
+    /// How a filter refers to the field: `DP`, or `FORMAT.GQ` for FORMAT keys.
+    pub fn path(&self) -> String {
+        match self.kind {
+            FieldKind::Format => format!("FORMAT.{}", self.name),
+            _ => self.name.clone(),
+        }
+    }
+
+    fn declared(kind: FieldKind, field: &InfoField) -> Self {
+        Self {
+            name: field.id.clone(),
+            kind,
+            number: Some(field.number.clone()),
+            field_type: Some(field.field_type.clone()),
+            description: field.description.clone(),
+            subfields: field.subfields.clone().unwrap_or_default(),
+        }
+    }
+}
+
+/// The fixed VCF columns, in column order.
+const BUILTIN_COLUMNS: &[(&str, InfoNumber, InfoType, &str)] = &[
+    (
+        "CHROM",
+        InfoNumber::Count(1),
+        InfoType::String,
+        "Chromosome",
+    ),
+    (
+        "POS",
+        InfoNumber::Count(1),
+        InfoType::Integer,
+        "1-based position",
+    ),
+    (
+        "ID",
+        InfoNumber::Variable,
+        InfoType::String,
+        "Variant identifiers",
+    ),
+    (
+        "REF",
+        InfoNumber::Count(1),
+        InfoType::String,
+        "Reference allele",
+    ),
+    (
+        "ALT",
+        InfoNumber::Variable,
+        InfoType::String,
+        "Alternate alleles",
+    ),
+    (
+        "QUAL",
+        InfoNumber::Count(1),
+        InfoType::Float,
+        "Phred-scaled quality",
+    ),
+    (
+        "FILTER",
+        InfoNumber::Variable,
+        InfoType::String,
+        "Filters applied",
+    ),
+];
+
+impl FilterEngine {
+    /// List the fields a filter can reference.
+    ///
+    /// Built-in columns come first in column order, then INFO fields,
+    /// virtual fields and FORMAT keys, each sorted by name.
+    pub fn describe_fields(&self) -> Vec<FieldDescription> {
+        let mut fields: Vec<FieldDescription> = BUILTIN_COLUMNS
+            .iter()
+            .map(|(name, number, field_type, description)| FieldDescription {
+                name: name.to_string(),
+                kind: FieldKind::Builtin,
+                number: Some(number.clone()),
+                field_type: Some(field_type.clone()),
+                description: description.to_string(),
+                subfields: Vec::new(),
+            })
+            .collect();
+
+        let mut info: Vec<&InfoField> = self.info_map().values().collect();
+        info.sort_by(|a, b| a.id.cmp(&b.id));
+        fields.extend(
+            info.into_iter()
+                .map(|field| FieldDescription::declared(FieldKind::Info, field)),
+        );
+
+        let mut virtual_fields: Vec<&String> = self.virtual_fields.iter().collect();
+        virtual_fields.sort();
+        virtual_fields.dedup();
+        fields.extend(virtual_fields.into_iter().map(|name| FieldDescription {
+            name: name.clone(),
+            kind: FieldKind::Virtual,
+            number: None,
+            field_type: None,
+            description: String::new(),
+            subfields: Vec::new(),
+        }));
+
+        let mut format: Vec<&InfoField> = self.format_map().values().collect();
+        format.sort_by(|a, b| a.id.cmp(&b.id));
+        fields.extend(
+            format
+                .into_iter()
+                .map(|field| FieldDescription::declared(FieldKind::Format, field)),
+        );
+        fields
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pedigree::Trio;
+
+    #[test]
+    fn test_describe_fields() {
+        let header = "##INFO=<ID=DP,Number=1,Type=Integer,Description=\"Depth\">\n\
+                      ##INFO=<ID=AF,Number=A,Type=Float,Description=\"Allele frequency\">\n\
+                      ##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">\n\
+                      #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tkid\tdad\tmom";
+        let engine = FilterEngine::new(header)
+            .unwrap()
+            .with_pedigree(&Trio::new("kid", "dad", "mom"))
+            .unwrap();
+
+        let fields = engine.describe_fields();
+        let paths: Vec<String> = fields.iter().map(FieldDescription::path).collect();
+        assert_eq!(
+            paths,
+            [
+                "CHROM",
+                "POS",
+                "ID",
+                "REF",
+                "ALT",
+                "QUAL",
+                "FILTER",
+                "AF",
+                "DP",
+                "INHERITANCE",
+                "FORMAT.GT"
+            ]
+        );
+        assert_eq!(fields[5].field_type, Some(InfoType::Float));
+        assert_eq!(fields[7].number, Some(InfoNumber::PerAltAllele));
+        assert_eq!(fields[9].kind, FieldKind::Virtual);
+        assert_eq!(fields[9].number, None);
+    }
+}
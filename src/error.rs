@@ -33,9 +33,36 @@ pub enum VcfFilterError {
         length: usize,
     },
 
+    /// A sandbox limit (size, depth, or time budget) was exceeded.
+    #[error("Limit exceeded: {0}")]
+    LimitExceeded(String),
+
+    /// Invalid filter configuration (presets, aliases, virtual fields).
+    #[error("Config error: {0}")]
+    ConfigError(String),
+
+    /// Invalid batch manifest or a failed batch job.
+    #[error("Batch error: {0}")]
+    BatchError(String),
+
+    /// Invalid BED/TSV interval annotation file.
+    #[error("Interval file error: {0}")]
+    IntervalError(String),
+
+    /// Failed to read or write a VCF file.
+    #[error("I/O error: {0}")]
+    IoError(String),
+
     /// Type mismatch during comparison.
     #[error("Type mismatch: cannot compare {left} with {right}")]
     TypeMismatch { left: String, right: String },
+
+    /// An error raised by one data row, quoting the row.
+    #[error("{source}\n  in row: {line}")]
+    InRow {
+        line: String,
+        source: Box<VcfFilterError>,
+    },
 }
 
 /// Result type alias for VCF filter operations.
@@ -2,12 +2,299 @@
 //!
 //! Evaluates parsed filter ASTs against VCF row data.
 
+use std::borrow::Cow;
+use std::time::Instant;
+
+use crate::chromosome::{self, ContigOrder};
+use crate::consequence;
 use crate::error::{Result, VcfFilterError};
 use crate::filter::{AccessPart, BinaryOp, Expr, UnaryOp};
-use crate::header::InfoMap;
+use crate::functions::{self, BUILTIN_FIELDS, FunctionRegistry};
+use crate::header::{InfoMap, InfoNumber, InfoType};
+use crate::intervals::BedLibrary;
 use crate::row::{VcfRow, get_all_annotation_subfields, get_annotation_subfield};
 use crate::value::Value;
 
+/// Shared state for evaluating an expression against a row.
+///
+/// Carries the header metadata plus any per-evaluation settings such as
+/// the time budget deadline.
+#[derive(Debug, Clone, Copy)]
+pub struct EvalContext<'a> {
+    /// The header metadata for resolving field types.
+    pub info_map: &'a InfoMap,
+    /// Evaluation fails with `LimitExceeded` once this instant has passed.
+    pub deadline: Option<Instant>,
+    /// How wildcard comparisons combine their per-element results.
+    pub quantifier: Quantifier,
+    /// The annotation a `[?(...)]` predicate is currently being evaluated against.
+    pub annotation: Option<AnnotationScope<'a>>,
+    /// The innermost `let` binding in scope.
+    pub bindings: Option<&'a Binding<'a>>,
+    /// User-registered functions available to `Expr::Call`.
+    pub functions: Option<&'a FunctionRegistry>,
+    /// Cached BED region sets for `in_bed(...)`.
+    pub regions: Option<&'a BedLibrary>,
+    /// Sample names in header column order, for `SAMPLE["name"]` access.
+    pub sample_names: &'a [String],
+    /// The zero-based ALT allele `[alt]` refers to, set during per-allele evaluation.
+    pub alt: Option<usize>,
+    /// Whether filters are evaluated per ALT allele, and how alleles combine.
+    pub allele_mode: AlleleMode,
+    /// Fail on unknown fields, out-of-range indexes and string/number comparisons.
+    pub strict: bool,
+    /// Fields added to rows outside the header, known to strict mode.
+    pub virtual_fields: &'a [String],
+    /// Propagate missing values through comparisons and logical operators.
+    pub three_valued: bool,
+    /// How comparisons match strings and numbers.
+    pub collation: Collation,
+    /// Header contigs ordering `CHROM` comparisons; natural order when unset.
+    pub contigs: Option<&'a ContigOrder>,
+    /// Number of function calls enclosing the expression being evaluated.
+    pub call_depth: usize,
+    /// Evaluation fails with `LimitExceeded` once calls nest deeper than this.
+    pub max_call_depth: Option<usize>,
+}
+
+/// A name bound by `let`, linked to the bindings of enclosing scopes.
+#[derive(Debug)]
+pub struct Binding<'a> {
+    /// The bound name.
+    pub name: &'a str,
+    /// The value, evaluated once when the binding is entered.
+    pub value: Value,
+    /// The enclosing binding, if any.
+    pub parent: Option<&'a Binding<'a>>,
+}
+
+impl Binding<'_> {
+    /// Find the innermost binding of `name`.
+    pub fn lookup(&self, name: &str) -> Option<&Value> {
+        let mut binding = Some(self);
+        while let Some(b) = binding {
+            if b.name == name {
+                return Some(&b.value);
+            }
+            binding = b.parent;
+        }
+        None
+    }
+}
+
+/// A single structured annotation in scope for predicate evaluation.
+///
+/// Bare names matching one of `subfields` resolve to the corresponding
+/// element of `values` instead of a row field.
+#[derive(Debug, Clone, Copy)]
+pub struct AnnotationScope<'a> {
+    /// Subfield names from the header, in annotation order.
+    pub subfields: &'a [String],
+    /// The annotation's values, indexed like `subfields`.
+    pub values: &'a [Value],
+}
+
+/// Whether a row passes a filter, with "could not be evaluated" kept apart from failing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The filter is true for the row.
+    Pass,
+    /// The filter is false for the row.
+    Fail,
+    /// A value the filter depends on is missing (three-valued logic only).
+    Unknown,
+}
+
+impl Outcome {
+    /// Classify the value a filter evaluated to.
+    ///
+    /// Only a literal `true` passes; a missing value is unknown under
+    /// three-valued logic and fails otherwise.
+    pub fn of(value: &Value, three_valued: bool) -> Self {
+        match value {
+            Value::Bool(true) => Outcome::Pass,
+            Value::Missing if three_valued => Outcome::Unknown,
+            _ => Outcome::Fail,
+        }
+    }
+}
+
+/// How comparisons match strings and numbers.
+///
+/// The default compares strings exactly and numbers to within `f64::EPSILON`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Collation {
+    /// Compare strings, including `contains` and `in`, ignoring case.
+    pub case_insensitive: bool,
+    /// Numbers this close compare equal (`==`, `<=`, `in`), and neither is
+    /// less than the other.
+    pub float_tolerance: f64,
+}
+
+impl Collation {
+    /// Whether two strings are equal under this collation.
+    fn strings_equal(&self, left: &str, right: &str) -> bool {
+        if self.case_insensitive {
+            left.to_lowercase() == right.to_lowercase()
+        } else {
+            left == right
+        }
+    }
+
+    /// Whether two numbers are equal under this collation.
+    fn numbers_equal(&self, left: f64, right: f64) -> bool {
+        let difference = (left - right).abs();
+        difference < f64::EPSILON || difference <= self.float_tolerance
+    }
+}
+
+/// How a comparison against a multi-valued (wildcard) operand is combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Quantifier {
+    /// True if any element satisfies the comparison (`[*]`).
+    #[default]
+    Any,
+    /// True if every element satisfies the comparison (`[all]`, `all(...)`).
+    ///
+    /// An empty or missing array never satisfies an `All` comparison.
+    All,
+}
+
+/// How a filter is applied to the ALT alleles of a row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AlleleMode {
+    /// Evaluate once per row. Filters using `[alt]` are still evaluated
+    /// once per allele and pass if any allele passes.
+    #[default]
+    Site,
+    /// Evaluate once per ALT allele, with `ALT` and `[alt]` bound to it;
+    /// the row passes if any allele passes.
+    Any,
+    /// Evaluate once per ALT allele, with `ALT` and `[alt]` bound to it;
+    /// the row passes if every allele passes.
+    All,
+}
+
+impl AlleleMode {
+    /// Whether an expression is evaluated once per ALT allele in this mode.
+    pub(crate) fn per_allele(self, expr: &Expr) -> bool {
+        self != AlleleMode::Site || uses_alt_access(expr)
+    }
+}
+
+impl<'a> EvalContext<'a> {
+    /// Create a context with no deadline.
+    pub fn new(info_map: &'a InfoMap) -> Self {
+        Self {
+            info_map,
+            deadline: None,
+            quantifier: Quantifier::Any,
+            annotation: None,
+            bindings: None,
+            functions: None,
+            regions: None,
+            sample_names: &[],
+            alt: None,
+            allele_mode: AlleleMode::Site,
+            strict: false,
+            virtual_fields: &[],
+            three_valued: false,
+            collation: Collation::default(),
+            contigs: None,
+            call_depth: 0,
+            max_call_depth: None,
+        }
+    }
+
+    /// Set the instant after which evaluation is aborted.
+    pub fn with_deadline(mut self, deadline: Option<Instant>) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// Make registered functions callable during evaluation.
+    pub fn with_functions(mut self, functions: &'a FunctionRegistry) -> Self {
+        self.functions = Some(functions);
+        self
+    }
+
+    /// Share loaded BED region sets with `in_bed(...)` calls.
+    pub fn with_regions(mut self, regions: &'a BedLibrary) -> Self {
+        self.regions = Some(regions);
+        self
+    }
+
+    /// Resolve `SAMPLE["name"]` and `FORMAT.DP["name"]` against these sample names.
+    pub fn with_sample_names(mut self, sample_names: &'a [String]) -> Self {
+        self.sample_names = sample_names;
+        self
+    }
+
+    /// Enable strict evaluation, treating `virtual_fields` as declared.
+    pub fn with_strict(mut self, strict: bool, virtual_fields: &'a [String]) -> Self {
+        self.strict = strict;
+        self.virtual_fields = virtual_fields;
+        self
+    }
+
+    /// Use three-valued logic, where missing means "unknown" (like SQL NULL).
+    pub fn with_three_valued(mut self, three_valued: bool) -> Self {
+        self.three_valued = three_valued;
+        self
+    }
+
+    /// Compare strings and numbers with this collation.
+    pub fn with_collation(mut self, collation: Collation) -> Self {
+        self.collation = collation;
+        self
+    }
+
+    /// Evaluate filters per ALT allele, combining alleles as `mode` says.
+    pub fn with_allele_mode(mut self, mode: AlleleMode) -> Self {
+        self.allele_mode = mode;
+        self
+    }
+
+    /// Order `CHROM` comparisons by the header's `##contig` lines.
+    pub fn with_contigs(mut self, contigs: &'a ContigOrder) -> Self {
+        self.contigs = Some(contigs);
+        self
+    }
+
+    /// Fail once function calls nest deeper than this.
+    pub fn with_max_call_depth(mut self, max_call_depth: Option<usize>) -> Self {
+        self.max_call_depth = max_call_depth;
+        self
+    }
+
+    /// Enter a function call, failing if it nests too deeply.
+    pub(crate) fn enter_call(&self) -> Result<Self> {
+        let call_depth = self.call_depth + 1;
+        if let Some(max) = self.max_call_depth
+            && call_depth > max
+        {
+            return Err(VcfFilterError::LimitExceeded(format!(
+                "function call depth {} exceeds maximum of {}",
+                call_depth, max
+            )));
+        }
+        Ok(Self {
+            call_depth,
+            ..*self
+        })
+    }
+
+    /// Fail if the evaluation deadline has passed.
+    pub(crate) fn check_deadline(&self) -> Result<()> {
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => Err(VcfFilterError::LimitExceeded(
+                "evaluation time budget exceeded".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
 /// Evaluate a filter expression against a VCF row.
 ///
 /// # Arguments
@@ -20,22 +307,84 @@ use crate::value::Value;
 ///
 /// The result of evaluating the expression as a `Value`.
 pub fn evaluate(expr: &Expr, row: &VcfRow, info_map: &InfoMap) -> Result<Value> {
+    evaluate_with(expr, row, &EvalContext::new(info_map))
+}
+
+/// Evaluate a filter expression against a VCF row using an explicit context.
+pub fn evaluate_with(expr: &Expr, row: &VcfRow, ctx: &EvalContext) -> Result<Value> {
+    ctx.check_deadline()?;
+
     match expr {
         Expr::Number(n) => Ok(Value::Number(*n)),
         Expr::String(s) => Ok(Value::String(s.clone())),
         Expr::Bool(b) => Ok(Value::Bool(*b)),
-        Expr::Var(parts) => resolve_variable(parts, row, info_map),
-        Expr::Binary(left, op, right) => evaluate_binary(left, op, right, row, info_map),
-        Expr::Unary(op, inner) => evaluate_unary(op, inner, row, info_map),
+        Expr::Var(parts) => {
+            let value = resolve_variable(parts, row, ctx)?;
+            // An absent Flag is false rather than missing; `exists()` still tells them apart
+            if value.is_missing() && names_flag(parts, ctx) {
+                return Ok(Value::Bool(false));
+            }
+            Ok(value)
+        }
+        Expr::Binary(left, op, right) => evaluate_binary(left, op, right, row, ctx),
+        Expr::Unary(op, inner) => evaluate_unary(op, inner, row, ctx),
         Expr::Exists(parts) => {
-            let value = resolve_variable(parts, row, info_map)?;
-            Ok(Value::Bool(!value.is_missing()))
+            let value = resolve_variable(parts, row, ctx)?;
+            // Bare subfield names inside a predicate are subfield paths too
+            let subfield = subfield_path(parts)
+                || matches!(
+                    (parts.as_slice(), ctx.annotation),
+                    ([AccessPart::Field(name)], Some(scope)) if scope.subfields.contains(name)
+                );
+            let present = match value {
+                _ if !subfield => !value.is_missing(),
+                // `[all]` requires every selected element to be present
+                Value::Array(ref arr) if parts.contains(&AccessPart::All) => {
+                    !arr.is_empty() && arr.iter().all(value_present)
+                }
+                _ => value_present(&value),
+            };
+            Ok(Value::Bool(present))
+        }
+        Expr::All(inner) => {
+            let all_ctx = EvalContext {
+                quantifier: Quantifier::All,
+                ..*ctx
+            };
+            evaluate_with(inner, row, &all_ctx)
         }
+        Expr::List(items) => Ok(Value::Array(
+            items
+                .iter()
+                .map(|item| evaluate_with(item, row, ctx))
+                .collect::<Result<_>>()?,
+        )),
+        Expr::Call(name, args) => functions::call_builtin(name, args, row, &ctx.enter_call()?),
+        Expr::Let(name, value, body) => {
+            let binding = Binding {
+                name,
+                value: evaluate_with(value, row, ctx)?,
+                parent: ctx.bindings,
+            };
+            let let_ctx = EvalContext {
+                bindings: Some(&binding),
+                ..*ctx
+            };
+            evaluate_with(body, row, &let_ctx)
+        }
+        Expr::Preset(name) => Err(VcfFilterError::EvaluationError(format!(
+            "unexpanded preset reference: @{}",
+            name
+        ))),
     }
 }
 
 /// Resolve a variable access path to a value.
-fn resolve_variable(parts: &[AccessPart], row: &VcfRow, info_map: &InfoMap) -> Result<Value> {
+pub(crate) fn resolve_variable(
+    parts: &[AccessPart],
+    row: &VcfRow,
+    ctx: &EvalContext,
+) -> Result<Value> {
     if parts.is_empty() {
         return Ok(Value::Missing);
     }
@@ -50,6 +399,47 @@ fn resolve_variable(parts: &[AccessPart], row: &VcfRow, info_map: &InfoMap) -> R
         }
     };
 
+    // Names bound by `let` shadow row fields
+    if parts.len() == 1
+        && let Some(value) = ctx.bindings.and_then(|b| b.lookup(field_name))
+    {
+        return Ok(value.clone());
+    }
+
+    // Inside a predicate, bare subfield names refer to the current annotation
+    if let Some(scope) = ctx.annotation
+        && parts.len() == 1
+        && let Some(i) = scope.subfields.iter().position(|s| s == field_name)
+    {
+        return Ok(scope.values.get(i).cloned().unwrap_or(Value::Missing));
+    }
+
+    // Every sample's FORMAT field: FORMAT[*].DP, FORMAT[all].DP (or SAMPLE[*].DP)
+    if (field_name == "FORMAT" || field_name == "SAMPLE")
+        && let [
+            AccessPart::Wildcard | AccessPart::All,
+            AccessPart::Field(key),
+            rest @ ..,
+        ] = &parts[1..]
+    {
+        return Ok(Value::Array(
+            (0..row.sample_count())
+                .map(|column| resolve_sample(column, key, rest, row, ctx))
+                .collect::<Result<_>>()?,
+        ));
+    }
+
+    // One sample's FORMAT field: SAMPLE["NA12878"].GT or SAMPLE[1].GT
+    if field_name == "SAMPLE" {
+        return Ok(match &parts[1..] {
+            [sample, AccessPart::Field(key), rest @ ..] => match sample_column(sample, row, ctx)? {
+                Some(column) => resolve_sample(column, key, rest, row, ctx)?,
+                None => Value::Missing,
+            },
+            _ => Value::Missing,
+        });
+    }
+
     // Namespace-qualified access: INFO.<field> or FORMAT.<field>
     if field_name == "INFO" || field_name == "FORMAT" {
         if parts.len() < 2 {
@@ -58,25 +448,26 @@ fn resolve_variable(parts: &[AccessPart], row: &VcfRow, info_map: &InfoMap) -> R
 
         let namespaced_field = match &parts[1] {
             AccessPart::Field(name) => name,
+            AccessPart::Glob(pattern) if field_name == "INFO" => {
+                return Ok(resolve_info_glob(pattern, row));
+            }
             _ => return Ok(Value::Missing),
         };
 
-        return Ok(resolve_with_base(
-            Some(field_name),
-            namespaced_field,
-            &parts[2..],
-            row,
-            info_map,
-        ));
+        // FORMAT.DP["tumor"]
+        if field_name == "FORMAT"
+            && let Some(sample @ AccessPart::Sample(_)) = parts.get(2)
+        {
+            return Ok(match sample_column(sample, row, ctx)? {
+                Some(column) => resolve_sample(column, namespaced_field, &parts[3..], row, ctx)?,
+                None => Value::Missing,
+            });
+        }
+
+        return resolve_with_base(Some(field_name), namespaced_field, &parts[2..], row, ctx);
     }
 
-    Ok(resolve_with_base(
-        None,
-        field_name,
-        &parts[1..],
-        row,
-        info_map,
-    ))
+    resolve_with_base(None, field_name, &parts[1..], row, ctx)
 }
 
 /// Resolve field access against a specific namespace.
@@ -90,66 +481,411 @@ fn resolve_with_base(
     field_name: &str,
     access_parts: &[AccessPart],
     row: &VcfRow,
-    info_map: &InfoMap,
-) -> Value {
+    ctx: &EvalContext,
+) -> Result<Value> {
+    if ctx.strict && !is_known_field(namespace, field_name, row, ctx) {
+        return Err(VcfFilterError::UnknownField(field_name.to_string()));
+    }
+    // Indexing into a field borrows it, so `ANN[0].Gene_Name` does not copy every annotation
     let base_value = match namespace {
-        Some("INFO") => row.info.get(field_name).cloned().unwrap_or(Value::Missing),
-        Some("FORMAT") => row
-            .format
-            .get(field_name)
-            .cloned()
-            .unwrap_or(Value::Missing),
+        Some("INFO") => borrowed(row.info.get(field_name)),
+        Some("FORMAT") => borrowed(row.format.get(field_name)),
+        _ if access_parts.is_empty() => Cow::Owned(column_value(field_name, row, ctx)),
+        _ => match row.get_ref(field_name) {
+            Some(value) => Cow::Borrowed(value),
+            None => Cow::Owned(row.get(field_name)),
+        },
+    };
+    resolve_access(namespace, field_name, base_value, access_parts, row, ctx)
+}
+
+/// Borrow a value, or get a missing one.
+fn borrowed(value: Option<&Value>) -> Cow<'_, Value> {
+    value.map_or(Cow::Owned(Value::Missing), Cow::Borrowed)
+}
+
+/// Get an unqualified field of the row; in [`AlleleMode::Any`] and
+/// [`AlleleMode::All`], `ALT` is the allele being evaluated.
+pub(crate) fn column_value(field_name: &str, row: &VcfRow, ctx: &EvalContext) -> Value {
+    match ctx.alt {
+        Some(alt) if field_name == "ALT" && ctx.allele_mode != AlleleMode::Site => {
+            Value::String(row.alt_alleles[alt].clone())
+        }
         _ => row.get(field_name),
+    }
+}
+
+/// Whether strict mode accepts a field name.
+///
+/// INFO fields must be declared in the header or added by the engine; an
+/// unqualified name may also be a FORMAT key of the row. FORMAT keys are not
+/// declared in the INFO header, so any `FORMAT.` name is accepted.
+fn is_known_field(namespace: Option<&str>, name: &str, row: &VcfRow, ctx: &EvalContext) -> bool {
+    match namespace {
+        Some("FORMAT") => true,
+        None if BUILTIN_FIELDS.contains(&name) || row.format.contains_key(name) => true,
+        _ => ctx.info_map.contains_key(name) || ctx.virtual_fields.iter().any(|f| f == name),
+    }
+}
+
+/// Find the column of the sample a `["name"]` or `[index]` part selects.
+///
+/// Strict mode fails on unknown names and out-of-range indexes.
+fn sample_column(part: &AccessPart, row: &VcfRow, ctx: &EvalContext) -> Result<Option<usize>> {
+    let column = match part {
+        AccessPart::Sample(name) => ctx.sample_names.iter().position(|s| s == name),
+        AccessPart::Index(index) => Some(*index),
+        _ => None,
     };
+    if ctx.strict {
+        match (part, column) {
+            (AccessPart::Sample(name), None) => {
+                return Err(VcfFilterError::UnknownField(format!(
+                    "SAMPLE[\"{}\"]",
+                    name
+                )));
+            }
+            (_, Some(index)) if index >= row.sample_count() => {
+                return Err(VcfFilterError::InvalidIndex {
+                    field: "SAMPLE".to_string(),
+                    index,
+                    length: row.sample_count(),
+                });
+            }
+            _ => {}
+        }
+    }
+    Ok(column)
+}
+
+/// Resolve a FORMAT field of the sample in `column`, then any further access.
+fn resolve_sample(
+    column: usize,
+    key: &str,
+    access_parts: &[AccessPart],
+    row: &VcfRow,
+    ctx: &EvalContext,
+) -> Result<Value> {
+    let base_value = borrowed(row.sample_value(column, key));
+    resolve_access(Some("FORMAT"), key, base_value, access_parts, row, ctx)
+}
 
+/// Apply index, wildcard, predicate and subfield access to a field's value.
+fn resolve_access(
+    namespace: Option<&str>,
+    field_name: &str,
+    base_value: Cow<'_, Value>,
+    access_parts: &[AccessPart],
+    row: &VcfRow,
+    ctx: &EvalContext,
+) -> Result<Value> {
+    let info_map = ctx.info_map;
     if access_parts.is_empty() {
-        return base_value;
+        return Ok(base_value.into_owned());
     }
 
     // Handle structured field access (e.g., ANN[0].Gene_Name)
     let mut current_index: Option<usize> = None;
     let mut is_wildcard = false;
     let mut subfield_name: Option<String> = None;
+    let mut predicate: Option<&Expr> = None;
 
     for part in access_parts {
         match part {
             AccessPart::Index(i) => {
                 current_index = Some(*i);
             }
-            AccessPart::Wildcard => {
+            AccessPart::Alt => {
+                let Some(alt) = ctx.alt else {
+                    return Ok(Value::Missing);
+                };
+                current_index = match allele_number(namespace, field_name, info_map) {
+                    // A value count that disagrees with ALT can't be matched to alleles
+                    Some(number) if value_count(&base_value) != allele_count(&number, row) => {
+                        return Ok(Value::Missing);
+                    }
+                    // Number=R fields list the REF value first
+                    Some(InfoNumber::PerAllele) => Some(alt + 1),
+                    _ => Some(alt),
+                };
+            }
+            AccessPart::Wildcard | AccessPart::All => {
                 is_wildcard = true;
             }
             AccessPart::Field(name) => {
                 subfield_name = Some(name.clone());
             }
+            AccessPart::Predicate(pred) => {
+                predicate = Some(pred);
+            }
+            AccessPart::Glob(_) | AccessPart::Sample(_) => return Ok(Value::Missing),
+        }
+    }
+
+    if let Some(pred) = predicate {
+        if namespace == Some("FORMAT") {
+            return Ok(Value::Missing);
         }
+        return resolve_predicate(
+            field_name,
+            &base_value,
+            pred,
+            current_index,
+            subfield_name.as_deref(),
+            row,
+            ctx,
+        );
     }
 
     // If we have a subfield access
     if let Some(ref subfield) = subfield_name {
         // FORMAT namespace does not support annotation-style subfield access.
         if namespace == Some("FORMAT") {
-            return Value::Missing;
+            return Ok(Value::Missing);
         }
 
         if is_wildcard {
             // Return array of all matching subfield values
             let values = get_all_annotation_subfields(row, field_name, subfield, info_map);
-            return Value::Array(values);
+            return Ok(Value::Array(values));
         } else if let Some(idx) = current_index {
+            if ctx.strict {
+                check_index(field_name, idx, &base_value)?;
+            }
             // Return specific index's subfield
-            return get_annotation_subfield(row, field_name, idx, subfield, info_map);
+            return Ok(get_annotation_subfield(
+                row, field_name, idx, subfield, info_map,
+            ));
         }
     }
 
     // Array access without subfield
     if let Some(idx) = current_index {
-        if let Value::Array(arr) = base_value {
-            return arr.get(idx).cloned().unwrap_or(Value::Missing);
+        if ctx.strict {
+            check_index(field_name, idx, &base_value)?;
+        }
+        match base_value.as_ref() {
+            Value::Array(arr) => return Ok(arr.get(idx).cloned().unwrap_or(Value::Missing)),
+            // A biallelic site's per-allele field, or one FILTER, is a single value
+            Value::Missing => {}
+            value if idx == 0 && is_list_field(namespace, field_name, info_map) => {
+                return Ok(value.clone());
+            }
+            _ => {}
+        }
+    }
+
+    // Wildcard over a plain multi-valued field (e.g., AF[*])
+    if is_wildcard && subfield_name.is_none() {
+        return Ok(match base_value.into_owned() {
+            Value::Array(arr) => Value::Array(arr),
+            Value::Missing => Value::Array(vec![]),
+            other => Value::Array(vec![other]),
+        });
+    }
+
+    Ok(Value::Missing)
+}
+
+/// Fail if `index` is past the end of a present value; a single value has length 1.
+fn check_index(field: &str, index: usize, value: &Value) -> Result<()> {
+    let length = match value {
+        Value::Missing => return Ok(()),
+        Value::Array(arr) => arr.len(),
+        _ => 1,
+    };
+    if index < length {
+        return Ok(());
+    }
+    Err(VcfFilterError::InvalidIndex {
+        field: field.to_string(),
+        index,
+        length,
+    })
+}
+
+/// Whether an access path is a whole INFO Flag field (`DB`, `INFO.DB`).
+fn names_flag(parts: &[AccessPart], ctx: &EvalContext) -> bool {
+    match parts {
+        [AccessPart::Field(ns), AccessPart::Field(name)] if ns == "INFO" => {
+            is_flag(Some("INFO"), name, ctx.info_map)
         }
+        // Names bound by `let` or a predicate's subfields shadow the flag
+        [AccessPart::Field(name)] => {
+            ctx.bindings.and_then(|b| b.lookup(name)).is_none()
+                && !ctx
+                    .annotation
+                    .is_some_and(|scope| scope.subfields.contains(name))
+                && is_flag(None, name, ctx.info_map)
+        }
+        _ => false,
+    }
+}
+
+/// Whether the header declares the field as a Flag (`Number=0` or `Type=Flag`).
+pub(crate) fn is_flag(namespace: Option<&str>, field_name: &str, info_map: &InfoMap) -> bool {
+    namespace != Some("FORMAT")
+        && info_map.get(field_name).is_some_and(|field| {
+            field.number == InfoNumber::Flag || field.field_type == InfoType::Flag
+        })
+}
+
+/// Get the declared Number of a per-allele field, `Number=A` or `Number=R`.
+///
+/// The ALT column counts as `Number=A`. FORMAT fields are not declared in the
+/// INFO header, so they never qualify.
+fn allele_number(
+    namespace: Option<&str>,
+    field_name: &str,
+    info_map: &InfoMap,
+) -> Option<InfoNumber> {
+    if namespace.is_none() && field_name == "ALT" {
+        return Some(InfoNumber::PerAltAllele);
+    }
+    if namespace == Some("FORMAT") {
+        return None;
     }
+    info_map
+        .get(field_name)
+        .map(|field| field.number.clone())
+        .filter(|number| matches!(number, InfoNumber::PerAltAllele | InfoNumber::PerAllele))
+}
 
-    Value::Missing
+/// The number of values a `Number=A` or `Number=R` field should have on this row.
+fn allele_count(number: &InfoNumber, row: &VcfRow) -> usize {
+    match number {
+        InfoNumber::PerAllele => row.alt_alleles.len() + 1,
+        _ => row.alt_alleles.len(),
+    }
+}
+
+/// The number of values in a field, counting a single value as one.
+fn value_count(value: &Value) -> usize {
+    match value {
+        Value::Array(values) => values.len(),
+        Value::Missing => 0,
+        _ => 1,
+    }
+}
+
+/// Whether a single value of the field stands for a one-element list: the
+/// `;`-separated ID and FILTER columns, ALT, and per-allele fields.
+pub(crate) fn is_list_field(namespace: Option<&str>, field_name: &str, info_map: &InfoMap) -> bool {
+    (namespace.is_none() && matches!(field_name, "ID" | "FILTER"))
+        || allele_number(namespace, field_name, info_map).is_some()
+}
+
+/// Resolve an INFO key pattern (e.g., `INFO.gnomAD_*`) to the values of all matching keys.
+///
+/// Keys are visited in sorted order and multi-valued fields are flattened, so
+/// the result is a flat array that works with wildcard comparisons and
+/// aggregate functions like `max()`. Missing values are skipped.
+fn resolve_info_glob(pattern: &str, row: &VcfRow) -> Value {
+    let mut keys: Vec<&String> = row
+        .info
+        .keys()
+        .filter(|key| glob_match(pattern, key))
+        .collect();
+    keys.sort();
+
+    let mut values = Vec::new();
+    for key in keys {
+        match &row.info[key] {
+            Value::Array(arr) => values.extend(arr.iter().filter(|v| !v.is_missing()).cloned()),
+            Value::Missing => {}
+            other => values.push(other.clone()),
+        }
+    }
+    Value::Array(values)
+}
+
+/// Match `text` against a pattern where `*` matches any run of characters.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let mut segments = pattern.split('*');
+    let first = segments.next().unwrap_or("");
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+
+    let segments: Vec<&str> = segments.collect();
+    let Some((last, middle)) = segments.split_last() else {
+        // No '*' in the pattern: exact match
+        return rest.is_empty();
+    };
+
+    for segment in middle {
+        match rest.find(segment) {
+            Some(i) => rest = &rest[i + segment.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// Resolve predicate-filtered access (e.g., `ANN[?(Annotation_Impact == "HIGH")].Gene_Name`).
+///
+/// The predicate is evaluated once per annotation with that annotation in
+/// scope. With a subfield, returns the subfield of every matching annotation
+/// (like `[*]`); an index after the predicate selects among the matches.
+/// Without a subfield, returns the matching annotations, or `Missing` if none
+/// match so that `exists(ANN[?(...)])` works.
+fn resolve_predicate(
+    field_name: &str,
+    base_value: &Value,
+    predicate: &Expr,
+    index: Option<usize>,
+    subfield: Option<&str>,
+    row: &VcfRow,
+    ctx: &EvalContext,
+) -> Result<Value> {
+    let Some(subfields) = ctx
+        .info_map
+        .get(field_name)
+        .and_then(|f| f.subfields.as_deref())
+    else {
+        return Ok(Value::Missing);
+    };
+    let Value::Array(annotations) = base_value else {
+        return Ok(Value::Missing);
+    };
+
+    let mut matches = Vec::new();
+    for annotation in annotations {
+        let Value::Array(values) = annotation else {
+            continue;
+        };
+        let scoped = EvalContext {
+            annotation: Some(AnnotationScope { subfields, values }),
+            ..*ctx
+        };
+        if value_to_bool(&evaluate_with(predicate, row, &scoped)?)? {
+            matches.push(values);
+        }
+    }
+
+    let subfield_index = match subfield {
+        Some(name) => match subfields.iter().position(|s| s == name) {
+            Some(i) => Some(i),
+            None => return Ok(Value::Missing),
+        },
+        None => None,
+    };
+    let project = |values: &Vec<Value>| match subfield_index {
+        Some(i) => values.get(i).cloned().unwrap_or(Value::Missing),
+        None => Value::Array(values.clone()),
+    };
+
+    Ok(match index {
+        Some(idx) => matches
+            .get(idx)
+            .map(|values| project(values))
+            .unwrap_or(Value::Missing),
+        None if subfield_index.is_some() => {
+            Value::Array(matches.into_iter().map(project).collect())
+        }
+        None if matches.is_empty() => Value::Missing,
+        None => Value::Array(matches.into_iter().map(project).collect()),
+    })
 }
 
 /// Evaluate a binary operation.
@@ -158,58 +894,321 @@ fn evaluate_binary(
     op: &BinaryOp,
     right: &Expr,
     row: &VcfRow,
-    info_map: &InfoMap,
+    ctx: &EvalContext,
 ) -> Result<Value> {
-    let left_val = evaluate(left, row, info_map)?;
-    let right_val = evaluate(right, row, info_map)?;
-
-    // Handle wildcard comparisons (array on left side)
-    if let Value::Array(ref arr) = left_val {
-        let result = match op {
-            BinaryOp::Eq => arr.iter().any(|v| values_equal(v, &right_val)),
-            BinaryOp::NotEq => arr.iter().all(|v| !values_equal(v, &right_val)),
-            BinaryOp::Contains => arr.iter().any(|v| value_contains(v, &right_val)),
-            _ => {
-                // For numeric comparisons, check if any match
-                arr.iter()
-                    .any(|v| compare_values(v, op, &right_val).unwrap_or(false))
-            }
-        };
-        return Ok(Value::Bool(result));
-    }
-
+    // Logical operators short-circuit before the right side is evaluated
     match op {
-        BinaryOp::Eq => Ok(Value::Bool(values_equal(&left_val, &right_val))),
-        BinaryOp::NotEq => Ok(Value::Bool(!values_equal(&left_val, &right_val))),
-        BinaryOp::Lt => Ok(Value::Bool(compare_values(&left_val, op, &right_val)?)),
-        BinaryOp::Gt => Ok(Value::Bool(compare_values(&left_val, op, &right_val)?)),
-        BinaryOp::LtEq => Ok(Value::Bool(compare_values(&left_val, op, &right_val)?)),
-        BinaryOp::GtEq => Ok(Value::Bool(compare_values(&left_val, op, &right_val)?)),
-        BinaryOp::Contains => Ok(Value::Bool(value_contains(&left_val, &right_val))),
+        BinaryOp::And | BinaryOp::Or if ctx.three_valued => {
+            // A side that decides the result on its own wins over an unknown one
+            let decisive = *op == BinaryOp::Or;
+            let left_val = truth(&evaluate_with(left, row, ctx)?)?;
+            if left_val == Some(decisive) {
+                return Ok(Value::Bool(decisive));
+            }
+            let right_val = truth(&evaluate_with(right, row, ctx)?)?;
+            return Ok(match (left_val, right_val) {
+                (_, Some(r)) if r == decisive => Value::Bool(decisive),
+                (Some(_), Some(_)) => Value::Bool(!decisive),
+                _ => Value::Missing,
+            });
+        }
         BinaryOp::And => {
-            let left_bool = value_to_bool(&left_val)?;
-            if !left_bool {
+            if !value_to_bool(&evaluate_with(left, row, ctx)?)? {
                 return Ok(Value::Bool(false));
             }
-            let right_bool = value_to_bool(&right_val)?;
-            Ok(Value::Bool(right_bool))
+            let right_val = evaluate_with(right, row, ctx)?;
+            return Ok(Value::Bool(value_to_bool(&right_val)?));
         }
         BinaryOp::Or => {
-            let left_bool = value_to_bool(&left_val)?;
-            if left_bool {
+            if value_to_bool(&evaluate_with(left, row, ctx)?)? {
                 return Ok(Value::Bool(true));
             }
-            let right_bool = value_to_bool(&right_val)?;
-            Ok(Value::Bool(right_bool))
+            let right_val = evaluate_with(right, row, ctx)?;
+            return Ok(Value::Bool(value_to_bool(&right_val)?));
+        }
+        _ => {}
+    }
+
+    let mut left_val = evaluate_with(left, row, ctx)?;
+    let mut right_val = evaluate_with(right, row, ctx)?;
+
+    if orders_chromosomes(left, op, right) && ctx.bindings.and_then(|b| b.lookup("CHROM")).is_none()
+    {
+        left_val = chrom_ranks(left_val, ctx.contigs);
+        right_val = chrom_ranks(right_val, ctx.contigs);
+    }
+
+    if matches!(
+        op,
+        BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div
+    ) {
+        return arithmetic(op, &left_val, &right_val);
+    }
+    if ctx.strict {
+        check_comparable(&left_val, op, &right_val)?;
+    }
+
+    let quantifier = if uses_all_access(left) {
+        Quantifier::All
+    } else {
+        ctx.quantifier
+    };
+    if ctx.three_valued {
+        return compare_three_valued(&left_val, op, &right_val, quantifier, ctx.collation);
+    }
+    compare(&left_val, op, &right_val, quantifier, ctx.collation).map(Value::Bool)
+}
+
+/// Apply a comparison operator, giving a missing value when an operand is missing.
+///
+/// Wildcard elements combine with Kleene logic: `[*]` is true if any
+/// element is true, else unknown if any is unknown; `[all]` (and `!=`
+/// against `[*]`) is false if any element is false, else unknown if any is
+/// unknown.
+fn compare_three_valued(
+    left: &Value,
+    op: &BinaryOp,
+    right: &Value,
+    quantifier: Quantifier,
+    collation: Collation,
+) -> Result<Value> {
+    let Value::Array(elements) = left else {
+        if left.is_missing() || right.is_missing() {
+            return Ok(Value::Missing);
+        }
+        return compare(left, op, right, quantifier, collation).map(Value::Bool);
+    };
+    if elements.is_empty() {
+        return compare(left, op, right, quantifier, collation).map(Value::Bool);
+    }
+    let every = quantifier == Quantifier::All || *op == BinaryOp::NotEq;
+    let mut unknown = false;
+    for element in elements {
+        match compare_three_valued(element, op, right, Quantifier::Any, collation)? {
+            Value::Bool(result) if result != every => return Ok(Value::Bool(result)),
+            Value::Missing => unknown = true,
+            _ => {}
         }
     }
+    Ok(if unknown {
+        Value::Missing
+    } else {
+        Value::Bool(every)
+    })
+}
+
+/// Get the truth of a logical operand; missing is unknown (`None`).
+fn truth(value: &Value) -> Result<Option<bool>> {
+    match value {
+        Value::Missing => Ok(None),
+        other => value_to_bool(other).map(Some),
+    }
+}
+
+/// Apply a comparison operator to evaluated operands.
+///
+/// An array on the left is a wildcard operand, combined with `quantifier`.
+pub(crate) fn compare(
+    left: &Value,
+    op: &BinaryOp,
+    right: &Value,
+    quantifier: Quantifier,
+    collation: Collation,
+) -> Result<bool> {
+    // Handle wildcard comparisons (array on left side)
+    if let Value::Array(arr) = left {
+        return Ok(compare_elements(
+            arr.iter(),
+            op,
+            right,
+            quantifier,
+            collation,
+        ));
+    }
+    if is_nan(left) || is_nan(right) {
+        return Ok(false);
+    }
+
+    match op {
+        BinaryOp::Eq => Ok(values_equal(left, right, collation)),
+        BinaryOp::NotEq => Ok(!values_equal(left, right, collation)),
+        BinaryOp::Lt | BinaryOp::Gt | BinaryOp::LtEq | BinaryOp::GtEq => {
+            compare_values(left, op, right, collation)
+        }
+        BinaryOp::Contains => Ok(value_contains(left, right, collation)),
+        BinaryOp::In => Ok(value_in(left, right, collation)),
+        BinaryOp::SeverityAtLeast => Ok(severity_at_least(left, right)),
+        BinaryOp::And
+        | BinaryOp::Or
+        | BinaryOp::Add
+        | BinaryOp::Sub
+        | BinaryOp::Mul
+        | BinaryOp::Div => unreachable!("logical and arithmetic operators are not comparisons"),
+    }
+}
+
+/// Compare each element of a wildcard operand, combining results with `quantifier`.
+pub(crate) fn compare_elements<'v>(
+    mut elements: impl Iterator<Item = &'v Value>,
+    op: &BinaryOp,
+    right: &Value,
+    quantifier: Quantifier,
+    collation: Collation,
+) -> bool {
+    let element_matches = |v: &Value| match op {
+        _ if is_nan(v) || is_nan(right) => false,
+        BinaryOp::Eq => values_equal(v, right, collation),
+        BinaryOp::NotEq => !values_equal(v, right, collation),
+        BinaryOp::Contains => value_contains(v, right, collation),
+        BinaryOp::In => value_in(v, right, collation),
+        BinaryOp::SeverityAtLeast => severity_at_least(v, right),
+        _ => compare_values(v, op, right, collation).unwrap_or(false),
+    };
+    match (quantifier, op) {
+        // `!=` against a wildcard means "no element equals"
+        (Quantifier::Any, BinaryOp::NotEq) => elements.all(element_matches),
+        (Quantifier::Any, _) => elements.any(element_matches),
+        (Quantifier::All, _) => {
+            let mut seen = false;
+            for element in elements {
+                if !element_matches(element) {
+                    return false;
+                }
+                seen = true;
+            }
+            seen
+        }
+    }
+}
+
+/// Apply an arithmetic operator.
+///
+/// Arrays combine element-wise with a scalar or with an array of the same
+/// length. A missing operand or division by zero yields a missing value, so
+/// `AD[1] / DP > 0.2` is false rather than an error when DP is 0.
+pub(crate) fn arithmetic(op: &BinaryOp, left: &Value, right: &Value) -> Result<Value> {
+    match (left, right) {
+        (Value::Missing, _) | (_, Value::Missing) => Ok(Value::Missing),
+        (Value::Array(l), Value::Array(r)) => {
+            if l.len() != r.len() {
+                return Err(VcfFilterError::EvaluationError(format!(
+                    "cannot combine arrays of length {} and {}",
+                    l.len(),
+                    r.len()
+                )));
+            }
+            l.iter()
+                .zip(r)
+                .map(|(l, r)| arithmetic(op, l, r))
+                .collect::<Result<_>>()
+                .map(Value::Array)
+        }
+        (Value::Array(l), r) => l
+            .iter()
+            .map(|l| arithmetic(op, l, r))
+            .collect::<Result<_>>()
+            .map(Value::Array),
+        (l, Value::Array(r)) => r
+            .iter()
+            .map(|r| arithmetic(op, l, r))
+            .collect::<Result<_>>()
+            .map(Value::Array),
+        (l, r) => {
+            let operand = |v: &Value| {
+                v.as_number().ok_or_else(|| {
+                    VcfFilterError::EvaluationError(format!(
+                        "arithmetic on non-numeric value {:?}",
+                        v
+                    ))
+                })
+            };
+            let (l, r) = (operand(l)?, operand(r)?);
+            Ok(match op {
+                BinaryOp::Add => Value::Number(l + r),
+                BinaryOp::Sub => Value::Number(l - r),
+                BinaryOp::Mul => Value::Number(l * r),
+                BinaryOp::Div if r == 0.0 => Value::Missing,
+                BinaryOp::Div => Value::Number(l / r),
+                _ => unreachable!("not an arithmetic operator"),
+            })
+        }
+    }
+}
+
+/// Whether a comparison orders `CHROM`, so both sides compare by chromosome rank.
+pub(crate) fn orders_chromosomes(left: &Expr, op: &BinaryOp, right: &Expr) -> bool {
+    let is_chrom = |expr: &Expr| matches!(expr, Expr::Var(parts) if matches!(parts.as_slice(), [AccessPart::Field(name)] if name == "CHROM"));
+    matches!(
+        op,
+        BinaryOp::Lt | BinaryOp::Gt | BinaryOp::LtEq | BinaryOp::GtEq
+    ) && (is_chrom(left) || is_chrom(right))
+}
+
+/// Replace chromosome names with their rank; unranked contigs become missing.
+///
+/// Ranks follow the header contigs when given, natural order otherwise.
+pub(crate) fn chrom_ranks(value: Value, contigs: Option<&ContigOrder>) -> Value {
+    match value {
+        Value::String(name) => {
+            let rank = match contigs {
+                Some(contigs) => contigs.rank(&name),
+                None => chromosome::rank(&name),
+            };
+            rank.map_or(Value::Missing, |rank| Value::Number(f64::from(rank)))
+        }
+        Value::Array(arr) => Value::Array(
+            arr.into_iter()
+                .map(|value| chrom_ranks(value, contigs))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+/// Check if an operand is a variable path using `[all]` access.
+pub(crate) fn uses_all_access(expr: &Expr) -> bool {
+    matches!(expr, Expr::Var(parts) if parts.contains(&AccessPart::All))
+}
+
+/// Whether an expression refers to the current allele with `[alt]` anywhere.
+pub(crate) fn uses_alt_access(expr: &Expr) -> bool {
+    let direct = match expr {
+        Expr::Var(parts) | Expr::Exists(parts) => parts.contains(&AccessPart::Alt),
+        _ => false,
+    };
+    direct || expr.children().into_iter().any(uses_alt_access)
+}
+
+/// Run an allele-specific filter once per ALT allele, with `[alt]` bound to it.
+///
+/// The filter passes if it holds for any allele, or with
+/// [`AlleleMode::All`] for every allele; either way a site without ALT
+/// alleles never passes. Spanning deletions (`*`) are skipped.
+pub(crate) fn each_allele(
+    row: &VcfRow,
+    ctx: &EvalContext,
+    mut test: impl FnMut(&EvalContext) -> Result<bool>,
+) -> Result<bool> {
+    let every = ctx.allele_mode == AlleleMode::All;
+    let alts = row.alt_indexes();
+    for &alt in &alts {
+        let allele_ctx = EvalContext {
+            alt: Some(alt),
+            ..*ctx
+        };
+        if test(&allele_ctx)? != every {
+            return Ok(!every);
+        }
+    }
+    Ok(every && !alts.is_empty())
 }
 
 /// Evaluate a unary operation.
-fn evaluate_unary(op: &UnaryOp, inner: &Expr, row: &VcfRow, info_map: &InfoMap) -> Result<Value> {
-    let val = evaluate(inner, row, info_map)?;
+fn evaluate_unary(op: &UnaryOp, inner: &Expr, row: &VcfRow, ctx: &EvalContext) -> Result<Value> {
+    let val = evaluate_with(inner, row, ctx)?;
 
     match op {
+        UnaryOp::Not if ctx.three_valued && val.is_missing() => Ok(Value::Missing),
         UnaryOp::Not => {
             let bool_val = value_to_bool(&val)?;
             Ok(Value::Bool(!bool_val))
@@ -217,32 +1216,132 @@ fn evaluate_unary(op: &UnaryOp, inner: &Expr, row: &VcfRow, info_map: &InfoMap)
     }
 }
 
+/// Check whether `exists()` on `parts` counts empty strings as absent.
+///
+/// Only subfield and indexed paths do, where an empty value is an
+/// unpopulated annotation subfield. A top-level field (`KEY=`) is present
+/// whatever it holds, also when named as `INFO.KEY` or `FORMAT.KEY`.
+pub(crate) fn subfield_path(parts: &[AccessPart]) -> bool {
+    match parts {
+        [_] => false,
+        [AccessPart::Field(namespace), AccessPart::Field(_)] => {
+            namespace != "INFO" && namespace != "FORMAT"
+        }
+        _ => true,
+    }
+}
+
+/// Check whether a value counts as present for `exists()` on a subfield path.
+///
+/// Missing values and empty strings (unpopulated annotation subfields) are
+/// absent; an array is present if any of its elements is.
+pub(crate) fn value_present(value: &Value) -> bool {
+    match value {
+        Value::Missing => false,
+        Value::String(s) => !s.is_empty(),
+        Value::Array(arr) => arr.iter().any(value_present),
+        Value::Number(_) | Value::Bool(_) => true,
+    }
+}
+
+/// Fail on an equality or ordering comparison between different types.
+///
+/// Used by strict mode: numeric strings compare with numbers, and missing
+/// values compare with anything. Each element of an array on the left is
+/// checked; an array on the right is a list, not checked.
+fn check_comparable(left: &Value, op: &BinaryOp, right: &Value) -> Result<()> {
+    if !matches!(
+        op,
+        BinaryOp::Eq
+            | BinaryOp::NotEq
+            | BinaryOp::Lt
+            | BinaryOp::Gt
+            | BinaryOp::LtEq
+            | BinaryOp::GtEq
+    ) {
+        return Ok(());
+    }
+    match (left, right) {
+        (Value::Array(items), _) => items
+            .iter()
+            .try_for_each(|item| check_comparable(item, op, right)),
+        (_, Value::Array(_) | Value::Missing) | (Value::Missing, _) => Ok(()),
+        (Value::String(s), Value::Number(_)) | (Value::Number(_), Value::String(s))
+            if s.parse::<f64>().is_ok() =>
+        {
+            Ok(())
+        }
+        (l, r) if l.type_name() == r.type_name() => Ok(()),
+        (l, r) => Err(VcfFilterError::TypeMismatch {
+            left: l.type_name().to_string(),
+            right: r.type_name().to_string(),
+        }),
+    }
+}
+
+/// Check whether a value is NaN, which no comparison is true of.
+fn is_nan(value: &Value) -> bool {
+    matches!(value, Value::Number(n) if n.is_nan())
+}
+
+/// Check whether a consequence term is at least as severe as another.
+fn severity_at_least(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::String(consequence), Value::String(threshold)) => {
+            consequence::at_least(consequence, threshold)
+        }
+        _ => false,
+    }
+}
+
 /// Check if two values are equal.
-fn values_equal(left: &Value, right: &Value) -> bool {
+fn values_equal(left: &Value, right: &Value, collation: Collation) -> bool {
     match (left, right) {
-        (Value::String(l), Value::String(r)) => l == r,
-        (Value::Number(l), Value::Number(r)) => (l - r).abs() < f64::EPSILON,
+        (Value::String(l), Value::String(r)) => collation.strings_equal(l, r),
+        (Value::Number(l), Value::Number(r)) => collation.numbers_equal(*l, *r),
         (Value::Bool(l), Value::Bool(r)) => l == r,
         (Value::Missing, Value::Missing) => true,
         // Try numeric comparison if one is a string that looks like a number
         (Value::String(s), Value::Number(n)) | (Value::Number(n), Value::String(s)) => s
             .parse::<f64>()
-            .map(|sn| (sn - n).abs() < f64::EPSILON)
+            .map(|sn| collation.numbers_equal(sn, *n))
             .unwrap_or(false),
         _ => false,
     }
 }
 
 /// Check if left contains right (string containment).
-fn value_contains(left: &Value, right: &Value) -> bool {
+///
+/// An array contains `right` if any of its elements does, so nested values
+/// such as whole annotations (`ANN contains "BRCA1"`) are searched too.
+fn value_contains(left: &Value, right: &Value, collation: Collation) -> bool {
     match (left, right) {
+        (Value::String(l), Value::String(r)) if collation.case_insensitive => {
+            l.to_lowercase().contains(&r.to_lowercase())
+        }
         (Value::String(l), Value::String(r)) => l.contains(r.as_str()),
+        (Value::Array(items), _) => items
+            .iter()
+            .any(|item| value_contains(item, right, collation)),
         _ => false,
     }
 }
 
+/// Check if a value is a member of a list (or equal to a single value).
+fn value_in(left: &Value, right: &Value, collation: Collation) -> bool {
+    match right {
+        Value::Array(items) => items.iter().any(|item| values_equal(left, item, collation)),
+        other => values_equal(left, other, collation),
+    }
+}
+
 /// Compare two values with a comparison operator.
-fn compare_values(left: &Value, op: &BinaryOp, right: &Value) -> Result<bool> {
+fn compare_values(
+    left: &Value,
+    op: &BinaryOp,
+    right: &Value,
+    collation: Collation,
+) -> Result<bool> {
     if matches!(left, Value::Missing) || matches!(right, Value::Missing) {
         return Ok(false);
     }
@@ -252,11 +1351,12 @@ fn compare_values(left: &Value, op: &BinaryOp, right: &Value) -> Result<bool> {
 
     match (left_num, right_num) {
         (Some(l), Some(r)) => {
+            let equal = collation.numbers_equal(l, r);
             let result = match op {
-                BinaryOp::Lt => l < r,
-                BinaryOp::Gt => l > r,
-                BinaryOp::LtEq => l <= r,
-                BinaryOp::GtEq => l >= r,
+                BinaryOp::Lt => l < r && !equal,
+                BinaryOp::Gt => l > r && !equal,
+                BinaryOp::LtEq => l <= r || equal,
+                BinaryOp::GtEq => l >= r || equal,
                 _ => {
                     return Err(VcfFilterError::EvaluationError(format!(
                         "Unexpected operator in compare_values: {:?}",
@@ -270,6 +1370,11 @@ fn compare_values(left: &Value, op: &BinaryOp, right: &Value) -> Result<bool> {
             // String comparison for non-numeric values
             match (left, right) {
                 (Value::String(l), Value::String(r)) => {
+                    let (l, r): (Cow<str>, Cow<str>) = if collation.case_insensitive {
+                        (l.to_lowercase().into(), r.to_lowercase().into())
+                    } else {
+                        (l.into(), r.into())
+                    };
                     let result = match op {
                         BinaryOp::Lt => l < r,
                         BinaryOp::Gt => l > r,
@@ -294,7 +1399,7 @@ fn compare_values(left: &Value, op: &BinaryOp, right: &Value) -> Result<bool> {
 }
 
 /// Convert a value to a boolean.
-fn value_to_bool(val: &Value) -> Result<bool> {
+pub(crate) fn value_to_bool(val: &Value) -> Result<bool> {
     match val {
         Value::Bool(b) => Ok(*b),
         Value::Missing => Ok(false),
@@ -471,6 +1576,74 @@ mod tests {
         assert!(!eval_filter(r#"CLNDN contains "diabetes""#, row, HEADER));
     }
 
+    #[test]
+    fn test_flag_fields_are_booleans() {
+        let header = r#"##INFO=<ID=DB,Number=0,Type=Flag,Description="dbSNP membership">"#;
+        let present = "chr1\t100\t.\tA\tG\t50\tPASS\tDB";
+        let absent = "chr1\t100\t.\tA\tG\t50\tPASS\t.";
+        assert!(eval_filter("DB && QUAL > 30", present, header));
+        assert!(!eval_filter("DB && QUAL > 30", absent, header));
+        assert!(eval_filter(
+            "DB == false && INFO.DB != true",
+            absent,
+            header
+        ));
+        assert!(eval_filter("!DB && !exists(DB)", absent, header));
+        // `let` bindings shadow the flag
+        assert!(eval_filter("let DB = 1 in DB == 1", absent, header));
+    }
+
+    #[test]
+    fn test_multi_filter_column() {
+        let row = "chr1\t100\t.\tA\tG\t50\tq10;s50\t.";
+        assert!(!eval_filter(r#"FILTER == "PASS""#, row, HEADER));
+        assert!(eval_filter(
+            r#"FILTER == "s50" && FILTER != "PASS""#,
+            row,
+            HEADER
+        ));
+        assert!(eval_filter(r#"FILTER contains "q1""#, row, HEADER));
+        assert!(eval_filter("len(FILTER) > 1", row, HEADER));
+        assert!(eval_filter(r#"FILTER[1] == "s50""#, row, HEADER));
+        assert!(eval_filter(r#"filter_has("s50")"#, row, HEADER));
+        assert!(!eval_filter(r#"filter_has("s5")"#, row, HEADER));
+
+        // One filter behaves as a one-element list
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\t.";
+        assert!(eval_filter(
+            r#"FILTER[0] == "PASS" && len(FILTER) == 1"#,
+            row,
+            HEADER
+        ));
+        assert!(eval_filter(r#"filter_has("PASS")"#, row, HEADER));
+
+        // `.` means no filters were applied
+        let row = "chr1\t100\t.\tA\tG\t50\t.\t.";
+        assert!(eval_filter(
+            r#"len(FILTER) == 0 && !exists(FILTER)"#,
+            row,
+            HEADER
+        ));
+        assert!(eval_filter(r#"FILTER != "PASS""#, row, HEADER));
+    }
+
+    #[test]
+    fn test_contains_on_arrays() {
+        let row = "chr1\t100\t.\tA\tG\t50\tLowQual;q10\tCLNDN=not_provided,Breast_cancer;ANN=G|missense|HIGH|BRCA1|E1|t|T1|pc|1|c.1|p.1|1|1|1||";
+        assert!(eval_filter(r#"FILTER contains "LowQual""#, row, HEADER));
+        assert!(eval_filter(r#"CLNDN contains "cancer""#, row, HEADER));
+        assert!(!eval_filter(r#"all(CLNDN contains "cancer")"#, row, HEADER));
+        // Elements that are themselves arrays are searched element by element
+        assert!(eval_filter(r#"ANN contains "BRCA""#, row, HEADER));
+        assert!(eval_filter(
+            r#"ANN[?(Annotation_Impact == "HIGH")] contains "missense""#,
+            row,
+            HEADER
+        ));
+        assert!(!eval_filter(r#"ANN contains "TP53""#, row, HEADER));
+        assert!(!eval_filter(r#"[1, 2] contains "1""#, row, HEADER));
+    }
+
     #[test]
     fn test_complex_expression() {
         let row = "chr1\t100\t.\tA\tG\t50\tPASS\tDP=30;ANN=G|missense|HIGH|BRCA1|E1|t|T1|pc|1|c.1|p.1|1|1|1||";
@@ -480,4 +1653,363 @@ mod tests {
             HEADER
         ));
     }
+
+    #[test]
+    fn test_all_access_quantifier() {
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\tANN=G|missense|LOW|BRCA1|E1|t|T1|pc|1|c.1|p.1|1|1|1||,G|synonymous|MODERATE|BRCA1|E2|t|T2|pc|2|c.2|p.2|2|2|2||";
+        assert!(eval_filter(r#"ANN[all].Gene_Name == "BRCA1""#, row, HEADER));
+        assert!(!eval_filter(
+            r#"ANN[all].Annotation_Impact == "LOW""#,
+            row,
+            HEADER
+        ));
+        assert!(eval_filter(
+            r#"ANN[all].Annotation_Impact != "HIGH""#,
+            row,
+            HEADER
+        ));
+        assert!(eval_filter(
+            r#"ANN[*].Annotation_Impact == "LOW""#,
+            row,
+            HEADER
+        ));
+    }
+
+    #[test]
+    fn test_all_function_quantifier() {
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\tANN=G|missense|LOW|BRCA1|E1|t|T1|pc|1|c.1|p.1|1|1|1||,G|synonymous|LOW|BRCA2|E2|t|T2|pc|2|c.2|p.2|2|2|2||";
+        assert!(eval_filter(
+            r#"all(ANN[*].Annotation_Impact == "LOW")"#,
+            row,
+            HEADER
+        ));
+        assert!(!eval_filter(
+            r#"all(ANN[*].Gene_Name == "BRCA1")"#,
+            row,
+            HEADER
+        ));
+        assert!(eval_filter(
+            r#"all(ANN[*].Gene_Name contains "BRCA")"#,
+            row,
+            HEADER
+        ));
+    }
+
+    #[test]
+    fn test_all_quantifier_on_missing_field_is_false() {
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\tDP=30";
+        assert!(!eval_filter(
+            r#"ANN[all].Annotation_Impact != "HIGH""#,
+            row,
+            HEADER
+        ));
+        assert!(!eval_filter(
+            r#"all(ANN[*].Gene_Name == "BRCA1")"#,
+            row,
+            HEADER
+        ));
+    }
+
+    #[test]
+    fn test_predicate_filtered_access() {
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\tDP=30;ANN=G|missense|MODERATE|TP53|E1|t|T1|pc|1|c.1|p.1|1|1|1||,G|stop_gained|HIGH|BRCA2|E2|t|T2|pc|2|c.2|p.2|2|2|2||";
+        assert!(eval_filter(
+            r#"ANN[?(Annotation_Impact == "HIGH")].Gene_Name contains "BRCA2""#,
+            row,
+            HEADER
+        ));
+        // TP53 is annotated, but not with HIGH impact
+        assert!(!eval_filter(
+            r#"ANN[?(Annotation_Impact == "HIGH")].Gene_Name contains "TP53""#,
+            row,
+            HEADER
+        ));
+        // Row fields remain visible inside the predicate
+        assert!(eval_filter(
+            r#"ANN[?(Gene_Name == "TP53" && DP >= 30)][0].Annotation == "missense""#,
+            row,
+            HEADER
+        ));
+        assert!(eval_filter(
+            r#"exists(ANN[?(Annotation == "stop_gained")])"#,
+            row,
+            HEADER
+        ));
+        assert!(!eval_filter(
+            r#"exists(ANN[?(Annotation == "frameshift")])"#,
+            row,
+            HEADER
+        ));
+    }
+
+    #[test]
+    fn test_exists_on_subfield_paths() {
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\tANN=G|missense|MODERATE|TP53|E1|t|T1|pc|1|c.1|p.Arg1Cys|1|1|1||,G|intron|MODIFIER|TP53|E1|t|T2|pc|2|c.2||2|2|2||";
+        assert!(eval_filter("exists(ANN[0].HGVS_p)", row, HEADER));
+        assert!(!eval_filter("exists(ANN[1].HGVS_p)", row, HEADER));
+        assert!(eval_filter("exists(ANN[*].HGVS_p)", row, HEADER));
+        assert!(!eval_filter("exists(ANN[all].HGVS_p)", row, HEADER));
+        assert!(!eval_filter("exists(ANN[*].Distance)", row, HEADER));
+        assert!(eval_filter("exists(ANN[all].Gene_Name)", row, HEADER));
+        assert!(!eval_filter("exists(ANN[*].No_Such_Subfield)", row, HEADER));
+
+        // No ANN at all: wildcard paths are absent
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\tDP=30";
+        assert!(!eval_filter("exists(ANN[*].Gene_Name)", row, HEADER));
+
+        // Empty top-level values are still present
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\tNOTE=;PAIR=,";
+        assert!(eval_filter("exists(NOTE)", row, HEADER));
+        assert!(eval_filter("exists(INFO.NOTE)", row, HEADER));
+        assert!(eval_filter("exists(PAIR)", row, HEADER));
+        assert!(!eval_filter("exists(PAIR[*])", row, HEADER));
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("gnomAD_*", "gnomAD_AF"));
+        assert!(glob_match("gnomAD_*_AF", "gnomAD_nfe_AF"));
+        assert!(!glob_match("gnomAD_*_AF", "gnomAD_nfe_AC"));
+        assert!(glob_match("*_AF", "ExAC_AF"));
+        assert!(!glob_match("gnomAD_*", "ExAC_AF"));
+        assert!(!glob_match("a*a", "a"));
+    }
+
+    #[test]
+    fn test_info_glob_access() {
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\tgnomAD_AF_afr=0.002;gnomAD_AF_nfe=0.05;gnomAD_AF_eas=.;ExAC_AF=0.3";
+        assert!(eval_filter("INFO.gnomAD_AF_* > 0.01", row, HEADER));
+        assert!(!eval_filter("INFO.gnomAD_AF_* > 0.1", row, HEADER));
+        assert!(eval_filter("max(INFO.gnomAD_AF_*) == 0.05", row, HEADER));
+        assert!(eval_filter("min(INFO.gnomAD_AF_*) == 0.002", row, HEADER));
+        assert!(eval_filter("all(INFO.gnomAD_AF_* < 0.1)", row, HEADER));
+        assert!(eval_filter("any(INFO.gnomAD_AF_* < 0.01)", row, HEADER));
+        assert!(!eval_filter("any(INFO.topmed_* > 0)", row, HEADER));
+    }
+
+    #[test]
+    fn test_in_operator() {
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\tDP=30;CLNSIG=Likely_pathogenic";
+        assert!(eval_filter(
+            r#"CLNSIG in ["Pathogenic", "Likely_pathogenic"]"#,
+            row,
+            HEADER
+        ));
+        assert!(!eval_filter(r#"CLNSIG in ["Benign"]"#, row, HEADER));
+        assert!(eval_filter("DP in [10, 20, 30]", row, HEADER));
+        assert!(!eval_filter(r#"MISSING in ["x"]"#, row, HEADER));
+        // Wildcard on the left: any element is a member
+        assert!(eval_filter(r#"ALT[*] in ["C", "G"]"#, row, HEADER));
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let header = r#"##INFO=<ID=DP,Number=1,Type=Integer,Description="Total depth">
+##INFO=<ID=AD,Number=R,Type=Integer,Description="Allele depths">"#;
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\tDP=40;AD=30,10";
+        assert!(eval_filter("AD[1] / DP == 0.25", row, header));
+        assert!(eval_filter("AD[0] + AD[1] == DP", row, header));
+        assert!(eval_filter("DP - AD[0] * 2 < 0", row, header));
+        assert!(eval_filter("(DP - AD[0]) * 2 == 20", row, header));
+        assert!(eval_filter("QUAL / 2 > 20", row, header));
+        // Arrays combine element-wise
+        assert!(eval_filter("AD[*] / DP > 0.5", row, header));
+        assert!(!eval_filter("all(AD[*] / DP > 0.5)", row, header));
+
+        // Missing operands and division by zero give missing, not errors
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\tDP=0;AD=0,0";
+        assert!(!eval_filter("AD[1] / DP > 0.2", row, header));
+        assert!(!eval_filter("AF * 2 > 0", row, header));
+
+        let info_map = parse_header(header).unwrap();
+        let row = parse_row(row, &info_map).unwrap();
+        let expr = parse_filter(r#"CHROM + 1 > 0"#).unwrap();
+        assert!(evaluate(&expr, &row, &info_map).is_err());
+    }
+
+    #[test]
+    fn test_let_bindings() {
+        let header = r#"##INFO=<ID=DP,Number=1,Type=Integer,Description="Total depth">
+##INFO=<ID=AD,Number=R,Type=Integer,Description="Allele depths">"#;
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\tDP=40;AD=30,10";
+        assert!(eval_filter(
+            "let ab = AD[1] / DP in ab > 0.2 && ab < 0.8",
+            row,
+            header
+        ));
+        assert!(!eval_filter("let ab = AD[1] / DP in ab > 0.3", row, header));
+        // Several bindings; later ones see earlier ones
+        assert!(eval_filter(
+            "let alt = AD[1], ab = alt / DP in alt == 10 && ab == 0.25",
+            row,
+            header
+        ));
+        // Bindings shadow fields, and nested lets shadow outer ones
+        assert!(eval_filter("let DP = 5 in DP == 5", row, header));
+        assert!(eval_filter(
+            "let x = 1 in (let x = 2 in x == 2) && x == 1",
+            row,
+            header
+        ));
+        // Comparisons can be bound when parenthesized
+        assert!(eval_filter(
+            "let deep = (DP >= 30) in deep && QUAL > 10",
+            row,
+            header
+        ));
+    }
+
+    #[test]
+    fn test_nan_comparisons() {
+        let header = r#"##INFO=<ID=CADD,Number=.,Type=Float,Description="CADD scores">"#;
+        let row = "chr1\t100\t.\tA\tG\tnan\tPASS\tCADD=nan,inf";
+        assert!(!eval_filter("QUAL > 0", row, header));
+        assert!(!eval_filter("QUAL <= 0", row, header));
+        assert!(!eval_filter("QUAL != 30", row, header));
+        assert!(!eval_filter("QUAL == QUAL", row, header));
+        assert!(!eval_filter("CADD[0] != 1", row, header));
+        // Infinities still compare, and other elements can match
+        assert!(eval_filter("CADD[1] > 1000", row, header));
+        assert!(eval_filter("CADD[*] > 1000", row, header));
+        assert!(!eval_filter("all(CADD[*] > 1000)", row, header));
+    }
+
+    #[test]
+    fn test_strict_mode() {
+        let header = r#"##INFO=<ID=DP,Number=1,Type=Integer,Description="Total depth">
+##INFO=<ID=AD,Number=R,Type=Integer,Description="Allele depths">
+##INFO=<ID=GENE,Number=1,Type=String,Description="Gene">"#;
+        let info_map = parse_header(header).unwrap();
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\tDP=40;AD=30,10;GENE=BRCA1;EXTRA=1\tGT\t0/1";
+        let row = parse_row(row, &info_map).unwrap();
+        let strict = EvalContext::new(&info_map).with_strict(true, &[]);
+        let eval = |filter: &str, ctx: &EvalContext| {
+            evaluate_with(&parse_filter(filter).unwrap(), &row, ctx)
+        };
+
+        for filter in [
+            "DP > 30 && AD[1] == 10",
+            r#"GENE == "BRCA1" && CHROM <= "chr2""#,
+            "AD[0] > 100 || GT == \"0/1\"",
+            "FORMAT.GQ > 10",
+            "let x = 1 in x == 1",
+        ] {
+            assert!(eval(filter, &strict).is_ok(), "{}", filter);
+        }
+        // Undeclared INFO keys fail even if the row carries them
+        assert!(matches!(
+            eval("GNOMAD_AF < 0.01", &strict),
+            Err(VcfFilterError::UnknownField(name)) if name == "GNOMAD_AF"
+        ));
+        assert!(matches!(
+            eval("EXTRA == 1", &strict),
+            Err(VcfFilterError::UnknownField(_))
+        ));
+        assert!(matches!(
+            eval("AD[2] > 5", &strict),
+            Err(VcfFilterError::InvalidIndex {
+                index: 2,
+                length: 2,
+                ..
+            })
+        ));
+        assert!(matches!(
+            eval("DP[1] > 5", &strict),
+            Err(VcfFilterError::InvalidIndex {
+                index: 1,
+                length: 1,
+                ..
+            })
+        ));
+        assert!(matches!(
+            eval("SAMPLE[1].GT == \"0/1\"", &strict),
+            Err(VcfFilterError::InvalidIndex { .. })
+        ));
+        assert!(matches!(
+            eval("GENE == 5", &strict),
+            Err(VcfFilterError::TypeMismatch { .. })
+        ));
+        assert!(matches!(
+            eval("AD[*] == \"x\"", &strict),
+            Err(VcfFilterError::TypeMismatch { .. })
+        ));
+
+        // The same filters quietly evaluate false by default
+        let lenient = EvalContext::new(&info_map);
+        for filter in ["GNOMAD_AF < 0.01", "AD[2] > 5", "GENE == 5"] {
+            assert_eq!(eval(filter, &lenient).unwrap(), Value::Bool(false));
+        }
+    }
+
+    #[test]
+    fn test_three_valued_logic() {
+        let header = r#"##INFO=<ID=DP,Number=1,Type=Integer,Description="Total depth">
+##INFO=<ID=AF,Number=A,Type=Float,Description="Allele frequency">
+##INFO=<ID=AD,Number=R,Type=Integer,Description="Allele depths">
+##INFO=<ID=CLNSIG,Number=.,Type=String,Description="Clinical significance">"#;
+        let info_map = parse_header(header).unwrap();
+        let row = "chr1\t100\t.\tA\tG,T\t50\tPASS\tDP=30;AD=20,.,5";
+        let row = parse_row(row, &info_map).unwrap();
+        let ctx = EvalContext::new(&info_map).with_three_valued(true);
+        let eval =
+            |filter: &str| evaluate_with(&parse_filter(filter).unwrap(), &row, &ctx).unwrap();
+
+        for (filter, expected) in [
+            ("AF[0] < 0.01", Value::Missing),
+            (r#"CLNSIG != "Benign""#, Value::Missing),
+            ("!(AF[0] < 0.01)", Value::Missing),
+            ("AF[0] < 0.01 && DP < 10", Value::Bool(false)),
+            ("AF[0] < 0.01 || DP > 10", Value::Bool(true)),
+            ("AF[0] < 0.01 || DP < 10", Value::Missing),
+            ("DP > 10 && QUAL > 10", Value::Bool(true)),
+            // Wildcards: any true element decides, else any unknown one
+            ("AD[*] > 10", Value::Bool(true)),
+            ("AD[*] > 50", Value::Missing),
+            ("AD[all] > 1", Value::Missing),
+            ("AD[all] > 10", Value::Bool(false)),
+            ("AD[*] != 5", Value::Bool(false)),
+            ("AF[*] > 0.5", Value::Bool(false)),
+        ] {
+            assert_eq!(eval(filter), expected, "{}", filter);
+        }
+
+        assert_eq!(Outcome::of(&Value::Missing, true), Outcome::Unknown);
+        assert_eq!(Outcome::of(&Value::Missing, false), Outcome::Fail);
+        assert_eq!(Outcome::of(&Value::Bool(true), true), Outcome::Pass);
+    }
+
+    #[test]
+    fn test_collation() {
+        let header = r#"##INFO=<ID=AF,Number=A,Type=Float,Description="Allele frequency">
+##INFO=<ID=CLNSIG,Number=.,Type=String,Description="Clinical significance">"#;
+        let info_map = parse_header(header).unwrap();
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\tAF=0.1000001;CLNSIG=Likely_Pathogenic";
+        let row = parse_row(row, &info_map).unwrap();
+        let ctx = EvalContext::new(&info_map).with_collation(Collation {
+            case_insensitive: true,
+            float_tolerance: 1e-6,
+        });
+        let eval = |ctx: &EvalContext, filter: &str| {
+            evaluate_with(&parse_filter(filter).unwrap(), &row, ctx).unwrap()
+        };
+
+        for filter in [
+            r#"CLNSIG == "likely_pathogenic""#,
+            r#"CLNSIG contains "PATHO""#,
+            r#"CLNSIG in ["benign", "LIKELY_PATHOGENIC"]"#,
+            r#"CLNSIG > "a""#,
+            "AF[0] == 0.1",
+            "AF[0] <= 0.1",
+            "AF[*] in [0.1, 0.2]",
+            "!(AF[0] > 0.1)",
+        ] {
+            assert_eq!(eval(&ctx, filter), Value::Bool(true), "{}", filter);
+            assert_eq!(
+                eval(&EvalContext::new(&info_map), filter),
+                Value::Bool(false),
+                "{}",
+                filter
+            );
+        }
+    }
 }
@@ -0,0 +1,200 @@
+//! Evaluation traces: why a row passed or failed a filter.
+//!
+//! [`FilterEngine::explain`](crate::FilterEngine::explain) evaluates a filter
+//! and records what each sub-expression evaluated to. Logical operators,
+//! comparisons, `!`, `all()`, `let` and function calls are broken down into
+//! their operands; literals are left out. The right side of a
+//! short-circuited `&&` or `||` is reported as not evaluated.
+//!
+//! An [`Explanation`] displays as an ASCII tree:
+//!
+//! ```text
+//! QUAL > 30 && DP >= 10 => false
+//! |-- QUAL > 30 => true
+//! |   `-- QUAL => 37.5
+//! `-- DP >= 10 => false
+//!     `-- DP => null
+//! ```
+
+use std::fmt;
+
+use crate::error::Result;
+use crate::eval::{Binding, EvalContext, Quantifier, evaluate_with, value_to_bool};
+use crate::filter::{BinaryOp, Expr};
+use crate::row::VcfRow;
+use crate::value::Value;
+
+/// What a sub-expression of a filter evaluated to, with its operands.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Explanation {
+    /// Canonical text of the sub-expression.
+    pub expr: String,
+    /// Its value, or `None` if short-circuiting skipped it.
+    pub value: Option<Value>,
+    /// Explanations of its non-literal operands, in expression order.
+    pub children: Vec<Explanation>,
+}
+
+impl Explanation {
+    /// Whether the explained expression evaluated to `true`.
+    pub fn passed(&self) -> bool {
+        self.value == Some(Value::Bool(true))
+    }
+
+    /// Write this node and its children, one line each.
+    fn write_tree(&self, f: &mut fmt::Formatter<'_>, indent: &str) -> fmt::Result {
+        match &self.value {
+            Some(value) => write!(f, "{} => {}", self.expr, value)?,
+            None => write!(f, "{} (not evaluated)", self.expr)?,
+        }
+        for (i, child) in self.children.iter().enumerate() {
+            let last = i + 1 == self.children.len();
+            let (branch, continuation) = if last {
+                ("`-- ", "    ")
+            } else {
+                ("|-- ", "|   ")
+            };
+            write!(f, "\n{}{}", indent, branch)?;
+            child.write_tree(f, &format!("{}{}", indent, continuation))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_tree(f, "")
+    }
+}
+
+/// Explain an expression's evaluation against a row.
+pub(crate) fn explain(expr: &Expr, row: &VcfRow, ctx: &EvalContext) -> Result<Explanation> {
+    let value = evaluate_with(expr, row, ctx)?;
+    let children = match expr {
+        Expr::Binary(left, op @ (BinaryOp::And | BinaryOp::Or), right) => {
+            let left = explain(left, row, ctx)?;
+            let right = if decides(left.value.as_ref(), op, ctx)? {
+                skipped(right)
+            } else {
+                explain(right, row, ctx)?
+            };
+            vec![left, right]
+        }
+        Expr::Binary(left, _, right) => explain_operands([&**left, &**right], row, ctx)?,
+        Expr::Unary(_, inner) => explain_operands([&**inner], row, ctx)?,
+        Expr::All(inner) => {
+            let all_ctx = EvalContext {
+                quantifier: Quantifier::All,
+                ..*ctx
+            };
+            explain_operands([&**inner], row, &all_ctx)?
+        }
+        Expr::Call(_, args) => explain_operands(args, row, ctx)?,
+        Expr::Let(name, bound, body) => {
+            let binding = Binding {
+                name,
+                value: evaluate_with(bound, row, ctx)?,
+                parent: ctx.bindings,
+            };
+            let let_ctx = EvalContext {
+                bindings: Some(&binding),
+                ..*ctx
+            };
+            let mut children = explain_operands([&**bound], row, ctx)?;
+            children.push(explain(body, row, &let_ctx)?);
+            children
+        }
+        _ => Vec::new(),
+    };
+    Ok(Explanation {
+        expr: expr.to_string(),
+        value: Some(value),
+        children,
+    })
+}
+
+/// Explain the operands of an expression that are not literals.
+fn explain_operands<'e>(
+    operands: impl IntoIterator<Item = &'e Expr>,
+    row: &VcfRow,
+    ctx: &EvalContext,
+) -> Result<Vec<Explanation>> {
+    operands
+        .into_iter()
+        .filter(|operand| !is_literal(operand))
+        .map(|operand| explain(operand, row, ctx))
+        .collect()
+}
+
+/// Whether the left side of `&&`/`||` decided the result, skipping the right side.
+fn decides(left: Option<&Value>, op: &BinaryOp, ctx: &EvalContext) -> Result<bool> {
+    let decisive = *op == BinaryOp::Or;
+    Ok(match left {
+        None => true,
+        Some(Value::Missing) if ctx.three_valued => false,
+        Some(value) => value_to_bool(value)? == decisive,
+    })
+}
+
+/// An operand that short-circuiting did not evaluate.
+fn skipped(expr: &Expr) -> Explanation {
+    Explanation {
+        expr: expr.to_string(),
+        value: None,
+        children: Vec::new(),
+    }
+}
+
+/// Whether an expression is a constant, which needs no explaining.
+fn is_literal(expr: &Expr) -> bool {
+    match expr {
+        Expr::Number(_) | Expr::String(_) | Expr::Bool(_) => true,
+        Expr::List(items) => items.iter().all(is_literal),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::parse_filter;
+    use crate::header::parse_header;
+    use crate::row::parse_row;
+
+    #[test]
+    fn test_explanation_tree() {
+        let header = r#"##INFO=<ID=DP,Number=1,Type=Integer,Description="Total depth">"#;
+        let info_map = parse_header(header).unwrap();
+        let row = parse_row("chr1\t100\t.\tA\tG\t37.5\tPASS\t.", &info_map).unwrap();
+        let ctx = EvalContext::new(&info_map);
+        let explain = |filter: &str| explain(&parse_filter(filter).unwrap(), &row, &ctx).unwrap();
+
+        let explanation = explain("QUAL > 30 && DP >= 10");
+        assert!(!explanation.passed());
+        assert_eq!(
+            explanation.to_string(),
+            "QUAL > 30 && DP >= 10 => false\n\
+             |-- QUAL > 30 => true\n\
+             |   `-- QUAL => 37.5\n\
+             `-- DP >= 10 => false\n    \
+             `-- DP => null"
+        );
+
+        // The right side of a decided `||` is skipped
+        let explanation = explain(r#"FILTER == "PASS" || DP > 10"#);
+        assert!(explanation.passed());
+        assert_eq!(explanation.children[1].value, None);
+        assert_eq!(
+            explanation.children[1].to_string(),
+            "DP > 10 (not evaluated)"
+        );
+
+        // Bindings are in scope for the body
+        let explanation = explain("let q = QUAL / 2 in q > 10");
+        assert_eq!(explanation.children.len(), 2);
+        assert_eq!(
+            explanation.children[1].children[0].value,
+            Some(Value::Number(18.75))
+        );
+    }
+}
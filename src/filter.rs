@@ -7,24 +7,37 @@
 //! - `ANN[*].Annotation_Impact == "HIGH"` (any match)
 //! - `DP > 10 && QUAL >= 30`
 //! - `CLNSIG == "Benign" || CLNSIG == "Likely_benign"`
+//! - `let ab = AD[1] / DP in ab > 0.2 && ab < 0.8`
+
+use std::fmt;
 
 use chumsky::prelude::*;
 
+use crate::error::VcfFilterError;
+
 /// Binary operators for comparisons and logic.
 #[derive(Debug, Clone, PartialEq)]
 pub enum BinaryOp {
     // Comparison
-    Eq,       // ==
-    NotEq,    // !=
-    Lt,       // <
-    Gt,       // >
-    LtEq,     // <=
-    GtEq,     // >=
-    Contains, // contains (string contains)
+    Eq,              // ==
+    NotEq,           // !=
+    Lt,              // <
+    Gt,              // >
+    LtEq,            // <=
+    GtEq,            // >=
+    Contains,        // contains (string contains)
+    In,              // in (membership in a list)
+    SeverityAtLeast, // severity_at_least (consequence term ranking)
 
     // Logical
     And, // &&
     Or,  // ||
+
+    // Arithmetic
+    Add, // +
+    Sub, // -
+    Mul, // *
+    Div, // /
 }
 
 /// Unary operators.
@@ -42,6 +55,23 @@ pub enum AccessPart {
     Index(usize),
     /// Wildcard array access (e.g., [*] - matches any).
     Wildcard,
+    /// Universal array access (e.g., [all] - every element must match).
+    All,
+    /// The ALT allele being evaluated (e.g., `AF[alt]`).
+    ///
+    /// A filter using `[alt]` is evaluated once per ALT allele and passes if
+    /// any allele passes. `Number=R` fields skip their leading REF value.
+    Alt,
+    /// A key pattern with `*` wildcards (e.g., `gnomAD_*` in `INFO.gnomAD_*`).
+    Glob(String),
+    /// A sample selected by name (e.g., `["NA12878"]` in `SAMPLE["NA12878"].GT`
+    /// or `FORMAT.DP["tumor"]`).
+    Sample(String),
+    /// Predicate-filtered access (e.g., `[?(Annotation_Impact == "HIGH")]`).
+    ///
+    /// Keeps only the annotations for which the predicate is true. Bare names
+    /// inside the predicate resolve to the current annotation's subfields.
+    Predicate(Box<Expr>),
 }
 
 /// A filter expression AST node.
@@ -62,6 +92,19 @@ pub enum Expr {
     Unary(UnaryOp, Box<Expr>),
     /// Check if a field exists (is not missing).
     Exists(Vec<AccessPart>),
+    /// Require wildcard comparisons inside the expression to hold for every element.
+    All(Box<Expr>),
+    /// A list literal (e.g., `["Pathogenic", "Likely_pathogenic"]`).
+    List(Vec<Expr>),
+    /// A call to a named function with argument expressions.
+    /// e.g., `gt_confidence()` becomes `Call("gt_confidence", [])`
+    Call(String, Vec<Expr>),
+    /// Bind a name to a value for use in the body expression.
+    /// e.g., `let ab = AD[1] / DP in ab > 0.2` becomes `Let("ab", AD[1] / DP, ab > 0.2)`
+    Let(String, Box<Expr>, Box<Expr>),
+    /// A reference to a named filter (e.g., `@rare_coding`), replaced by its
+    /// definition when the filter is parsed by a `FilterEngine`.
+    Preset(String),
 }
 
 impl Expr {
@@ -69,13 +112,186 @@ impl Expr {
     pub fn var(name: &str) -> Self {
         Expr::Var(vec![AccessPart::Field(name.to_string())])
     }
+
+    /// Get the direct sub-expressions of this node.
+    pub fn children(&self) -> Vec<&Expr> {
+        match self {
+            Expr::Binary(left, _, right) | Expr::Let(_, left, right) => vec![left, right],
+            Expr::Unary(_, inner) | Expr::All(inner) => vec![inner],
+            Expr::Call(_, args) | Expr::List(args) => args.iter().collect(),
+            Expr::Var(parts) | Expr::Exists(parts) => parts
+                .iter()
+                .filter_map(|part| match part {
+                    AccessPart::Predicate(pred) => Some(pred.as_ref()),
+                    _ => None,
+                })
+                .collect(),
+            Expr::Number(_) | Expr::String(_) | Expr::Bool(_) | Expr::Preset(_) => vec![],
+        }
+    }
+}
+
+impl BinaryOp {
+    /// Binding strength; higher binds tighter.
+    fn precedence(&self) -> u8 {
+        match self {
+            BinaryOp::Or => 1,
+            BinaryOp::And => 2,
+            BinaryOp::Eq
+            | BinaryOp::NotEq
+            | BinaryOp::Lt
+            | BinaryOp::Gt
+            | BinaryOp::LtEq
+            | BinaryOp::GtEq
+            | BinaryOp::Contains
+            | BinaryOp::In
+            | BinaryOp::SeverityAtLeast => 3,
+            BinaryOp::Add | BinaryOp::Sub => 4,
+            BinaryOp::Mul | BinaryOp::Div => 5,
+        }
+    }
+}
+
+impl fmt::Display for BinaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BinaryOp::Eq => "==",
+            BinaryOp::NotEq => "!=",
+            BinaryOp::Lt => "<",
+            BinaryOp::Gt => ">",
+            BinaryOp::LtEq => "<=",
+            BinaryOp::GtEq => ">=",
+            BinaryOp::Contains => "contains",
+            BinaryOp::In => "in",
+            BinaryOp::SeverityAtLeast => "severity_at_least",
+            BinaryOp::And => "&&",
+            BinaryOp::Or => "||",
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+        })
+    }
+}
+
+impl fmt::Display for UnaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnaryOp::Not => f.write_str("!"),
+        }
+    }
+}
+
+/// Precedence of `let`, which extends as far right as possible.
+const LET_PRECEDENCE: u8 = 0;
+/// Precedence of `!`.
+const UNARY_PRECEDENCE: u8 = 6;
+/// Precedence of literals, variables, calls and other self-delimiting forms.
+const ATOM_PRECEDENCE: u8 = 7;
+
+impl Expr {
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Let(..) => LET_PRECEDENCE,
+            Expr::Binary(_, op, _) => op.precedence(),
+            Expr::Unary(..) => UNARY_PRECEDENCE,
+            _ => ATOM_PRECEDENCE,
+        }
+    }
+
+    /// Write this expression, parenthesized if it binds looser than `min`.
+    fn fmt_at(&self, f: &mut fmt::Formatter<'_>, min: u8) -> fmt::Result {
+        if self.precedence() < min {
+            write!(f, "({})", self)
+        } else {
+            write!(f, "{}", self)
+        }
+    }
+}
+
+/// Canonical filter text: single spaces around binary operators and only the
+/// parentheses precedence requires. The output parses back to an equal `Expr`,
+/// except for strings containing `"` and non-finite numbers, which the filter
+/// syntax cannot express.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Number(n) => write!(f, "{}", n),
+            Expr::String(s) => write!(f, "\"{}\"", s),
+            Expr::Bool(b) => write!(f, "{}", b),
+            Expr::Var(parts) => write_path(f, parts),
+            Expr::Binary(left, op, right) => {
+                // Binary operators are left-associative
+                left.fmt_at(f, op.precedence())?;
+                write!(f, " {} ", op)?;
+                right.fmt_at(f, op.precedence() + 1)
+            }
+            Expr::Unary(op, inner) => {
+                write!(f, "{}", op)?;
+                inner.fmt_at(f, UNARY_PRECEDENCE)
+            }
+            Expr::Exists(parts) => {
+                f.write_str("exists(")?;
+                write_path(f, parts)?;
+                f.write_str(")")
+            }
+            Expr::All(inner) => write!(f, "all({})", inner),
+            Expr::List(items) => {
+                f.write_str("[")?;
+                write_separated(f, items)?;
+                f.write_str("]")
+            }
+            Expr::Call(name, args) => {
+                write!(f, "{}(", name)?;
+                write_separated(f, args)?;
+                f.write_str(")")
+            }
+            Expr::Let(name, value, body) => {
+                write!(f, "let {} = ", name)?;
+                // Binding values are parsed at the arithmetic level
+                value.fmt_at(f, BinaryOp::Add.precedence())?;
+                write!(f, " in {}", body)
+            }
+            Expr::Preset(name) => write!(f, "@{}", name),
+        }
+    }
+}
+
+/// Write an access path such as `ANN[?(Annotation_Impact == "HIGH")].Gene_Name`.
+fn write_path(f: &mut fmt::Formatter<'_>, parts: &[AccessPart]) -> fmt::Result {
+    for (i, part) in parts.iter().enumerate() {
+        match part {
+            AccessPart::Field(name) if i == 0 => f.write_str(name)?,
+            AccessPart::Field(name) | AccessPart::Glob(name) => write!(f, ".{}", name)?,
+            AccessPart::Index(index) => write!(f, "[{}]", index)?,
+            AccessPart::Wildcard => f.write_str("[*]")?,
+            AccessPart::All => f.write_str("[all]")?,
+            AccessPart::Alt => f.write_str("[alt]")?,
+            AccessPart::Sample(name) => write!(f, "[\"{}\"]", name)?,
+            AccessPart::Predicate(pred) => write!(f, "[?({})]", pred)?,
+        }
+    }
+    Ok(())
+}
+
+/// Write comma-separated expressions.
+fn write_separated(f: &mut fmt::Formatter<'_>, exprs: &[Expr]) -> fmt::Result {
+    for (i, expr) in exprs.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{}", expr)?;
+    }
+    Ok(())
 }
 
 /// Create the filter expression parser.
 pub fn parser() -> impl Parser<char, Expr, Error = Simple<char>> {
     recursive(|full_expr| {
-        // Number literal
-        let number = text::int(10)
+        // Number literal, optionally negative (`-` must touch the digits)
+        let number = just('-')
+            .or_not()
+            .chain::<char, _, _>(text::int(10))
             .chain::<char, _, _>(just('.').chain(text::digits(10)).or_not().flatten())
             .collect::<String>()
             .map(|s| Expr::Number(s.parse().unwrap()))
@@ -99,24 +315,45 @@ pub fn parser() -> impl Parser<char, Expr, Error = Simple<char>> {
         // Identifier (field name)
         let ident = text::ident().padded();
 
-        // Array index: [0], [1], etc.
+        // Predicate: ?(expr)
+        let predicate = just('?')
+            .ignore_then(just('(').padded())
+            .ignore_then(full_expr.clone())
+            .then_ignore(just(')').padded())
+            .map(|expr| AccessPart::Predicate(Box::new(expr)));
+
+        // Sample name: ["NA12878"]
+        let sample = just('"')
+            .ignore_then(filter(|c| *c != '"').repeated())
+            .then_ignore(just('"'))
+            .collect::<String>()
+            .map(AccessPart::Sample);
+
+        // Array index: [0], [1], [*], [all], [alt], [?(expr)], ["sample"]
         let array_index = just('[')
             .ignore_then(
-                just('*')
-                    .to(AccessPart::Wildcard)
-                    .or(text::int(10).map(|s: String| AccessPart::Index(s.parse().unwrap()))),
+                choice((
+                    just('*').to(AccessPart::Wildcard),
+                    text::keyword("all").to(AccessPart::All),
+                    text::keyword("alt").to(AccessPart::Alt),
+                    text::int(10).map(|s: String| AccessPart::Index(s.parse().unwrap())),
+                    predicate,
+                    sample,
+                ))
+                .padded(),
             )
             .then_ignore(just(']'));
 
-        // Field access: .FieldName
+        // Field access: .FieldName (may start a new line: `ANN[*]\n  .Gene_Name`)
         let field_access = just('.')
+            .padded()
             .ignore_then(text::ident().padded())
             .map(|s: String| AccessPart::Field(s));
 
         // Variable with optional access chain: ANN[0].Gene_Name
         let variable = ident
             .map(|s: String| AccessPart::Field(s))
-            .then(choice((array_index.clone(), field_access.clone())).repeated())
+            .then(choice((array_index.clone(), field_access)).repeated())
             .map(|(first, rest)| {
                 let mut parts = vec![first];
                 parts.extend(rest);
@@ -124,6 +361,30 @@ pub fn parser() -> impl Parser<char, Expr, Error = Simple<char>> {
             })
             .padded();
 
+        // INFO key glob: INFO.gnomAD_* matches every INFO key starting with "gnomAD_"
+        let info_glob = text::keyword("INFO")
+            .ignore_then(just('.'))
+            .ignore_then(
+                filter(|c: &char| c.is_ascii_alphanumeric() || *c == '_' || *c == '*')
+                    .repeated()
+                    .at_least(1)
+                    .collect::<String>(),
+            )
+            .try_map(|pattern, span| {
+                if pattern.contains('*') {
+                    Ok(Expr::Var(vec![
+                        AccessPart::Field("INFO".to_string()),
+                        AccessPart::Glob(pattern),
+                    ]))
+                } else {
+                    Err(Simple::custom(
+                        span,
+                        "expected a key pattern containing '*'",
+                    ))
+                }
+            })
+            .padded();
+
         // exists(field) function
         let exists_fn = text::keyword("exists")
             .padded()
@@ -145,14 +406,48 @@ pub fn parser() -> impl Parser<char, Expr, Error = Simple<char>> {
             )
             .map(Expr::Exists);
 
+        // Preset reference: @name
+        let preset = just('@')
+            .ignore_then(text::ident())
+            .map(Expr::Preset)
+            .padded();
+
         // Parenthesized expression (uses full_expr recursively)
         let paren_expr = just('(')
             .padded()
-            .ignore_then(full_expr)
+            .ignore_then(full_expr.clone())
             .then_ignore(just(')').padded());
 
-        // Atoms: literals, variables, or parenthesized expressions
-        let atom = choice((exists_fn, boolean, number, string, paren_expr, variable));
+        // all(expr) universal quantifier
+        let all_fn = text::keyword("all")
+            .padded()
+            .ignore_then(paren_expr.clone())
+            .map(|expr| Expr::All(Box::new(expr)));
+
+        // List literal: [expr, ...]
+        let list = full_expr
+            .clone()
+            .separated_by(just(',').padded())
+            .allow_trailing()
+            .delimited_by(just('[').padded(), just(']').padded())
+            .map(Expr::List);
+
+        // Function call: name(arg, ...)
+        let call = text::ident()
+            .padded()
+            .then(
+                full_expr
+                    .clone()
+                    .separated_by(just(',').padded())
+                    .delimited_by(just('(').padded(), just(')').padded()),
+            )
+            .map(|(name, args)| Expr::Call(name, args));
+
+        // Atoms: literals, function calls, variables, or parenthesized expressions
+        let atom = choice((
+            exists_fn, all_fn, boolean, number, string, list, preset, paren_expr, call, info_glob,
+            variable,
+        ));
 
         // Unary operators (!)
         let unary = just('!')
@@ -161,6 +456,21 @@ pub fn parser() -> impl Parser<char, Expr, Error = Simple<char>> {
             .then(atom)
             .foldr(|_op, expr| Expr::Unary(UnaryOp::Not, Box::new(expr)));
 
+        // Multiplication and division
+        let product_op =
+            choice((just('*').to(BinaryOp::Mul), just('/').to(BinaryOp::Div))).padded();
+        let product = unary
+            .clone()
+            .then(product_op.then(unary).repeated())
+            .foldl(|left, (op, right)| Expr::Binary(Box::new(left), op, Box::new(right)));
+
+        // Addition and subtraction
+        let sum_op = choice((just('+').to(BinaryOp::Add), just('-').to(BinaryOp::Sub))).padded();
+        let sum = product
+            .clone()
+            .then(sum_op.then(product).repeated())
+            .foldl(|left, (op, right)| Expr::Binary(Box::new(left), op, Box::new(right)));
+
         // Comparison operators
         let cmp_op = choice((
             just("==").to(BinaryOp::Eq),
@@ -170,13 +480,26 @@ pub fn parser() -> impl Parser<char, Expr, Error = Simple<char>> {
             just("<").to(BinaryOp::Lt),
             just(">").to(BinaryOp::Gt),
             text::keyword("contains").to(BinaryOp::Contains),
+            text::keyword("in").to(BinaryOp::In),
+            text::keyword("severity_at_least").to(BinaryOp::SeverityAtLeast),
         ))
         .padded();
 
+        // `x in_file("genes.txt")` is shorthand for `x in in_file("genes.txt")`
+        let in_file = text::keyword("in_file")
+            .padded()
+            .ignore_then(
+                full_expr
+                    .clone()
+                    .separated_by(just(',').padded())
+                    .delimited_by(just('(').padded(), just(')').padded()),
+            )
+            .map(|args| (BinaryOp::In, Expr::Call("in_file".to_string(), args)));
+
         // Comparison expressions
-        let comparison = unary
+        let comparison = sum
             .clone()
-            .then(cmp_op.then(unary).repeated())
+            .then(choice((in_file, cmp_op.then(sum.clone()))).repeated())
             .foldl(|left, (op, right)| Expr::Binary(Box::new(left), op, Box::new(right)));
 
         // Logical AND (&&)
@@ -188,10 +511,25 @@ pub fn parser() -> impl Parser<char, Expr, Error = Simple<char>> {
 
         // Logical OR (||)
         let or_op = just("||").padded().to(BinaryOp::Or);
-        and_expr
+        let or_expr = and_expr
             .clone()
             .then(or_op.then(and_expr).repeated())
-            .foldl(|left, (op, right)| Expr::Binary(Box::new(left), op, Box::new(right)))
+            .foldl(|left, (op, right)| Expr::Binary(Box::new(left), op, Box::new(right)));
+
+        // Let-bindings: let name = value, ... in body
+        // Values are arithmetic expressions; parenthesize comparisons.
+        let binding = text::ident()
+            .padded()
+            .then_ignore(just('=').padded())
+            .then(sum);
+        let let_expr = text::keyword("let")
+            .padded()
+            .ignore_then(binding.separated_by(just(',').padded()).at_least(1))
+            .then_ignore(text::keyword("in").padded())
+            .then(full_expr)
+            .foldr(|(name, value), body| Expr::Let(name, Box::new(value), Box::new(body)));
+
+        choice((let_expr, or_expr))
     })
     .then_ignore(end())
 }
@@ -201,10 +539,30 @@ pub fn parse_filter(filter: &str) -> Result<Expr, Vec<Simple<char>>> {
     parser().parse(filter)
 }
 
+/// Convert parser errors into a single `FilterParseError`.
+pub(crate) fn parse_error(errs: Vec<Simple<char>>) -> VcfFilterError {
+    VcfFilterError::FilterParseError(
+        errs.into_iter()
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>()
+            .join(", "),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_multi_line_filter() {
+        let filter = "\n    CLNSIG in [\n        \"Pathogenic\",\n        \"Likely_pathogenic\",\n    ]\r\n\t&& ANN[ ?( Annotation_Impact == \"HIGH\" ) ]\n        .Gene_Name == \"BRCA1\"\n    && exists(\n        ANN[ * ].HGVS_p\n    )\n";
+        let expected = parse_filter(
+            r#"CLNSIG in ["Pathogenic", "Likely_pathogenic"] && ANN[?(Annotation_Impact == "HIGH")].Gene_Name == "BRCA1" && exists(ANN[*].HGVS_p)"#,
+        )
+        .unwrap();
+        assert_eq!(parse_filter(filter).unwrap(), expected);
+    }
+
     #[test]
     fn test_parse_simple_comparison() {
         let expr = parse_filter("QUAL > 30").unwrap();
@@ -281,6 +639,209 @@ mod tests {
         assert!(matches!(expr, Expr::Exists(_)));
     }
 
+    #[test]
+    fn test_parse_all_quantifier() {
+        let expr = parse_filter(r#"ANN[all].Annotation_Impact != "HIGH""#).unwrap();
+        if let Expr::Binary(left, BinaryOp::NotEq, _) = expr {
+            assert_eq!(
+                *left,
+                Expr::Var(vec![
+                    AccessPart::Field("ANN".to_string()),
+                    AccessPart::All,
+                    AccessPart::Field("Annotation_Impact".to_string()),
+                ])
+            );
+        } else {
+            panic!("Expected Binary");
+        }
+
+        let expr = parse_filter(r#"all(ANN[*].Gene_Name == "BRCA1")"#).unwrap();
+        assert!(matches!(expr, Expr::All(_)));
+    }
+
+    #[test]
+    fn test_parse_predicate_access() {
+        let expr = parse_filter(r#"ANN[?(Annotation_Impact == "HIGH")].Gene_Name contains "TP53""#)
+            .unwrap();
+        if let Expr::Binary(left, BinaryOp::Contains, _) = expr {
+            assert_eq!(
+                *left,
+                Expr::Var(vec![
+                    AccessPart::Field("ANN".to_string()),
+                    AccessPart::Predicate(Box::new(Expr::Binary(
+                        Box::new(Expr::var("Annotation_Impact")),
+                        BinaryOp::Eq,
+                        Box::new(Expr::String("HIGH".to_string())),
+                    ))),
+                    AccessPart::Field("Gene_Name".to_string()),
+                ])
+            );
+        } else {
+            panic!("Expected Binary");
+        }
+    }
+
+    #[test]
+    fn test_parse_info_glob() {
+        let expr = parse_filter("max(INFO.gnomAD_*_AF) > 0.01").unwrap();
+        if let Expr::Binary(left, BinaryOp::Gt, _) = expr {
+            assert_eq!(
+                *left,
+                Expr::Call(
+                    "max".to_string(),
+                    vec![Expr::Var(vec![
+                        AccessPart::Field("INFO".to_string()),
+                        AccessPart::Glob("gnomAD_*_AF".to_string()),
+                    ])]
+                )
+            );
+        } else {
+            panic!("Expected Binary");
+        }
+
+        // Without '*' it is an ordinary INFO path
+        assert_eq!(
+            parse_filter("INFO.AF").unwrap(),
+            Expr::Var(vec![
+                AccessPart::Field("INFO".to_string()),
+                AccessPart::Field("AF".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_in_list() {
+        let expr = parse_filter(r#"CLNSIG in ["Pathogenic", "Likely_pathogenic"]"#).unwrap();
+        assert_eq!(
+            expr,
+            Expr::Binary(
+                Box::new(Expr::var("CLNSIG")),
+                BinaryOp::In,
+                Box::new(Expr::List(vec![
+                    Expr::String("Pathogenic".to_string()),
+                    Expr::String("Likely_pathogenic".to_string()),
+                ])),
+            )
+        );
+
+        // Identifiers starting with "in" are still fields
+        assert_eq!(parse_filter("index").unwrap(), Expr::var("index"));
+
+        // `in_file(...)` after an operand is shorthand for `in in_file(...)`
+        assert_eq!(
+            parse_filter(r#"ANN[*].Gene_Name in_file("acmg_genes.txt")"#).unwrap(),
+            parse_filter(r#"ANN[*].Gene_Name in in_file("acmg_genes.txt")"#).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_arithmetic_precedence() {
+        let bin = |l: Expr, op: BinaryOp, r: Expr| Expr::Binary(Box::new(l), op, Box::new(r));
+        assert_eq!(
+            parse_filter("DP - AD[0] * 2 > 1 + 1").unwrap(),
+            bin(
+                bin(
+                    Expr::var("DP"),
+                    BinaryOp::Sub,
+                    bin(
+                        Expr::Var(vec![
+                            AccessPart::Field("AD".to_string()),
+                            AccessPart::Index(0)
+                        ]),
+                        BinaryOp::Mul,
+                        Expr::Number(2.0)
+                    )
+                ),
+                BinaryOp::Gt,
+                bin(Expr::Number(1.0), BinaryOp::Add, Expr::Number(1.0))
+            )
+        );
+        // Division is left-associative
+        assert_eq!(
+            parse_filter("a / b / c").unwrap(),
+            bin(
+                bin(Expr::var("a"), BinaryOp::Div, Expr::var("b")),
+                BinaryOp::Div,
+                Expr::var("c")
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_let() {
+        let expr = parse_filter("let ab = AD / DP, hi = ab * 2 in hi > 1").unwrap();
+        assert_eq!(
+            expr,
+            Expr::Let(
+                "ab".to_string(),
+                Box::new(Expr::Binary(
+                    Box::new(Expr::var("AD")),
+                    BinaryOp::Div,
+                    Box::new(Expr::var("DP"))
+                )),
+                Box::new(Expr::Let(
+                    "hi".to_string(),
+                    Box::new(Expr::Binary(
+                        Box::new(Expr::var("ab")),
+                        BinaryOp::Mul,
+                        Box::new(Expr::Number(2.0))
+                    )),
+                    Box::new(Expr::Binary(
+                        Box::new(Expr::var("hi")),
+                        BinaryOp::Gt,
+                        Box::new(Expr::Number(1.0))
+                    )),
+                ))
+            )
+        );
+
+        // A let inside a larger expression needs parentheses
+        assert!(parse_filter("QUAL > 1 && (let x = DP in x > 1)").is_ok());
+        assert!(parse_filter("let x = DP").is_err());
+        // Identifiers starting with "let" are still fields
+        assert_eq!(parse_filter("letter").unwrap(), Expr::var("letter"));
+    }
+
+    #[test]
+    fn test_parse_preset_reference() {
+        assert_eq!(
+            parse_filter("@rare && QUAL > 30").unwrap(),
+            Expr::Binary(
+                Box::new(Expr::Preset("rare".to_string())),
+                BinaryOp::And,
+                Box::new(Expr::Binary(
+                    Box::new(Expr::var("QUAL")),
+                    BinaryOp::Gt,
+                    Box::new(Expr::Number(30.0))
+                )),
+            )
+        );
+        assert!(parse_filter("@ rare").is_err());
+    }
+
+    #[test]
+    fn test_parse_function_call() {
+        let expr = parse_filter("gt_confidence() >= 20").unwrap();
+        if let Expr::Binary(left, BinaryOp::GtEq, _) = expr {
+            assert_eq!(*left, Expr::Call("gt_confidence".to_string(), vec![]));
+        } else {
+            panic!("Expected Binary");
+        }
+
+        let expr = parse_filter(r#"f(DP, "x", 1)"#).unwrap();
+        assert_eq!(
+            expr,
+            Expr::Call(
+                "f".to_string(),
+                vec![
+                    Expr::var("DP"),
+                    Expr::String("x".to_string()),
+                    Expr::Number(1.0)
+                ]
+            )
+        );
+    }
+
     #[test]
     fn test_parse_contains() {
         let expr = parse_filter(r#"CLNDN contains "BRCA""#).unwrap();
@@ -299,6 +860,90 @@ mod tests {
         assert_eq!(expr, Expr::Number(42.5));
     }
 
+    #[test]
+    fn test_parse_negative_number_literal() {
+        assert_eq!(parse_filter("-1.5").unwrap(), Expr::Number(-1.5));
+        assert_eq!(
+            parse_filter("DP - 1").unwrap(),
+            Expr::Binary(
+                Box::new(Expr::var("DP")),
+                BinaryOp::Sub,
+                Box::new(Expr::Number(1.0))
+            )
+        );
+    }
+
+    #[test]
+    fn test_display_is_canonical() {
+        let cases = [
+            (
+                "QUAL>30&&(DP<10||DP>=100)",
+                "QUAL > 30 && (DP < 10 || DP >= 100)",
+            ),
+            ("((a && b)) && c", "a && b && c"),
+            ("a && (b && c)", "a && (b && c)"),
+            ("!(DP > 10) && !!DB", "!(DP > 10) && !!DB"),
+            ("(AD[1] + 1) / DP * 2.50", "(AD[1] + 1) / DP * 2.5"),
+            ("a - (b - -3)", "a - (b - -3)"),
+            (
+                "(let x = (DP > 1) in x) || y",
+                "(let x = (DP > 1) in x) || y",
+            ),
+            (
+                "let a = 1, b = a + 1 in b",
+                "let a = 1 in let b = a + 1 in b",
+            ),
+            (
+                "ANN[ ?(Annotation_Impact in [\"HIGH\",\"MODERATE\",]) ].Gene_Name",
+                "ANN[?(Annotation_Impact in [\"HIGH\", \"MODERATE\"])].Gene_Name",
+            ),
+            (
+                "exists( INFO.ANN[*].HGVS_p ) || all(LOF[all].Perc > 0.5)",
+                "exists(INFO.ANN[*].HGVS_p) || all(LOF[all].Perc > 0.5)",
+            ),
+            ("max( INFO.gnomAD_* ) < 0.01", "max(INFO.gnomAD_*) < 0.01"),
+            ("AF[ alt ]>0.01", "AF[alt] > 0.01"),
+            (
+                "ANN[*].Annotation  severity_at_least\"stop_gained\"",
+                "ANN[*].Annotation severity_at_least \"stop_gained\"",
+            ),
+            ("@rare && default(AF,0)<1", "@rare && default(AF, 0) < 1"),
+            ("(a == b) == true", "a == b == true"),
+            ("a == (b == true)", "a == (b == true)"),
+            (
+                "GENE in_file( \"panel.txt\" )",
+                "GENE in in_file(\"panel.txt\")",
+            ),
+        ];
+        for (input, canonical) in cases {
+            let expr = parse_filter(input).unwrap();
+            assert_eq!(expr.to_string(), canonical, "{}", input);
+            assert_eq!(parse_filter(canonical).unwrap(), expr, "{}", canonical);
+        }
+    }
+
+    #[test]
+    fn test_display_round_trips_built_expressions() {
+        let expr = Expr::Binary(
+            Box::new(Expr::Unary(
+                UnaryOp::Not,
+                Box::new(Expr::Binary(
+                    Box::new(Expr::var("DP")),
+                    BinaryOp::Mul,
+                    Box::new(Expr::Number(-2.0)),
+                )),
+            )),
+            BinaryOp::Or,
+            Box::new(Expr::Let(
+                "x".to_string(),
+                Box::new(Expr::Number(1e-7)),
+                Box::new(Expr::var("x")),
+            )),
+        );
+        assert_eq!(expr.to_string(), "!(DP * -2) || (let x = 0.0000001 in x)");
+        assert_eq!(parse_filter(&expr.to_string()).unwrap(), expr);
+    }
+
     #[test]
     fn test_parse_namespaced_info_field() {
         let expr = parse_filter("INFO.DP > 12").unwrap();
@@ -311,6 +956,22 @@ mod tests {
         assert!(matches!(expr, Expr::Binary(_, BinaryOp::GtEq, _)));
     }
 
+    #[test]
+    fn test_parse_sample_access() {
+        let sample = |name: &str| AccessPart::Sample(name.to_string());
+        let field = |name: &str| AccessPart::Field(name.to_string());
+        assert_eq!(
+            parse_filter(r#"SAMPLE["NA12878"].GT"#).unwrap(),
+            Expr::Var(vec![field("SAMPLE"), sample("NA12878"), field("GT")])
+        );
+        assert_eq!(
+            parse_filter(r#"FORMAT.DP["tumor"]"#).unwrap(),
+            Expr::Var(vec![field("FORMAT"), field("DP"), sample("tumor")])
+        );
+        let expr = parse_filter(r#"SAMPLE[ "tumor sample" ].DP > 50"#).unwrap();
+        assert_eq!(expr.to_string(), r#"SAMPLE["tumor sample"].DP > 50"#);
+    }
+
     #[test]
     fn test_parse_namespaced_exists() {
         let expr = parse_filter("exists(INFO.DP)").unwrap();
@@ -0,0 +1,1365 @@
+//! Built-in and user-registered filter functions.
+//!
+//! Functions are called by name from the filter DSL (e.g., `gt_confidence()`).
+//! Built-ins receive their argument expressions unevaluated, so each function
+//! decides how (and whether) to evaluate them. Functions registered through
+//! [`FunctionRegistry`] (usually via
+//! [`FilterEngine::register_function`](crate::FilterEngine::register_function))
+//! receive their arguments already evaluated.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::error::{Result, VcfFilterError};
+use crate::eval::{
+    EvalContext, Quantifier, chrom_ranks, evaluate_with, resolve_variable, value_to_bool,
+};
+use crate::filter::{AccessPart, Expr};
+use crate::intervals::{Region, bed_name};
+use crate::lists::in_file;
+use crate::row::{DEFAULT_AF_TOLERANCE, Genotype, VariantClass, VcfRow};
+use crate::value::Value;
+
+/// Names of the built-in functions, which registered functions may not shadow.
+pub const BUILTIN_FUNCTIONS: &[&str] = &[
+    "gt_confidence",
+    "default",
+    "split",
+    "len",
+    "number",
+    "string",
+    "replace",
+    "substring",
+    "computed_af",
+    "af_consistent",
+    "is_missing",
+    "is_empty",
+    "max",
+    "min",
+    "abs",
+    "log10",
+    "sqrt",
+    "any",
+    "in_file",
+    "in_bed",
+    "region",
+    "is_snp",
+    "is_mnp",
+    "is_indel",
+    "is_sv",
+    "variant_type",
+    "alt_count",
+    "sv_type",
+    "sv_end",
+    "mate_chrom",
+    "mate_pos",
+    "is_het",
+    "is_hom_ref",
+    "is_hom_alt",
+    "is_phased",
+    "ploidy",
+    "n_het",
+    "n_hom_ref",
+    "n_hom_alt",
+    "n_missing",
+    "vaf",
+    "allele_balance",
+    "allele_len",
+    "indel_len",
+    "has_id",
+    "filter_has",
+    "chrom_rank",
+    // Parsed as dedicated syntax rather than calls
+    "exists",
+    "all",
+];
+
+/// A user-registered function taking evaluated arguments.
+pub type CustomFunction = dyn Fn(&[Value]) -> Result<Value> + Send + Sync;
+
+/// User-registered functions callable from filter expressions.
+#[derive(Clone, Default)]
+pub struct FunctionRegistry {
+    functions: HashMap<String, Arc<CustomFunction>>,
+}
+
+impl FunctionRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a function under `name`, replacing any previous registration.
+    ///
+    /// Fails if `name` is not an identifier or is a built-in function name.
+    pub fn register<F>(&mut self, name: &str, function: F) -> Result<()>
+    where
+        F: Fn(&[Value]) -> Result<Value> + Send + Sync + 'static,
+    {
+        let is_ident = name
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_ident {
+            return Err(VcfFilterError::ConfigError(format!(
+                "invalid function name: {}",
+                name
+            )));
+        }
+        if BUILTIN_FUNCTIONS.contains(&name) {
+            return Err(VcfFilterError::ConfigError(format!(
+                "cannot register built-in function: {}",
+                name
+            )));
+        }
+        self.functions.insert(name.to_string(), Arc::new(function));
+        Ok(())
+    }
+
+    /// Get a registered function by name.
+    pub fn get(&self, name: &str) -> Option<&CustomFunction> {
+        self.functions.get(name).map(Arc::as_ref)
+    }
+
+    /// Iterate over the registered function names.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.functions.keys().map(String::as_str)
+    }
+}
+
+impl fmt::Debug for FunctionRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.functions.keys()).finish()
+    }
+}
+
+/// Call a built-in or registered function by name.
+pub(crate) fn call_builtin(
+    name: &str,
+    args: &[Expr],
+    row: &VcfRow,
+    ctx: &EvalContext,
+) -> Result<Value> {
+    match name {
+        "gt_confidence" => {
+            expect_args(name, args, 0)?;
+            Ok(gt_confidence(row))
+        }
+        "default" => {
+            if args.len() < 2 {
+                return Err(VcfFilterError::EvaluationError(format!(
+                    "default() expects at least 2 arguments, got {}",
+                    args.len()
+                )));
+            }
+            let fallback = &args[args.len() - 1];
+            for arg in &args[..args.len() - 1] {
+                match evaluate_with(arg, row, ctx)? {
+                    Value::Missing => continue,
+                    Value::Array(arr) if arr.iter().any(Value::is_missing) => {
+                        // Fill missing elements (e.g., `AD=10,.`) with the fallback
+                        let fill = evaluate_with(fallback, row, ctx)?;
+                        return Ok(Value::Array(
+                            arr.into_iter()
+                                .map(|v| if v.is_missing() { fill.clone() } else { v })
+                                .collect(),
+                        ));
+                    }
+                    value => return Ok(value),
+                }
+            }
+            evaluate_with(fallback, row, ctx)
+        }
+        "split" => {
+            expect_args(name, args, 2)?;
+            let separator = match evaluate_with(&args[1], row, ctx)? {
+                Value::String(sep) if !sep.is_empty() => sep,
+                _ => {
+                    return Err(VcfFilterError::EvaluationError(
+                        "split() separator must be a non-empty string".to_string(),
+                    ));
+                }
+            };
+            Ok(split_value(evaluate_with(&args[0], row, ctx)?, &separator))
+        }
+        "len" => {
+            expect_args(name, args, 1)?;
+            let count = match evaluate_with(&args[0], row, ctx)? {
+                Value::Array(arr) => arr.len(),
+                Value::Missing => 0,
+                _ => 1,
+            };
+            Ok(Value::Number(count as f64))
+        }
+        "number" => {
+            expect_args(name, args, 1)?;
+            cast_number(evaluate_with(&args[0], row, ctx)?)
+        }
+        "string" => {
+            expect_args(name, args, 1)?;
+            Ok(cast_string(evaluate_with(&args[0], row, ctx)?))
+        }
+        "replace" => {
+            expect_args(name, args, 3)?;
+            let from = match evaluate_with(&args[1], row, ctx)? {
+                Value::String(from) if !from.is_empty() => from,
+                _ => {
+                    return Err(VcfFilterError::EvaluationError(
+                        "replace() pattern must be a non-empty string".to_string(),
+                    ));
+                }
+            };
+            let to = match evaluate_with(&args[2], row, ctx)? {
+                Value::String(to) => to,
+                _ => {
+                    return Err(VcfFilterError::EvaluationError(
+                        "replace() replacement must be a string".to_string(),
+                    ));
+                }
+            };
+            let value = evaluate_with(&args[0], row, ctx)?;
+            Ok(map_strings(value, &|s| s.replace(&from, &to)))
+        }
+        "substring" => {
+            if !(2..=3).contains(&args.len()) {
+                return Err(VcfFilterError::EvaluationError(format!(
+                    "substring() expects 2 or 3 arguments, got {}",
+                    args.len()
+                )));
+            }
+            let position = |arg: &Expr| -> Result<usize> {
+                match evaluate_with(arg, row, ctx)?.as_number() {
+                    Some(n) if n >= 0.0 && n.fract() == 0.0 => Ok(n as usize),
+                    _ => Err(VcfFilterError::EvaluationError(
+                        "substring() positions must be non-negative integers".to_string(),
+                    )),
+                }
+            };
+            let start = position(&args[1])?;
+            let end = args.get(2).map(position).transpose()?;
+            let value = evaluate_with(&args[0], row, ctx)?;
+            Ok(map_strings(value, &|s| {
+                let end = end.unwrap_or(usize::MAX).max(start);
+                s.chars().skip(start).take(end - start).collect()
+            }))
+        }
+        "computed_af" => {
+            expect_args(name, args, 0)?;
+            Ok(match row.computed_allele_frequencies() {
+                Some(afs) if afs.len() == 1 => Value::Number(afs[0]),
+                Some(afs) => Value::Array(afs.into_iter().map(Value::Number).collect()),
+                None => Value::Missing,
+            })
+        }
+        "af_consistent" => {
+            let tolerance = match args {
+                [] => DEFAULT_AF_TOLERANCE,
+                [arg] => evaluate_with(arg, row, ctx)?.as_number().ok_or_else(|| {
+                    VcfFilterError::EvaluationError(
+                        "af_consistent() tolerance must be a number".to_string(),
+                    )
+                })?,
+                _ => {
+                    return Err(VcfFilterError::EvaluationError(format!(
+                        "af_consistent() expects 0 or 1 argument(s), got {}",
+                        args.len()
+                    )));
+                }
+            };
+            Ok(match row.allele_frequency_mismatches(tolerance) {
+                Some(mismatches) => Value::Bool(mismatches.is_empty()),
+                None => Value::Missing,
+            })
+        }
+        "is_missing" => {
+            expect_args(name, args, 1)?;
+            let states = field_states(name, &args[0], row, ctx)?;
+            Ok(Value::Bool(states.contains(&FieldState::Missing)))
+        }
+        "is_empty" => {
+            expect_args(name, args, 1)?;
+            let states = field_states(name, &args[0], row, ctx)?;
+            Ok(Value::Bool(states.contains(&FieldState::Empty)))
+        }
+        "max" | "min" => {
+            let mut numbers = Vec::new();
+            for arg in args {
+                collect_numbers(&evaluate_with(arg, row, ctx)?, &mut numbers);
+            }
+            let pick = if name == "max" { f64::max } else { f64::min };
+            Ok(numbers
+                .into_iter()
+                .reduce(pick)
+                .map(Value::Number)
+                .unwrap_or(Value::Missing))
+        }
+        "abs" | "log10" | "sqrt" => {
+            expect_args(name, args, 1)?;
+            let f = match name {
+                "abs" => f64::abs,
+                "log10" => f64::log10,
+                _ => f64::sqrt,
+            };
+            Ok(map_numbers(evaluate_with(&args[0], row, ctx)?, f))
+        }
+        "any" => {
+            expect_args(name, args, 1)?;
+            let any_ctx = EvalContext {
+                quantifier: Quantifier::Any,
+                ..*ctx
+            };
+            let value = evaluate_with(&args[0], row, &any_ctx)?;
+            let result = match value {
+                Value::Array(arr) => arr
+                    .iter()
+                    .map(value_to_bool)
+                    .collect::<Result<Vec<_>>>()?
+                    .contains(&true),
+                other => value_to_bool(&other)?,
+            };
+            Ok(Value::Bool(result))
+        }
+        "is_snp" | "is_mnp" | "is_indel" | "is_sv" => {
+            expect_args(name, args, 0)?;
+            let matches: fn(&VariantClass) -> bool = match name {
+                "is_snp" => |class| class == &VariantClass::Snp,
+                "is_mnp" => |class| class == &VariantClass::Mnp,
+                "is_indel" => VariantClass::is_indel,
+                _ => VariantClass::is_sv,
+            };
+            Ok(Value::Bool(row.variant_classes().iter().any(matches)))
+        }
+        "has_id" => {
+            expect_args(name, args, 0)?;
+            Ok(Value::Bool(!row.ids().is_empty()))
+        }
+        "filter_has" => {
+            expect_args(name, args, 1)?;
+            let Value::String(filter) = evaluate_with(&args[0], row, ctx)? else {
+                return Err(VcfFilterError::EvaluationError(
+                    "filter_has() expects a filter name".to_string(),
+                ));
+            };
+            Ok(Value::Bool(row.filter.contains(&filter)))
+        }
+        "chrom_rank" => {
+            let chrom = match args {
+                [] => Value::String(row.chrom.clone()),
+                [arg] => evaluate_with(arg, row, ctx)?,
+                _ => {
+                    return Err(VcfFilterError::EvaluationError(format!(
+                        "{}() expects 0 or 1 argument(s), got {}",
+                        name,
+                        args.len()
+                    )));
+                }
+            };
+            Ok(chrom_ranks(chrom, ctx.contigs))
+        }
+        "is_het" | "is_hom_ref" | "is_hom_alt" | "is_phased" => {
+            let sample = sample_index(name, args, row, ctx)?;
+            let Some(genotype) = row.genotype(sample) else {
+                return Ok(Value::Missing);
+            };
+            let result = match name {
+                "is_het" => genotype.is_het(),
+                "is_hom_ref" => genotype.is_hom_ref(),
+                "is_hom_alt" => genotype.is_hom_alt(),
+                _ => Some(genotype.phased),
+            };
+            Ok(result.map_or(Value::Missing, Value::Bool))
+        }
+        "ploidy" => {
+            let sample = sample_index(name, args, row, ctx)?;
+            Ok(row.genotype(sample).map_or(Value::Missing, |genotype| {
+                Value::Number(genotype.ploidy() as f64)
+            }))
+        }
+        "n_het" | "n_hom_ref" | "n_hom_alt" | "n_missing" => {
+            expect_args(name, args, 0)?;
+            let count = (0..row.sample_count())
+                .filter(|&sample| {
+                    let genotype = row.genotype(sample);
+                    let called = |check: fn(&Genotype) -> Option<bool>| {
+                        genotype.and_then(check) == Some(true)
+                    };
+                    match name {
+                        "n_het" => called(Genotype::is_het),
+                        "n_hom_ref" => called(Genotype::is_hom_ref),
+                        "n_hom_alt" => called(Genotype::is_hom_alt),
+                        _ => genotype.and_then(|g| g.called()).is_none(),
+                    }
+                })
+                .count();
+            Ok(Value::Number(count as f64))
+        }
+        "vaf" | "allele_balance" => {
+            let sample = sample_index(name, args, row, ctx)?;
+            Ok(row
+                .variant_allele_fractions(sample)
+                .map_or(Value::Missing, |fractions| {
+                    per_alt(fractions.into_iter().map(Value::Number))
+                }))
+        }
+        "variant_type" => {
+            expect_args(name, args, 0)?;
+            Ok(per_alt(
+                row.variant_classes()
+                    .iter()
+                    .map(|class| Value::String(class.name().to_string())),
+            ))
+        }
+        "alt_count" => {
+            expect_args(name, args, 0)?;
+            Ok(Value::Number(row.alt_indexes().len() as f64))
+        }
+        "sv_type" => {
+            expect_args(name, args, 0)?;
+            Ok(row
+                .sv_type()
+                .map_or(Value::Missing, |svtype| Value::String(svtype.to_string())))
+        }
+        "sv_end" => {
+            expect_args(name, args, 0)?;
+            Ok(Value::Number(row.sv_end() as f64))
+        }
+        "mate_chrom" | "mate_pos" => {
+            expect_args(name, args, 0)?;
+            Ok(per_alt(row.breakends().into_iter().map(
+                |breakend| match (breakend, name) {
+                    (Some(breakend), "mate_chrom") => Value::String(breakend.mate_chrom),
+                    (Some(breakend), _) => Value::Number(breakend.mate_pos as f64),
+                    (None, _) => Value::Missing,
+                },
+            )))
+        }
+        "allele_len" => {
+            let allele_len = |allele: &str| {
+                if allele.starts_with('<')
+                    || allele.contains(['[', ']'])
+                    || allele == "*"
+                    || allele == "."
+                {
+                    Value::Missing
+                } else {
+                    Value::Number(allele.len() as f64)
+                }
+            };
+            match args {
+                [] => Ok(per_alt(row.alt_alleles.iter().map(|alt| allele_len(alt)))),
+                [index] => match evaluate_with(index, row, ctx)?.as_number() {
+                    Some(0.0) => Ok(allele_len(&row.ref_allele)),
+                    Some(n) if n > 0.0 && n.fract() == 0.0 => Ok(row
+                        .alt_alleles
+                        .get(n as usize - 1)
+                        .map_or(Value::Missing, |alt| allele_len(alt))),
+                    _ => Err(VcfFilterError::EvaluationError(
+                        "allele_len() index must be a non-negative integer".to_string(),
+                    )),
+                },
+                _ => Err(VcfFilterError::EvaluationError(format!(
+                    "allele_len() expects 0 or 1 argument(s), got {}",
+                    args.len()
+                ))),
+            }
+        }
+        "indel_len" => {
+            expect_args(name, args, 0)?;
+            Ok(per_alt(
+                row.indel_lengths()
+                    .into_iter()
+                    .map(|len| len.map_or(Value::Missing, Value::Number)),
+            ))
+        }
+        "in_file" => in_file(args),
+        "in_bed" => {
+            let name = bed_name(args)?;
+            // Without an engine's library the file would be re-read per row
+            let library = ctx.regions.ok_or_else(|| {
+                VcfFilterError::EvaluationError(format!(
+                    "in_bed(\"{}\") needs a BED library: evaluate through a FilterEngine \
+                     or register the set with FilterEngine::with_bed",
+                    name
+                ))
+            })?;
+            Ok(Value::Bool(library.get(name)?.overlaps(row)))
+        }
+        "region" => {
+            if args.is_empty() {
+                return Err(VcfFilterError::EvaluationError(
+                    "region() expects at least 1 argument, got 0".to_string(),
+                ));
+            }
+            for arg in args {
+                let value = evaluate_with(arg, row, ctx)?;
+                let regions = match &value {
+                    Value::Array(items) => items.as_slice(),
+                    single => std::slice::from_ref(single),
+                };
+                for region in regions {
+                    let Value::String(text) = region else {
+                        return Err(VcfFilterError::EvaluationError(
+                            "region() arguments must be strings like \"chr1:100-200\"".to_string(),
+                        ));
+                    };
+                    if Region::parse(text)?.overlaps(row) {
+                        return Ok(Value::Bool(true));
+                    }
+                }
+            }
+            Ok(Value::Bool(false))
+        }
+        _ => {
+            let Some(function) = ctx.functions.and_then(|registry| registry.get(name)) else {
+                return Err(VcfFilterError::EvaluationError(format!(
+                    "Unknown function: {}",
+                    name
+                )));
+            };
+            let values = args
+                .iter()
+                .map(|arg| evaluate_with(arg, row, ctx))
+                .collect::<Result<Vec<_>>>()?;
+            function(&values)
+        }
+    }
+}
+
+/// Combine per-ALT values: one value for a biallelic site, an array otherwise.
+fn per_alt(values: impl Iterator<Item = Value>) -> Value {
+    let mut values: Vec<Value> = values.collect();
+    match values.len() {
+        0 => Value::Missing,
+        1 => values.remove(0),
+        _ => Value::Array(values),
+    }
+}
+
+/// Get the optional zero-based sample index argument of a per-sample function.
+fn sample_index(name: &str, args: &[Expr], row: &VcfRow, ctx: &EvalContext) -> Result<usize> {
+    match args {
+        [] => Ok(0),
+        [index] => match evaluate_with(index, row, ctx)?.as_number() {
+            Some(n) if n >= 0.0 && n.fract() == 0.0 => Ok(n as usize),
+            _ => Err(VcfFilterError::EvaluationError(format!(
+                "{}() sample index must be a non-negative integer",
+                name
+            ))),
+        },
+        _ => Err(VcfFilterError::EvaluationError(format!(
+            "{}() expects 0 or 1 argument(s), got {}",
+            name,
+            args.len()
+        ))),
+    }
+}
+
+/// Fail unless exactly `expected` arguments were passed.
+fn expect_args(name: &str, args: &[Expr], expected: usize) -> Result<()> {
+    if args.len() == expected {
+        Ok(())
+    } else {
+        Err(VcfFilterError::EvaluationError(format!(
+            "{}() expects {} argument(s), got {}",
+            name,
+            expected,
+            args.len()
+        )))
+    }
+}
+
+/// Split packed strings (e.g., `CLNDN=a|b|c`) into an array for `split()`.
+///
+/// Arrays are split element-wise and flattened; empty pieces are dropped.
+fn split_value(value: Value, separator: &str) -> Value {
+    match value {
+        Value::Missing => Value::Missing,
+        Value::Array(arr) => Value::Array(
+            arr.into_iter()
+                .flat_map(|v| match split_value(v, separator) {
+                    Value::Array(parts) => parts,
+                    Value::Missing => Vec::new(),
+                    other => vec![other],
+                })
+                .collect(),
+        ),
+        Value::String(s) => Value::Array(
+            s.split(separator)
+                .filter(|part| !part.is_empty())
+                .map(|part| Value::String(part.to_string()))
+                .collect(),
+        ),
+        other => Value::Array(vec![other]),
+    }
+}
+
+/// Convert a value to a number for `number()`.
+///
+/// Missing values (and `.`) stay missing, booleans become 1/0 and arrays are
+/// converted element-wise. A string that is not a number is an error rather
+/// than a comparison that silently evaluates to false.
+fn cast_number(value: Value) -> Result<Value> {
+    match value {
+        Value::Number(_) | Value::Missing => Ok(value),
+        Value::Bool(b) => Ok(Value::Number(if b { 1.0 } else { 0.0 })),
+        Value::String(s) if s.is_empty() || s == "." => Ok(Value::Missing),
+        Value::String(s) => s.trim().parse().map(Value::Number).map_err(|_| {
+            VcfFilterError::EvaluationError(format!(
+                "number() cannot convert \"{}\" to a number",
+                s
+            ))
+        }),
+        Value::Array(arr) => arr
+            .into_iter()
+            .map(cast_number)
+            .collect::<Result<_>>()
+            .map(Value::Array),
+    }
+}
+
+/// Convert a value to a string for `string()`.
+fn cast_string(value: Value) -> Value {
+    match value {
+        Value::String(_) | Value::Missing => value,
+        Value::Number(n) => Value::String(n.to_string()),
+        Value::Bool(b) => Value::String(b.to_string()),
+        Value::Array(arr) => Value::Array(arr.into_iter().map(cast_string).collect()),
+    }
+}
+
+/// Apply a string transformation to a value for `replace()`/`substring()`.
+///
+/// Arrays are transformed element-wise, numbers and booleans are converted
+/// to strings first, and missing values stay missing.
+fn map_strings(value: Value, f: &dyn Fn(&str) -> String) -> Value {
+    match cast_string(value) {
+        Value::String(s) => Value::String(f(&s)),
+        Value::Array(arr) => Value::Array(arr.into_iter().map(|v| map_strings(v, f)).collect()),
+        other => other,
+    }
+}
+
+/// Apply a math function to a number, or to each element of a list.
+///
+/// Non-numbers and undefined results (`log10(0)`, `sqrt(-1)`) are missing.
+fn map_numbers(value: Value, f: fn(f64) -> f64) -> Value {
+    match value {
+        Value::Array(arr) => Value::Array(arr.into_iter().map(|v| map_numbers(v, f)).collect()),
+        other => other
+            .as_number()
+            .map(f)
+            .filter(|n| n.is_finite())
+            .map_or(Value::Missing, Value::Number),
+    }
+}
+
+/// Collect the numeric elements of a value, flattening arrays and skipping non-numbers.
+fn collect_numbers(value: &Value, numbers: &mut Vec<f64>) {
+    match value {
+        Value::Array(arr) => arr.iter().for_each(|v| collect_numbers(v, numbers)),
+        other => numbers.extend(other.as_number()),
+    }
+}
+
+/// How a field is (or is not) present in a record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FieldState {
+    /// The key is not in the record at all.
+    Absent,
+    /// The key is present with the value `.`.
+    Missing,
+    /// The key is present with an empty string value.
+    Empty,
+    /// The key is present with a real value.
+    Present,
+}
+
+/// Classify a field reference for `is_missing()` / `is_empty()`.
+///
+/// A top-level field (`CADD`, `INFO.CADD`, `FORMAT.GQ`) yields a single state
+/// based on the key's presence and whole value. An access path
+/// (`ANN[0].HGVS_p`, `ANN[*].HGVS_p`) yields one state per selected element,
+/// where an unselected annotation is `Absent` and a `.` subfield is `Missing`.
+fn field_states(
+    name: &str,
+    arg: &Expr,
+    row: &VcfRow,
+    ctx: &EvalContext,
+) -> Result<Vec<FieldState>> {
+    let Expr::Var(parts) = arg else {
+        return Err(VcfFilterError::EvaluationError(format!(
+            "{}() expects a field reference",
+            name
+        )));
+    };
+
+    let (namespace, field, rest) = match parts.as_slice() {
+        [AccessPart::Field(ns), AccessPart::Field(field), rest @ ..]
+            if ns == "INFO" || ns == "FORMAT" =>
+        {
+            (Some(ns.as_str()), field, rest)
+        }
+        [AccessPart::Field(field), rest @ ..] => (None, field, rest),
+        _ => return Ok(vec![FieldState::Absent]),
+    };
+
+    let in_scope = ctx
+        .annotation
+        .is_some_and(|scope| scope.subfields.contains(field));
+    let is_path = !rest.is_empty() || (namespace.is_none() && in_scope);
+
+    if !is_path {
+        let key_present = match namespace {
+            Some("INFO") => row.info.contains_key(field),
+            Some(_) => row.format.contains_key(field),
+            None => {
+                BUILTIN_FIELDS.contains(&field.as_str())
+                    || row.info.contains_key(field)
+                    || row.format.contains_key(field)
+            }
+        };
+        if !key_present {
+            return Ok(vec![FieldState::Absent]);
+        }
+    }
+
+    let value = resolve_variable(parts, row, ctx)?;
+    Ok(match value {
+        Value::Array(elements) if is_path => elements.iter().map(value_state).collect(),
+        Value::Missing if is_path => vec![FieldState::Absent],
+        other => vec![value_state(&other)],
+    })
+}
+
+/// Fields that are always present in a VCF record.
+pub(crate) const BUILTIN_FIELDS: &[&str] = &["CHROM", "POS", "ID", "REF", "ALT", "QUAL", "FILTER"];
+
+/// Classify a value whose key is known to be present.
+fn value_state(value: &Value) -> FieldState {
+    match value {
+        Value::Missing => FieldState::Missing,
+        Value::String(s) if s == "." => FieldState::Missing,
+        Value::String(s) if s.is_empty() => FieldState::Empty,
+        _ => FieldState::Present,
+    }
+}
+
+/// Phred-scaled gap between the best and second-best genotype likelihood.
+///
+/// Unlike GQ, which many callers cap at 99, this considers every genotype
+/// in PL/GL, so it stays meaningful at multiallelic sites. Missing if the
+/// sample has fewer than two likelihoods.
+fn gt_confidence(row: &VcfRow) -> Value {
+    let Some(mut likelihoods) = row.genotype_likelihoods() else {
+        return Value::Missing;
+    };
+    if likelihoods.len() < 2 {
+        return Value::Missing;
+    }
+
+    likelihoods.sort_by(f64::total_cmp);
+    Value::Number(likelihoods[1] - likelihoods[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::evaluate;
+    use crate::filter::parse_filter;
+    use crate::header::parse_header;
+    use crate::row::parse_row;
+
+    fn eval_row(filter: &str, sample: &str) -> Result<Value> {
+        let info_map = parse_header("").unwrap();
+        let row = format!("chr1\t100\t.\tA\tG,T\t50\tPASS\t.\t{}", sample);
+        let row = parse_row(&row, &info_map).unwrap();
+        evaluate(&parse_filter(filter).unwrap(), &row, &info_map)
+    }
+
+    #[test]
+    fn test_gt_confidence_from_pl() {
+        let value = eval_row("gt_confidence()", "GT:PL\t0/1:40,0,250").unwrap();
+        assert_eq!(value, Value::Number(40.0));
+
+        // Multiallelic: best and second-best are taken across all genotypes
+        let value = eval_row("gt_confidence()", "GT:PL\t1/2:300,200,250,20,0,15").unwrap();
+        assert_eq!(value, Value::Number(15.0));
+    }
+
+    #[test]
+    fn test_gt_confidence_from_gl() {
+        let value = eval_row("gt_confidence()", "GT:GL\t0/1:-4.5,0,-25").unwrap();
+        assert_eq!(value, Value::Number(45.0));
+    }
+
+    #[test]
+    fn test_gt_confidence_missing() {
+        assert_eq!(
+            eval_row("gt_confidence()", "GT:GQ\t0/1:99").unwrap(),
+            Value::Missing
+        );
+        assert_eq!(
+            eval_row("gt_confidence() >= 20", "GT:PL\t0/1:0").unwrap(),
+            Value::Bool(false)
+        );
+    }
+
+    #[test]
+    fn test_is_missing_and_is_empty() {
+        let header = r#"##INFO=<ID=CADD,Number=1,Type=Float,Description="CADD score">
+##INFO=<ID=NOTE,Number=1,Type=String,Description="Free text">
+##INFO=<ID=ANN,Number=.,Type=String,Description="Functional annotations: 'Allele | Annotation | Gene_Name | HGVS.p'">"#;
+        let info_map = parse_header(header).unwrap();
+        let check = |filter: &str, info: &str| {
+            let row = format!("chr1\t100\t.\tA\tG\t.\tPASS\t{}", info);
+            let row = parse_row(&row, &info_map).unwrap();
+            evaluate(&parse_filter(filter).unwrap(), &row, &info_map).unwrap() == Value::Bool(true)
+        };
+
+        // Key absent vs value "." vs empty string
+        assert!(!check("is_missing(CADD)", "NOTE=x"));
+        assert!(!check("is_empty(CADD)", "NOTE=x"));
+        assert!(check("is_missing(CADD)", "CADD=."));
+        assert!(check("is_missing(INFO.CADD)", "CADD=."));
+        assert!(!check("is_empty(CADD)", "CADD=."));
+        assert!(check("is_empty(NOTE)", "NOTE="));
+        assert!(!check("is_missing(NOTE)", "NOTE="));
+        assert!(!check("is_missing(CADD) || is_empty(CADD)", "CADD=12.5"));
+
+        // Built-in columns are always present
+        assert!(check("is_missing(QUAL)", "NOTE=x"));
+        assert!(check("is_missing(ID)", "NOTE=x"));
+
+        // Annotation subfields
+        let ann = "ANN=G|missense|TP53|p.R1C,G|intron|TP53|,G|splice|TP53|.";
+        assert!(check("is_empty(ANN[1].HGVS_p)", ann));
+        assert!(!check("is_empty(ANN[0].HGVS_p)", ann));
+        assert!(check("is_missing(ANN[2].HGVS_p)", ann));
+        assert!(!check("is_missing(ANN[5].HGVS_p)", ann));
+        assert!(check("is_empty(ANN[*].HGVS_p)", ann));
+        assert!(check(
+            r#"ANN[?(is_empty(HGVS_p))][0].Annotation == "intron""#,
+            ann
+        ));
+
+        assert!(eval_row("is_missing(1)", "GT\t0/1").is_err());
+    }
+
+    #[test]
+    fn test_max_min_any() {
+        let value = eval_row("max(QUAL, 80, 12)", "GT\t0/1").unwrap();
+        assert_eq!(value, Value::Number(80.0));
+        let value = eval_row("min(QUAL, 80, 12)", "GT\t0/1").unwrap();
+        assert_eq!(value, Value::Number(12.0));
+        assert_eq!(eval_row("max(ID)", "GT\t0/1").unwrap(), Value::Missing);
+        assert_eq!(
+            eval_row(r#"any(ALT[*] == "T")"#, "GT\t0/1").unwrap(),
+            Value::Bool(true)
+        );
+    }
+
+    #[test]
+    fn test_math_functions() {
+        let eval = |filter: &str| eval_row(filter, "GT\t0/1").unwrap();
+        assert_eq!(eval("abs(10 - QUAL)"), Value::Number(40.0));
+        assert_eq!(eval("log10(QUAL * 20)"), Value::Number(3.0));
+        assert_eq!(eval("sqrt(QUAL / 2)"), Value::Number(5.0));
+        assert_eq!(
+            eval("abs([-1.5, 2, ID])"),
+            Value::Array(vec![Value::Number(1.5), Value::Number(2.0), Value::Missing])
+        );
+        assert_eq!(eval("log10(0)"), Value::Missing);
+        assert_eq!(eval("sqrt(-4)"), Value::Missing);
+        assert!(eval_row("abs(1, 2)", "GT\t0/1").is_err());
+    }
+
+    #[test]
+    fn test_chrom_rank() {
+        let eval = |filter: &str| eval_row(filter, "GT\t0/1").unwrap();
+        assert_eq!(eval("chrom_rank()"), Value::Number(1.0));
+        assert_eq!(eval(r#"chrom_rank("chrX")"#), Value::Number(23.0));
+        assert_eq!(eval(r#"chrom_rank("chrUn_gl000220")"#), Value::Missing);
+        assert!(eval_row("chrom_rank(CHROM, 1)", "GT\t0/1").is_err());
+    }
+
+    #[test]
+    fn test_allele_frequency_functions() {
+        let header = r#"##INFO=<ID=AC,Number=A,Type=Integer,Description="Allele count">
+##INFO=<ID=AF,Number=A,Type=Float,Description="Allele frequency">
+##INFO=<ID=AN,Number=1,Type=Integer,Description="Total alleles">"#;
+        let info_map = parse_header(header).unwrap();
+        let eval = |filter: &str, info: &str| {
+            let row = format!("chr1\t100\t.\tA\tG\t50\tPASS\t{}", info);
+            let row = parse_row(&row, &info_map).unwrap();
+            evaluate(&parse_filter(filter).unwrap(), &row, &info_map).unwrap()
+        };
+
+        assert_eq!(eval("computed_af()", "AC=1;AN=4"), Value::Number(0.25));
+        assert_eq!(
+            eval("af_consistent()", "AC=1;AN=4;AF=0.250"),
+            Value::Bool(true)
+        );
+        assert_eq!(
+            eval("af_consistent()", "AC=1;AN=4;AF=0.5"),
+            Value::Bool(false)
+        );
+        assert_eq!(
+            eval("af_consistent(0.3)", "AC=1;AN=4;AF=0.5"),
+            Value::Bool(true)
+        );
+        // Records that cannot be checked are neither consistent nor inconsistent
+        assert_eq!(eval("af_consistent()", "AF=0.5"), Value::Missing);
+        assert_eq!(
+            eval("af_consistent() == false", "AF=0.5"),
+            Value::Bool(false)
+        );
+    }
+
+    #[test]
+    fn test_default() {
+        let header = r#"##INFO=<ID=AF,Number=A,Type=Float,Description="Allele frequency">
+##INFO=<ID=AD,Number=R,Type=Integer,Description="Allelic depths">"#;
+        let info_map = parse_header(header).unwrap();
+        let eval = |filter: &str, info: &str| {
+            let row = format!("chr1\t100\t.\tA\tG\t.\tPASS\t{}", info);
+            let row = parse_row(&row, &info_map).unwrap();
+            evaluate(&parse_filter(filter).unwrap(), &row, &info_map).unwrap()
+        };
+
+        assert_eq!(eval("default(AF, 0.0) < 0.01", "AD=1"), Value::Bool(true));
+        assert_eq!(
+            eval("default(AF, 0.0) < 0.01", "AF=0.2"),
+            Value::Bool(false)
+        );
+        assert_eq!(eval("default(AF, 1) < 0.01", "AF=."), Value::Bool(false));
+        assert_eq!(eval("default(QUAL, AF, 7)", "AF=0.2"), Value::Number(0.2));
+        assert_eq!(
+            eval("default(AD, 0)", "AD=10,."),
+            Value::Array(vec![Value::Number(10.0), Value::Number(0.0)])
+        );
+        assert!(eval_row("default(QUAL)", "GT\t0/1").is_err());
+    }
+
+    #[test]
+    fn test_number_and_string_casts() {
+        let header = r#"##INFO=<ID=dbNSFP_CADD_phred,Number=.,Type=String,Description="CADD phred">
+##INFO=<ID=NOTE,Number=1,Type=String,Description="Free text">"#;
+        let info_map = parse_header(header).unwrap();
+        let eval = |filter: &str, info: &str| {
+            let row = format!("chr1\t100\t.\tA\tG\t30\tPASS\t{}", info);
+            let row = parse_row(&row, &info_map).unwrap();
+            evaluate(&parse_filter(filter).unwrap(), &row, &info_map)
+        };
+
+        assert_eq!(
+            eval("number(dbNSFP_CADD_phred)", "dbNSFP_CADD_phred=23.1").unwrap(),
+            Value::Number(23.1)
+        );
+        assert_eq!(
+            eval(
+                "number(dbNSFP_CADD_phred) >= 20",
+                "dbNSFP_CADD_phred=23.1,."
+            )
+            .unwrap(),
+            Value::Bool(true)
+        );
+        assert_eq!(
+            eval("number(dbNSFP_CADD_phred)", "NOTE=x").unwrap(),
+            Value::Missing
+        );
+        assert!(eval("number(NOTE) > 1", "NOTE=high").is_err());
+
+        assert_eq!(
+            eval("string(QUAL)", "NOTE=x").unwrap(),
+            Value::String("30".to_string())
+        );
+        assert_eq!(
+            eval(r#"string(POS) contains "10""#, "NOTE=x").unwrap(),
+            Value::Bool(true)
+        );
+    }
+
+    #[test]
+    fn test_replace_and_substring() {
+        let eval = |filter: &str| eval_row(filter, "GT\t0/1").unwrap();
+
+        assert_eq!(
+            eval(r#"replace(CHROM, "chr", "")"#),
+            Value::String("1".to_string())
+        );
+        assert_eq!(
+            eval(r#"replace(ALT, "T", "U")"#),
+            Value::Array(vec![
+                Value::String("G".to_string()),
+                Value::String("U".to_string())
+            ])
+        );
+        assert_eq!(eval("substring(CHROM, 3)"), Value::String("1".to_string()));
+        assert_eq!(
+            eval("substring(CHROM, 0, 3)"),
+            Value::String("chr".to_string())
+        );
+        assert_eq!(
+            eval("substring(CHROM, 2, 100)"),
+            Value::String("r1".to_string())
+        );
+        assert_eq!(eval("substring(CHROM, 10)"), Value::String(String::new()));
+        assert_eq!(eval("substring(ID, 0, 2)"), Value::Missing);
+        assert_eq!(
+            eval(r#"substring(string(QUAL), 0, 1) == "5""#),
+            Value::Bool(true)
+        );
+
+        assert!(eval_row(r#"replace(CHROM, "", "x")"#, "GT\t0/1").is_err());
+        assert!(eval_row("substring(CHROM, 1, 2, 3)", "GT\t0/1").is_err());
+        assert!(eval_row(r#"substring(CHROM, "a")"#, "GT\t0/1").is_err());
+    }
+
+    #[test]
+    fn test_split_and_len() {
+        let header = r#"##INFO=<ID=CLNDN,Number=.,Type=String,Description="Disease names">
+##INFO=<ID=CLNSIGCONF,Number=.,Type=String,Description="Conflicting interpretations">"#;
+        let info_map = parse_header(header).unwrap();
+        let eval = |filter: &str| {
+            let row = "chr1\t100\t.\tA\tG\t50\tPASS\tCLNDN=Hereditary_cancer|Lynch_syndrome|not_provided;CLNSIGCONF=Pathogenic(2)|Benign(1)";
+            let row = parse_row(row, &info_map).unwrap();
+            evaluate(&parse_filter(filter).unwrap(), &row, &info_map).unwrap()
+        };
+
+        assert_eq!(eval(r#"len(split(CLNDN, "|"))"#), Value::Number(3.0));
+        assert_eq!(
+            eval(r#""Lynch_syndrome" in split(CLNDN, "|")"#),
+            Value::Bool(true)
+        );
+        assert_eq!(
+            eval(r#"split(CLNDN, "|") == "not_provided""#),
+            Value::Bool(true)
+        );
+        assert_eq!(
+            eval(r#"split(CLNDN, "|") contains "cancer""#),
+            Value::Bool(true)
+        );
+        assert_eq!(
+            eval(r#"all(split(CLNSIGCONF, "|") contains "Pathogenic")"#),
+            Value::Bool(false)
+        );
+        assert_eq!(eval(r#"split(MISSING, "|")"#), Value::Missing);
+        assert_eq!(eval("len(MISSING)"), Value::Number(0.0));
+        assert_eq!(eval("len(CLNDN)"), Value::Number(1.0));
+        assert!(eval_row(r#"split(ID, "")"#, "GT\t0/1").is_err());
+    }
+
+    #[test]
+    fn test_variant_class_predicates() {
+        let classes = |ref_allele: &str, alt: &str, info: &str| {
+            let info_map = parse_header("").unwrap();
+            let row = format!("chr1\t100\t.\t{}\t{}\t50\tPASS\t{}", ref_allele, alt, info);
+            let row = parse_row(&row, &info_map).unwrap();
+            ["is_snp()", "is_mnp()", "is_indel()", "is_sv()"]
+                .into_iter()
+                .filter(|f| {
+                    evaluate(&parse_filter(f).unwrap(), &row, &info_map).unwrap()
+                        == Value::Bool(true)
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(classes("A", "G", "."), ["is_snp()"]);
+        assert_eq!(classes("AC", "GT", "."), ["is_mnp()"]);
+        assert_eq!(classes("A", "AT", "."), ["is_indel()"]);
+        assert_eq!(classes("AT", "A", "."), ["is_indel()"]);
+        assert_eq!(classes("A", "<DEL>", "."), ["is_sv()"]);
+        assert_eq!(classes("G", "G]chr2:500]", "."), ["is_sv()"]);
+        assert_eq!(classes("A", "ATTTT", "SVTYPE=INS"), ["is_sv()"]);
+        // Multiallelic sites match each class any ALT allele has
+        assert_eq!(classes("A", "G,AT", "."), ["is_snp()", "is_indel()"]);
+        assert!(classes("A", "*", ".").is_empty());
+        assert!(classes("A", ".", ".").is_empty());
+        assert!(eval_row("is_snp(1)", "GT\t0/1").is_err());
+    }
+
+    #[test]
+    fn test_variant_type() {
+        let variant_type = |ref_allele: &str, alt: &str, info: &str| {
+            let info_map = parse_header("").unwrap();
+            let row = format!("chr1\t100\t.\t{}\t{}\t50\tPASS\t{}", ref_allele, alt, info);
+            let row = parse_row(&row, &info_map).unwrap();
+            evaluate(&parse_filter("variant_type()").unwrap(), &row, &info_map).unwrap()
+        };
+        let string = |s: &str| Value::String(s.to_string());
+        assert_eq!(variant_type("A", "G", "."), string("SNP"));
+        assert_eq!(variant_type("AC", "GT", "."), string("MNP"));
+        assert_eq!(variant_type("A", "AT", "."), string("INS"));
+        assert_eq!(variant_type("AT", "A", "."), string("DEL"));
+        assert_eq!(variant_type("A", "<DUP>", "."), string("SV"));
+        assert_eq!(variant_type("G", "G]chr2:500]", "."), string("BND"));
+        assert_eq!(variant_type("G", "<TRA>", "SVTYPE=BND"), string("BND"));
+        assert_eq!(variant_type("A", "*", "."), string("OTHER"));
+        assert_eq!(
+            variant_type("A", "G,AT", "."),
+            Value::Array(vec![string("SNP"), string("INS")])
+        );
+    }
+
+    #[test]
+    fn test_structural_variant_functions() {
+        let eval = |filter: &str, alt: &str, info: &str| {
+            let info_map = parse_header("").unwrap();
+            let row = format!("chr1\t100\t.\tG\t{}\t50\tPASS\t{}", alt, info);
+            let row = parse_row(&row, &info_map).unwrap();
+            evaluate(&parse_filter(filter).unwrap(), &row, &info_map).unwrap()
+        };
+        let string = |s: &str| Value::String(s.to_string());
+        assert_eq!(eval("sv_type()", "<DUP:TANDEM>", "."), string("DUP"));
+        assert_eq!(eval("sv_type()", "<DEL>", "SVTYPE=CNV"), string("CNV"));
+        assert_eq!(eval("sv_type()", "G]chr17:198982]", "."), string("BND"));
+        assert_eq!(eval("sv_type()", "A", "."), Value::Missing);
+
+        assert_eq!(eval("sv_end()", "<DEL>", "END=600"), Value::Number(600.0));
+        assert_eq!(
+            eval("sv_end()", "<DEL>", "SVLEN=-250"),
+            Value::Number(350.0)
+        );
+        assert_eq!(eval("sv_end()", "<INS>", "SVLEN=250"), Value::Number(100.0));
+        assert_eq!(eval("sv_end()", "A", "."), Value::Number(100.0));
+
+        assert_eq!(
+            eval("mate_chrom()", "G]chr17:198982]", "."),
+            string("chr17")
+        );
+        assert_eq!(
+            eval("mate_pos()", "[chr2:321682[T,<DEL>", "."),
+            Value::Array(vec![Value::Number(321682.0), Value::Missing])
+        );
+        assert_eq!(eval("mate_pos()", "A", "."), Value::Missing);
+    }
+
+    #[test]
+    fn test_allele_and_indel_lengths() {
+        let eval = |filter: &str, ref_allele: &str, alt: &str, info: &str| {
+            let info_map = parse_header("").unwrap();
+            let row = format!("chr1\t100\t.\t{}\t{}\t50\tPASS\t{}", ref_allele, alt, info);
+            let row = parse_row(&row, &info_map).unwrap();
+            evaluate(&parse_filter(filter).unwrap(), &row, &info_map).unwrap()
+        };
+        let numbers = |ns: &[f64]| Value::Array(ns.iter().copied().map(Value::Number).collect());
+
+        assert_eq!(
+            eval("allele_len(0)", "ACGT", "A,AT", "."),
+            Value::Number(4.0)
+        );
+        assert_eq!(
+            eval("allele_len()", "ACGT", "A,AT", "."),
+            numbers(&[1.0, 2.0])
+        );
+        assert_eq!(
+            eval("allele_len(2)", "ACGT", "A,AT", "."),
+            Value::Number(2.0)
+        );
+        assert_eq!(eval("allele_len(3)", "ACGT", "A,AT", "."), Value::Missing);
+        assert_eq!(eval("allele_len()", "A", "<DEL>", "."), Value::Missing);
+
+        assert_eq!(
+            eval("indel_len()", "ACGT", "A,AT", "."),
+            numbers(&[-3.0, -2.0])
+        );
+        assert_eq!(eval("indel_len()", "A", "ATTT", "."), Value::Number(3.0));
+        assert_eq!(eval("indel_len()", "A", "G", "."), Value::Number(0.0));
+        // Symbolic alleles use SVLEN; deletions are negative whichever sign is stored
+        assert_eq!(
+            eval("indel_len()", "A", "<INS>", "SVLEN=300"),
+            Value::Number(300.0)
+        );
+        assert_eq!(
+            eval("indel_len()", "A", "<DEL>", "SVLEN=500"),
+            Value::Number(-500.0)
+        );
+        assert_eq!(
+            eval("indel_len()", "A", "<DEL>", "SVLEN=-500"),
+            Value::Number(-500.0)
+        );
+        assert_eq!(
+            eval("indel_len()", "A", "<DEL>", "END=600"),
+            Value::Number(-500.0)
+        );
+        assert_eq!(eval("indel_len()", "A", "<INV>", "END=600"), Value::Missing);
+        assert_eq!(eval("indel_len()", "G", "G]chr2:500]", "."), Value::Missing);
+        assert_eq!(
+            eval("indel_len() <= -50", "A", "<DEL>", "SVLEN=500"),
+            Value::Bool(true)
+        );
+    }
+
+    #[test]
+    fn test_genotype_predicates() {
+        let predicates = |gt: &str| {
+            ["is_het()", "is_hom_ref()", "is_hom_alt()", "is_phased()"]
+                .into_iter()
+                .filter(|f| eval_row(f, &format!("GT\t{}", gt)).unwrap() == Value::Bool(true))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(predicates("0/1"), ["is_het()"]);
+        assert_eq!(predicates("1|0"), ["is_het()", "is_phased()"]);
+        assert_eq!(predicates("0/2"), ["is_het()"]);
+        assert_eq!(predicates("1/2"), ["is_het()"]);
+        assert_eq!(predicates("0/0"), ["is_hom_ref()"]);
+        assert_eq!(predicates("2|2"), ["is_hom_alt()", "is_phased()"]);
+        assert_eq!(predicates("1"), ["is_hom_alt()"]);
+        // Uncalled genotypes are missing rather than false
+        assert_eq!(eval_row("is_het()", "GT\t./.").unwrap(), Value::Missing);
+        assert_eq!(eval_row("is_hom_ref()", "GT\t0/.").unwrap(), Value::Missing);
+        assert_eq!(eval_row("is_het()", "DP\t30").unwrap(), Value::Missing);
+        // Partly called and VCFv4.4 prefix-phased genotypes
+        assert_eq!(predicates(".|1"), ["is_phased()"]);
+        assert_eq!(predicates("|1"), ["is_hom_alt()", "is_phased()"]);
+        assert_eq!(eval_row("ploidy()", "GT\t1").unwrap(), Value::Number(1.0));
+        assert_eq!(eval_row("ploidy()", "GT\t./.").unwrap(), Value::Number(2.0));
+        assert_eq!(eval_row("ploidy()", "DP\t30").unwrap(), Value::Missing);
+
+        // Later samples by index
+        assert_eq!(
+            eval_row("is_hom_alt(1)", "GT\t0/1\t1/1").unwrap(),
+            Value::Bool(true)
+        );
+        assert_eq!(eval_row("is_het(5)", "GT\t0/1").unwrap(), Value::Missing);
+        assert!(eval_row("is_het(-1)", "GT\t0/1").is_err());
+    }
+
+    #[test]
+    fn test_genotype_counts() {
+        let samples = "GT:DP\t0/1:10\t1|0:12\t1/1:9\t0/0:20\t./.:.\t0/.:3\t.";
+        assert_eq!(eval_row("n_het()", samples).unwrap(), Value::Number(2.0));
+        assert_eq!(
+            eval_row("n_hom_alt()", samples).unwrap(),
+            Value::Number(1.0)
+        );
+        assert_eq!(
+            eval_row("n_hom_ref()", samples).unwrap(),
+            Value::Number(1.0)
+        );
+        // No-calls, partial calls and empty sample columns
+        assert_eq!(
+            eval_row("n_missing()", samples).unwrap(),
+            Value::Number(3.0)
+        );
+        assert_eq!(
+            eval_row("n_het() >= 2 && n_missing() == 0", samples).unwrap(),
+            Value::Bool(false)
+        );
+        assert!(eval_row("n_het(0)", samples).is_err());
+    }
+
+    #[test]
+    fn test_vaf() {
+        assert_eq!(
+            eval_row("vaf()", "GT:AD:DP\t0/1:90,10:100").unwrap(),
+            Value::Number(0.1)
+        );
+        assert_eq!(
+            eval_row("vaf() > 0.05 && allele_balance() < 0.5", "GT:AD\t0/1:90,10").unwrap(),
+            Value::Bool(true)
+        );
+        // Multiallelic: one fraction per ALT allele
+        assert_eq!(
+            eval_row("vaf()", "GT:AD\t1/2:0,30,10").unwrap(),
+            Value::Array(vec![Value::Number(0.75), Value::Number(0.25)])
+        );
+        // Fallbacks: the sample's AF, then AO/DP
+        assert_eq!(
+            eval_row("vaf()", "GT:AF\t0/1:0.12").unwrap(),
+            Value::Number(0.12)
+        );
+        assert_eq!(
+            eval_row("vaf()", "GT:AO:DP\t0/1:5:50").unwrap(),
+            Value::Number(0.1)
+        );
+        assert_eq!(
+            eval_row("vaf(1)", "GT:AD\t0/1:5,5\t0/1:3,1").unwrap(),
+            Value::Number(0.25)
+        );
+        assert_eq!(eval_row("vaf()", "GT:AD\t0/0:0,0").unwrap(), Value::Missing);
+        assert_eq!(eval_row("vaf()", "GT:AD\t0/1:.,.").unwrap(), Value::Missing);
+        assert_eq!(eval_row("vaf()", "GT\t0/1").unwrap(), Value::Missing);
+    }
+
+    #[test]
+    fn test_region() {
+        let in_region = |filter: &str, info: &str| {
+            let info_map = parse_header("").unwrap();
+            let row = format!("chr1\t1000\t.\tA\t<DEL>\t50\tPASS\t{}", info);
+            let row = parse_row(&row, &info_map).unwrap();
+            evaluate(&parse_filter(filter).unwrap(), &row, &info_map)
+        };
+        assert_eq!(
+            in_region(r#"region("chr1:900-1,000")"#, ".").unwrap(),
+            Value::Bool(true)
+        );
+        assert_eq!(
+            in_region(r#"region("chr1:1001-2000")"#, ".").unwrap(),
+            Value::Bool(false)
+        );
+        // A structural variant overlaps through its END
+        assert_eq!(
+            in_region(r#"region("chr1:1001-2000")"#, "END=1500").unwrap(),
+            Value::Bool(true)
+        );
+        // Several regions match if any overlaps
+        assert_eq!(
+            in_region(r#"region("chr2", "chr1:1000")"#, ".").unwrap(),
+            Value::Bool(true)
+        );
+        assert!(in_region(r#"region("chr1:x")"#, ".").is_err());
+        assert!(in_region("region(1)", ".").is_err());
+        assert!(in_region("region()", ".").is_err());
+        // in_bed needs an engine's BED library rather than re-reading per row
+        assert!(in_region(r#"in_bed("exons.bed")"#, ".").is_err());
+    }
+
+    #[test]
+    fn test_unknown_function_and_arity() {
+        assert!(eval_row("nope()", "GT\t0/1").is_err());
+        assert!(eval_row("gt_confidence(DP)", "GT\t0/1").is_err());
+    }
+
+    #[test]
+    fn test_registered_functions() {
+        let mut registry = FunctionRegistry::new();
+        registry
+            .register("pathogenicity", |args| match args {
+                [Value::Number(cadd), Value::String(clnsig)] => Ok(Value::Number(
+                    cadd / 10.0 + if clnsig == "Pathogenic" { 5.0 } else { 0.0 },
+                )),
+                [Value::Missing, _] | [_, Value::Missing] => Ok(Value::Missing),
+                _ => Err(VcfFilterError::EvaluationError(
+                    "pathogenicity() expects (number, string)".to_string(),
+                )),
+            })
+            .unwrap();
+        assert!(registry.register("max", |_| Ok(Value::Missing)).is_err());
+        assert!(
+            registry
+                .register("bad-name", |_| Ok(Value::Missing))
+                .is_err()
+        );
+
+        let info_map = parse_header("").unwrap();
+        let ctx = EvalContext::new(&info_map).with_functions(&registry);
+        let eval = |filter: &str, info: &str| {
+            let row = format!("chr1\t100\t.\tA\tG\t50\tPASS\t{}", info);
+            let row = parse_row(&row, &info_map).unwrap();
+            evaluate_with(&parse_filter(filter).unwrap(), &row, &ctx)
+        };
+
+        assert_eq!(
+            eval("pathogenicity(CADD, CLNSIG)", "CADD=25;CLNSIG=Pathogenic").unwrap(),
+            Value::Number(7.5)
+        );
+        assert_eq!(
+            eval("pathogenicity(CADD, CLNSIG) > 5", "CLNSIG=Pathogenic").unwrap(),
+            Value::Bool(false)
+        );
+        assert!(eval(r#"pathogenicity(CADD, 1)"#, "CADD=25").is_err());
+        assert!(eval("unregistered(CADD)", "CADD=25").is_err());
+    }
+}
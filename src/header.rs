@@ -1,9 +1,41 @@
 //! VCF header parser.
 //!
 //! Parses ##INFO lines to extract field metadata, including subfield names
-//! for structured annotations like ANN, LOF, and NMD.
+//! for structured annotations like ANN, LOF, and NMD, ##FORMAT lines to
+//! type sample values, ##FILTER lines declaring FILTER names, and ##contig
+//! lines listing the reference sequences.
+//!
+//! [`VcfHeader`] keeps every header line in order, so a header can be
+//! modified (declaring computed INFO fields or soft-filter names) and
+//! written back out:
+//!
+//! ```rust
+//! use vcf_filter::header::{FilterField, VcfHeader};
+//!
+//! let mut header = VcfHeader::parse(concat!(
+//!     "##fileformat=VCFv4.2\n",
+//!     "##INFO=<ID=DP,Number=1,Type=Integer,Description=\"Depth\">\n",
+//!     "##source=caller\n",
+//!     "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO",
+//! ));
+//! header.add_filter(&FilterField {
+//!     id: "LowQual".to_string(),
+//!     description: "QUAL below 30".to_string(),
+//! });
+//! assert!(header.remove_info("DP"));
+//! assert_eq!(
+//!     header.to_string(),
+//!     concat!(
+//!         "##fileformat=VCFv4.2\n",
+//!         "##source=caller\n",
+//!         "##FILTER=<ID=LowQual,Description=\"QUAL below 30\">\n",
+//!         "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO",
+//!     )
+//! );
+//! ```
 
 use std::collections::HashMap;
+use std::fmt;
 
 use crate::error::Result;
 
@@ -34,6 +66,34 @@ pub enum InfoType {
     String,
 }
 
+/// Formats the number as written in a header's `Number=` attribute.
+impl fmt::Display for InfoNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InfoNumber::Count(n) => write!(f, "{}", n),
+            InfoNumber::PerAltAllele => write!(f, "A"),
+            InfoNumber::PerGenotype => write!(f, "G"),
+            InfoNumber::PerAllele => write!(f, "R"),
+            InfoNumber::Variable => write!(f, "."),
+            InfoNumber::Flag => write!(f, "0"),
+        }
+    }
+}
+
+/// Formats the type as written in a header's `Type=` attribute.
+impl fmt::Display for InfoType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            InfoType::Integer => "Integer",
+            InfoType::Float => "Float",
+            InfoType::Flag => "Flag",
+            InfoType::Character => "Character",
+            InfoType::String => "String",
+        };
+        f.write_str(name)
+    }
+}
+
 /// Metadata for a single INFO field parsed from the header.
 #[derive(Debug, Clone)]
 pub struct InfoField {
@@ -48,11 +108,158 @@ pub struct InfoField {
     /// Subfield names for structured fields (e.g., ANN).
     /// Extracted from the description if it contains a format specification.
     pub subfields: Option<Vec<String>>,
+    /// How a structured field's value splits into annotations and subfields.
+    pub delimiters: Delimiters,
+}
+
+/// Separators of a structured INFO field's annotations and subfields.
+///
+/// Defaults to `,` between annotations and `|` between subfields, as in
+/// SnpEff `ANN` and VEP `CSQ`. A `char` converts to delimiters with that
+/// subfield separator and the default `,` between annotations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Delimiters {
+    /// Separates annotations within the value.
+    pub record: char,
+    /// Separates subfields within an annotation.
+    pub subfield: char,
+}
+
+impl Delimiters {
+    /// Delimiters with the given annotation and subfield separators.
+    pub fn new(record: char, subfield: char) -> Self {
+        Self { record, subfield }
+    }
+}
+
+impl Default for Delimiters {
+    fn default() -> Self {
+        Self::new(',', '|')
+    }
+}
+
+impl From<char> for Delimiters {
+    fn from(subfield: char) -> Self {
+        Self {
+            subfield,
+            ..Self::default()
+        }
+    }
 }
 
 /// Map of INFO field ID to its metadata.
 pub type InfoMap = HashMap<String, InfoField>;
 
+/// Map of FORMAT field ID to its metadata.
+///
+/// ##FORMAT lines have the same attributes as ##INFO lines; `subfields` is
+/// always `None`.
+pub type FormatMap = HashMap<String, InfoField>;
+
+impl InfoField {
+    /// The `##INFO` header line declaring this field.
+    pub fn header_line(&self) -> String {
+        format!(
+            "##INFO=<ID={},Number={},Type={},Description=\"{}\">",
+            self.id,
+            self.number,
+            self.field_type,
+            escape_description(&self.description)
+        )
+    }
+}
+
+/// A field two headers declare with different Number, Type or subfields.
+#[derive(Debug, Clone)]
+pub struct FieldConflict {
+    /// The declaration kept, from the earlier header.
+    pub kept: InfoField,
+    /// The later declaration that was ignored.
+    pub ignored: InfoField,
+}
+
+impl fmt::Display for FieldConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} is declared Number={},Type={} and Number={},Type={}",
+            self.kept.id,
+            self.kept.number,
+            self.kept.field_type,
+            self.ignored.number,
+            self.ignored.field_type
+        )?;
+        if self.kept.subfields != self.ignored.subfields {
+            write!(f, " with different subfields")?;
+        }
+        write!(f, "; keeping the first")
+    }
+}
+
+/// Merge the fields of another header's map into `into`.
+///
+/// Fields missing from `into` are added. A field declared in both keeps
+/// its first declaration; differing Number, Type or subfields are
+/// returned as conflicts, sorted by ID (descriptions may differ freely).
+/// Works for [`FormatMap`]s too.
+pub fn merge_info_maps(into: &mut InfoMap, other: &InfoMap) -> Vec<FieldConflict> {
+    let mut conflicts = Vec::new();
+    for (id, field) in other {
+        match into.get(id) {
+            None => {
+                into.insert(id.clone(), field.clone());
+            }
+            Some(kept)
+                if kept.number != field.number
+                    || kept.field_type != field.field_type
+                    || kept.subfields != field.subfields =>
+            {
+                conflicts.push(FieldConflict {
+                    kept: kept.clone(),
+                    ignored: field.clone(),
+                });
+            }
+            Some(_) => {}
+        }
+    }
+    conflicts.sort_by(|a, b| a.kept.id.cmp(&b.kept.id));
+    conflicts
+}
+
+/// A FILTER name declared by a ##FILTER header line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterField {
+    /// The FILTER name (e.g., "LowQual").
+    pub id: String,
+    /// The description from the header.
+    pub description: String,
+}
+
+impl FilterField {
+    /// The `##FILTER` header line declaring this name.
+    pub fn header_line(&self) -> String {
+        format!(
+            "##FILTER=<ID={},Description=\"{}\">",
+            self.id,
+            escape_description(&self.description)
+        )
+    }
+}
+
+/// Escape a description for a quoted header attribute.
+pub(crate) fn escape_description(description: &str) -> String {
+    description.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// A reference sequence declared by a ##contig header line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contig {
+    /// The contig name, as written in CHROM (e.g., "chr1").
+    pub id: String,
+    /// The sequence length, if declared.
+    pub length: Option<u64>,
+}
+
 /// Parse the Number attribute from an INFO line.
 fn parse_number(s: &str) -> InfoNumber {
     match s {
@@ -125,11 +332,16 @@ fn parse_info_attrs(content: &str) -> HashMap<String, String> {
 /// Looks for patterns like:
 /// - "Format: 'Gene_Name | Gene_ID | ...'"
 /// - "'Allele | Annotation | Annotation_Impact | ...'"
+/// - "... Ensembl VEP. Format: Allele|Consequence|IMPACT|SYMBOL|..." (unquoted)
 fn extract_subfields(description: &str) -> Option<Vec<String>> {
-    // Look for content between single quotes that contains pipe separators
-    let start = description.find('\'')?;
-    let end = description[start + 1..].find('\'')? + start + 1;
-    let format_str = &description[start + 1..end];
+    // Look for content between single quotes, else after VEP's unquoted "Format: "
+    let format_str = match description.find('\'') {
+        Some(start) => {
+            let end = description[start + 1..].find('\'')? + start + 1;
+            &description[start + 1..end]
+        }
+        None => description.split_once("Format: ")?.1,
+    };
 
     // Check if it looks like a pipe-separated format
     if !format_str.contains('|') {
@@ -138,12 +350,7 @@ fn extract_subfields(description: &str) -> Option<Vec<String>> {
 
     let subfields: Vec<String> = format_str
         .split('|')
-        .map(|s| {
-            s.trim()
-                .replace(' ', "_")
-                .replace('.', "_")
-                .replace('/', "_")
-        })
+        .map(|s| s.trim().replace([' ', '.', '/'], "_"))
         .filter(|s| !s.is_empty())
         .collect();
 
@@ -156,7 +363,20 @@ fn extract_subfields(description: &str) -> Option<Vec<String>> {
 
 /// Parse a single ##INFO line.
 fn parse_info_line(line: &str) -> Option<InfoField> {
-    let line = line.strip_prefix("##INFO=<")?;
+    parse_field_line(line.strip_prefix("##INFO=<")?)
+}
+
+/// Parse a single ##FORMAT line.
+fn parse_format_line(line: &str) -> Option<InfoField> {
+    let field = parse_field_line(line.strip_prefix("##FORMAT=<")?)?;
+    Some(InfoField {
+        subfields: None,
+        ..field
+    })
+}
+
+/// Parse the attributes of an ##INFO or ##FORMAT line after its `<`.
+fn parse_field_line(line: &str) -> Option<InfoField> {
     let line = line.strip_suffix('>')?;
 
     let attrs = parse_info_attrs(line);
@@ -177,6 +397,7 @@ fn parse_info_line(line: &str) -> Option<InfoField> {
         field_type,
         description,
         subfields,
+        delimiters: Delimiters::default(),
     })
 }
 
@@ -194,16 +415,292 @@ pub fn parse_header(header: &str) -> Result<InfoMap> {
 
     for line in header.lines() {
         let line = line.trim();
-        if line.starts_with("##INFO=<") {
-            if let Some(field) = parse_info_line(line) {
-                info_map.insert(field.id.clone(), field);
-            }
+        if line.starts_with("##INFO=<")
+            && let Some(field) = parse_info_line(line)
+        {
+            info_map.insert(field.id.clone(), field);
         }
     }
 
     Ok(info_map)
 }
 
+/// Parse all ##FORMAT lines from a VCF header string.
+///
+/// # Arguments
+///
+/// * `header` - The full VCF header as a string (all lines starting with ##)
+///
+/// # Returns
+///
+/// A map of FORMAT field IDs to their metadata.
+pub fn parse_format_header(header: &str) -> Result<FormatMap> {
+    let mut format_map = HashMap::new();
+
+    for line in header.lines() {
+        let line = line.trim();
+        if line.starts_with("##FORMAT=<")
+            && let Some(field) = parse_format_line(line)
+        {
+            format_map.insert(field.id.clone(), field);
+        }
+    }
+
+    Ok(format_map)
+}
+
+/// Parse all ##FILTER lines from a VCF header string, in header order.
+///
+/// Lines without an `ID` are skipped.
+pub fn parse_filter_header(header: &str) -> Result<Vec<FilterField>> {
+    let filters = header
+        .lines()
+        .filter_map(|line| {
+            let attrs =
+                parse_info_attrs(line.trim().strip_prefix("##FILTER=<")?.strip_suffix('>')?);
+            Some(FilterField {
+                id: attrs.get("ID")?.clone(),
+                description: attrs.get("Description").cloned().unwrap_or_default(),
+            })
+        })
+        .collect();
+    Ok(filters)
+}
+
+/// Parse all ##contig lines from a VCF header string, in header order.
+///
+/// Lines without an `ID` are skipped; an unparseable `length` is `None`.
+pub fn parse_contig_header(header: &str) -> Result<Vec<Contig>> {
+    let contigs = header
+        .lines()
+        .filter_map(|line| {
+            let attrs =
+                parse_info_attrs(line.trim().strip_prefix("##contig=<")?.strip_suffix('>')?);
+            Some(Contig {
+                id: attrs.get("ID")?.clone(),
+                length: attrs.get("length").and_then(|length| length.parse().ok()),
+            })
+        })
+        .collect();
+    Ok(contigs)
+}
+
+/// A VCF specification version, from the `##fileformat=VCFv4.x` line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VcfVersion {
+    /// The major version (4).
+    pub major: u32,
+    /// The minor version.
+    pub minor: u32,
+}
+
+impl VcfVersion {
+    /// A version from its major and minor numbers.
+    pub const fn new(major: u32, minor: u32) -> Self {
+        Self { major, minor }
+    }
+
+    /// Whether INFO values use `%XX` escapes for reserved characters (VCFv4.3+).
+    pub fn percent_encoding(&self) -> bool {
+        *self >= Self::new(4, 3)
+    }
+
+    /// Whether this version defines a Number code.
+    ///
+    /// `R` (one value per allele, including the reference) was introduced
+    /// in VCFv4.2; the other codes are defined by every 4.x version.
+    pub fn defines_number(&self, number: &InfoNumber) -> bool {
+        match number {
+            InfoNumber::PerAllele => *self >= Self::new(4, 2),
+            _ => true,
+        }
+    }
+}
+
+impl fmt::Display for VcfVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "VCFv{}.{}", self.major, self.minor)
+    }
+}
+
+/// Get the version from the `##fileformat` line of a VCF header.
+///
+/// Returns `None` if the line is missing or not of the form `VCFv<major>.<minor>`.
+pub fn parse_fileformat(header: &str) -> Option<VcfVersion> {
+    let version = header
+        .lines()
+        .find_map(|line| line.trim_end().strip_prefix("##fileformat=VCFv"))?;
+    let (major, minor) = version.split_once('.')?;
+    Some(VcfVersion::new(major.parse().ok()?, minor.parse().ok()?))
+}
+
+/// Get the sample names from the `#CHROM` line of a VCF header.
+///
+/// Returns an empty list if the header has no `#CHROM` line or no samples.
+pub fn parse_sample_names(header: &str) -> Vec<String> {
+    header
+        .lines()
+        .find(|line| line.starts_with("#CHROM"))
+        .map(|line| {
+            line.trim_end()
+                .split('\t')
+                .skip(9)
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// A VCF header kept line by line, in order.
+///
+/// Unlike the [`InfoMap`] view, nothing is dropped: unknown meta lines,
+/// their order and the `#CHROM` line are written back unchanged by the
+/// `Display` impl, one line per header line without a trailing newline.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VcfHeader {
+    /// The `##` meta-information lines.
+    meta_lines: Vec<String>,
+    /// The `#CHROM` column header line, if present.
+    column_line: Option<String>,
+}
+
+impl VcfHeader {
+    /// Split header text into lines; blank lines are dropped.
+    pub fn parse(text: &str) -> Self {
+        Self::from_lines(text.lines())
+    }
+
+    /// Build a header from its lines, such as those returned by
+    /// [`FilterEngine::from_reader`](crate::FilterEngine::from_reader).
+    ///
+    /// Lines after the `#CHROM` line are ignored.
+    pub fn from_lines<I, S>(lines: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut header = Self::default();
+        for line in lines {
+            let line = line.as_ref().trim_end_matches(['\r', '\n']);
+            if line.starts_with("#CHROM") {
+                header.column_line = Some(line.to_string());
+                break;
+            }
+            if !line.is_empty() {
+                header.meta_lines.push(line.to_string());
+            }
+        }
+        header
+    }
+
+    /// The `##` meta-information lines, in order.
+    pub fn meta_lines(&self) -> &[String] {
+        &self.meta_lines
+    }
+
+    /// The `#CHROM` column header line, if present.
+    pub fn column_line(&self) -> Option<&str> {
+        self.column_line.as_deref()
+    }
+
+    /// The ##INFO field metadata, as [`parse_header`] reads it.
+    pub fn info_map(&self) -> Result<InfoMap> {
+        parse_header(&self.to_string())
+    }
+
+    /// The ##FILTER declarations, in order.
+    pub fn filters(&self) -> Result<Vec<FilterField>> {
+        parse_filter_header(&self.to_string())
+    }
+
+    /// The sample names from the `#CHROM` line.
+    pub fn sample_names(&self) -> Vec<String> {
+        self.column_line
+            .as_deref()
+            .map(parse_sample_names)
+            .unwrap_or_default()
+    }
+
+    /// Add a meta line.
+    ///
+    /// A structured line (`##KEY=<ID=...>`) replaces the line with the same
+    /// key and ID in place, or else follows the last line with the same key;
+    /// other lines, and keys not yet in the header, go after all others.
+    pub fn add_meta_line(&mut self, line: &str) {
+        let key_and_id = structured_key_and_id(line);
+        if let Some(target) = &key_and_id
+            && let Some(existing) = self
+                .meta_lines
+                .iter_mut()
+                .find(|other| structured_key_and_id(other).as_ref() == Some(target))
+        {
+            *existing = line.to_string();
+            return;
+        }
+        let position = key_and_id
+            .and_then(|(key, _)| {
+                self.meta_lines
+                    .iter()
+                    .rposition(|other| structured_key_and_id(other).is_some_and(|(k, _)| k == key))
+            })
+            .map_or(self.meta_lines.len(), |last| last + 1);
+        self.meta_lines.insert(position, line.to_string());
+    }
+
+    /// Declare an INFO field, replacing any declaration with its ID.
+    pub fn add_info(&mut self, field: &InfoField) {
+        self.add_meta_line(&field.header_line());
+    }
+
+    /// Remove the declaration of an INFO field; returns whether it existed.
+    pub fn remove_info(&mut self, id: &str) -> bool {
+        self.remove_structured("INFO", id)
+    }
+
+    /// Declare a FILTER name, replacing any declaration with its ID.
+    pub fn add_filter(&mut self, filter: &FilterField) {
+        self.add_meta_line(&filter.header_line());
+    }
+
+    /// Remove the declaration of a FILTER name; returns whether it existed.
+    pub fn remove_filter(&mut self, id: &str) -> bool {
+        self.remove_structured("FILTER", id)
+    }
+
+    /// Remove every `##KEY=<ID=id,...>` line.
+    fn remove_structured(&mut self, key: &str, id: &str) -> bool {
+        let before = self.meta_lines.len();
+        self.meta_lines.retain(|line| {
+            structured_key_and_id(line).is_none_or(|(k, other)| k != key || other != id)
+        });
+        self.meta_lines.len() != before
+    }
+}
+
+impl fmt::Display for VcfHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut lines = self
+            .meta_lines
+            .iter()
+            .map(String::as_str)
+            .chain(self.column_line());
+        if let Some(first) = lines.next() {
+            f.write_str(first)?;
+        }
+        for line in lines {
+            write!(f, "\n{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+/// The key and ID of a structured meta line such as `##INFO=<ID=DP,...>`.
+fn structured_key_and_id(line: &str) -> Option<(&str, String)> {
+    let (key, rest) = line.strip_prefix("##")?.split_once("=<")?;
+    let id = parse_info_attrs(rest.strip_suffix('>')?).remove("ID")?;
+    Some((key, id))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -249,6 +746,18 @@ mod tests {
         assert_eq!(subfields[1], "Gene_ID");
     }
 
+    #[test]
+    fn test_parse_vep_csq_subfields() {
+        let header = r#"##INFO=<ID=CSQ,Number=.,Type=String,Description="Consequence annotations from Ensembl VEP. Format: Allele|Consequence|IMPACT|SYMBOL|Gene|HGVSc">"#;
+        let map = parse_header(header).unwrap();
+
+        let subfields = map.get("CSQ").unwrap().subfields.as_ref().unwrap();
+        assert_eq!(
+            subfields,
+            &["Allele", "Consequence", "IMPACT", "SYMBOL", "Gene", "HGVSc"]
+        );
+    }
+
     #[test]
     fn test_parse_multiple_info_lines() {
         let header = r#"##INFO=<ID=DP,Number=1,Type=Integer,Description="Total depth">
@@ -263,4 +772,150 @@ mod tests {
 
         assert_eq!(map.get("AF").unwrap().number, InfoNumber::PerAltAllele);
     }
+
+    #[test]
+    fn test_parse_format_lines() {
+        let header = r#"##INFO=<ID=DP,Number=1,Type=Integer,Description="Total depth">
+##FORMAT=<ID=GT,Number=1,Type=String,Description="Genotype">
+##FORMAT=<ID=PL,Number=G,Type=Integer,Description="Phred-scaled likelihoods: 'a | b'">"#;
+
+        let map = parse_format_header(header).unwrap();
+        assert_eq!(map.len(), 2);
+        let pl = map.get("PL").unwrap();
+        assert_eq!(pl.number, InfoNumber::PerGenotype);
+        assert_eq!(pl.field_type, InfoType::Integer);
+        assert_eq!(pl.subfields, None);
+        assert!(!parse_header(header).unwrap().contains_key("PL"));
+    }
+
+    #[test]
+    fn test_parse_filter_lines() {
+        let header = r#"##FILTER=<ID=PASS,Description="All filters passed">
+##FILTER=<ID=q10,Description="Quality below 10, or low">
+##FILTER=<Description="No ID">
+##INFO=<ID=DP,Number=1,Type=Integer,Description="Total depth">"#;
+
+        let filters = parse_filter_header(header).unwrap();
+        let ids: Vec<&str> = filters.iter().map(|f| f.id.as_str()).collect();
+        assert_eq!(ids, ["PASS", "q10"]);
+        assert_eq!(filters[1].description, "Quality below 10, or low");
+    }
+
+    #[test]
+    fn test_parse_contig_lines() {
+        let header = r#"##contig=<ID=chr2,length=242193529,assembly=GRCh38>
+##contig=<ID=chr1,length=248956422>
+##contig=<ID=chrUn_KI270302v1>"#;
+
+        let contigs = parse_contig_header(header).unwrap();
+        assert_eq!(
+            contigs,
+            [
+                Contig {
+                    id: "chr2".to_string(),
+                    length: Some(242193529)
+                },
+                Contig {
+                    id: "chr1".to_string(),
+                    length: Some(248956422)
+                },
+                Contig {
+                    id: "chrUn_KI270302v1".to_string(),
+                    length: None
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_header_round_trip_and_edits() {
+        let text = "##fileformat=VCFv4.2\n\
+                    ##FILTER=<ID=q10,Description=\"Quality below 10\">\n\
+                    ##INFO=<ID=DP,Number=1,Type=Integer,Description=\"Depth\">\n\
+                    ##INFO=<ID=AF,Number=A,Type=Float,Description=\"Allele frequency\">\n\
+                    ##source=caller v1\n\
+                    #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tkid";
+        let mut header = VcfHeader::parse(text);
+        assert_eq!(header.to_string(), text);
+        assert_eq!(header.sample_names(), ["kid"]);
+        assert_eq!(header.info_map().unwrap().len(), 2);
+
+        header.add_info(&InfoField {
+            id: "SCORE".to_string(),
+            number: InfoNumber::Count(1),
+            field_type: InfoType::Float,
+            description: "Computed \"score\"".to_string(),
+            subfields: None,
+            delimiters: Delimiters::default(),
+        });
+        header.add_filter(&FilterField {
+            id: "q10".to_string(),
+            description: "Quality < 10".to_string(),
+        });
+        assert!(header.remove_info("DP"));
+        assert!(!header.remove_filter("s50"));
+        header.add_meta_line("##reheader=yes");
+
+        let lines: Vec<&str> = header.meta_lines().iter().map(String::as_str).collect();
+        assert_eq!(
+            lines,
+            [
+                "##fileformat=VCFv4.2",
+                r#"##FILTER=<ID=q10,Description="Quality < 10">"#,
+                r#"##INFO=<ID=AF,Number=A,Type=Float,Description="Allele frequency">"#,
+                r#"##INFO=<ID=SCORE,Number=1,Type=Float,Description="Computed \"score\"">"#,
+                "##source=caller v1",
+                "##reheader=yes",
+            ]
+        );
+        assert!(header.column_line().unwrap().ends_with("\tkid"));
+        assert_eq!(VcfHeader::parse(&header.to_string()), header);
+    }
+
+    #[test]
+    fn test_merge_info_maps() {
+        let mut merged = parse_header(
+            r#"##INFO=<ID=DP,Number=1,Type=Integer,Description="Depth">
+##INFO=<ID=AF,Number=A,Type=Float,Description="Allele frequency">"#,
+        )
+        .unwrap();
+        let other = parse_header(
+            r#"##INFO=<ID=DP,Number=1,Type=Integer,Description="Read depth">
+##INFO=<ID=AF,Number=1,Type=String,Description="Allele frequency">
+##INFO=<ID=DB,Number=0,Type=Flag,Description="dbSNP">"#,
+        )
+        .unwrap();
+
+        let conflicts = merge_info_maps(&mut merged, &other);
+        assert_eq!(merged.len(), 3);
+        assert_eq!(merged["AF"].field_type, InfoType::Float);
+        assert_eq!(merged["DP"].description, "Depth");
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(
+            conflicts[0].to_string(),
+            "AF is declared Number=A,Type=Float and Number=1,Type=String; keeping the first"
+        );
+    }
+
+    #[test]
+    fn test_parse_fileformat() {
+        let version = parse_fileformat("##fileformat=VCFv4.3\n##source=x").unwrap();
+        assert_eq!(version, VcfVersion::new(4, 3));
+        assert_eq!(version.to_string(), "VCFv4.3");
+        assert!(version.percent_encoding());
+        assert!(!VcfVersion::new(4, 2).percent_encoding());
+        assert!(VcfVersion::new(4, 2).defines_number(&InfoNumber::PerAllele));
+        assert!(!VcfVersion::new(4, 1).defines_number(&InfoNumber::PerAllele));
+        assert!(VcfVersion::new(4, 1) < VcfVersion::new(4, 10));
+
+        assert_eq!(parse_fileformat("##source=x"), None);
+        assert_eq!(parse_fileformat("##fileformat=VCFv4"), None);
+    }
+
+    #[test]
+    fn test_parse_sample_names() {
+        let header = "##fileformat=VCFv4.2\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tkid\tdad\tmom";
+        assert_eq!(parse_sample_names(header), vec!["kid", "dad", "mom"]);
+        assert!(parse_sample_names("##fileformat=VCFv4.2").is_empty());
+    }
 }
@@ -0,0 +1,619 @@
+//! Interval annotations joined onto records from BED/TSV files.
+//!
+//! Each interval carries values for one or more named columns. Records that
+//! overlap an interval gain those columns as virtual fields, so filters can
+//! use them like INFO fields:
+//!
+//! ```text
+//! #chrom  start   end     ROH_REGION  MAPPABILITY
+//! chr1    1000    5000    roh_1       0.92
+//! chr1    4000    9000    .           0.31
+//! ```
+//!
+//! ```rust
+//! use vcf_filter::FilterEngine;
+//! use vcf_filter::intervals::IntervalAnnotation;
+//!
+//! let tsv = "#chrom\tstart\tend\tROH_REGION\tMAPPABILITY\nchr1\t1000\t5000\troh_1\t0.92\n";
+//! let engine = FilterEngine::new("")
+//!     .unwrap()
+//!     .with_intervals(IntervalAnnotation::parse_tsv(tsv).unwrap());
+//!
+//! let row = "chr1\t2000\t.\tA\tG\t50\tPASS\t.";
+//! assert!(engine.evaluate(r#"ROH_REGION == "roh_1" && MAPPABILITY > 0.5"#, row).unwrap());
+//! ```
+//!
+//! Coordinates are BED-style: zero-based, half-open. A record covers the
+//! bases of its REF allele, or for a structural variant the bases up to its
+//! INFO `END` (or POS + |SVLEN|); see [`VcfRow::span`]. When several intervals overlap a record, the
+//! field holds an array of their values (compared with `[*]` semantics).
+//! Fields already present in the record's INFO column are not overwritten.
+//!
+//! The same index backs `in_bed("exons.bed")`, which tests whether a record
+//! overlaps any region of a BED file (see [`BedLibrary`]), and
+//! `region("chr1:100-200")` parses a single [`Region`].
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::error::{Result, VcfFilterError};
+use crate::filter::Expr;
+use crate::row::VcfRow;
+use crate::value::Value;
+
+/// A set of intervals supporting overlap queries.
+///
+/// This is a sorted array rather than an interval tree: intervals are sorted
+/// by start alongside a running maximum of end positions. A query binary
+/// searches for the last interval starting before the query end, then scans
+/// backwards until no earlier interval can reach the query start. One long
+/// interval early on the chromosome therefore lengthens every later scan.
+#[derive(Debug, Clone)]
+pub struct IntervalIndex<T> {
+    /// `(start, end, value)` sorted by start.
+    intervals: Vec<(u64, u64, T)>,
+    /// `max_end[i]` is the largest end among `intervals[..=i]`.
+    max_end: Vec<u64>,
+}
+
+impl<T> IntervalIndex<T> {
+    /// Build an index from zero-based, half-open `(start, end, value)` intervals.
+    pub fn new(mut intervals: Vec<(u64, u64, T)>) -> Self {
+        intervals.sort_by_key(|(start, end, _)| (*start, *end));
+        let max_end = intervals
+            .iter()
+            .scan(0, |max, (_, end, _)| {
+                *max = (*max).max(*end);
+                Some(*max)
+            })
+            .collect();
+        Self { intervals, max_end }
+    }
+
+    /// Get the values of all intervals overlapping `[start, end)`, in start order.
+    pub fn overlapping(&self, start: u64, end: u64) -> Vec<&T> {
+        let candidates = self.intervals.partition_point(|(s, _, _)| *s < end);
+        let mut found = Vec::new();
+        for i in (0..candidates).rev() {
+            if self.max_end[i] <= start {
+                break;
+            }
+            let (_, interval_end, value) = &self.intervals[i];
+            if *interval_end > start {
+                found.push(value);
+            }
+        }
+        found.reverse();
+        found
+    }
+
+    /// Number of intervals in the index.
+    pub fn len(&self) -> usize {
+        self.intervals.len()
+    }
+
+    /// Returns true if the index holds no intervals.
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+}
+
+/// A genomic region written `chr1:186,000,000-187,000,000`.
+///
+/// Region text uses one-based, inclusive coordinates as in samtools and
+/// bcftools; commas are ignored. `chr1:1000` is a single base and `chr1`
+/// the whole chromosome.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    /// Chromosome name, compared exactly with CHROM.
+    pub chrom: String,
+    /// Zero-based start.
+    pub start: u64,
+    /// Zero-based, exclusive end.
+    pub end: u64,
+}
+
+impl Region {
+    /// Parse `chrom`, `chrom:pos` or `chrom:start-end`.
+    pub fn parse(text: &str) -> Result<Self> {
+        let invalid = || VcfFilterError::IntervalError(format!("invalid region '{}'", text));
+        let text = text.trim();
+        let Some((chrom, range)) = text.rsplit_once(':') else {
+            if text.is_empty() {
+                return Err(invalid());
+            }
+            return Ok(Self {
+                chrom: text.to_string(),
+                start: 0,
+                end: u64::MAX,
+            });
+        };
+        let position = |s: &str| {
+            s.trim()
+                .replace(',', "")
+                .parse::<u64>()
+                .ok()
+                .filter(|&p| p > 0)
+                .ok_or_else(invalid)
+        };
+        let (first, last) = match range.split_once('-') {
+            Some((first, last)) => (position(first)?, position(last)?),
+            None => {
+                let pos = position(range)?;
+                (pos, pos)
+            }
+        };
+        if chrom.is_empty() || last < first {
+            return Err(invalid());
+        }
+        Ok(Self {
+            chrom: chrom.to_string(),
+            start: first - 1,
+            end: last,
+        })
+    }
+
+    /// Check whether a record's span (see [`VcfRow::span`]) overlaps the region.
+    pub fn overlaps(&self, row: &VcfRow) -> bool {
+        let (start, end) = row.span();
+        row.chrom == self.chrom && start < self.end && end > self.start
+    }
+}
+
+/// Named column values from a BED/TSV file, indexed by chromosome.
+#[derive(Debug, Clone)]
+pub struct IntervalAnnotation {
+    /// Virtual field names, one per value column.
+    fields: Vec<String>,
+    /// Per-chromosome interval index holding one value per field.
+    chroms: HashMap<String, IntervalIndex<Vec<Value>>>,
+}
+
+impl IntervalAnnotation {
+    /// Parse a BED file, naming the columns after `end` with `fields`.
+    ///
+    /// Columns beyond `fields.len()` are ignored; missing columns are `Missing`.
+    /// `track`, `browser` and `#` lines are skipped.
+    pub fn parse_bed(text: &str, fields: &[&str]) -> Result<Self> {
+        let fields = fields.iter().map(|f| f.to_string()).collect();
+        Self::parse_rows(text, fields)
+    }
+
+    /// Parse a TSV whose first line names the columns.
+    ///
+    /// The header line is `#chrom<TAB>start<TAB>end<TAB>FIELD...` (the leading
+    /// `#` is optional); each name after `end` becomes a virtual field.
+    pub fn parse_tsv(text: &str) -> Result<Self> {
+        let header = text
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .ok_or_else(|| VcfFilterError::IntervalError("empty interval file".to_string()))?;
+        let columns: Vec<&str> = header.trim_start_matches('#').split('\t').collect();
+        if columns.len() < 4 {
+            return Err(VcfFilterError::IntervalError(format!(
+                "header must name chrom, start, end and at least one field: {}",
+                header
+            )));
+        }
+
+        let fields = columns[3..].iter().map(|c| c.trim().to_string()).collect();
+        let body = text.split_once(header).map(|(_, rest)| rest).unwrap_or("");
+        Self::parse_rows(body, fields)
+    }
+
+    /// Parse data lines into per-chromosome indexes.
+    fn parse_rows(text: &str, fields: Vec<String>) -> Result<Self> {
+        let mut by_chrom: HashMap<String, Vec<(u64, u64, Vec<Value>)>> = HashMap::new();
+
+        for (line_no, line) in text.lines().enumerate() {
+            if line.trim().is_empty()
+                || line.starts_with('#')
+                || line.starts_with("track")
+                || line.starts_with("browser")
+            {
+                continue;
+            }
+
+            let columns: Vec<&str> = line.split('\t').collect();
+            if columns.len() < 3 {
+                return Err(VcfFilterError::IntervalError(format!(
+                    "line {}: expected at least 3 columns",
+                    line_no + 1
+                )));
+            }
+            let coordinate = |s: &str| {
+                s.trim().parse::<u64>().map_err(|_| {
+                    VcfFilterError::IntervalError(format!(
+                        "line {}: invalid coordinate '{}'",
+                        line_no + 1,
+                        s
+                    ))
+                })
+            };
+            let (start, end) = (coordinate(columns[1])?, coordinate(columns[2])?);
+            if start > end {
+                return Err(VcfFilterError::IntervalError(format!(
+                    "line {}: start {} is after end {}",
+                    line_no + 1,
+                    start,
+                    end
+                )));
+            }
+
+            let values = (0..fields.len())
+                .map(|i| {
+                    columns
+                        .get(i + 3)
+                        .map_or(Value::Missing, |c| parse_column(c))
+                })
+                .collect();
+            by_chrom
+                .entry(columns[0].to_string())
+                .or_default()
+                .push((start, end, values));
+        }
+
+        Ok(Self {
+            fields,
+            chroms: by_chrom
+                .into_iter()
+                .map(|(chrom, intervals)| (chrom, IntervalIndex::new(intervals)))
+                .collect(),
+        })
+    }
+
+    /// Get the virtual field names this annotation provides.
+    pub fn fields(&self) -> &[String] {
+        &self.fields
+    }
+
+    /// Get the column values of every interval overlapping a record's span
+    /// (see [`VcfRow::span`]).
+    pub fn lookup(&self, row: &VcfRow) -> Vec<&[Value]> {
+        let (start, end) = row.span();
+        self.overlapping(&row.chrom, start, end)
+    }
+
+    /// Get the column values of every interval overlapping `chrom:[start, end)`.
+    pub fn overlapping(&self, chrom: &str, start: u64, end: u64) -> Vec<&[Value]> {
+        let Some(index) = self.chroms.get(chrom) else {
+            return Vec::new();
+        };
+        index
+            .overlapping(start, end)
+            .into_iter()
+            .map(Vec::as_slice)
+            .collect()
+    }
+
+    /// Number of intervals across all chromosomes.
+    pub fn len(&self) -> usize {
+        self.chroms.values().map(IntervalIndex::len).sum()
+    }
+
+    /// Returns true if the annotation holds no intervals.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Add the joined values to a record as virtual fields.
+    pub fn annotate(&self, row: &mut VcfRow) {
+        let hits = self.lookup(row);
+        if hits.is_empty() {
+            return;
+        }
+
+        for (i, field) in self.fields.iter().enumerate() {
+            if row.info.contains_key(field) {
+                continue;
+            }
+            let mut values: Vec<Value> = hits.iter().map(|values| values[i].clone()).collect();
+            let value = if values.len() == 1 {
+                values.remove(0)
+            } else {
+                Value::Array(values)
+            };
+            row.info.insert(field.clone(), value);
+        }
+    }
+}
+
+/// The regions of a BED file, tested with `in_bed("exons.bed")`.
+#[derive(Debug, Clone)]
+pub struct RegionSet {
+    intervals: IntervalAnnotation,
+}
+
+impl RegionSet {
+    /// Parse BED text; columns after `end` are ignored.
+    pub fn parse_bed(text: &str) -> Result<Self> {
+        Ok(Self {
+            intervals: IntervalAnnotation::parse_bed(text, &[])?,
+        })
+    }
+
+    /// Read and parse a BED file.
+    pub fn read_bed(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| VcfFilterError::IntervalError(format!("{}: {}", path.display(), e)))?;
+        Self::parse_bed(&text)
+    }
+
+    /// Check whether a record's span (see [`VcfRow::span`]) overlaps any region.
+    pub fn overlaps(&self, row: &VcfRow) -> bool {
+        let (start, end) = row.span();
+        !self
+            .intervals
+            .overlapping(&row.chrom, start, end)
+            .is_empty()
+    }
+
+    /// Number of regions in the set.
+    pub fn len(&self) -> usize {
+        self.intervals.len()
+    }
+
+    /// Returns true if the set holds no regions.
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+}
+
+/// BED region sets for `in_bed(...)`, loaded once and shared by clones.
+///
+/// Sets registered under a name take precedence over a file of the same
+/// path. Files referenced by path are read on first use and cached.
+#[derive(Debug, Clone, Default)]
+pub struct BedLibrary {
+    sets: Arc<RwLock<HashMap<String, Arc<RegionSet>>>>,
+}
+
+impl BedLibrary {
+    /// Create an empty library.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a region set under `name`, replacing any previous definition.
+    pub fn define(&mut self, name: &str, set: RegionSet) {
+        self.write().insert(name.to_string(), Arc::new(set));
+    }
+
+    /// Read a BED file and register it under `name`.
+    pub fn load(&mut self, name: &str, path: impl AsRef<Path>) -> Result<()> {
+        let set = RegionSet::read_bed(path.as_ref())?;
+        self.define(name, set);
+        Ok(())
+    }
+
+    /// Get the region set `in_bed(name)` refers to, reading the file on first use.
+    pub fn get(&self, name: &str) -> Result<Arc<RegionSet>> {
+        if let Some(set) = self.read().get(name) {
+            return Ok(Arc::clone(set));
+        }
+        let set = Arc::new(RegionSet::read_bed(Path::new(name))?);
+        Ok(Arc::clone(
+            self.write().entry(name.to_string()).or_insert(set),
+        ))
+    }
+
+    /// Load every file an expression references with `in_bed("path")`.
+    ///
+    /// Surfaces unreadable files when a filter is parsed rather than on the
+    /// first row.
+    pub fn preload(&self, expr: &Expr) -> Result<()> {
+        if let Expr::Call(name, args) = expr
+            && name == IN_BED
+        {
+            self.get(bed_name(args)?)?;
+        }
+        expr.children()
+            .into_iter()
+            .try_for_each(|child| self.preload(child))
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, HashMap<String, Arc<RegionSet>>> {
+        self.sets.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, HashMap<String, Arc<RegionSet>>> {
+        self.sets.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Name of the BED overlap function.
+const IN_BED: &str = "in_bed";
+
+/// Get the BED name from `in_bed` arguments, which must be one string literal.
+pub(crate) fn bed_name(args: &[Expr]) -> Result<&str> {
+    match args {
+        [Expr::String(name)] => Ok(name),
+        _ => Err(VcfFilterError::IntervalError(
+            "in_bed() expects a single string literal".to_string(),
+        )),
+    }
+}
+
+/// Parse a single column value, typing numbers and treating `.` as missing.
+fn parse_column(raw: &str) -> Value {
+    let raw = raw.trim();
+    if raw == "." || raw.is_empty() {
+        Value::Missing
+    } else if let Ok(n) = raw.parse::<f64>() {
+        Value::Number(n)
+    } else {
+        Value::String(raw.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::parse_header;
+    use crate::row::parse_row;
+
+    fn row(chrom: &str, pos: u64, ref_allele: &str) -> VcfRow {
+        let info_map = parse_header("").unwrap();
+        let line = format!("{}\t{}\t.\t{}\tG\t50\tPASS\t.", chrom, pos, ref_allele);
+        parse_row(&line, &info_map).unwrap()
+    }
+
+    #[test]
+    fn test_interval_index_overlapping() {
+        let index = IntervalIndex::new(vec![(10, 20, "a"), (0, 100, "b"), (30, 40, "c")]);
+        assert_eq!(index.overlapping(15, 16), vec![&"b", &"a"]);
+        assert_eq!(index.overlapping(20, 30), vec![&"b"]);
+        assert_eq!(index.overlapping(35, 50), vec![&"b", &"c"]);
+        assert!(index.overlapping(100, 200).is_empty());
+        assert_eq!(index.len(), 3);
+    }
+
+    #[test]
+    fn test_parse_tsv_and_annotate() {
+        let tsv = "#chrom\tstart\tend\tROH_REGION\tMAPPABILITY\n\
+                   chr1\t1000\t5000\troh_1\t0.92\n\
+                   chr1\t4000\t9000\t.\t0.31\n";
+        let annotation = IntervalAnnotation::parse_tsv(tsv).unwrap();
+        assert_eq!(annotation.fields(), ["ROH_REGION", "MAPPABILITY"]);
+
+        let mut record = row("chr1", 2000, "A");
+        annotation.annotate(&mut record);
+        assert_eq!(record.get("ROH_REGION"), Value::String("roh_1".to_string()));
+        assert_eq!(record.get("MAPPABILITY"), Value::Number(0.92));
+
+        // Overlapping both intervals
+        let mut record = row("chr1", 4500, "A");
+        annotation.annotate(&mut record);
+        assert_eq!(
+            record.get("MAPPABILITY"),
+            Value::Array(vec![Value::Number(0.92), Value::Number(0.31)])
+        );
+
+        let mut record = row("chr2", 2000, "A");
+        annotation.annotate(&mut record);
+        assert_eq!(record.get("ROH_REGION"), Value::Missing);
+    }
+
+    #[test]
+    fn test_parse_bed_half_open_coordinates() {
+        let bed = "track name=roh\nchr1\t99\t200\tregion_a\t7\n";
+        let annotation = IntervalAnnotation::parse_bed(bed, &["ROH_REGION"]).unwrap();
+
+        // VCF position 100 is BED base 99, the first base of the interval
+        assert_eq!(annotation.lookup(&row("chr1", 100, "A")).len(), 1);
+        assert_eq!(annotation.lookup(&row("chr1", 200, "A")).len(), 1);
+        assert!(annotation.lookup(&row("chr1", 201, "A")).is_empty());
+        // A deletion starting before the interval still overlaps it
+        assert_eq!(annotation.lookup(&row("chr1", 97, "ACGT")).len(), 1);
+    }
+
+    #[test]
+    fn test_region_overlaps() {
+        let region = Region::parse("chr1:186,000,000-187,000,000").unwrap();
+        assert_eq!((region.start, region.end), (185_999_999, 187_000_000));
+        assert!(region.overlaps(&row("chr1", 186_000_000, "A")));
+        assert!(region.overlaps(&row("chr1", 187_000_000, "A")));
+        assert!(!region.overlaps(&row("chr1", 187_000_001, "A")));
+        assert!(!region.overlaps(&row("chr2", 186_500_000, "A")));
+        // A deletion starting before the region still overlaps it
+        assert!(region.overlaps(&row("chr1", 185_999_998, "ACG")));
+
+        assert!(
+            Region::parse("chr1")
+                .unwrap()
+                .overlaps(&row("chr1", 5, "A"))
+        );
+        assert!(
+            Region::parse("chr1:100")
+                .unwrap()
+                .overlaps(&row("chr1", 100, "A"))
+        );
+        assert!(
+            !Region::parse("chr1:100")
+                .unwrap()
+                .overlaps(&row("chr1", 101, "A"))
+        );
+        // HLA contigs contain colons
+        assert_eq!(
+            Region::parse("HLA-A*01:01:01:01:1-10").unwrap().chrom,
+            "HLA-A*01:01:01:01"
+        );
+
+        for invalid in ["", "chr1:", "chr1:0-10", "chr1:20-10", "chr1:a-b", ":1-10"] {
+            assert!(Region::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_bed_library_region_sets() {
+        let mut library = BedLibrary::new();
+        library.define(
+            "exons",
+            RegionSet::parse_bed("chr1\t99\t200\texon_1\nchr2\t0\t10\n").unwrap(),
+        );
+        let exons = library.get("exons").unwrap();
+        assert_eq!(exons.len(), 2);
+        assert!(exons.overlaps(&row("chr1", 150, "A")));
+        assert!(!exons.overlaps(&row("chr1", 300, "A")));
+
+        // A structural variant overlaps through its END
+        let info_map = parse_header("").unwrap();
+        let sv = parse_row("chr1\t50\t.\tA\t<DEL>\t50\tPASS\tEND=120", &info_map).unwrap();
+        assert!(exons.overlaps(&sv));
+
+        assert!(library.get("/nonexistent/exons.bed").is_err());
+        assert!(
+            library
+                .preload(&Expr::Call("in_bed".to_string(), vec![Expr::var("x")]))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_invalid_interval_files() {
+        assert!(IntervalAnnotation::parse_bed("chr1\tx\t10\n", &[]).is_err());
+        assert!(IntervalAnnotation::parse_tsv("#chrom\tstart\tend\n").is_err());
+
+        let error = IntervalAnnotation::parse_bed("chr1\t1\t5\nchr1\t50\t10\tx\n", &[])
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "Interval file error: line 2: start 50 is after end 10"
+        );
+    }
+
+    #[test]
+    fn test_structural_variant_spans() {
+        let info_map = parse_header("").unwrap();
+        // A symbolic deletion without END spans POS + |SVLEN|
+        let deletion =
+            parse_row("chr1\t100\t.\tA\t<DEL>\t50\tPASS\tSVLEN=-500", &info_map).unwrap();
+        assert_eq!(deletion.span(), (99, 600));
+        let region = Region::parse("chr1:550-560").unwrap();
+        assert!(region.overlaps(&deletion));
+        assert!(!Region::parse("chr1:601-700").unwrap().overlaps(&deletion));
+
+        let regions = RegionSet::parse_bed("chr1\t400\t450\n").unwrap();
+        assert!(regions.overlaps(&deletion));
+        let tsv = "#chrom\tstart\tend\tROH\nchr1\t400\t450\troh_1\n";
+        let annotation = IntervalAnnotation::parse_tsv(tsv).unwrap();
+        assert_eq!(annotation.lookup(&deletion).len(), 1);
+
+        // END wins over SVLEN; an END inside REF keeps the REF span
+        let with_end = parse_row(
+            "chr1\t100\t.\tA\t<DUP>\t50\tPASS\tEND=300;SVLEN=500",
+            &info_map,
+        )
+        .unwrap();
+        assert_eq!(with_end.span(), (99, 300));
+        let short_end = parse_row("chr1\t100\t.\tACGT\tA\t50\tPASS\tEND=100", &info_map).unwrap();
+        assert_eq!(short_end.span(), (99, 103));
+        // Insertions and breakends are points
+        let insertion =
+            parse_row("chr1\t100\t.\tA\t<INS>\t50\tPASS\tSVLEN=300", &info_map).unwrap();
+        assert_eq!(insertion.span(), (99, 100));
+    }
+}
@@ -45,29 +45,106 @@
 //! - `<` Less than
 //! - `>=` Greater than or equal
 //! - `<=` Less than or equal
-//! - `contains` String containment
+//! - `contains` String containment (any element of an array, e.g. `FILTER contains "LowQual"`)
+//! - `in` Membership in a list literal or array (`CLNSIG in ["Pathogenic", "Likely_pathogenic"]`)
+//! - `severity_at_least` Consequence ranking (`ANN[*].Annotation severity_at_least "missense_variant"`);
+//!   see [`consequence`]
+//!
+//! Ordering comparisons against `CHROM` use natural chromosome order
+//! (`CHROM <= "chr9"` excludes `chr10`), or the header's `##contig` order
+//! when it declares contigs; see [`chromosome`].
 //!
 //! ### Logical Operators
 //! - `&&` Logical AND
 //! - `||` Logical OR
 //! - `!` Logical NOT
 //!
+//! ### Arithmetic and Let-Bindings
+//! - `+`, `-`, `*`, `/` - Arithmetic (`AD[1] / DP > 0.2`); missing operands or division by zero give missing
+//! - `let ab = AD[1] / DP in ab > 0.2 && ab < 0.8` - Compute a value once and name it
+//!
+//! ### Named Filters
+//! - `@rare_coding` - A filter registered with [`FilterEngine::with_preset`] or a
+//!   [`presets::PresetLibrary`] (`@rare_coding := AF < 0.01 && ...`), or a
+//!   `[presets]` entry of a [`config::FilterConfig`] set with [`FilterEngine::with_config`]
+//!
 //! ### Field Access
 //! - `QUAL` - Built-in VCF column
 //! - `FILTER` - Filter status
 //! - `DP` - INFO field
+//! - `DB` - INFO Flag field, `false` when absent
 //! - `INFO.DP` - Explicit INFO field lookup
-//! - `FORMAT.DP` - Explicit FORMAT field lookup
+//! - `FORMAT.DP` - Explicit FORMAT field lookup (numeric when `##FORMAT` declares `Integer`/`Float`)
+//! - `SAMPLE["NA12878"].GT` / `FORMAT.DP["tumor"]` - A named sample's FORMAT field
+//! - `FORMAT[*].DP` / `FORMAT[all].DP` - Any / every sample's FORMAT field
+//! - `AF[alt]` / `AD[alt]` - The value for the ALT allele being tested
+//!   (`Number=A` / `Number=R`); the filter passes if any allele passes
 //! - `ANN[0].Gene_Name` - First annotation's gene name
 //! - `INFO.ANN[0].Gene_Name` - First annotation gene via explicit INFO namespace
 //! - `ANN[*].Annotation_Impact` - Any annotation's impact (wildcard)
+//! - `ANN[all].Annotation_Impact` - Every annotation's impact (universal)
+//! - `ANN[?(Annotation_Impact == "HIGH")].Gene_Name` - Genes of HIGH impact annotations only
+//! - `INFO.gnomAD_*` - Values of every INFO key matching the pattern
 //!
 //! Non-built-in field resolution order is INFO first, then FORMAT.
 //! Use `INFO.<field>` or `FORMAT.<field>` for strict namespace resolution.
 //! If a numeric comparison involves a missing value, the comparison evaluates to `false`.
 //!
 //! ### Functions
-//! - `exists(field)` - Check if a field exists
+//! - `exists(field)` - Check if a field exists (also `exists(ANN[*].HGVS_p)`; empty subfields are absent)
+//! - `all(expr)` - Require wildcard comparisons in `expr` to hold for every element
+//! - `is_missing(field)` / `is_empty(field)` - Field present with value `.` / empty string
+//! - `default(field, fallback)` - Use `fallback` when `field` is missing
+//! - `split(field, "|")` / `len(x)` - Split packed strings into arrays and count values
+//! - `number(x)` / `string(x)` - Explicit type conversion (non-numeric text is an error)
+//! - `replace(x, "chr", "")` / `substring(x, start[, end])` - Normalize strings before comparing
+//! - `max(...)` / `min(...)` - Largest/smallest number among the arguments
+//! - `abs(x)` / `log10(x)` / `sqrt(x)` - Score transforms (`log10(AF) < -3`); missing when undefined
+//! - `any(expr)` - True if any element of a wildcard comparison or array is true
+//! - `region("chr1:186,000,000-187,000,000")` - Record overlaps a region (REF span, or up to INFO `END` / POS + |SVLEN|)
+//! - `in_bed("exons.bed")` - Record overlaps a region of a BED file or [`FilterEngine::with_bed`] set
+//! - `has_id()` - The ID column holds an identifier (`ID` itself is split on `;`)
+//! - `filter_has("q10")` - The FILTER column lists this exact name
+//! - `chrom_rank([name])` - Natural chromosome rank (chr1 = 1, chrX = 23, ...); missing for other contigs
+//! - `is_snp()` / `is_mnp()` / `is_indel()` / `is_sv()` - Variant class of any ALT allele (from REF/ALT)
+//! - `allele_len([n])` / `indel_len()` - Allele lengths and signed length change (SVLEN for symbolic alleles)
+//! - `is_het()` / `is_hom_ref()` / `is_hom_alt()` / `is_phased()` - Parsed GT of the first sample
+//!   (or `is_het(n)` for the nth); missing for uncalled genotypes
+//! - `ploidy([n])` - Number of alleles in a sample's GT (1 for haploid calls)
+//! - `n_het()` / `n_hom_ref()` / `n_hom_alt()` / `n_missing()` - Genotype counts over all samples
+//! - `vaf()` / `allele_balance()` - ALT depth / total depth from AD (else the sample's AF, then AO/DP)
+//! - `variant_type()` - `"SNP"`, `"MNP"`, `"INS"`, `"DEL"`, `"SV"`, `"BND"` or `"OTHER"` per ALT allele
+//! - `alt_count()` - Number of ALT alleles, leaving out the spanning deletion `*`
+//! - `sv_type()` / `sv_end()` - Structural variant type (SVTYPE, `<DUP:TANDEM>` gives `"DUP"`) and end position
+//! - `mate_chrom()` / `mate_pos()` - Mate location of breakend ALT alleles (`G]chr17:198982]`)
+//! - `x in_file("genes.txt")` - Membership in a list file or a list registered with
+//!   [`FilterEngine::with_list`]; see [`lists`]
+//! - `computed_af()` / `af_consistent([tolerance])` - Recompute AF as AC/AN and check the stored AF
+//! - `gt_confidence()` - Gap between the best and second-best PL/GL genotype likelihood
+//!
+//! Custom functions can be added with [`FilterEngine::register_function`].
+//!
+//! Filters can also be built in code without string formatting, e.g.
+//! `Expr::field("DP").gt(30).and(Expr::field("FILTER").eq("PASS"))`.
+//!
+//! [`FilterEngine::check_filter`] reports unknown fields, misspelled subfields and
+//! type mismatches against the header before any rows are processed.
+//!
+//! [`FilterEngine::explain`] traces what each sub-expression evaluated to,
+//! to show why a row failed a filter.
+//!
+//! [`FilterEngine::annotations`] computes new INFO fields (`VAF=AD[1] / DP`)
+//! and writes them back onto rows, with generated `##INFO` lines; see [`annotate`].
+//!
+//! [`FilterEngine::soft_filter_lines`] keeps failing records and tags them in
+//! the FILTER column, like `bcftools filter -s`; see [`soft_filter`].
+//!
+//! [`tabix::open_regions`] reads only the records of some regions from an
+//! indexed bgzip VCF, using its `.tbi` or `.csi` index; see [`tabix`].
+//!
+//! [`FilterEngine::builder`] configures strict mode, missing-value handling,
+//! case-insensitive matching, float tolerance, sample selection and custom
+//! structured fields; see [`options`].
 
 /// Embedded README.md documentation
 const README: &str = include_str!("../README.md");
@@ -86,23 +163,82 @@ pub fn docs() -> &'static str {
     README
 }
 
+pub mod annotate;
+pub mod batch;
+mod builder;
+mod cache;
+pub mod chromosome;
+pub mod compiled;
+pub mod compression;
+pub mod config;
+pub mod conformance;
+pub mod consequence;
+pub mod describe;
 pub mod error;
 pub mod eval;
+pub mod explain;
 pub mod filter;
+pub mod functions;
 pub mod header;
+pub mod intervals;
+pub mod limits;
+pub mod lists;
+pub mod options;
+#[cfg(feature = "parallel")]
+pub mod parallel;
+pub mod pedigree;
+mod plan;
+pub mod presets;
 pub mod row;
+pub mod row_ref;
+pub mod soft_filter;
+pub mod stats;
+pub mod tabix;
+pub mod validate;
 pub mod value;
 
+pub use chromosome::ContigOrder;
 pub use error::{Result, VcfFilterError};
+pub use eval::{AlleleMode, Collation, Outcome};
 pub use filter::{AccessPart, BinaryOp, Expr, UnaryOp};
-pub use header::{InfoField, InfoMap, InfoNumber, InfoType};
-pub use row::VcfRow;
+pub use header::{
+    Contig, Delimiters, FilterField, FormatMap, InfoField, InfoMap, InfoNumber, InfoType,
+    VcfHeader, VcfVersion,
+};
+pub use limits::EvalLimits;
+pub use options::{EngineOptions, FilterEngineBuilder};
+pub use row::{
+    AlleleFrequencyMismatch, Annotation, AnnotationMismatch, Breakend, DEFAULT_AF_TOLERANCE,
+    Genotype, MissingValueMode, NonFiniteMode, ParseOptions, VariantClass, VcfRow,
+};
+pub use row_ref::VcfRowRef;
 pub use value::Value;
 
-use crate::eval::evaluate;
-use crate::filter::parse_filter;
-use crate::header::parse_header;
-use crate::row::parse_row;
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::cache::ExprCache;
+use crate::compiled::CompiledFilter;
+use crate::config::{ConfigWatcher, FilterConfig};
+use crate::eval::{EvalContext, evaluate_with};
+use crate::explain::Explanation;
+use crate::filter::{parse_error, parse_filter};
+use crate::functions::FunctionRegistry;
+use crate::header::{
+    merge_info_maps, parse_contig_header, parse_fileformat, parse_filter_header,
+    parse_format_header, parse_header, parse_sample_names,
+};
+use crate::intervals::{BedLibrary, IntervalAnnotation};
+use crate::lists::ListLibrary;
+use crate::pedigree::{INHERITANCE_FIELD, Inheritance, Trio, TrioColumns};
+use crate::presets::PresetLibrary;
+use crate::row::parse_row_with_keys;
+use crate::soft_filter::SoftFilter;
+use crate::stats::FilterStats;
+use crate::validate::{Diagnostic, RowDiagnostic, Severity};
 
 /// The main filter engine for evaluating VCF filters.
 ///
@@ -112,6 +248,52 @@ use crate::row::parse_row;
 pub struct FilterEngine {
     /// Parsed INFO field metadata from the header.
     info_map: InfoMap,
+    /// Parsed FORMAT field metadata from the header, used to type sample values.
+    format_map: FormatMap,
+    /// FILTER names declared by the header's ##FILTER lines, in order.
+    filters: Vec<FilterField>,
+    /// Contigs declared by the header's ##contig lines, ordering `CHROM` comparisons.
+    contigs: ContigOrder,
+    /// The VCF version from the header's ##fileformat line.
+    version: Option<VcfVersion>,
+    /// Options applied when parsing data rows.
+    parse_options: ParseOptions,
+    /// Sandbox limits applied to filters and their evaluation.
+    limits: EvalLimits,
+    /// Interval annotations joined onto every parsed row.
+    intervals: Vec<Arc<IntervalAnnotation>>,
+    /// Sample names from the `#CHROM` header line.
+    sample_names: Vec<String>,
+    /// Trio used to compute the `INHERITANCE` virtual field.
+    trio: Option<TrioColumns>,
+    /// Fields the engine adds to parsed rows (interval columns, `INHERITANCE`).
+    virtual_fields: Vec<String>,
+    /// Whether evaluation fails on unknown fields, bad indexes and type mismatches.
+    strict: bool,
+    /// How comparisons match strings (case) and numbers (tolerance).
+    collation: Collation,
+    /// Header sample columns kept on parsed rows, if only some are selected.
+    sample_columns: Option<Vec<usize>>,
+    /// Whether missing values propagate as "unknown" (three-valued logic).
+    three_valued: bool,
+    /// Whether records whose only ALT allele is `*` fail every filter.
+    drop_spanning_deletions: bool,
+    /// Whether filters are evaluated per ALT allele, and how alleles combine.
+    allele_mode: AlleleMode,
+    /// Named filters referenced as `@name` in expressions.
+    presets: PresetLibrary,
+    /// Presets, aliases and virtual fields expanded in every parsed filter.
+    config: Arc<FilterConfig>,
+    /// Reloadable configuration used instead of `config`, read for every parsed filter.
+    config_watcher: Option<Arc<ConfigWatcher>>,
+    /// Value lists referenced as `in_file("name")` in expressions.
+    lists: ListLibrary,
+    /// BED region sets referenced as `in_bed("name")` in expressions.
+    beds: BedLibrary,
+    /// User-registered functions callable from expressions.
+    functions: FunctionRegistry,
+    /// Recently parsed filters for `evaluate` and the other string-based methods.
+    expr_cache: ExprCache,
 }
 
 impl FilterEngine {
@@ -135,328 +317,3148 @@ impl FilterEngine {
     /// ```
     pub fn new(header: &str) -> Result<Self> {
         let info_map = parse_header(header)?;
-        Ok(Self { info_map })
+        Ok(Self {
+            sample_names: parse_sample_names(header),
+            trio: None,
+            virtual_fields: Vec::new(),
+            strict: false,
+            collation: Collation::default(),
+            sample_columns: None,
+            three_valued: false,
+            drop_spanning_deletions: false,
+            allele_mode: AlleleMode::Site,
+            info_map,
+            format_map: parse_format_header(header)?,
+            filters: parse_filter_header(header)?,
+            contigs: ContigOrder::new(parse_contig_header(header)?),
+            version: parse_fileformat(header),
+            parse_options: ParseOptions::default(),
+            limits: EvalLimits::default(),
+            intervals: Vec::new(),
+            presets: PresetLibrary::new(),
+            config: Arc::new(FilterConfig::default()),
+            config_watcher: None,
+            lists: ListLibrary::new(),
+            beds: BedLibrary::new(),
+            functions: FunctionRegistry::new(),
+            expr_cache: ExprCache::default(),
+        }
+        .with_parse_options(ParseOptions::default()))
     }
 
-    /// Evaluate a filter expression against a VCF row.
-    ///
-    /// # Arguments
+    /// Start building an engine with non-default options.
     ///
-    /// * `filter` - The filter expression string (e.g., `"QUAL > 30"`)
-    /// * `row` - A single VCF data row (tab-separated)
-    ///
-    /// # Returns
+    /// See [`options`] for the available settings.
+    pub fn builder(header: &str) -> FilterEngineBuilder {
+        FilterEngineBuilder::new(header)
+    }
+
+    /// Create one engine for several VCFs, such as per-chromosome shards.
     ///
-    /// `true` if the row matches the filter, `false` otherwise.
+    /// The first header is the base; INFO and FORMAT fields, FILTER names
+    /// and contigs only declared by later headers are added to it. A field
+    /// declared with a different Number, Type or subfields keeps its first
+    /// declaration and is reported as a warning, as is a header whose
+    /// samples differ from the first. Fails with `ConfigError` when no
+    /// header is given.
     ///
     /// # Example
     ///
     /// ```rust
     /// use vcf_filter::FilterEngine;
     ///
-    /// let header = r#"##INFO=<ID=DP,Number=1,Type=Integer,Description="Depth">"#;
-    /// let engine = FilterEngine::new(header).unwrap();
+    /// let chr1 = r#"##INFO=<ID=DP,Number=1,Type=Integer,Description="Depth">"#;
+    /// let chr2 = concat!(
+    ///     "##INFO=<ID=DP,Number=1,Type=Float,Description=\"Depth\">\n",
+    ///     "##INFO=<ID=DB,Number=0,Type=Flag,Description=\"dbSNP\">",
+    /// );
+    /// let (engine, warnings) = FilterEngine::from_headers(&[chr1, chr2]).unwrap();
     ///
-    /// let row = "chr1\t100\t.\tA\tG\t50\tPASS\tDP=30";
-    /// assert!(engine.evaluate("QUAL > 30", row).unwrap());
+    /// assert!(engine.info_map().contains_key("DB"));
+    /// assert_eq!(warnings.len(), 1);
+    /// assert!(warnings[0].message.contains("DP is declared Number=1,Type=Integer"));
     /// ```
-    pub fn evaluate(&self, filter: &str, row: &str) -> Result<bool> {
-        let parsed_row = parse_row(row, &self.info_map)?;
-        let expr = parse_filter(filter).map_err(|errs| {
-            VcfFilterError::FilterParseError(
-                errs.into_iter()
-                    .map(|e| e.to_string())
-                    .collect::<Vec<_>>()
-                    .join(", "),
-            )
-        })?;
-        let result = evaluate(&expr, &parsed_row, &self.info_map)?;
-        Ok(result.as_bool().unwrap_or(false))
+    pub fn from_headers(headers: &[&str]) -> Result<(Self, Vec<Diagnostic>)> {
+        let Some((first, rest)) = headers.split_first() else {
+            return Err(VcfFilterError::ConfigError(
+                "at least one header is needed".to_string(),
+            ));
+        };
+        let mut engine = Self::new(first)?;
+        let mut contigs = engine.contigs.contigs().to_vec();
+        let mut warnings = Vec::new();
+        let mut warn = |message: String| {
+            warnings.push(Diagnostic {
+                severity: Severity::Warning,
+                message,
+            })
+        };
+        for header in rest {
+            for conflict in merge_info_maps(&mut engine.info_map, &parse_header(header)?) {
+                warn(format!("##INFO {}", conflict));
+            }
+            for conflict in merge_info_maps(&mut engine.format_map, &parse_format_header(header)?) {
+                warn(format!("##FORMAT {}", conflict));
+            }
+            for filter in parse_filter_header(header)? {
+                if !engine.filters.iter().any(|f| f.id == filter.id) {
+                    engine.filters.push(filter);
+                }
+            }
+            for contig in parse_contig_header(header)? {
+                if !contigs.iter().any(|c| c.id == contig.id) {
+                    contigs.push(contig);
+                }
+            }
+            let samples = parse_sample_names(header);
+            if samples != engine.sample_names {
+                warn(format!(
+                    "samples [{}] differ from the first header's [{}]",
+                    samples.join(", "),
+                    engine.sample_names.join(", ")
+                ));
+            }
+        }
+        engine.contigs = ContigOrder::new(contigs);
+        Ok((engine, warnings))
     }
 
-    /// Parse a VCF row without evaluating a filter.
+    /// Read a VCF header from a stream and create an engine from it.
     ///
-    /// Useful for inspecting row data or performing multiple evaluations
-    /// against the same row.
+    /// Consumes lines up to and including the `#CHROM` line, leaving the
+    /// reader at the first data row. Returns the engine and the raw header
+    /// lines (without line endings) for copying to an output.
     ///
-    /// # Arguments
+    /// # Example
     ///
-    /// * `row` - A single VCF data row (tab-separated)
+    /// ```rust
+    /// use std::io::{BufRead, Cursor};
+    /// use vcf_filter::FilterEngine;
     ///
-    /// # Returns
+    /// let vcf = "##fileformat=VCFv4.2\n\
+    ///            ##INFO=<ID=DP,Number=1,Type=Integer,Description=\"Depth\">\n\
+    ///            #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+    ///            chr1\t100\t.\tA\tG\t50\tPASS\tDP=30\n";
+    /// let mut reader = Cursor::new(vcf);
+    /// let (engine, header_lines) = FilterEngine::from_reader(&mut reader).unwrap();
+    /// assert_eq!(header_lines.len(), 3);
     ///
-    /// A parsed `VcfRow` structure.
-    pub fn parse_row(&self, row: &str) -> Result<VcfRow> {
-        parse_row(row, &self.info_map)
+    /// for line in reader.lines() {
+    ///     assert!(engine.evaluate("DP >= 10", &line.unwrap()).unwrap());
+    /// }
+    /// ```
+    pub fn from_reader(reader: &mut impl BufRead) -> Result<(Self, Vec<String>)> {
+        let mut header_lines = Vec::new();
+        for line in reader.lines() {
+            let line = line.map_err(|e| {
+                VcfFilterError::HeaderParseError(format!("failed to read header: {}", e))
+            })?;
+            if !line.starts_with('#') {
+                return Err(VcfFilterError::HeaderParseError(
+                    "No VCF header found before data rows".to_string(),
+                ));
+            }
+            let is_column_header = line.starts_with("#CHROM");
+            header_lines.push(line);
+            if is_column_header {
+                let engine = Self::new(&header_lines.join("\n"))?;
+                return Ok((engine, header_lines));
+            }
+        }
+        Err(VcfFilterError::HeaderParseError(
+            "missing #CHROM header line".to_string(),
+        ))
     }
 
-    /// Parse a filter expression without evaluating it.
+    /// Filter a VCF file into another, returning `(passed, total)` row counts.
     ///
-    /// Useful for validating filter syntax or caching parsed expressions.
-    ///
-    /// # Arguments
+    /// Plain, gzip and bgzip inputs are detected by their magic bytes; the
+    /// output is written BGZF-compressed if its name ends in `.gz` or `.bgz`
+    /// (see [`compression`]). The engine is created from the input's own
+    /// header, which is copied to the output ahead of the passing rows. The
+    /// filter is parsed before either file is opened.
     ///
-    /// * `filter` - The filter expression string
+    /// # Example
     ///
-    /// # Returns
+    /// ```rust,no_run
+    /// use vcf_filter::FilterEngine;
     ///
-    /// A parsed `Expr` AST.
-    pub fn parse_filter(&self, filter: &str) -> Result<Expr> {
-        parse_filter(filter).map_err(|errs| {
-            VcfFilterError::FilterParseError(
-                errs.into_iter()
-                    .map(|e| e.to_string())
-                    .collect::<Vec<_>>()
-                    .join(", "),
-            )
-        })
+    /// let (passed, total) =
+    ///     FilterEngine::filter_file("calls.vcf.gz", "calls.pass.vcf.gz", "QUAL >= 30").unwrap();
+    /// println!("{} of {} rows passed", passed, total);
+    /// ```
+    pub fn filter_file(
+        input: impl AsRef<Path>,
+        output: impl AsRef<Path>,
+        filter: &str,
+    ) -> Result<(u64, u64)> {
+        let expr = parse_filter(filter).map_err(parse_error)?;
+        let reader = compression::open_input(&input)?;
+        let mut writers = [compression::create_output(output)?];
+        let (passed, total) = batch::filter_stream(reader, &[expr], &mut writers)?;
+        Ok((passed[0], total))
     }
 
-    /// Evaluate a pre-parsed filter expression against a pre-parsed row.
-    ///
-    /// This is more efficient when evaluating the same filter against
-    /// many rows, or the same row against many filters.
+    /// Replace the row parsing options used by this engine.
     ///
-    /// # Arguments
+    /// # Example
     ///
-    /// * `expr` - The parsed filter expression
-    /// * `row` - The parsed VCF row
+    /// ```rust
+    /// use vcf_filter::{FilterEngine, MissingValueMode, ParseOptions};
     ///
-    /// # Returns
+    /// let header = r#"##INFO=<ID=AD,Number=R,Type=Integer,Description="Allele depths">"#;
+    /// let engine = FilterEngine::new(header).unwrap().with_parse_options(ParseOptions {
+    ///     missing_values: MissingValueMode::Strict,
+    ///     ..ParseOptions::default()
+    /// });
     ///
-    /// `true` if the row matches the filter, `false` otherwise.
-    pub fn evaluate_parsed(&self, expr: &Expr, row: &VcfRow) -> Result<bool> {
-        let result = evaluate(expr, row, &self.info_map)?;
-        Ok(result.as_bool().unwrap_or(false))
+    /// let row = "chr1\t100\t.\tA\tG\t50\tPASS\tAD=10,.";
+    /// assert!(engine.evaluate("AD[0] == 10", row).is_err());
+    /// ```
+    pub fn with_parse_options(mut self, mut options: ParseOptions) -> Self {
+        if options.percent_decoding.is_none() {
+            options.percent_decoding = Some(self.version.is_none_or(|v| v.percent_encoding()));
+        }
+        for field in self.info_map.values_mut() {
+            if options.is_aligned(&field.id) && field.subfields.is_none() {
+                field.number = InfoNumber::Variable;
+            }
+        }
+        self.parse_options = options;
+        self
     }
 
-    /// Get the INFO field metadata map.
+    /// Parse INFO keys starting with `prefix` as aligned per-transcript arrays.
     ///
-    /// Useful for inspecting what fields are available and their types.
-    pub fn info_map(&self) -> &InfoMap {
-        &self.info_map
+    /// For annotations such as SnpSift's dbNSFP fields, whose values are
+    /// comma-joined per transcript and pair up by position across keys;
+    /// see [`ParseOptions::aligned_prefixes`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use vcf_filter::FilterEngine;
+    ///
+    /// let header = r#"##INFO=<ID=dbNSFP_SIFT_pred,Number=A,Type=String,Description="SIFT prediction">"#;
+    /// let engine = FilterEngine::new(header).unwrap().with_aligned_prefix("dbNSFP_");
+    ///
+    /// let row = "chr1\t100\t.\tA\tG\t50\tPASS\tdbNSFP_SIFT_pred=T,.,D;dbNSFP_Ensembl_transcriptid=ENST1,ENST2,ENST3";
+    /// assert!(engine.evaluate(r#"dbNSFP_SIFT_pred[*] == "D""#, row).unwrap());
+    /// assert!(engine.evaluate(r#"dbNSFP_Ensembl_transcriptid[2] == "ENST3" && dbNSFP_SIFT_pred[2] == "D""#, row).unwrap());
+    /// assert!(engine.evaluate("len(dbNSFP_SIFT_pred) == 3", row).unwrap());
+    /// ```
+    pub fn with_aligned_prefix(self, prefix: &str) -> Self {
+        let options = self.parse_options.clone().with_aligned_prefix(prefix);
+        self.with_parse_options(options)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Get the row parsing options used by this engine.
+    pub fn parse_options(&self) -> &ParseOptions {
+        &self.parse_options
+    }
 
-    const SAMPLE_VCF: &str = include_str!("../samples/sample.vcf");
+    /// Apply sandbox limits to filters parsed and evaluated by this engine.
+    ///
+    /// Size and depth limits are checked by `parse_filter` and `evaluate`;
+    /// the time budget applies to every row evaluation.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use vcf_filter::{EvalLimits, FilterEngine, VcfFilterError};
+    ///
+    /// let engine = FilterEngine::new("").unwrap().with_limits(EvalLimits {
+    ///     max_depth: Some(4),
+    ///     ..Default::default()
+    /// });
+    ///
+    /// let err = engine.parse_filter("((((((QUAL > 1))))))").unwrap_err();
+    /// assert!(matches!(err, VcfFilterError::LimitExceeded(_)));
+    /// ```
+    pub fn with_limits(mut self, limits: EvalLimits) -> Self {
+        self.limits = limits;
+        self.expr_cache.clear();
+        self
+    }
 
-    const FULL_HEADER: &str = r#"##INFO=<ID=END,Number=1,Type=Integer,Description="End position (for use with symbolic alleles)">
-##INFO=<ID=ANN,Number=.,Type=String,Description="Functional annotations: 'Allele | Annotation | Annotation_Impact | Gene_Name | Gene_ID | Feature_Type | Feature_ID | Transcript_BioType | Rank | HGVS.c | HGVS.p | cDNA.pos / cDNA.length | CDS.pos / CDS.length | AA.pos / AA.length | Distance | ERRORS / WARNINGS / INFO'">
-##INFO=<ID=LOF,Number=.,Type=String,Description="Predicted loss of function effects for this variant. Format: 'Gene_Name | Gene_ID | Number_of_transcripts_in_gene | Percent_of_transcripts_affected'">
-##INFO=<ID=NMD,Number=.,Type=String,Description="Predicted nonsense mediated decay effects for this variant. Format: 'Gene_Name | Gene_ID | Number_of_transcripts_in_gene | Percent_of_transcripts_affected'">
-##INFO=<ID=CLNSIG,Number=.,Type=String,Description="Aggregate germline classification for this single variant; multiple values are separated by a vertical bar">
-##INFO=<ID=CLNDN,Number=.,Type=String,Description="ClinVar's preferred disease name for the concept specified by disease identifiers in CLNDISDB">"#;
+    /// Get the sandbox limits applied by this engine.
+    pub fn limits(&self) -> &EvalLimits {
+        &self.limits
+    }
 
-    const REAL_ROW: &str = "chr1\t186308857\trs3737940\tA\tG\t37.5\tPASS\tANN=G|synonymous_variant|LOW|PRG4|ENSG00000116690|transcript|ENST00000445192.6|protein_coding|7/13|c.3138A>G|p.Pro1046Pro|3183/5044|3138/4215|1046/1404||,G|synonymous_variant|LOW|PRG4|ENSG00000116690|transcript|ENST00000367483.8|protein_coding|6/12|c.3015A>G|p.Pro1005Pro|3060/4921|3015/4092|1005/1363||,G|synonymous_variant|LOW|PRG4|ENSG00000116690|transcript|ENST00000367485.4|protein_coding|5/11|c.2859A>G|p.Pro953Pro|2904/4765|2859/3936|953/1311||,G|synonymous_variant|LOW|PRG4|ENSG00000116690|transcript|ENST00000635041.1|protein_coding|6/12|c.3009A>G|p.Pro1003Pro|3054/4915|3009/4086|1003/1361||,G|downstream_gene_variant|MODIFIER|TPR|ENSG00000047410|transcript|ENST00000367478.9|protein_coding||c.*5114T>C|||||2795|,G|downstream_gene_variant|MODIFIER|PRG4|ENSG00000116690|transcript|ENST00000533951.5|protein_coding||c.*2379A>G|||||2379|WARNING_TRANSCRIPT_NO_STOP_CODON,G|downstream_gene_variant|MODIFIER|PRG4|ENSG00000116690|transcript|ENST00000367482.8|protein_coding||c.*175A>G|||||175|WARNING_TRANSCRIPT_INCOMPLETE,G|downstream_gene_variant|MODIFIER|RNU6-1240P|ENSG00000202025|transcript|ENST00000365155.1|snRNA||n.*2968T>C|||||2968|;CLNSIG=Benign;CLNDN=not_provided|Camptodactyly-arthropathy-coxa_vara-pericarditis_syndrome|not_specified";
+    /// Make evaluation fail instead of quietly evaluating false.
+    ///
+    /// In strict mode, referencing a field that is neither declared in the
+    /// header nor a built-in or virtual field fails with `UnknownField`,
+    /// indexing past the end of a list (or a single value) fails with
+    /// `InvalidIndex`, and comparing a string with a number or boolean fails
+    /// with `TypeMismatch`. Absent values and `.` are still missing, not errors.
+    /// Rows whose FILTER column names a filter not declared by a ##FILTER
+    /// header line (other than `PASS`) fail to parse with `RowParseError`.
+    /// [`quick_reject`](Self::quick_reject) does not screen lines in strict
+    /// mode, since a screened-out line would hide its errors.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use vcf_filter::{FilterEngine, VcfFilterError};
+    ///
+    /// let header = r#"##INFO=<ID=AF,Number=A,Type=Float,Description="Allele frequency">"#;
+    /// let engine = FilterEngine::new(header).unwrap().with_strict(true);
+    ///
+    /// let row = "chr1\t100\t.\tA\tG\t50\tPASS\tAF=0.01";
+    /// assert!(engine.evaluate("AF[0] < 0.05", row).unwrap());
+    /// assert!(matches!(
+    ///     engine.evaluate("AF[1] < 0.05", row),
+    ///     Err(VcfFilterError::InvalidIndex { index: 1, length: 1, .. })
+    /// ));
+    /// assert!(matches!(
+    ///     engine.evaluate("GNOMAD_AF < 0.05", row),
+    ///     Err(VcfFilterError::UnknownField(_))
+    /// ));
+    /// ```
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
 
-    #[test]
-    fn test_engine_creation() {
-        let engine = FilterEngine::new(FULL_HEADER).unwrap();
-        assert!(engine.info_map().contains_key("ANN"));
-        assert!(engine.info_map().contains_key("CLNSIG"));
+    /// Whether this engine evaluates in strict mode.
+    pub fn is_strict(&self) -> bool {
+        self.strict
     }
 
-    #[test]
-    fn test_real_row_qual() {
-        let engine = FilterEngine::new(FULL_HEADER).unwrap();
-        assert!(engine.evaluate("QUAL > 30", REAL_ROW).unwrap());
-        assert!(engine.evaluate("QUAL < 40", REAL_ROW).unwrap());
-        assert!(engine.evaluate("QUAL == 37.5", REAL_ROW).unwrap());
+    /// Propagate missing values through comparisons and logic, like SQL NULL.
+    ///
+    /// With three-valued logic a comparison with a missing operand is
+    /// unknown rather than false (or, for `!=`, true); `!` keeps it unknown,
+    /// `false && unknown` is false and `true || unknown` is true. Wildcards
+    /// are true if any element is, unknown if any element is unknown, else
+    /// false. Use [`evaluate_outcome`](Self::evaluate_outcome) to tell
+    /// unknown rows from failing ones; the `bool` methods treat unknown as
+    /// not passing.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use vcf_filter::{FilterEngine, Outcome};
+    ///
+    /// let header = r#"##INFO=<ID=AF,Number=A,Type=Float,Description="Allele frequency">"#;
+    /// let engine = FilterEngine::new(header).unwrap().with_three_valued(true);
+    ///
+    /// let row = "chr1\t100\t.\tA\tG\t50\tPASS\t.";
+    /// assert_eq!(engine.evaluate_outcome("AF < 0.01", row).unwrap(), Outcome::Unknown);
+    /// assert_eq!(engine.evaluate_outcome("AF < 0.01 && QUAL > 60", row).unwrap(), Outcome::Fail);
+    /// assert!(!engine.evaluate("!(AF >= 0.01)", row).unwrap());
+    /// ```
+    pub fn with_three_valued(mut self, three_valued: bool) -> Self {
+        self.three_valued = three_valued;
+        self
     }
 
-    #[test]
-    fn test_real_row_filter() {
-        let engine = FilterEngine::new(FULL_HEADER).unwrap();
-        assert!(engine.evaluate(r#"FILTER == "PASS""#, REAL_ROW).unwrap());
+    /// Whether this engine uses three-valued logic.
+    pub fn is_three_valued(&self) -> bool {
+        self.three_valued
     }
 
-    #[test]
-    fn test_real_row_clnsig() {
-        let engine = FilterEngine::new(FULL_HEADER).unwrap();
-        assert!(engine.evaluate(r#"CLNSIG == "Benign""#, REAL_ROW).unwrap());
+    /// Compare strings ignoring case.
+    ///
+    /// Applies to `==`, `!=`, ordering, `contains` and `in` (including
+    /// `in_file` lists). [`quick_reject`](Self::quick_reject) does not
+    /// screen lines, since its literal search is case-sensitive.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use vcf_filter::FilterEngine;
+    ///
+    /// let engine = FilterEngine::new("").unwrap().with_case_insensitive(true);
+    ///
+    /// let row = "chr1\t100\t.\tA\tG\t50\tPASS\tCLNSIG=Pathogenic";
+    /// assert!(engine.evaluate(r#"CLNSIG == "pathogenic""#, row).unwrap());
+    /// ```
+    pub fn with_case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.collation.case_insensitive = case_insensitive;
+        self
     }
 
-    #[test]
-    fn test_real_row_ann_first() {
-        let engine = FilterEngine::new(FULL_HEADER).unwrap();
-        assert!(
-            engine
-                .evaluate(r#"ANN[0].Annotation == "synonymous_variant""#, REAL_ROW)
-                .unwrap()
-        );
-        assert!(
-            engine
-                .evaluate(r#"ANN[0].Annotation_Impact == "LOW""#, REAL_ROW)
-                .unwrap()
-        );
-        assert!(
-            engine
-                .evaluate(r#"ANN[0].Gene_Name == "PRG4""#, REAL_ROW)
-                .unwrap()
-        );
+    /// Fail records whose only ALT allele is the spanning deletion `*`.
+    ///
+    /// Such records only restate a deletion called by another record, so
+    /// they can be dropped whatever the filter. `*` alleles of other records
+    /// are always skipped by per-allele filters and allele classification.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use vcf_filter::FilterEngine;
+    ///
+    /// let engine = FilterEngine::new("").unwrap().with_drop_spanning_deletions(true);
+    ///
+    /// assert!(!engine.evaluate("QUAL > 30", "chr1\t101\t.\tA\t*\t50\tPASS\t.").unwrap());
+    /// assert!(engine.evaluate("QUAL > 30", "chr1\t101\t.\tA\tG,*\t50\tPASS\t.").unwrap());
+    /// ```
+    pub fn with_drop_spanning_deletions(mut self, drop: bool) -> Self {
+        self.drop_spanning_deletions = drop;
+        self
     }
 
-    #[test]
-    fn test_real_row_ann_wildcard() {
-        let engine = FilterEngine::new(FULL_HEADER).unwrap();
-        // Any annotation has MODIFIER impact
-        assert!(
-            engine
-                .evaluate(r#"ANN[*].Annotation_Impact == "MODIFIER""#, REAL_ROW)
-                .unwrap()
-        );
-        // Any annotation is for TPR gene
-        assert!(
-            engine
-                .evaluate(r#"ANN[*].Gene_Name == "TPR""#, REAL_ROW)
-                .unwrap()
-        );
-        // No annotation has HIGH impact
-        assert!(
-            !engine
-                .evaluate(r#"ANN[*].Annotation_Impact == "HIGH""#, REAL_ROW)
-                .unwrap()
-        );
+    /// Evaluate every filter once per ALT allele.
+    ///
+    /// With [`AlleleMode::Any`] or [`AlleleMode::All`], `ALT` and `[alt]`
+    /// (`AF[alt]`, `AD[alt]`) refer to the allele being evaluated, and a row
+    /// passes if any or every allele passes, without splitting the record.
+    /// Spanning deletions (`*`) are skipped and a row without ALT alleles
+    /// never passes. The default, [`AlleleMode::Site`], evaluates a filter
+    /// once per row unless it uses `[alt]`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use vcf_filter::{AlleleMode, FilterEngine};
+    ///
+    /// let header = r#"##INFO=<ID=AF,Number=A,Type=Float,Description="Allele frequency">"#;
+    /// let row = "chr1\t100\t.\tA\tG,T\t50\tPASS\tAF=0.2,0.01";
+    /// let filter = r#"ALT != "T" && AF[alt] > 0.1"#;
+    ///
+    /// let any = FilterEngine::new(header).unwrap().with_allele_mode(AlleleMode::Any);
+    /// assert!(any.evaluate(filter, row).unwrap());
+    /// let all = FilterEngine::new(header).unwrap().with_allele_mode(AlleleMode::All);
+    /// assert!(!all.evaluate(filter, row).unwrap());
+    /// ```
+    pub fn with_allele_mode(mut self, mode: AlleleMode) -> Self {
+        self.allele_mode = mode;
+        self
     }
 
-    #[test]
-    fn test_real_row_chrom_pos() {
-        let engine = FilterEngine::new(FULL_HEADER).unwrap();
-        assert!(engine.evaluate(r#"CHROM == "chr1""#, REAL_ROW).unwrap());
-        assert!(engine.evaluate("POS > 186000000", REAL_ROW).unwrap());
+    /// Get how filters are applied to ALT alleles.
+    pub fn allele_mode(&self) -> AlleleMode {
+        self.allele_mode
     }
 
-    #[test]
-    fn test_real_row_complex_filter() {
-        let engine = FilterEngine::new(FULL_HEADER).unwrap();
-        // Combined filter: high quality, passed, and benign
-        assert!(
-            engine
-                .evaluate(
-                    r#"QUAL > 30 && FILTER == "PASS" && CLNSIG == "Benign""#,
-                    REAL_ROW
-                )
-                .unwrap()
-        );
+    /// Whether this engine compares strings ignoring case.
+    pub fn is_case_insensitive(&self) -> bool {
+        self.collation.case_insensitive
     }
 
-    #[test]
-    fn test_real_row_contains() {
-        let engine = FilterEngine::new(FULL_HEADER).unwrap();
-        assert!(
-            engine
-                .evaluate(r#"CLNDN contains "Camptodactyly""#, REAL_ROW)
-                .unwrap()
-        );
+    /// Treat numbers within `tolerance` of each other as equal.
+    ///
+    /// Affects `==`, `!=`, `in` and the ordering operators, so with a
+    /// tolerance of `1e-6` both `AF == 0.1` and `AF < 0.1` treat an AF of
+    /// `0.1000001` as `0.1`. Fails with `ConfigError` on a negative or
+    /// non-finite tolerance.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use vcf_filter::FilterEngine;
+    ///
+    /// let engine = FilterEngine::new("").unwrap().with_float_tolerance(1e-6).unwrap();
+    ///
+    /// let row = "chr1\t100\t.\tA\tG\t50\tPASS\tAF=0.1000001";
+    /// assert!(engine.evaluate("AF == 0.1", row).unwrap());
+    /// assert!(!engine.evaluate("AF > 0.1", row).unwrap());
+    /// ```
+    pub fn with_float_tolerance(mut self, tolerance: f64) -> Result<Self> {
+        if !tolerance.is_finite() || tolerance < 0.0 {
+            return Err(VcfFilterError::ConfigError(format!(
+                "float tolerance must be a non-negative number, got {}",
+                tolerance
+            )));
+        }
+        self.collation.float_tolerance = tolerance;
+        Ok(self)
     }
 
-    #[test]
-    fn test_parse_and_reuse() {
-        let engine = FilterEngine::new(FULL_HEADER).unwrap();
+    /// Get the tolerance within which numbers compare equal.
+    pub fn float_tolerance(&self) -> f64 {
+        self.collation.float_tolerance
+    }
 
-        // Parse filter once
+    /// Keep only the named samples, in the given order, on parsed rows.
+    ///
+    /// Filters then see just these samples: `FORMAT[*]`, `SAMPLE[0]`,
+    /// genotype counts such as `n_het()` and the first-sample shorthand
+    /// (`FORMAT.GT`) all refer to the selection. Fails with `ConfigError` if
+    /// a name is not a sample in the header, or if a pedigree was already
+    /// added (select samples first).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use vcf_filter::FilterEngine;
+    ///
+    /// let header = "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tnormal\ttumor";
+    /// let engine = FilterEngine::new(header).unwrap().with_samples(["tumor"]).unwrap();
+    ///
+    /// let row = "chr1\t100\t.\tA\tG\t50\tPASS\t.\tGT\t0/0\t0/1";
+    /// assert!(engine.evaluate("is_het()", row).unwrap());
+    /// assert_eq!(engine.sample_names(), ["tumor"]);
+    /// ```
+    pub fn with_samples<I, S>(mut self, names: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        if self.trio.is_some() {
+            return Err(VcfFilterError::ConfigError(
+                "samples must be selected before adding a pedigree".to_string(),
+            ));
+        }
+        let mut names_kept = Vec::new();
+        let mut columns = Vec::new();
+        for name in names {
+            let name = name.as_ref();
+            let index = self
+                .sample_names
+                .iter()
+                .position(|sample| sample == name)
+                .ok_or_else(|| VcfFilterError::ConfigError(format!("unknown sample: {}", name)))?;
+            // Selections compose, so columns always index the header
+            columns.push(match &self.sample_columns {
+                Some(selected) => selected[index],
+                None => index,
+            });
+            names_kept.push(name.to_string());
+        }
+        self.sample_names = names_kept;
+        self.sample_columns = Some(columns);
+        Ok(self)
+    }
+
+    /// Declare the subfields of a structured INFO field such as `CSQ`.
+    ///
+    /// Overrides the subfield names parsed from the header description, for
+    /// annotators whose descriptions do not list them in a recognised
+    /// format. A field missing from the header is declared as a String
+    /// field. Values are split into annotations on `,` and subfields on `|`,
+    /// as for `ANN`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use vcf_filter::FilterEngine;
+    ///
+    /// let engine = FilterEngine::new("")
+    ///     .unwrap()
+    ///     .with_structured_field("GENEINFO", ["Gene", "Impact"]);
+    ///
+    /// let row = "chr1\t100\t.\tA\tG\t50\tPASS\tGENEINFO=BRCA1|HIGH,BRCA2|LOW";
+    /// assert!(engine.evaluate(r#"GENEINFO[1].Gene == "BRCA2""#, row).unwrap());
+    /// ```
+    pub fn with_structured_field<I, S>(mut self, id: &str, subfields: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let subfields = subfields.into_iter().map(Into::into).collect();
+        self.set_structured_field(id, subfields, Delimiters::default());
+        self
+    }
+
+    /// Rename subfields of a structured INFO field.
+    ///
+    /// Replaces awkward names sanitized from the header description (such
+    /// as `HGVS_c` or `ERRORS___WARNINGS___INFO`) or resolves names that
+    /// collide. Renames take effect after header detection and
+    /// [`with_structured_field`](Self::with_structured_field). Fails with
+    /// `ConfigError` if the field has no subfields, a renamed subfield does
+    /// not exist, or two subfields would end up with the same name.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use vcf_filter::FilterEngine;
+    ///
+    /// let header = r#"##INFO=<ID=ANN,Number=.,Type=String,Description="Functional annotations: 'Allele | HGVS.c | ERRORS / WARNINGS / INFO'">"#;
+    /// let engine = FilterEngine::new(header)
+    ///     .unwrap()
+    ///     .with_subfield_renames("ANN", [("HGVS_c", "hgvs"), ("ERRORS___WARNINGS___INFO", "Messages")])
+    ///     .unwrap();
+    ///
+    /// let row = "chr1\t100\t.\tA\tG\t50\tPASS\tANN=G|c.100A>G|WARNING_REF_DOES_NOT_MATCH_GENOME";
+    /// assert!(engine.evaluate(r#"ANN[0].hgvs == "c.100A>G" && ANN[0].Messages contains "WARNING""#, row).unwrap());
+    /// ```
+    pub fn with_subfield_renames<I, S, T>(mut self, id: &str, renames: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (S, T)>,
+        S: AsRef<str>,
+        T: Into<String>,
+    {
+        let Some(subfields) = self
+            .info_map
+            .get_mut(id)
+            .and_then(|field| field.subfields.as_mut())
+        else {
+            return Err(VcfFilterError::ConfigError(format!(
+                "{} is not a structured field",
+                id
+            )));
+        };
+        let mut renamed = subfields.clone();
+        for (from, to) in renames {
+            let from = from.as_ref();
+            let index = subfields
+                .iter()
+                .position(|name| name == from)
+                .ok_or_else(|| {
+                    VcfFilterError::ConfigError(format!("{} has no subfield {}", id, from))
+                })?;
+            renamed[index] = to.into();
+        }
+        for (i, name) in renamed.iter().enumerate() {
+            if renamed[..i].contains(name) {
+                return Err(VcfFilterError::ConfigError(format!(
+                    "{} would have two subfields named {}",
+                    id, name
+                )));
+            }
+        }
+        *subfields = renamed;
+        Ok(self)
+    }
+
+    /// Declare a structured INFO field with its own separators.
+    ///
+    /// Like [`with_structured_field`](Self::with_structured_field), but the
+    /// annotations and subfields of the value are split on `delimiters`; a
+    /// single `char` sets the subfield separator and keeps `,` between
+    /// annotations. Fails with `ConfigError` if no subfields are given, the
+    /// two separators are equal, or either is `;`, `=` or a tab, which
+    /// cannot appear inside an INFO value.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use vcf_filter::{Delimiters, FilterEngine};
+    ///
+    /// let mut engine = FilterEngine::new("").unwrap();
+    /// engine
+    ///     .register_structured_field("SPLICEAI", &["ALLELE", "SYMBOL", "DS_AG"], '|')
+    ///     .unwrap();
+    /// engine
+    ///     .register_structured_field("HITS", &["Source", "Score"], Delimiters::new('&', ':'))
+    ///     .unwrap();
+    ///
+    /// let row = "chr1\t100\t.\tA\tG\t50\tPASS\tSPLICEAI=G|BRCA1|0.91;HITS=db1:7&db2:3";
+    /// assert!(engine.evaluate(r#"SPLICEAI[0].SYMBOL == "BRCA1""#, row).unwrap());
+    /// assert!(engine.evaluate(r#"HITS[1].Source == "db2""#, row).unwrap());
+    /// ```
+    pub fn register_structured_field(
+        &mut self,
+        id: &str,
+        subfields: &[&str],
+        delimiters: impl Into<Delimiters>,
+    ) -> Result<()> {
+        let delimiters = delimiters.into();
+        let reserved = |c: char| matches!(c, ';' | '=' | '\t');
+        if subfields.is_empty()
+            || delimiters.record == delimiters.subfield
+            || reserved(delimiters.record)
+            || reserved(delimiters.subfield)
+        {
+            return Err(VcfFilterError::ConfigError(format!(
+                "invalid structured field {}: needs subfields and two distinct separators other than ';', '=' and tab",
+                id
+            )));
+        }
+        let subfields = subfields.iter().map(|name| name.to_string()).collect();
+        self.set_structured_field(id, subfields, delimiters);
+        Ok(())
+    }
+
+    /// Set the subfields and delimiters of an INFO field, declaring it if missing.
+    fn set_structured_field(&mut self, id: &str, subfields: Vec<String>, delimiters: Delimiters) {
+        let field = self
+            .info_map
+            .entry(id.to_string())
+            .or_insert_with(|| InfoField {
+                id: id.to_string(),
+                number: InfoNumber::Variable,
+                field_type: InfoType::String,
+                description: String::new(),
+                subfields: None,
+                delimiters,
+            });
+        field.subfields = Some(subfields);
+        field.delimiters = delimiters;
+    }
+
+    /// Join a BED/TSV interval annotation onto every row this engine parses.
+    ///
+    /// The annotation's columns become virtual fields on overlapping records;
+    /// see [`intervals`] for the file formats.
+    pub fn with_intervals(mut self, annotation: IntervalAnnotation) -> Self {
+        self.virtual_fields
+            .extend(annotation.fields().iter().cloned());
+        self.intervals.push(Arc::new(annotation));
+        self
+    }
+
+    /// Compute the `INHERITANCE` virtual field for a trio on every parsed row.
+    ///
+    /// Fails if any trio member is not a sample in the header; see
+    /// [`pedigree`] for the inheritance values.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use vcf_filter::FilterEngine;
+    /// use vcf_filter::pedigree::Trio;
+    ///
+    /// let header = "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tkid\tdad\tmom";
+    /// let engine = FilterEngine::new(header)
+    ///     .unwrap()
+    ///     .with_pedigree(&Trio::new("kid", "dad", "mom"))
+    ///     .unwrap();
+    ///
+    /// let row = "chr1\t100\t.\tA\tG\t50\tPASS\t.\tGT\t0/1\t0/0\t0/0";
+    /// assert!(engine.evaluate(r#"INHERITANCE == "denovo""#, row).unwrap());
+    /// ```
+    pub fn with_pedigree(mut self, trio: &Trio) -> Result<Self> {
+        let columns = TrioColumns::resolve(trio, &self.sample_names)?;
+        if self.trio.replace(columns).is_none() {
+            self.virtual_fields.push(INHERITANCE_FIELD.to_string());
+        }
+        Ok(self)
+    }
+
+    /// Register a named filter that expressions can reference as `@name`.
+    ///
+    /// The filter may reference presets registered before it; see
+    /// [`presets`] for the library format.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use vcf_filter::FilterEngine;
+    ///
+    /// let engine = FilterEngine::new("")
+    ///     .unwrap()
+    ///     .with_preset("rare", "AF < 0.01")
+    ///     .unwrap()
+    ///     .with_preset("rare_pass", r#"@rare && FILTER == "PASS""#)
+    ///     .unwrap();
+    ///
+    /// let row = "chr1\t100\t.\tA\tG\t50\tPASS\tAF=0.001";
+    /// assert!(engine.evaluate("@rare_pass", row).unwrap());
+    /// ```
+    pub fn with_preset(mut self, name: &str, filter: &str) -> Result<Self> {
+        self.limits.check_filter_text(filter)?;
+        self.presets.define(name, filter)?;
+        self.expr_cache.clear();
+        Ok(self)
+    }
+
+    /// Replace the engine's named filters with a parsed library.
+    pub fn with_presets(mut self, library: PresetLibrary) -> Self {
+        self.presets = library;
+        self.expr_cache.clear();
+        self
+    }
+
+    /// Get the named filters registered on this engine.
+    pub fn presets(&self) -> &PresetLibrary {
+        &self.presets
+    }
+
+    /// Expand a configuration's presets, aliases and virtual fields in every
+    /// filter this engine parses.
+    ///
+    /// A config preset shadows an engine preset of the same name; see
+    /// [`config`] for the file format.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use vcf_filter::FilterEngine;
+    /// use vcf_filter::config::FilterConfig;
+    ///
+    /// let config = FilterConfig::parse(
+    ///     "[presets]\n@pass := FILTER == \"PASS\"\n[aliases]\ndepth = INFO.DP",
+    /// )
+    /// .unwrap();
+    /// let engine = FilterEngine::new("").unwrap().with_config(config);
+    ///
+    /// let row = "chr1\t100\t.\tA\tG\t50\tPASS\tDP=40";
+    /// assert!(engine.evaluate("@pass && depth > 30", row).unwrap());
+    /// ```
+    pub fn with_config(mut self, config: FilterConfig) -> Self {
+        self.config = Arc::new(config);
+        self.config_watcher = None;
+        self.expr_cache.clear();
+        self
+    }
+
+    /// Expand the current configuration of a [`ConfigWatcher`] in every
+    /// filter this engine parses.
+    ///
+    /// The engine and its clones read a fresh snapshot for each filter they
+    /// parse. After a reload, filters cached by [`evaluate`](Self::evaluate)
+    /// and friends are parsed again, and [`CompiledFilter::refresh`]
+    /// recompiles a compiled filter. See [`ConfigWatcher`] for an example.
+    pub fn with_config_watcher(mut self, watcher: Arc<ConfigWatcher>) -> Self {
+        self.config_watcher = Some(watcher);
+        self.expr_cache.clear();
+        self
+    }
+
+    /// Get the configuration expanded in filters parsed by this engine.
+    ///
+    /// With a [`ConfigWatcher`], this is a snapshot of its current configuration.
+    pub fn config(&self) -> Arc<FilterConfig> {
+        match &self.config_watcher {
+            Some(watcher) => watcher.config(),
+            None => Arc::clone(&self.config),
+        }
+    }
+
+    /// Register a value list that expressions can reference as `in_file("name")`.
+    ///
+    /// A registered name takes precedence over a file of the same path; see
+    /// [`lists`] for the file format.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use vcf_filter::FilterEngine;
+    ///
+    /// let engine = FilterEngine::new("")
+    ///     .unwrap()
+    ///     .with_list("acmg", ["BRCA1", "BRCA2", "TP53"]);
+    ///
+    /// let row = "chr1\t100\t.\tA\tG\t50\tPASS\tGENE=TP53";
+    /// assert!(engine.evaluate(r#"GENE in_file("acmg")"#, row).unwrap());
+    /// ```
+    pub fn with_list<I, S>(mut self, name: &str, values: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.lists.define(name, values);
+        self.expr_cache.clear();
+        self
+    }
+
+    /// Read a list file and register it as `in_file("name")`.
+    pub fn with_list_file(mut self, name: &str, path: impl AsRef<std::path::Path>) -> Result<Self> {
+        self.lists.load(name, path)?;
+        self.expr_cache.clear();
+        Ok(self)
+    }
+
+    /// Get the value lists registered on this engine.
+    pub fn lists(&self) -> &ListLibrary {
+        &self.lists
+    }
+
+    /// Read a BED file and register it as `in_bed("name")`.
+    ///
+    /// Filters may also name a BED file by path; either way the file is
+    /// read once and queried through an interval index. See [`intervals`].
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use vcf_filter::FilterEngine;
+    ///
+    /// let engine = FilterEngine::new("")
+    ///     .unwrap()
+    ///     .with_bed("exons", "exons.bed")
+    ///     .unwrap();
+    ///
+    /// let row = "chr1\t100\t.\tA\tG\t50\tPASS\t.";
+    /// engine.evaluate(r#"in_bed("exons") && QUAL > 30"#, row).unwrap();
+    /// ```
+    pub fn with_bed(mut self, name: &str, path: impl AsRef<std::path::Path>) -> Result<Self> {
+        self.beds.load(name, path)?;
+        Ok(self)
+    }
+
+    /// Get the BED region sets loaded by this engine.
+    pub fn beds(&self) -> &BedLibrary {
+        &self.beds
+    }
+
+    /// Register a custom function callable from filter expressions.
+    ///
+    /// The function receives its arguments already evaluated. Built-in
+    /// function names cannot be registered.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use vcf_filter::{FilterEngine, Value};
+    ///
+    /// let mut engine = FilterEngine::new("").unwrap();
+    /// engine
+    ///     .register_function("score", |args| {
+    ///         let ac = args.first().and_then(Value::as_number).unwrap_or(0.0);
+    ///         Ok(Value::Number(ac * 10.0))
+    ///     })
+    ///     .unwrap();
+    ///
+    /// let row = "chr1\t100\t.\tA\tG\t50\tPASS\tAC=3";
+    /// assert!(engine.evaluate("score(AC) > 20", row).unwrap());
+    /// ```
+    pub fn register_function<F>(&mut self, name: &str, function: F) -> Result<()>
+    where
+        F: Fn(&[Value]) -> Result<Value> + Send + Sync + 'static,
+    {
+        self.functions.register(name, function)
+    }
+
+    /// Get the functions registered on this engine.
+    pub fn functions(&self) -> &FunctionRegistry {
+        &self.functions
+    }
+
+    /// Get the sample names from the header's `#CHROM` line.
+    pub fn sample_names(&self) -> &[String] {
+        &self.sample_names
+    }
+
+    /// Get the zero-based column of a sample in the VCF data lines.
+    ///
+    /// Sample columns start after `FORMAT`, at column 9. Positions refer to
+    /// the input file, so they are unchanged by
+    /// [`with_samples`](Self::with_samples); unselected names return `None`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use vcf_filter::FilterEngine;
+    ///
+    /// let header = "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tnormal\ttumor";
+    /// let engine = FilterEngine::new(header).unwrap();
+    /// assert_eq!(engine.sample_column("tumor"), Some(10));
+    ///
+    /// let tumor_only = engine.with_samples(["tumor"]).unwrap();
+    /// assert_eq!(tumor_only.sample_column("tumor"), Some(10));
+    /// assert_eq!(tumor_only.sample_column("normal"), None);
+    /// ```
+    pub fn sample_column(&self, name: &str) -> Option<usize> {
+        let index = self.sample_names.iter().position(|sample| sample == name)?;
+        let header_index = match &self.sample_columns {
+            Some(selected) => selected[index],
+            None => index,
+        };
+        Some(9 + header_index)
+    }
+
+    /// Get the FORMAT fields of a parsed row's sample by name.
+    ///
+    /// Names come from the header's `#CHROM` line (after
+    /// [`with_samples`](Self::with_samples) selection). Returns `None` for
+    /// an unknown name or a row without that sample column.
+    pub fn sample_fields<'r>(
+        &self,
+        row: &'r VcfRow,
+        name: &str,
+    ) -> Option<&'r HashMap<String, Value>> {
+        let index = self.sample_names.iter().position(|sample| sample == name)?;
+        row.samples.get(index)
+    }
+
+    /// Call the inheritance of a parsed row for the configured trio.
+    ///
+    /// Returns `None` without a pedigree, when the child carries no alt
+    /// allele, or when any trio genotype is missing.
+    pub fn inheritance(&self, row: &VcfRow) -> Option<Inheritance> {
+        self.trio.as_ref()?.inheritance(row)
+    }
+
+    /// Build the evaluation context for a single row evaluation.
+    fn eval_context(&self) -> EvalContext<'_> {
+        EvalContext::new(&self.info_map)
+            .with_deadline(
+                self.limits
+                    .time_budget
+                    .map(|budget| Instant::now() + budget),
+            )
+            .with_max_call_depth(self.limits.max_call_depth)
+            .with_functions(&self.functions)
+            .with_regions(&self.beds)
+            .with_sample_names(&self.sample_names)
+            .with_strict(self.strict, &self.virtual_fields)
+            .with_three_valued(self.three_valued)
+            .with_collation(self.collation)
+            .with_allele_mode(self.allele_mode)
+            .with_contigs(&self.contigs)
+    }
+
+    /// Evaluate a filter expression against a VCF row.
+    ///
+    /// The engine keeps its most recently used filters parsed, so calling
+    /// this once per row does not re-parse the filter each time.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter` - The filter expression string (e.g., `"QUAL > 30"`)
+    /// * `row` - A single VCF data row (tab-separated)
+    ///
+    /// # Returns
+    ///
+    /// `true` if the row matches the filter, `false` otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use vcf_filter::FilterEngine;
+    ///
+    /// let header = r#"##INFO=<ID=DP,Number=1,Type=Integer,Description="Depth">"#;
+    /// let engine = FilterEngine::new(header).unwrap();
+    ///
+    /// let row = "chr1\t100\t.\tA\tG\t50\tPASS\tDP=30";
+    /// assert!(engine.evaluate("QUAL > 30", row).unwrap());
+    /// ```
+    pub fn evaluate(&self, filter: &str, row: &str) -> Result<bool> {
+        let parsed_row = self.parse_row(row)?;
+        let expr = self.parse_filter_cached(filter)?;
+        self.evaluate_parsed(&expr, &parsed_row)
+    }
+
+    /// Evaluate an expression against a VCF row and return its value.
+    ///
+    /// Uses the same machinery as filters, so projections such as
+    /// `ANN[0].Gene_Name` or `AD[1] / DP` can be extracted from rows.
+    /// An expression using `[alt]`, or any expression with a per-allele
+    /// [`AlleleMode`], yields an array with one value per ALT allele.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use vcf_filter::{FilterEngine, Value};
+    ///
+    /// let header = r#"##INFO=<ID=DP,Number=1,Type=Integer,Description="Depth">
+    /// ##INFO=<ID=AD,Number=R,Type=Integer,Description="Allele depths">"#;
+    /// let engine = FilterEngine::new(header).unwrap();
+    ///
+    /// let row = "chr1\t100\t.\tA\tG\t50\tPASS\tDP=40;AD=30,10";
+    /// assert_eq!(engine.evaluate_value("AD[1] / DP", row).unwrap(), Value::Number(0.25));
+    /// assert_eq!(engine.evaluate_value("DP > 30", row).unwrap(), Value::Bool(true));
+    /// ```
+    pub fn evaluate_value(&self, expr: &str, row: &str) -> Result<Value> {
+        let parsed_row = self.parse_row(row)?;
+        let expr = self.parse_filter_cached(expr)?;
+        self.evaluate_parsed_value(&expr, &parsed_row)
+    }
+
+    /// Evaluate a pre-parsed expression against a pre-parsed row and return its value.
+    ///
+    /// See [`evaluate_value`](Self::evaluate_value).
+    pub fn evaluate_parsed_value(&self, expr: &Expr, row: &VcfRow) -> Result<Value> {
+        let ctx = self.eval_context();
+        if !ctx.allele_mode.per_allele(expr) {
+            return evaluate_with(expr, row, &ctx);
+        }
+        row.alt_indexes()
+            .into_iter()
+            .map(|alt| {
+                evaluate_with(
+                    expr,
+                    row,
+                    &EvalContext {
+                        alt: Some(alt),
+                        ..ctx
+                    },
+                )
+            })
+            .collect::<Result<_>>()
+            .map(Value::Array)
+    }
+
+    /// Extract several fields or expressions from a VCF row.
+    ///
+    /// Each entry is evaluated like [`evaluate_value`](Self::evaluate_value),
+    /// so access paths (`ANN[0].Gene_Name`, `INFO.AF`, `FORMAT[*].GT`) and
+    /// computed values (`AD[1] / DP`) can be mixed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use vcf_filter::{FilterEngine, Value};
+    ///
+    /// let header = r#"##INFO=<ID=AF,Number=A,Type=Float,Description="Allele frequency">"#;
+    /// let engine = FilterEngine::new(header).unwrap();
+    ///
+    /// let row = "chr1\t100\t.\tA\tG\t50\tPASS\tAF=0.25";
+    /// let values = engine.select(&["CHROM", "POS", "INFO.AF"], row).unwrap();
+    /// assert_eq!(
+    ///     values,
+    ///     [
+    ///         Value::String("chr1".to_string()),
+    ///         Value::Number(100.0),
+    ///         Value::Number(0.25),
+    ///     ]
+    /// );
+    /// assert_eq!(engine.select_tsv(&["CHROM", "POS", "INFO.AF", "DP"], row).unwrap(), "chr1\t100\t0.25\t.");
+    /// ```
+    pub fn select(&self, fields: &[&str], row: &str) -> Result<Vec<Value>> {
+        let parsed_row = self.parse_row(row)?;
+        let exprs = fields
+            .iter()
+            .map(|field| self.parse_filter_cached(field))
+            .collect::<Result<Vec<_>>>()?;
+        exprs
+            .iter()
+            .map(|expr| self.evaluate_parsed_value(expr, &parsed_row))
+            .collect()
+    }
+
+    /// Extract pre-parsed expressions from a pre-parsed row.
+    ///
+    /// See [`select`](Self::select).
+    pub fn select_parsed(&self, exprs: &[Expr], row: &VcfRow) -> Result<Vec<Value>> {
+        exprs
+            .iter()
+            .map(|expr| self.evaluate_parsed_value(expr, row))
+            .collect()
+    }
+
+    /// Extract fields from a row as one tab-separated line.
+    ///
+    /// Values are written as in a VCF ([`Value::to_vcf_string`]): missing
+    /// values as `.` and lists comma-separated.
+    pub fn select_tsv(&self, fields: &[&str], row: &str) -> Result<String> {
+        Ok(self
+            .select(fields, row)?
+            .iter()
+            .map(Value::to_vcf_string)
+            .collect::<Vec<_>>()
+            .join("\t"))
+    }
+
+    /// Trace a filter's evaluation against a VCF row.
+    ///
+    /// Records what each sub-expression evaluated to, to show why a row
+    /// failed a long filter; see [`explain`] for the tree layout. A filter
+    /// evaluated per allele (using `[alt]`, or with a per-allele
+    /// [`AlleleMode`]) is explained once per ALT allele.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use vcf_filter::FilterEngine;
+    ///
+    /// let header = r#"##INFO=<ID=DP,Number=1,Type=Integer,Description="Depth">"#;
+    /// let engine = FilterEngine::new(header).unwrap();
+    ///
+    /// let row = "chr1\t100\t.\tA\tG\t50\tPASS\tDP=8";
+    /// let explanation = engine.explain("QUAL > 30 && DP >= 10", row).unwrap();
+    /// assert!(!explanation.passed());
+    /// println!("{}", explanation);
+    /// ```
+    pub fn explain(&self, filter: &str, row: &str) -> Result<Explanation> {
+        let parsed_row = self.parse_row(row)?;
+        let expr = self.parse_filter_cached(filter)?;
+        self.explain_parsed(&expr, &parsed_row)
+    }
+
+    /// Trace a pre-parsed filter's evaluation against a pre-parsed row.
+    ///
+    /// See [`explain`](Self::explain).
+    pub fn explain_parsed(&self, expr: &Expr, row: &VcfRow) -> Result<Explanation> {
+        let ctx = self.eval_context();
+        if !ctx.allele_mode.per_allele(expr) {
+            return explain::explain(expr, row, &ctx);
+        }
+        let children = row
+            .alt_indexes()
+            .into_iter()
+            .map(|alt| {
+                let mut allele = explain::explain(
+                    expr,
+                    row,
+                    &EvalContext {
+                        alt: Some(alt),
+                        ..ctx
+                    },
+                )?;
+                allele.expr = format!("[alt = {}] {}", row.alt_alleles[alt], allele.expr);
+                Ok(allele)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let passed = match ctx.allele_mode {
+            AlleleMode::All => !children.is_empty() && children.iter().all(Explanation::passed),
+            _ => children.iter().any(Explanation::passed),
+        };
+        Ok(Explanation {
+            expr: expr.to_string(),
+            value: Some(Value::Bool(passed)),
+            children,
+        })
+    }
+
+    /// Evaluate a filter against a VCF row, reporting undecidable rows.
+    ///
+    /// Returns [`Outcome::Unknown`] only with
+    /// [`with_three_valued`](Self::with_three_valued); otherwise a filter
+    /// that is not true fails.
+    pub fn evaluate_outcome(&self, filter: &str, row: &str) -> Result<Outcome> {
+        let parsed_row = self.parse_row(row)?;
+        let expr = self.parse_filter_cached(filter)?;
+        self.evaluate_parsed_outcome(&expr, &parsed_row)
+    }
+
+    /// Parse a VCF row without evaluating a filter.
+    ///
+    /// Useful for inspecting row data or performing multiple evaluations
+    /// against the same row.
+    ///
+    /// # Arguments
+    ///
+    /// * `row` - A single VCF data row (tab-separated)
+    ///
+    /// # Returns
+    ///
+    /// A parsed `VcfRow` structure.
+    pub fn parse_row(&self, row: &str) -> Result<VcfRow> {
+        self.parse_row_with_keys(row, None)
+    }
+
+    /// Parse a VCF row, parsing only the INFO keys in `keys` when given.
+    fn parse_row_with_keys(&self, row: &str, keys: Option<&[String]>) -> Result<VcfRow> {
+        let mut parsed = parse_row_with_keys(
+            row,
+            &self.info_map,
+            &self.format_map,
+            &self.parse_options,
+            keys,
+        )?;
+        if self.strict
+            && let Some(name) = self.undeclared_filters(&parsed).first()
+        {
+            return Err(VcfFilterError::RowParseError(format!(
+                "FILTER value '{}' is not declared by a ##FILTER header line",
+                name
+            )));
+        }
+        if let Some(columns) = &self.sample_columns {
+            let mut samples = std::mem::take(&mut parsed.samples);
+            parsed.samples = columns
+                .iter()
+                .map(|&i| samples.get_mut(i).map(std::mem::take).unwrap_or_default())
+                .collect();
+            let mut genotypes = std::mem::take(&mut parsed.genotypes);
+            parsed.genotypes = columns
+                .iter()
+                .map(|&i| genotypes.get_mut(i).and_then(Option::take))
+                .collect();
+            parsed.format = parsed.samples.first().cloned().unwrap_or_default();
+        }
+        for annotation in &self.intervals {
+            annotation.annotate(&mut parsed);
+        }
+        if let Some(inheritance) = self.inheritance(&parsed) {
+            parsed
+                .info
+                .entry(INHERITANCE_FIELD.to_string())
+                .or_insert_with(|| Value::String(inheritance.to_string()));
+        }
+        Ok(parsed)
+    }
+
+    /// Parse a filter expression without evaluating it.
+    ///
+    /// Useful for validating filter syntax or caching parsed expressions.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter` - The filter expression string
+    ///
+    /// # Returns
+    ///
+    /// A parsed `Expr` AST.
+    pub fn parse_filter(&self, filter: &str) -> Result<Expr> {
+        self.parse_filter_with(filter, &self.config())
+    }
+
+    /// Parse a filter, expanding a given snapshot of the configuration.
+    pub(crate) fn parse_filter_with(&self, filter: &str, config: &FilterConfig) -> Result<Expr> {
+        self.limits.check_filter_text(filter)?;
+        let expr =
+            config.expand_with(&parse_filter(filter).map_err(parse_error)?, &self.presets)?;
+        let expr = self.lists.expand(&expr)?;
+        self.beds.preload(&expr)?;
+        self.limits.check_expr(&expr)?;
+        Ok(expr)
+    }
+
+    /// Parse a filter through the engine's cache of recently used filters.
+    fn parse_filter_cached(&self, filter: &str) -> Result<Arc<Expr>> {
+        let config = self.config();
+        self.expr_cache.get_or_parse(filter, &config, |filter| {
+            self.parse_filter_with(filter, &config)
+        })
+    }
+
+    /// Parse a filter and derive the literals a line must contain to pass it.
+    ///
+    /// The result keeps a copy of this engine, so
+    /// [`CompiledFilter::matches`] tests lines against the header it was
+    /// compiled for. When passing it back to engine methods, use the engine
+    /// that compiled it. See [`compiled`] for how literals are derived.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use vcf_filter::FilterEngine;
+    ///
+    /// let engine = FilterEngine::new("").unwrap();
+    /// let filter = engine.compile(r#"CLNSIG == "Pathogenic""#).unwrap();
+    ///
+    /// let line = "chr1\t100\t.\tA\tG\t50\tPASS\tDP=30";
+    /// assert_eq!(engine.quick_reject(&filter, line.as_bytes()), Some(false));
+    ///
+    /// let line = "chr1\t100\t.\tA\tG\t50\tPASS\tCLNSIG=Pathogenic";
+    /// assert_eq!(engine.quick_reject(&filter, line.as_bytes()), None);
+    /// assert!(engine.evaluate_line(&filter, line).unwrap());
+    /// ```
+    pub fn compile(&self, filter: &str) -> Result<CompiledFilter> {
+        CompiledFilter::from_source(filter, Arc::new(self.clone()))
+    }
+
+    /// Compile an already parsed expression; see [`compile`](Self::compile).
+    pub fn compile_expr(&self, expr: Expr) -> CompiledFilter {
+        CompiledFilter::new(expr, Arc::new(self.clone()))
+    }
+
+    /// Get the VCF version declared by the header's `##fileformat` line.
+    ///
+    /// Where the specification versions differ, the engine follows the
+    /// declared one: `%XX` escapes in String values are decoded only for
+    /// VCFv4.3 and later (or when no version is declared), unless
+    /// [`ParseOptions::percent_decoding`] says otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use vcf_filter::{FilterEngine, VcfVersion};
+    ///
+    /// let row = "chr1\t100\t.\tA\tG\t50\tPASS\tNOTE=a%3Bb";
+    /// let v43 = FilterEngine::new("##fileformat=VCFv4.3").unwrap();
+    /// assert_eq!(v43.vcf_version(), Some(VcfVersion::new(4, 3)));
+    /// assert!(v43.evaluate(r#"NOTE == "a;b""#, row).unwrap());
+    ///
+    /// let v42 = FilterEngine::new("##fileformat=VCFv4.2").unwrap();
+    /// assert!(v42.evaluate(r#"NOTE == "a%3Bb""#, row).unwrap());
+    /// ```
+    pub fn vcf_version(&self) -> Option<VcfVersion> {
+        self.version
+    }
+
+    /// Report header declarations the declared VCF version does not define,
+    /// such as `Number=R` in a VCFv4.1 header.
+    ///
+    /// Such fields are still parsed as declared; the warnings flag headers
+    /// that other tools may reject.
+    pub fn header_diagnostics(&self) -> Vec<Diagnostic> {
+        let Some(version) = self.version else {
+            return Vec::new();
+        };
+        let mut fields: Vec<(&str, &InfoField)> = self
+            .info_map
+            .values()
+            .map(|field| ("INFO", field))
+            .chain(self.format_map.values().map(|field| ("FORMAT", field)))
+            .filter(|(_, field)| !version.defines_number(&field.number))
+            .collect();
+        fields.sort_by(|a, b| (a.0, &a.1.id).cmp(&(b.0, &b.1.id)));
+        fields
+            .into_iter()
+            .map(|(kind, field)| Diagnostic {
+                severity: Severity::Warning,
+                message: format!(
+                    "##{} {} declares Number={}, which {} does not define",
+                    kind, field.id, field.number, version
+                ),
+            })
+            .collect()
+    }
+
+    /// Check a filter's field names, subfields and operand types against the header.
+    ///
+    /// Returns no diagnostics for a filter that matches the header schema.
+    /// A filter that fails to parse yields a single error. See [`validate`]
+    /// for what is checked.
+    pub fn check_filter(&self, filter: &str) -> Vec<Diagnostic> {
+        match self.parse_filter(filter) {
+            Ok(expr) => validate::check_expr(
+                &expr,
+                &validate::Schema {
+                    info_map: &self.info_map,
+                    virtual_fields: &self.virtual_fields,
+                    functions: &self.functions,
+                    sample_names: &self.sample_names,
+                    contigs: &self.contigs,
+                },
+            ),
+            Err(e) => vec![Diagnostic {
+                severity: Severity::Error,
+                message: e.to_string(),
+            }],
+        }
+    }
+
+    /// Check a data line against the header's declarations.
+    ///
+    /// Reports INFO and FORMAT values whose count disagrees with `Number`
+    /// or whose elements are not of `Type`, undeclared FILTER names and
+    /// contigs, and positions past a contig's declared length. Returns no
+    /// diagnostics for a conforming line; fails with `RowParseError` only if
+    /// the line has too few columns or an invalid POS. See [`validate`] for
+    /// an example.
+    pub fn validate_row(&self, line: &str) -> Result<Vec<RowDiagnostic>> {
+        let row = VcfRowRef::parse(line)?;
+        Ok(validate::check_row(
+            &row,
+            &validate::RowSchema {
+                info_map: &self.info_map,
+                format_map: &self.format_map,
+                filters: &self.filters,
+                contigs: &self.contigs,
+            },
+        ))
+    }
+
+    /// Pre-screen a raw data line at byte level, without parsing it.
+    ///
+    /// Returns `Some(result)` when the outcome is known from the raw bytes:
+    /// `Some(false)` if the line lacks a literal the filter requires, or the
+    /// value of a constant filter. Returns `None` when the line has to be
+    /// parsed and evaluated. Screened-out lines are never parsed, so row
+    /// parse errors on them go unreported. In strict mode lines are not
+    /// screened, so every line's evaluation errors are reported; nor are
+    /// they when comparing case-insensitively.
+    pub fn quick_reject(&self, compiled: &CompiledFilter, line: &[u8]) -> Option<bool> {
+        if let Expr::Bool(constant) = compiled.expr() {
+            return Some(*constant);
+        }
+        let screens = !self.strict && !self.collation.case_insensitive;
+        if screens && !compiled.may_match(line) {
+            return Some(false);
+        }
+        None
+    }
+
+    /// Evaluate a compiled filter against a raw data line.
+    ///
+    /// Lines screened out by [`quick_reject`](Self::quick_reject) are
+    /// rejected without being parsed.
+    pub fn evaluate_line(&self, compiled: &CompiledFilter, line: &str) -> Result<bool> {
+        if let Some(result) = self.quick_reject(compiled, line.as_bytes()) {
+            return Ok(result);
+        }
+        let row = self.parse_row_with_keys(line, compiled.info_keys())?;
+        self.evaluate_compiled(compiled, &row)
+    }
+
+    /// Parse one INFO key a row left unparsed, returning its value.
+    ///
+    /// Rows tested by [`CompiledFilter::matches`] only parse the INFO keys
+    /// the filter reads. The value is kept in the row, so each key is
+    /// parsed at most once; already parsed keys are returned as they are.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use vcf_filter::{FilterEngine, Value};
+    ///
+    /// let header = r#"##INFO=<ID=DP,Number=1,Type=Integer,Description="Depth">"#;
+    /// let engine = FilterEngine::new(header).unwrap();
+    /// let mut row = engine.parse_row("chr1\t100\t.\tA\tG\t50\tPASS\tDP=30").unwrap();
+    /// assert_eq!(engine.parse_info_key(&mut row, "DP").unwrap(), Some(&Value::Number(30.0)));
+    /// assert_eq!(engine.parse_info_key(&mut row, "MQ").unwrap(), None);
+    /// ```
+    pub fn parse_info_key<'a>(&self, row: &'a mut VcfRow, key: &str) -> Result<Option<&'a Value>> {
+        row.parse_deferred(key, &self.info_map, &self.parse_options)?;
+        Ok(row.info.get(key))
+    }
+
+    /// Parse every INFO key a row left unparsed.
+    pub fn parse_all_info(&self, row: &mut VcfRow) -> Result<()> {
+        let keys: Vec<String> = row.unparsed_info().map(str::to_string).collect();
+        for key in keys {
+            row.parse_deferred(&key, &self.info_map, &self.parse_options)?;
+        }
+        Ok(())
+    }
+
+    /// Split a multiallelic row into one row per ALT allele.
+    ///
+    /// Like [`VcfRow::split_multiallelic`], also slicing the per-allele
+    /// FORMAT keys declared in the header.
+    pub fn split_multiallelic(&self, row: &VcfRow) -> Vec<VcfRow> {
+        row.split_with_format(&self.info_map, &self.format_map)
+    }
+
+    /// Stream the data lines of a reader that pass a filter.
+    ///
+    /// The filter is compiled once; header (`#`) and empty lines are
+    /// skipped. Pair with [`from_reader`](Self::from_reader) to filter a
+    /// whole VCF stream.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::io::Cursor;
+    /// use vcf_filter::FilterEngine;
+    ///
+    /// let vcf = "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+    ///            chr1\t100\t.\tA\tG\t50\tPASS\t.\n\
+    ///            chr1\t200\t.\tA\tG\t5\tPASS\t.\n";
+    /// let mut reader = Cursor::new(vcf);
+    /// let (engine, _header) = FilterEngine::from_reader(&mut reader).unwrap();
+    ///
+    /// let passing: Vec<String> = engine
+    ///     .filter_lines(reader, "QUAL > 30")
+    ///     .unwrap()
+    ///     .collect::<Result<_, _>>()
+    ///     .unwrap();
+    /// assert_eq!(passing, ["chr1\t100\t.\tA\tG\t50\tPASS\t."]);
+    /// ```
+    pub fn filter_lines<R: BufRead>(
+        &self,
+        reader: R,
+        filter: &str,
+    ) -> Result<impl Iterator<Item = Result<String>> + use<R>> {
+        Ok(self
+            .evaluate_lines(reader, filter)?
+            .filter_map(|result| match result {
+                Ok((line, true)) => Some(Ok(line)),
+                Ok((_, false)) => None,
+                Err(e) => Some(Err(e)),
+            }))
+    }
+
+    /// Stream every data line of a reader, tagging failing lines in the FILTER column.
+    ///
+    /// Like [`filter_lines`](Self::filter_lines), but no line is dropped;
+    /// see [`soft_filter`] for how FILTER is rewritten.
+    pub fn soft_filter_lines<R: BufRead>(
+        &self,
+        reader: R,
+        filter: &str,
+        soft: &SoftFilter,
+    ) -> Result<impl Iterator<Item = Result<String>> + use<R>> {
+        let soft = soft.clone();
+        Ok(self
+            .evaluate_lines(reader, filter)?
+            .map(move |result| result.map(|(line, passed)| soft.apply(&line, passed))))
+    }
+
+    /// Stream every data line of a reader with whether it passes a filter.
+    ///
+    /// Like [`filter_lines`](Self::filter_lines), but yields failing lines too.
+    pub fn evaluate_lines<R: BufRead>(
+        &self,
+        reader: R,
+        filter: &str,
+    ) -> Result<impl Iterator<Item = Result<(String, bool)>> + use<R>> {
+        let compiled = self.compile(filter)?;
+        Ok(reader.lines().filter_map(move |line| {
+            let line = match line {
+                Ok(line) if line.is_empty() || line.starts_with('#') => return None,
+                Ok(line) => line,
+                Err(e) => {
+                    return Some(Err(VcfFilterError::RowParseError(format!(
+                        "failed to read line: {}",
+                        e
+                    ))));
+                }
+            };
+            Some(compiled.matches(&line).map(|passed| (line, passed)))
+        }))
+    }
+
+    /// Filter the data lines of a reader on all cores, writing passing lines in order.
+    ///
+    /// Returns `(passed, total)` row counts. Header (`#`) and empty lines
+    /// are skipped, as in [`filter_lines`](Self::filter_lines); pair with
+    /// [`from_reader`](Self::from_reader) to copy the header first. See
+    /// [`parallel`] for how work is split. Requires the `parallel` feature.
+    #[cfg(feature = "parallel")]
+    pub fn filter_parallel<R: BufRead + Send, W: std::io::Write>(
+        &self,
+        reader: R,
+        writer: &mut W,
+        filter: &str,
+    ) -> Result<(u64, u64)> {
+        parallel::filter_chunks(&self.compile(filter)?, reader, writer)
+    }
+
+    /// Evaluate a compiled filter against a pre-parsed row.
+    ///
+    /// Gives the same result as [`evaluate_parsed`](Self::evaluate_parsed)
+    /// on the filter's expression, without re-resolving field paths per row.
+    pub fn evaluate_compiled(&self, compiled: &CompiledFilter, row: &VcfRow) -> Result<bool> {
+        if self.drops(row) {
+            return Ok(false);
+        }
+        compiled.evaluate(row, &self.eval_context())
+    }
+
+    /// Whether a row fails every filter regardless of its values.
+    fn drops(&self, row: &VcfRow) -> bool {
+        self.drop_spanning_deletions && row.is_spanning_deletion_only()
+    }
+
+    /// Evaluate a pre-parsed filter expression against a pre-parsed row.
+    ///
+    /// This is more efficient when evaluating the same filter against
+    /// many rows, or the same row against many filters.
+    ///
+    /// # Arguments
+    ///
+    /// * `expr` - The parsed filter expression
+    /// * `row` - The parsed VCF row
+    ///
+    /// # Returns
+    ///
+    /// `true` if the row matches the filter, `false` otherwise.
+    pub fn evaluate_parsed(&self, expr: &Expr, row: &VcfRow) -> Result<bool> {
+        Ok(self.evaluate_parsed_outcome(expr, row)? == Outcome::Pass)
+    }
+
+    /// Evaluate a pre-parsed filter expression, reporting undecidable rows.
+    ///
+    /// See [`evaluate_outcome`](Self::evaluate_outcome).
+    pub fn evaluate_parsed_outcome(&self, expr: &Expr, row: &VcfRow) -> Result<Outcome> {
+        if self.drops(row) {
+            return Ok(Outcome::Fail);
+        }
+        let ctx = self.eval_context();
+        let outcome = |ctx: &EvalContext| {
+            Ok(Outcome::of(
+                &evaluate_with(expr, row, ctx)?,
+                ctx.three_valued,
+            ))
+        };
+        if !ctx.allele_mode.per_allele(expr) {
+            return outcome(&ctx);
+        }
+        // Any passing allele passes the row (with AlleleMode::All, any failing
+        // one fails it); otherwise an unknown allele leaves it unknown
+        let (decisive, otherwise) = match ctx.allele_mode {
+            AlleleMode::All => (Outcome::Fail, Outcome::Pass),
+            _ => (Outcome::Pass, Outcome::Fail),
+        };
+        let alts = row.alt_indexes();
+        if alts.is_empty() {
+            return Ok(Outcome::Fail);
+        }
+        let mut result = otherwise;
+        for alt in alts {
+            match outcome(&EvalContext {
+                alt: Some(alt),
+                ..ctx
+            })? {
+                Outcome::Unknown => result = Outcome::Unknown,
+                allele if allele == decisive => return Ok(decisive),
+                _ => {}
+            }
+        }
+        Ok(result)
+    }
+
+    /// Start collecting per-clause rejection statistics for a filter.
+    ///
+    /// Record rows with [`evaluate_counted`](Self::evaluate_counted); see
+    /// [`stats`] for how rows are attributed to clauses.
+    pub fn stats(&self, filter: &str) -> Result<FilterStats> {
+        Ok(FilterStats::new(self.parse_filter(filter)?))
+    }
+
+    /// Evaluate a row against the filter of `stats`, counting which clauses rejected it.
+    ///
+    /// Returns whether the row passed, as [`evaluate_parsed`](Self::evaluate_parsed)
+    /// would. Clauses are only evaluated separately for failing rows.
+    pub fn evaluate_counted(&self, stats: &mut FilterStats, row: &VcfRow) -> Result<bool> {
+        let passed = self.evaluate_parsed(&stats.expr, row)?;
+        let mut failed = Vec::new();
+        if !passed {
+            for (i, clause) in stats.clauses.iter().enumerate() {
+                if self.evaluate_parsed_outcome(clause, row)? != Outcome::Pass {
+                    failed.push(i);
+                }
+            }
+        }
+        stats.record(passed, &failed);
+        Ok(passed)
+    }
+
+    /// Find LOF/NMD genes on a parsed row that are missing from its ANN genes.
+    ///
+    /// A non-empty result flags an inconsistent SnpEff annotation; see
+    /// [`VcfRow::lof_gene_mismatches`].
+    pub fn annotation_mismatches(&self, row: &VcfRow) -> Vec<AnnotationMismatch> {
+        row.lof_gene_mismatches(&self.info_map)
+    }
+
+    /// Get the INFO field metadata map.
+    ///
+    /// Useful for inspecting what fields are available and their types.
+    pub fn info_map(&self) -> &InfoMap {
+        &self.info_map
+    }
+
+    /// Get the FILTER names declared by the header's ##FILTER lines, in header order.
+    pub fn filters(&self) -> &[FilterField] {
+        &self.filters
+    }
+
+    /// Get the contigs declared by the header's ##contig lines, in header order.
+    ///
+    /// `CHROM` orderings and `chrom_rank()` follow this order when the header
+    /// declares contigs, and natural chromosome order otherwise.
+    pub fn contigs(&self) -> &ContigOrder {
+        &self.contigs
+    }
+
+    /// Get the FILTER names of a row that no ##FILTER header line declares.
+    ///
+    /// `PASS` is always considered declared.
+    pub fn undeclared_filters<'r>(&self, row: &'r VcfRow) -> Vec<&'r str> {
+        row.filter
+            .iter()
+            .map(String::as_str)
+            .filter(|name| *name != "PASS" && !self.filters.iter().any(|f| f.id == *name))
+            .collect()
+    }
+
+    /// Get the FORMAT field metadata map.
+    ///
+    /// FORMAT keys declared `Integer` or `Float` are parsed as numbers.
+    pub fn format_map(&self) -> &FormatMap {
+        &self.format_map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_VCF: &str = include_str!("../samples/sample.vcf");
+
+    const FULL_HEADER: &str = r#"##INFO=<ID=END,Number=1,Type=Integer,Description="End position (for use with symbolic alleles)">
+##INFO=<ID=ANN,Number=.,Type=String,Description="Functional annotations: 'Allele | Annotation | Annotation_Impact | Gene_Name | Gene_ID | Feature_Type | Feature_ID | Transcript_BioType | Rank | HGVS.c | HGVS.p | cDNA.pos / cDNA.length | CDS.pos / CDS.length | AA.pos / AA.length | Distance | ERRORS / WARNINGS / INFO'">
+##INFO=<ID=LOF,Number=.,Type=String,Description="Predicted loss of function effects for this variant. Format: 'Gene_Name | Gene_ID | Number_of_transcripts_in_gene | Percent_of_transcripts_affected'">
+##INFO=<ID=NMD,Number=.,Type=String,Description="Predicted nonsense mediated decay effects for this variant. Format: 'Gene_Name | Gene_ID | Number_of_transcripts_in_gene | Percent_of_transcripts_affected'">
+##INFO=<ID=CLNSIG,Number=.,Type=String,Description="Aggregate germline classification for this single variant; multiple values are separated by a vertical bar">
+##INFO=<ID=CLNDN,Number=.,Type=String,Description="ClinVar's preferred disease name for the concept specified by disease identifiers in CLNDISDB">"#;
+
+    const REAL_ROW: &str = "chr1\t186308857\trs3737940\tA\tG\t37.5\tPASS\tANN=G|synonymous_variant|LOW|PRG4|ENSG00000116690|transcript|ENST00000445192.6|protein_coding|7/13|c.3138A>G|p.Pro1046Pro|3183/5044|3138/4215|1046/1404||,G|synonymous_variant|LOW|PRG4|ENSG00000116690|transcript|ENST00000367483.8|protein_coding|6/12|c.3015A>G|p.Pro1005Pro|3060/4921|3015/4092|1005/1363||,G|synonymous_variant|LOW|PRG4|ENSG00000116690|transcript|ENST00000367485.4|protein_coding|5/11|c.2859A>G|p.Pro953Pro|2904/4765|2859/3936|953/1311||,G|synonymous_variant|LOW|PRG4|ENSG00000116690|transcript|ENST00000635041.1|protein_coding|6/12|c.3009A>G|p.Pro1003Pro|3054/4915|3009/4086|1003/1361||,G|downstream_gene_variant|MODIFIER|TPR|ENSG00000047410|transcript|ENST00000367478.9|protein_coding||c.*5114T>C|||||2795|,G|downstream_gene_variant|MODIFIER|PRG4|ENSG00000116690|transcript|ENST00000533951.5|protein_coding||c.*2379A>G|||||2379|WARNING_TRANSCRIPT_NO_STOP_CODON,G|downstream_gene_variant|MODIFIER|PRG4|ENSG00000116690|transcript|ENST00000367482.8|protein_coding||c.*175A>G|||||175|WARNING_TRANSCRIPT_INCOMPLETE,G|downstream_gene_variant|MODIFIER|RNU6-1240P|ENSG00000202025|transcript|ENST00000365155.1|snRNA||n.*2968T>C|||||2968|;CLNSIG=Benign;CLNDN=not_provided|Camptodactyly-arthropathy-coxa_vara-pericarditis_syndrome|not_specified";
+
+    #[test]
+    fn test_engine_creation() {
+        let engine = FilterEngine::new(FULL_HEADER).unwrap();
+        assert!(engine.info_map().contains_key("ANN"));
+        assert!(engine.info_map().contains_key("CLNSIG"));
+    }
+
+    #[test]
+    fn test_real_row_qual() {
+        let engine = FilterEngine::new(FULL_HEADER).unwrap();
+        assert!(engine.evaluate("QUAL > 30", REAL_ROW).unwrap());
+        assert!(engine.evaluate("QUAL < 40", REAL_ROW).unwrap());
+        assert!(engine.evaluate("QUAL == 37.5", REAL_ROW).unwrap());
+    }
+
+    #[test]
+    fn test_real_row_filter() {
+        let engine = FilterEngine::new(FULL_HEADER).unwrap();
+        assert!(engine.evaluate(r#"FILTER == "PASS""#, REAL_ROW).unwrap());
+    }
+
+    #[test]
+    fn test_real_row_clnsig() {
+        let engine = FilterEngine::new(FULL_HEADER).unwrap();
+        assert!(engine.evaluate(r#"CLNSIG == "Benign""#, REAL_ROW).unwrap());
+    }
+
+    #[test]
+    fn test_real_row_ann_first() {
+        let engine = FilterEngine::new(FULL_HEADER).unwrap();
+        assert!(
+            engine
+                .evaluate(r#"ANN[0].Annotation == "synonymous_variant""#, REAL_ROW)
+                .unwrap()
+        );
+        assert!(
+            engine
+                .evaluate(r#"ANN[0].Annotation_Impact == "LOW""#, REAL_ROW)
+                .unwrap()
+        );
+        assert!(
+            engine
+                .evaluate(r#"ANN[0].Gene_Name == "PRG4""#, REAL_ROW)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_real_row_ann_wildcard() {
+        let engine = FilterEngine::new(FULL_HEADER).unwrap();
+        // Any annotation has MODIFIER impact
+        assert!(
+            engine
+                .evaluate(r#"ANN[*].Annotation_Impact == "MODIFIER""#, REAL_ROW)
+                .unwrap()
+        );
+        // Any annotation is for TPR gene
+        assert!(
+            engine
+                .evaluate(r#"ANN[*].Gene_Name == "TPR""#, REAL_ROW)
+                .unwrap()
+        );
+        // No annotation has HIGH impact
+        assert!(
+            !engine
+                .evaluate(r#"ANN[*].Annotation_Impact == "HIGH""#, REAL_ROW)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_real_row_chrom_pos() {
+        let engine = FilterEngine::new(FULL_HEADER).unwrap();
+        assert!(engine.evaluate(r#"CHROM == "chr1""#, REAL_ROW).unwrap());
+        assert!(engine.evaluate("POS > 186000000", REAL_ROW).unwrap());
+    }
+
+    #[test]
+    fn test_real_row_complex_filter() {
+        let engine = FilterEngine::new(FULL_HEADER).unwrap();
+        // Combined filter: high quality, passed, and benign
+        assert!(
+            engine
+                .evaluate(
+                    r#"QUAL > 30 && FILTER == "PASS" && CLNSIG == "Benign""#,
+                    REAL_ROW
+                )
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_real_row_contains() {
+        let engine = FilterEngine::new(FULL_HEADER).unwrap();
+        assert!(
+            engine
+                .evaluate(r#"CLNDN contains "Camptodactyly""#, REAL_ROW)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_and_reuse() {
+        let engine = FilterEngine::new(FULL_HEADER).unwrap();
+
+        // Parse filter once
+        let expr = engine.parse_filter("QUAL > 30").unwrap();
+
+        // Parse row once
+        let row = engine.parse_row(REAL_ROW).unwrap();
+
+        // Evaluate multiple times efficiently
+        assert!(engine.evaluate_parsed(&expr, &row).unwrap());
+    }
+
+    #[test]
+    fn test_missing_field() {
+        let engine = FilterEngine::new(FULL_HEADER).unwrap();
+        // LOF doesn't exist in this row
+        assert!(!engine.evaluate("exists(LOF)", REAL_ROW).unwrap());
+        assert!(engine.evaluate("!exists(LOF)", REAL_ROW).unwrap());
+    }
+
+    #[test]
+    fn test_existing_field() {
+        let engine = FilterEngine::new(FULL_HEADER).unwrap();
+        assert!(engine.evaluate("exists(CLNSIG)", REAL_ROW).unwrap());
+        assert!(engine.evaluate("exists(ANN)", REAL_ROW).unwrap());
+    }
+
+    #[test]
+    fn test_sample_vcf_all_info_fields_filterable() {
+        let mut header_lines = Vec::new();
+        let mut rows = Vec::new();
+
+        for line in SAMPLE_VCF.lines() {
+            if line.starts_with("##") {
+                header_lines.push(line);
+            } else if !line.is_empty() && !line.starts_with('#') {
+                rows.push(line);
+            }
+        }
+
+        let header = header_lines.join("\n");
+        let engine = FilterEngine::new(&header).unwrap();
+
+        let checks = [
+            ("FREQ", r#"FREQ contains "1000Genomes""#),
+            ("COMMON", "exists(COMMON)"),
+            ("ANN", r#"ANN[*].Gene_Name == "DARC""#),
+            ("LOF", r#"LOF[0].Gene_Name contains "MLTK""#),
+            ("NMD", r#"NMD[0].Gene_Name contains "MLTK""#),
+            ("CLNSIG", r#"CLNSIG == "Pathogenic""#),
+            (
+                "CLNDN",
+                r#"CLNDN contains "Resistance_to_Plasmodium_vivax_infection""#,
+            ),
+            ("ALLELEID", "ALLELEID > 0"),
+        ];
+
+        for (field, filter) in checks {
+            assert!(
+                engine.info_map().contains_key(field),
+                "INFO field {field} was not parsed from sample header"
+            );
+
+            let matched = rows
+                .iter()
+                .any(|row| engine.evaluate(filter, row).unwrap_or(false));
+
+            assert!(
+                matched,
+                "No sample row matched filter '{filter}' for INFO field {field}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_doctest_scenario() {
+        // This matches the doctest example exactly
+        let header = r#"
+##INFO=<ID=DP,Number=1,Type=Integer,Description="Total depth">
+##INFO=<ID=ANN,Number=.,Type=String,Description="Functional annotations: 'Allele | Annotation | Annotation_Impact | Gene_Name | Gene_ID'">
+"#;
+        let engine = FilterEngine::new(header).unwrap();
+
+        // Check that ANN subfields were parsed
+        let ann_field = engine.info_map().get("ANN").unwrap();
+        let subfields = ann_field.subfields.as_ref().unwrap();
+        assert_eq!(subfields[0], "Allele");
+        assert_eq!(subfields[1], "Annotation");
+        assert_eq!(subfields[2], "Annotation_Impact");
+        assert_eq!(subfields[3], "Gene_Name");
+        assert_eq!(subfields[4], "Gene_ID");
+
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\tDP=30;ANN=G|missense|HIGH|BRCA1|ENSG123";
+
+        assert!(engine.evaluate("QUAL > 30", row).unwrap());
+        assert!(engine.evaluate(r#"FILTER == "PASS""#, row).unwrap());
+        assert!(engine.evaluate("DP >= 30", row).unwrap());
+        assert!(
+            engine
+                .evaluate(r#"ANN[0].Gene_Name == "BRCA1""#, row)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_limits_reject_oversized_filters() {
+        let engine = FilterEngine::new(FULL_HEADER)
+            .unwrap()
+            .with_limits(EvalLimits::untrusted());
+        assert!(engine.evaluate("QUAL > 30", REAL_ROW).unwrap());
+
+        let deep = format!("{}QUAL > 30{}", "(".repeat(500), ")".repeat(500));
+        assert!(matches!(
+            engine.evaluate(&deep, REAL_ROW),
+            Err(VcfFilterError::LimitExceeded(_))
+        ));
+        // A chain of prefix operators is rejected before it reaches the parser
+        let negated = format!("{}QUAL > 1", "!".repeat(5000));
+        assert!(matches!(
+            engine.evaluate(&negated, REAL_ROW),
+            Err(VcfFilterError::LimitExceeded(_))
+        ));
+    }
+
+    #[test]
+    fn test_time_budget_exceeded() {
+        let engine = FilterEngine::new(FULL_HEADER)
+            .unwrap()
+            .with_limits(EvalLimits {
+                time_budget: Some(std::time::Duration::ZERO),
+                ..Default::default()
+            });
         let expr = engine.parse_filter("QUAL > 30").unwrap();
+        let row = engine.parse_row(REAL_ROW).unwrap();
+        assert!(matches!(
+            engine.evaluate_parsed(&expr, &row),
+            Err(VcfFilterError::LimitExceeded(_))
+        ));
+        let compiled = engine.compile_expr(expr);
+        assert!(matches!(
+            engine.evaluate_compiled(&compiled, &row),
+            Err(VcfFilterError::LimitExceeded(_))
+        ));
+    }
+
+    #[test]
+    fn test_max_call_depth() {
+        let mut engine = FilterEngine::new(FULL_HEADER)
+            .unwrap()
+            .with_limits(EvalLimits {
+                max_call_depth: Some(2),
+                ..Default::default()
+            });
+        engine
+            .register_function("double", |args| match args {
+                [Value::Number(n)] => Ok(Value::Number(n * 2.0)),
+                _ => Ok(Value::Missing),
+            })
+            .unwrap();
+        assert!(engine.evaluate("abs(double(QUAL)) > 30", REAL_ROW).unwrap());
+        // Built-in and registered calls count alike
+        for filter in ["abs(abs(abs(QUAL))) > 30", "double(abs(double(QUAL))) > 30"] {
+            assert!(matches!(
+                engine.evaluate(filter, REAL_ROW),
+                Err(VcfFilterError::LimitExceeded(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_compiled_evaluation_agrees_on_sample_vcf() {
+        let header: Vec<&str> = SAMPLE_VCF.lines().filter(|l| l.starts_with('#')).collect();
+        let engine = FilterEngine::new(&header.join("\n")).unwrap();
+        for filter in [
+            r#"ANN[*].Annotation_Impact == "HIGH" || ANN[0].Gene_Name == "PRG4""#,
+            r#"ANN[all].Annotation_Impact != "MODIFIER" && exists(CLNSIG)"#,
+            r#"CLNSIG in ["Benign", "Pathogenic"] || !exists(ANN[*].HGVS_p)"#,
+            r#"QUAL > 30 || FILTER == "PASS" || FORMAT.DP >= 10"#,
+        ] {
+            let compiled = engine.compile(filter).unwrap();
+            for line in SAMPLE_VCF.lines().filter(|l| !l.starts_with('#')) {
+                let row = engine.parse_row(line).unwrap();
+                assert_eq!(
+                    engine.evaluate_compiled(&compiled, &row).unwrap(),
+                    engine.evaluate_parsed(compiled.expr(), &row).unwrap(),
+                    "{}",
+                    filter
+                );
+            }
+        }
+
+        // An empty top-level value is present, its empty elements are not
+        let row = engine
+            .parse_row("chr1\t100\t.\tA\tG\t50\tPASS\tNOTE=;PAIR=,")
+            .unwrap();
+        for (filter, expected) in [
+            ("exists(NOTE)", true),
+            ("exists(INFO.NOTE)", true),
+            ("exists(PAIR)", true),
+            ("exists(PAIR[*])", false),
+            ("exists(PAIR[all])", false),
+        ] {
+            let compiled = engine.compile(filter).unwrap();
+            assert_eq!(
+                engine.evaluate_compiled(&compiled, &row).unwrap(),
+                expected,
+                "{}",
+                filter
+            );
+        }
+    }
+
+    #[test]
+    fn test_quick_reject_agrees_with_evaluation() {
+        let header = format!(
+            "{}\n##INFO=<ID=DB,Number=0,Type=Flag,Description=\"dbSNP membership\">",
+            FULL_HEADER
+        );
+        let engine = FilterEngine::new(&header).unwrap();
+        for filter in [
+            "QUAL > 30",
+            r#"CLNSIG == "Pathogenic""#,
+            r#"ANN[*].Gene_Name == "NOT_A_GENE""#,
+            r#"CLNSIG == "Benign" || DP > 10"#,
+            "!exists(NOT_A_FIELD)",
+            // Missing equals missing, so these pass without the keys
+            "NOPE == NOPE2",
+            "DP == DP",
+            "DP in [NOPE]",
+            // An absent flag is false
+            "DB == false",
+            "DB != true",
+            "DB == false && QUAL > 30",
+        ] {
+            let compiled = engine.compile(filter).unwrap();
+            let expected = engine.evaluate(filter, REAL_ROW).unwrap();
+            assert_eq!(engine.evaluate_line(&compiled, REAL_ROW).unwrap(), expected);
+            if engine.quick_reject(&compiled, REAL_ROW.as_bytes()) == Some(false) {
+                assert!(!expected, "{} was screened out but passes", filter);
+            }
+        }
+
+        // Only reserved characters are percent-decoded, so `%20` is compared as written
+        let row = REAL_ROW.replacen("CLNSIG=", "NOTE=100%20sure;CLNSIG=", 1);
+        for filter in [r#"NOTE == "100 sure""#, r#"NOTE == "100%20sure""#] {
+            let compiled = engine.compile(filter).unwrap();
+            assert_eq!(
+                engine.evaluate_line(&compiled, &row).unwrap(),
+                engine.evaluate(filter, &row).unwrap()
+            );
+        }
+        assert!(engine.evaluate(r#"NOTE == "100%20sure""#, &row).unwrap());
+
+        // Virtual fields are not in the line text, so they never screen it out
+        let tsv = "#chrom\tstart\tend\tROH_REGION\nchr1\t0\t1000000000\troh_1\n";
+        let engine = engine.with_intervals(IntervalAnnotation::parse_tsv(tsv).unwrap());
+        let compiled = engine.compile(r#"ROH_REGION == "roh_1""#).unwrap();
+        assert_eq!(engine.quick_reject(&compiled, REAL_ROW.as_bytes()), None);
+    }
+
+    #[test]
+    fn test_gene_panel_lists() {
+        let path =
+            std::env::temp_dir().join(format!("vcf-filter-panel-{}.txt", std::process::id()));
+        std::fs::write(&path, "# panel\nBRCA1\nPRG4\n").unwrap();
+        let engine = FilterEngine::new(FULL_HEADER)
+            .unwrap()
+            .with_list("other", ["TP53"])
+            .with_list_file("panel", &path)
+            .unwrap();
+
+        let by_path = format!("ANN[*].Gene_Name in_file({:?})", path.to_str().unwrap());
+        assert!(engine.evaluate(&by_path, REAL_ROW).unwrap());
+        assert!(
+            engine
+                .evaluate(r#"ANN[*].Gene_Name in_file("panel")"#, REAL_ROW)
+                .unwrap()
+        );
+        assert!(
+            !engine
+                .evaluate(r#"ANN[*].Gene_Name in_file("other")"#, REAL_ROW)
+                .unwrap()
+        );
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            engine.compile(&by_path),
+            Err(VcfFilterError::ConfigError(_))
+        ));
+    }
+
+    #[test]
+    fn test_clinvar_classifications() {
+        let engine = FilterEngine::new(FULL_HEADER).unwrap();
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\tCLNSIG=Benign/Likely_benign";
+        for (filter, expected) in [
+            (r#"CLNSIG contains "Likely_benign""#, true),
+            (
+                r#"CLNSIG in ["Likely_benign", "Uncertain_significance"]"#,
+                true,
+            ),
+            (r#"CLNSIG == "Benign" && len(CLNSIG) == 2"#, true),
+            (r#"CLNSIG == "Benign/Likely_benign""#, false),
+        ] {
+            assert_eq!(
+                engine.evaluate(filter, row).unwrap(),
+                expected,
+                "{}",
+                filter
+            );
+        }
+
+        let engine = FilterEngine::new(FULL_HEADER)
+            .unwrap()
+            .with_parse_options(ParseOptions::default().with_separators("CLNSIG", ""));
+        assert!(
+            engine
+                .evaluate(r#"CLNSIG == "Benign/Likely_benign""#, row)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_consequence_severity() {
+        let engine = FilterEngine::new(FULL_HEADER).unwrap();
+        for (filter, expected) in [
+            (
+                r#"ANN[*].Annotation severity_at_least "synonymous_variant""#,
+                true,
+            ),
+            (
+                r#"ANN[*].Annotation severity_at_least "missense_variant""#,
+                false,
+            ),
+            (
+                r#"ANN[all].Annotation severity_at_least "downstream_gene_variant""#,
+                true,
+            ),
+            (
+                r#"ANN[0].Annotation severity_at_least "stop_gained""#,
+                false,
+            ),
+        ] {
+            assert_eq!(
+                engine.evaluate(filter, REAL_ROW).unwrap(),
+                expected,
+                "{}",
+                filter
+            );
+            let compiled = engine.compile(filter).unwrap();
+            assert_eq!(
+                engine.evaluate_line(&compiled, REAL_ROW).unwrap(),
+                expected,
+                "{}",
+                filter
+            );
+        }
+    }
+
+    #[test]
+    fn test_id_matching() {
+        let engine = FilterEngine::new("")
+            .unwrap()
+            .with_list("rsids", ["rs334", "rs113993960"]);
+        let row = |id: &str| format!("chr1\t100\t{}\tA\tG\t50\tPASS\t.", id);
+        for (filter, id, expected) in [
+            (r#"ID in_file("rsids")"#, "COSV1;rs334", true),
+            (r#"ID in_file("rsids")"#, "rs335", false),
+            (r#"ID == "COSV1""#, "COSV1;rs334", true),
+            ("has_id()", "rs335", true),
+            ("has_id()", ".", false),
+            ("!has_id() && !exists(ID)", ".", true),
+        ] {
+            assert_eq!(
+                engine.evaluate(filter, &row(id)).unwrap(),
+                expected,
+                "{} on {}",
+                filter,
+                id
+            );
+            let compiled = engine.compile(filter).unwrap();
+            assert_eq!(
+                engine.evaluate_line(&compiled, &row(id)).unwrap(),
+                expected,
+                "{} on {}",
+                filter,
+                id
+            );
+        }
+    }
+
+    #[test]
+    fn test_bed_overlap() {
+        let path =
+            std::env::temp_dir().join(format!("vcf-filter-exons-{}.bed", std::process::id()));
+        std::fs::write(&path, "track name=exons\nchr1\t186000000\t187000000\n").unwrap();
+        let engine = FilterEngine::new(FULL_HEADER)
+            .unwrap()
+            .with_bed("exons", &path)
+            .unwrap();
+        let by_path = format!("in_bed({:?}) && QUAL > 30", path.to_str().unwrap());
+        let compiled = engine.compile(&by_path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // Both the registered name and the path were loaded before removal
+        let row = engine.parse_row(REAL_ROW).unwrap();
+        assert!(engine.evaluate_compiled(&compiled, &row).unwrap());
+        assert!(engine.evaluate(r#"in_bed("exons")"#, REAL_ROW).unwrap());
+        assert!(
+            engine
+                .compile(r#"in_bed("/nonexistent/exons.bed")"#)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_sample_access_by_name() {
+        let header = "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tnormal\ttumor";
+        let engine = FilterEngine::new(header).unwrap();
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\t.\tGT:DP\t0/0:30\t0/1:60";
+        for (filter, expected) in [
+            (r#"SAMPLE["tumor"].GT == "0/1""#, true),
+            (
+                r#"SAMPLE["normal"].GT == "0/0" && FORMAT.DP["tumor"] > 50"#,
+                true,
+            ),
+            (r#"FORMAT.DP["normal"] > 50"#, false),
+            (r#"SAMPLE[1].DP == 60"#, true),
+            (r#"exists(SAMPLE["tumor"].GT)"#, true),
+            (r#"exists(SAMPLE["nobody"].GT)"#, false),
+        ] {
+            assert_eq!(
+                engine.evaluate(filter, row).unwrap(),
+                expected,
+                "{}",
+                filter
+            );
+            let compiled = engine.compile(filter).unwrap();
+            assert_eq!(
+                engine.evaluate_line(&compiled, row).unwrap(),
+                expected,
+                "{}",
+                filter
+            );
+        }
+    }
+
+    #[test]
+    fn test_sample_wildcards() {
+        let header = "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tkid\tdad\tmom";
+        let engine = FilterEngine::new(header).unwrap();
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\t.\tGT:DP\t0/1:30\t0/0:8\t./.:.";
+        for (filter, expected) in [
+            ("FORMAT[*].DP >= 10", true),
+            ("FORMAT[all].DP >= 5", false),
+            ("all(SAMPLE[*].DP >= 5)", false),
+            (r#"FORMAT[*].GT == "0/1""#, true),
+            (r#"FORMAT[all].GT != "1/1""#, true),
+            ("max(FORMAT[*].DP) == 30", true),
+        ] {
+            assert_eq!(
+                engine.evaluate(filter, row).unwrap(),
+                expected,
+                "{}",
+                filter
+            );
+            let compiled = engine.compile(filter).unwrap();
+            assert_eq!(
+                engine.evaluate_line(&compiled, row).unwrap(),
+                expected,
+                "{}",
+                filter
+            );
+        }
 
-        // Parse row once
-        let row = engine.parse_row(REAL_ROW).unwrap();
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\t.\tGT:DP\t0/1:30\t0/0:12\t0/0:10";
+        assert!(engine.evaluate("FORMAT[all].DP >= 10", row).unwrap());
+    }
 
-        // Evaluate multiple times efficiently
-        assert!(engine.evaluate_parsed(&expr, &row).unwrap());
+    #[test]
+    fn test_per_allele_access() {
+        let header = r#"##INFO=<ID=AF,Number=A,Type=Float,Description="Allele frequency">
+##INFO=<ID=AC,Number=A,Type=Integer,Description="Allele count">
+##INFO=<ID=AD,Number=R,Type=Integer,Description="Allelic depths">"#;
+        let engine = FilterEngine::new(header).unwrap();
+        let multiallelic = "chr1\t100\t.\tA\tG,T\t50\tPASS\tAF=0.002,0.3;AC=1,40;AD=20,1,12";
+        let biallelic = "chr1\t100\t.\tA\tG\t50\tPASS\tAF=0.3;AC=40;AD=20,12";
+        let mismatched = "chr1\t100\t.\tA\tG,T\t50\tPASS\tAF=0.3;AC=40";
+        for (filter, expected) in [
+            ("AF[alt] > 0.01 && AC[alt] > 2", [true, true, false]),
+            ("AF[alt] < 0.01 && AC[alt] > 2", [false, false, false]),
+            (r#"ALT[alt] == "T" && AD[alt] >= 12"#, [true, false, false]),
+            ("AF[0] == 0.3", [false, true, true]),
+            ("exists(AF[alt])", [true, true, false]),
+        ] {
+            for (row, expected) in [multiallelic, biallelic, mismatched]
+                .into_iter()
+                .zip(expected)
+            {
+                assert_eq!(
+                    engine.evaluate(filter, row).unwrap(),
+                    expected,
+                    "{} on {}",
+                    filter,
+                    row
+                );
+                let compiled = engine.compile(filter).unwrap();
+                assert_eq!(
+                    engine.evaluate_line(&compiled, row).unwrap(),
+                    expected,
+                    "{} on {}",
+                    filter,
+                    row
+                );
+            }
+        }
     }
 
     #[test]
-    fn test_missing_field() {
+    fn test_chromosome_ordering() {
+        let engine = FilterEngine::new("").unwrap();
+        let row = |chrom: &str| format!("{}\t100\t.\tA\tG\t50\tPASS\t.", chrom);
+        for (filter, chrom, expected) in [
+            (r#"CHROM <= "chr9""#, "chr2", true),
+            (r#"CHROM <= "chr9""#, "chr10", false),
+            (r#"CHROM < "chrX""#, "chr22", true),
+            (r#"CHROM > "chr22""#, "chrY", true),
+            (r#""chr10" > CHROM"#, "chr9", true),
+            (r#"CHROM >= "1" && CHROM <= "3""#, "chr2", true),
+            (r#"CHROM < "chrX""#, "chrUn_gl000220", false),
+            ("chrom_rank() > 22", "chrM", true),
+            // Equality still compares names
+            (r#"CHROM == "chr2""#, "2", false),
+        ] {
+            assert_eq!(
+                engine.evaluate(filter, &row(chrom)).unwrap(),
+                expected,
+                "{} on {}",
+                filter,
+                chrom
+            );
+            let compiled = engine.compile(filter).unwrap();
+            assert_eq!(
+                engine.evaluate_line(&compiled, &row(chrom)).unwrap(),
+                expected,
+                "{} on {}",
+                filter,
+                chrom
+            );
+        }
+    }
+
+    #[test]
+    fn test_strict_evaluation() {
+        let engine = FilterEngine::new(FULL_HEADER).unwrap().with_strict(true);
+        assert!(engine.is_strict());
+        for filter in [
+            r#"ANN[0].Gene_Name == "PRG4""#,
+            r#"ANN[*].Annotation == "synonymous_variant""#,
+        ] {
+            assert!(engine.evaluate(filter, REAL_ROW).unwrap(), "{}", filter);
+            let compiled = engine.compile(filter).unwrap();
+            assert!(engine.evaluate_line(&compiled, REAL_ROW).unwrap());
+        }
+        for filter in [
+            r#"ANN[99].Gene_Name == "PRG4""#,
+            "NOT_IN_HEADER > 1",
+            "QUAL == \"high\"",
+        ] {
+            assert!(engine.evaluate(filter, REAL_ROW).is_err(), "{}", filter);
+            // The compiled path reports the same errors
+            let compiled = engine.compile(filter).unwrap();
+            assert!(
+                engine.evaluate_line(&compiled, REAL_ROW).is_err(),
+                "{}",
+                filter
+            );
+        }
+    }
+
+    #[test]
+    fn test_three_valued_outcomes() {
+        let header = r#"##INFO=<ID=AF,Number=A,Type=Float,Description="Allele frequency">
+##INFO=<ID=CLNSIG,Number=.,Type=String,Description="Clinical significance">"#;
+        let engine = FilterEngine::new(header).unwrap().with_three_valued(true);
+        assert!(engine.is_three_valued());
+        let row = "chr1\t100\t.\tA\tG,T\t50\tPASS\tAF=.,0.2";
+        for (filter, expected) in [
+            (r#"CLNSIG != "Benign""#, Outcome::Unknown),
+            ("AF[alt] < 0.01", Outcome::Unknown),
+            ("AF[alt] > 0.1", Outcome::Pass),
+            ("AF[1] < 0.01", Outcome::Fail),
+            ("AF[0] < 0.01 && QUAL > 60", Outcome::Fail),
+        ] {
+            assert_eq!(
+                engine.evaluate_outcome(filter, row).unwrap(),
+                expected,
+                "{}",
+                filter
+            );
+            let passes = expected == Outcome::Pass;
+            assert_eq!(engine.evaluate(filter, row).unwrap(), passes, "{}", filter);
+            let compiled = engine.compile(filter).unwrap();
+            assert_eq!(
+                engine.evaluate_line(&compiled, row).unwrap(),
+                passes,
+                "{}",
+                filter
+            );
+        }
+
+        // Two-valued engines never report unknown
+        let engine = FilterEngine::new(header).unwrap();
+        assert_eq!(
+            engine
+                .evaluate_outcome(r#"CLNSIG != "Benign""#, row)
+                .unwrap(),
+            Outcome::Pass
+        );
+        assert_eq!(
+            engine.evaluate_outcome("AF[0] < 0.01", row).unwrap(),
+            Outcome::Fail
+        );
+    }
+
+    #[test]
+    fn test_evaluate_value() {
         let engine = FilterEngine::new(FULL_HEADER).unwrap();
-        // LOF doesn't exist in this row
-        assert!(!engine.evaluate("exists(LOF)", REAL_ROW).unwrap());
-        assert!(engine.evaluate("!exists(LOF)", REAL_ROW).unwrap());
+        assert_eq!(
+            engine.evaluate_value("ANN[0].Gene_Name", REAL_ROW).unwrap(),
+            Value::String("PRG4".to_string())
+        );
+        assert_eq!(
+            engine.evaluate_value("QUAL * 2", REAL_ROW).unwrap(),
+            Value::Number(75.0)
+        );
+        assert_eq!(
+            engine.evaluate_value("NOT_IN_ROW", REAL_ROW).unwrap(),
+            Value::Missing
+        );
+        assert!(engine.evaluate_value("QUAL +", REAL_ROW).is_err());
+
+        let header = r#"##INFO=<ID=AF,Number=A,Type=Float,Description="Allele frequency">"#;
+        let engine = FilterEngine::new(header).unwrap();
+        let row = "chr1\t100\t.\tA\tG,T\t50\tPASS\tAF=0.1,0.3";
+        assert_eq!(
+            engine.evaluate_value("AF[alt] * 10", row).unwrap(),
+            Value::Array(vec![Value::Number(1.0), Value::Number(3.0)])
+        );
     }
 
     #[test]
-    fn test_existing_field() {
+    fn test_explain() {
         let engine = FilterEngine::new(FULL_HEADER).unwrap();
-        assert!(engine.evaluate("exists(CLNSIG)", REAL_ROW).unwrap());
-        assert!(engine.evaluate("exists(ANN)", REAL_ROW).unwrap());
+        let filter = r#"QUAL > 30 && (ANN[0].Gene_Name == "BRCA1" || exists(LOF))"#;
+        let explanation = engine.explain(filter, REAL_ROW).unwrap();
+        assert_eq!(
+            explanation.passed(),
+            engine.evaluate(filter, REAL_ROW).unwrap()
+        );
+        assert_eq!(
+            explanation.to_string(),
+            "QUAL > 30 && (ANN[0].Gene_Name == \"BRCA1\" || exists(LOF)) => false\n\
+             |-- QUAL > 30 => true\n\
+             |   `-- QUAL => 37.5\n\
+             `-- ANN[0].Gene_Name == \"BRCA1\" || exists(LOF) => false\n    \
+             |-- ANN[0].Gene_Name == \"BRCA1\" => false\n    \
+             |   `-- ANN[0].Gene_Name => \"PRG4\"\n    \
+             `-- exists(LOF) => false"
+        );
+
+        let header = r#"##INFO=<ID=AF,Number=A,Type=Float,Description="Allele frequency">"#;
+        let engine = FilterEngine::new(header).unwrap();
+        let row = "chr1\t100\t.\tA\tG,T\t50\tPASS\tAF=0.1,0.3";
+        let explanation = engine.explain("AF[alt] > 0.2", row).unwrap();
+        assert!(explanation.passed());
+        assert_eq!(explanation.children.len(), 2);
+        assert_eq!(explanation.children[0].expr, "[alt = G] AF[alt] > 0.2");
+        assert!(!explanation.children[0].passed());
+        assert!(explanation.children[1].passed());
     }
 
     #[test]
-    fn test_sample_vcf_all_info_fields_filterable() {
-        let mut header_lines = Vec::new();
-        let mut rows = Vec::new();
+    fn test_from_reader() {
+        let vcf = format!(
+            "##fileformat=VCFv4.2\n{}\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n{}\n",
+            FULL_HEADER, REAL_ROW
+        );
+        let mut reader = std::io::Cursor::new(vcf);
+        let (engine, header_lines) = FilterEngine::from_reader(&mut reader).unwrap();
+        assert!(header_lines[0].starts_with("##fileformat"));
+        assert!(header_lines.last().unwrap().starts_with("#CHROM"));
+        assert!(engine.info_map().contains_key("ANN"));
 
-        for line in SAMPLE_VCF.lines() {
-            if line.starts_with("##") {
-                header_lines.push(line);
-            } else if !line.is_empty() && !line.starts_with('#') {
-                rows.push(line);
-            }
+        // The reader is left at the first data row
+        let mut rows = reader.lines();
+        let row = rows.next().unwrap().unwrap();
+        assert!(
+            engine
+                .evaluate(r#"ANN[0].Gene_Name == "PRG4""#, &row)
+                .unwrap()
+        );
+        assert!(rows.next().is_none());
+
+        for input in [
+            "##fileformat=VCFv4.2\n",
+            "",
+            "chr1\t100\t.\tA\tG\t50\tPASS\t.\n",
+        ] {
+            let err = FilterEngine::from_reader(&mut input.as_bytes()).unwrap_err();
+            assert!(
+                matches!(err, VcfFilterError::HeaderParseError(_)),
+                "{:?}",
+                input
+            );
         }
+    }
 
-        let header = header_lines.join("\n");
+    #[test]
+    fn test_filter_lines() {
+        let engine = FilterEngine::new(FULL_HEADER).unwrap();
+        let low = REAL_ROW.replacen("\t37.5\t", "\t12\t", 1);
+        let input = format!(
+            "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n{}\n\n{}\n",
+            REAL_ROW, low
+        );
+
+        let passing: Vec<String> = engine
+            .filter_lines(input.as_bytes(), "QUAL > 30")
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(passing, [REAL_ROW]);
+
+        let results: Vec<(String, bool)> = engine
+            .evaluate_lines(input.as_bytes(), "QUAL > 30")
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(results, [(REAL_ROW.to_string(), true), (low, false)]);
+
+        assert!(engine.filter_lines(input.as_bytes(), "QUAL >").is_err());
+        let mut bad_rows = engine
+            .filter_lines("chr1\tnot_a_pos".as_bytes(), "QUAL > 30")
+            .unwrap();
+        assert!(bad_rows.next().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_filter_file() {
+        use std::io::{Read, Write};
+
+        let dir = std::env::temp_dir().join(format!("vcf-filter-file-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let low = REAL_ROW.replacen("\t37.5\t", "\t12\t", 1);
+        let vcf = format!(
+            "{}\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n{}\n{}\n",
+            FULL_HEADER, REAL_ROW, low
+        );
+
+        // gzip in, bgzip out
+        let input = dir.join("in.vcf.gz");
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(vcf.as_bytes()).unwrap();
+        std::fs::write(&input, gzip.finish().unwrap()).unwrap();
+        let output = dir.join("out.vcf.gz");
+        assert_eq!(
+            FilterEngine::filter_file(&input, &output, "QUAL > 30").unwrap(),
+            (1, 2)
+        );
+        let mut filtered = String::new();
+        flate2::read::MultiGzDecoder::new(std::fs::File::open(&output).unwrap())
+            .read_to_string(&mut filtered)
+            .unwrap();
+        assert!(filtered.starts_with("##"));
+        assert!(filtered.ends_with(&format!("{}\n", REAL_ROW)));
+
+        // bgzip in, plain out
+        let plain = dir.join("out.vcf");
+        assert_eq!(
+            FilterEngine::filter_file(&output, &plain, "QUAL > 30").unwrap(),
+            (1, 1)
+        );
+        assert_eq!(std::fs::read_to_string(&plain).unwrap(), filtered);
+
+        assert!(matches!(
+            FilterEngine::filter_file(dir.join("missing.vcf"), &plain, "QUAL > 30"),
+            Err(VcfFilterError::IoError(_))
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_evaluate_reuses_parsed_filters() {
+        let engine = FilterEngine::new(FULL_HEADER)
+            .unwrap()
+            .with_preset("good", "QUAL > 30")
+            .unwrap();
+        for _ in 0..3 {
+            assert!(engine.evaluate("@good", REAL_ROW).unwrap());
+            assert!(engine.evaluate("QUAL > 30", REAL_ROW).unwrap());
+        }
+        assert!(engine.evaluate("QUAL >", REAL_ROW).is_err());
+        assert!(engine.evaluate("QUAL >", REAL_ROW).is_err());
+
+        // Redefining a preset or tightening limits invalidates cached filters
+        let engine = engine.with_preset("good", "QUAL > 50").unwrap();
+        assert!(!engine.evaluate("@good", REAL_ROW).unwrap());
+        let engine = engine.with_limits(EvalLimits {
+            max_nodes: Some(2),
+            ..EvalLimits::default()
+        });
+        assert!(matches!(
+            engine.evaluate("QUAL > 30", REAL_ROW),
+            Err(VcfFilterError::LimitExceeded(_))
+        ));
+    }
+
+    #[test]
+    fn test_config_presets_aliases_and_fields() {
+        let config = FilterConfig::parse(
+            "[presets]\n@good := QUAL > 30\n[aliases]\nq = QUAL\n[fields]\nlow = q < 10\n",
+        )
+        .unwrap();
+        let engine = FilterEngine::new(FULL_HEADER)
+            .unwrap()
+            .with_preset("good", "QUAL > 50")
+            .unwrap()
+            .with_preset("any", "QUAL > 0")
+            .unwrap()
+            .with_config(config);
+        // Config presets shadow engine presets; others still resolve
+        assert!(engine.evaluate("@good && @any", REAL_ROW).unwrap());
+        assert!(engine.evaluate("q > 30 && !low", REAL_ROW).unwrap());
+        assert!(matches!(
+            engine.evaluate("@nope", REAL_ROW),
+            Err(VcfFilterError::ConfigError(_))
+        ));
+    }
+
+    #[test]
+    fn test_config_watcher_reload_without_new_engine() {
+        let path = std::env::temp_dir().join(format!(
+            "vcf_filter_engine_watch_{}.ini",
+            std::process::id()
+        ));
+        std::fs::write(&path, "[presets]\ngood = QUAL > 30\n[aliases]\nq = QUAL").unwrap();
+        let watcher = Arc::new(ConfigWatcher::new(&path).unwrap());
+        let engine = FilterEngine::new(FULL_HEADER)
+            .unwrap()
+            .with_config_watcher(Arc::clone(&watcher));
+        let mut compiled = engine.compile("@good").unwrap();
+        let mut from_expr = engine.compile_expr(engine.parse_filter("@good").unwrap());
+        assert!(engine.evaluate("@good", REAL_ROW).unwrap());
+        assert!(engine.evaluate("q > 30", REAL_ROW).unwrap());
+        assert!(!compiled.refresh().unwrap());
+
+        std::fs::write(&path, "[presets]\ngood = QUAL > 50\n[aliases]\nq = POS").unwrap();
+        watcher.reload().unwrap();
+        // The same engine, its cached filters and its clones see the reload
+        assert!(!engine.evaluate("@good", REAL_ROW).unwrap());
+        assert!(!engine.clone().evaluate("@good", REAL_ROW).unwrap());
+        assert!(engine.evaluate("q > 30", REAL_ROW).unwrap());
+        assert!(compiled.matches(REAL_ROW).unwrap());
+        assert!(compiled.refresh().unwrap());
+        assert!(!compiled.matches(REAL_ROW).unwrap());
+        assert!(!compiled.refresh().unwrap());
+        assert!(!from_expr.refresh().unwrap());
+
+        // A reload that drops a preset fails to refresh and keeps the filter
+        watcher.update("[presets]\nother = QUAL > 0").unwrap();
+        assert!(compiled.refresh().is_err());
+        assert_eq!(compiled.expr(), &parse_filter("QUAL > 50").unwrap());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_clause_stats() {
+        let engine = FilterEngine::new(FULL_HEADER).unwrap();
+        let mut stats = engine
+            .stats(r#"QUAL > 30 && ANN[*].Gene_Name == "NOPE" && QUAL > 0"#)
+            .unwrap();
+        let row = engine.parse_row(REAL_ROW).unwrap();
+        let low = engine
+            .parse_row(&REAL_ROW.replacen("\t37.5\t", "\t12\t", 1))
+            .unwrap();
+        assert!(!engine.evaluate_counted(&mut stats, &row).unwrap());
+        assert!(!engine.evaluate_counted(&mut stats, &low).unwrap());
+
+        let counts: Vec<(u64, u64)> = stats
+            .clauses()
+            .iter()
+            .map(|c| (c.rejected, c.rejected_alone))
+            .collect();
+        assert_eq!(counts, [(1, 0), (2, 1), (0, 0)]);
+        assert_eq!((stats.rows(), stats.passed()), (2, 0));
+    }
+
+    #[test]
+    fn test_select() {
+        let engine = FilterEngine::new(FULL_HEADER).unwrap();
+        let fields = ["CHROM", "POS", "ANN[0].Gene_Name", "QUAL / 2", "DP"];
+        let values = engine.select(&fields, REAL_ROW).unwrap();
+        assert_eq!(values.len(), fields.len());
+        assert_eq!(values[3], Value::Number(18.75));
+        assert_eq!(values[4], Value::Missing);
+
+        let tsv = engine.select_tsv(&fields, REAL_ROW).unwrap();
+        let columns: Vec<&str> = tsv.split('\t').collect();
+        assert_eq!(columns.len(), fields.len());
+        assert_eq!(columns[3..], ["18.75", "."]);
+
+        let row = engine.parse_row(REAL_ROW).unwrap();
+        let exprs: Vec<Expr> = fields
+            .iter()
+            .map(|f| engine.parse_filter(f).unwrap())
+            .collect();
+        assert_eq!(engine.select_parsed(&exprs, &row).unwrap(), values);
+        assert!(engine.select(&["POS >"], REAL_ROW).is_err());
+    }
+
+    #[test]
+    fn test_many_samples() {
+        let names: Vec<String> = (0..12).map(|i| format!("S{}", i)).collect();
+        let header = format!(
+            "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\t{}",
+            names.join("\t")
+        );
         let engine = FilterEngine::new(&header).unwrap();
+        let genotypes: Vec<String> = (0..12).map(|i| format!("0/{}:{}", i % 2, i)).collect();
+        let line = format!(
+            "chr1\t100\t.\tA\tG\t50\tPASS\t.\tGT:DP\t{}",
+            genotypes.join("\t")
+        );
 
-        let checks = [
-            ("FREQ", r#"FREQ contains "1000Genomes""#),
-            ("COMMON", "exists(COMMON)"),
-            ("ANN", r#"ANN[*].Gene_Name == "DARC""#),
-            ("LOF", r#"LOF[0].Gene_Name contains "MLTK""#),
-            ("NMD", r#"NMD[0].Gene_Name contains "MLTK""#),
-            ("CLNSIG", r#"CLNSIG == "Pathogenic""#),
-            (
-                "CLNDN",
-                r#"CLNDN contains "Resistance_to_Plasmodium_vivax_infection""#,
-            ),
-            ("ALLELEID", "ALLELEID > 0"),
-        ];
+        let row = engine.parse_row(&line).unwrap();
+        assert_eq!(row.sample_count(), 12);
+        let last = engine.sample_fields(&row, "S11").unwrap();
+        assert_eq!(last.get("DP"), Some(&Value::String("11".to_string())));
+        assert!(engine.sample_fields(&row, "S12").is_none());
+        assert!(engine.evaluate(r#"SAMPLE["S11"].DP == 11"#, &line).unwrap());
+        assert!(engine.evaluate("n_het() == 6", &line).unwrap());
+    }
 
-        for (field, filter) in checks {
-            assert!(
-                engine.info_map().contains_key(field),
-                "INFO field {field} was not parsed from sample header"
-            );
+    #[test]
+    fn test_typed_format_comparisons() {
+        let header = "##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">\n\
+                      ##FORMAT=<ID=GQ,Number=1,Type=Integer,Description=\"Genotype quality\">\n\
+                      ##FORMAT=<ID=AD,Number=R,Type=Integer,Description=\"Allele depths\">\n\
+                      ##FORMAT=<ID=PL,Number=G,Type=Integer,Description=\"Likelihoods\">\n\
+                      #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tNA1\tNA2";
+        let engine = FilterEngine::new(header).unwrap();
+        assert!(engine.format_map().contains_key("GQ"));
 
-            let matched = rows
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\t.\tGT:GQ:AD:PL\t0/1:9:6,4:30,0,200\t1/1:100:0,20:0,60,600";
+        assert_eq!(
+            engine.evaluate_value("GQ", row).unwrap(),
+            Value::Number(9.0)
+        );
+        assert!(engine.evaluate("GQ < 20", row).unwrap());
+        assert!(engine.evaluate("SAMPLE[\"NA2\"].GQ > 20", row).unwrap());
+        assert!(engine.evaluate("FORMAT[*].PL[0] == 0", row).unwrap());
+        assert!(
+            engine
+                .evaluate("AD[1] / (AD[0] + AD[1]) == 0.4", row)
+                .unwrap()
+        );
+        assert!(engine.evaluate("vaf() == 0.4", row).unwrap());
+        assert!(engine.evaluate("gt_confidence() == 30", row).unwrap());
+    }
+
+    #[test]
+    fn test_filter_declarations() {
+        let header = r#"##FILTER=<ID=q10,Description="Quality below 10">
+##FILTER=<ID=s50,Description="Less than 50% of samples have data">"#;
+        let engine = FilterEngine::new(header).unwrap();
+        let ids: Vec<&str> = engine.filters().iter().map(|f| f.id.as_str()).collect();
+        assert_eq!(ids, ["q10", "s50"]);
+
+        let row = "chr1\t100\t.\tA\tG\t5\tq10;LowDP\t.";
+        assert_eq!(
+            engine.undeclared_filters(&engine.parse_row(row).unwrap()),
+            ["LowDP"]
+        );
+        assert!(engine.evaluate(r#"filter_has("q10")"#, row).unwrap());
+
+        let strict = engine.with_strict(true);
+        assert!(matches!(
+            strict.evaluate("QUAL < 10", row),
+            Err(VcfFilterError::RowParseError(_))
+        ));
+        for filter in ["PASS", "q10;s50", "."] {
+            let row = format!("chr1\t100\t.\tA\tG\t5\t{}\t.", filter);
+            assert!(strict.evaluate("QUAL < 10", &row).unwrap(), "{}", filter);
+        }
+    }
+
+    #[test]
+    fn test_contig_order() {
+        let header = r#"##contig=<ID=chrM,length=16569>
+##contig=<ID=chr1,length=248956422>
+##contig=<ID=chrUn_gl000220,length=161802>"#;
+        let engine = FilterEngine::new(header).unwrap();
+        let ids: Vec<&str> = engine
+            .contigs()
+            .contigs()
+            .iter()
+            .map(|c| c.id.as_str())
+            .collect();
+        assert_eq!(ids, ["chrM", "chr1", "chrUn_gl000220"]);
+
+        let row = |chrom: &str| format!("{}\t100\t.\tA\tG\t50\tPASS\t.", chrom);
+        assert!(engine.evaluate(r#"CHROM < "chr1""#, &row("chrM")).unwrap());
+        assert!(
+            engine
+                .evaluate(r#"CHROM > "chr1""#, &row("chrUn_gl000220"))
+                .unwrap()
+        );
+        assert!(!engine.evaluate(r#"CHROM > "chr1""#, &row("chr2")).unwrap());
+        assert!(engine.evaluate("chrom_rank() == 1", &row("chrM")).unwrap());
+
+        let natural = FilterEngine::new("").unwrap();
+        assert!(natural.contigs().is_empty());
+        assert!(!natural.evaluate(r#"CHROM < "chr1""#, &row("chrM")).unwrap());
+        assert!(
+            engine
+                .check_filter(r#"region("chr1:300000000-300000100")"#)
                 .iter()
-                .any(|row| engine.evaluate(filter, row).unwrap_or(false));
+                .any(|d| d.message.contains("past the end"))
+        );
+    }
 
-            assert!(
-                matched,
-                "No sample row matched filter '{filter}' for INFO field {field}"
-            );
+    #[test]
+    fn test_vep_csq_access() {
+        let header = r#"##INFO=<ID=CSQ,Number=.,Type=String,Description="Consequence annotations from Ensembl VEP. Format: Allele|Consequence|IMPACT|SYMBOL">"#;
+        let engine = FilterEngine::new(header).unwrap();
+        let row = "chr17\t100\t.\tA\tG\t50\tPASS\tCSQ=G|missense_variant|MODERATE|BRCA1,G|intron_variant|MODIFIER|NBR2";
+
+        assert!(engine.evaluate(r#"CSQ[0].SYMBOL == "BRCA1""#, row).unwrap());
+        assert!(engine.evaluate(r#"CSQ[*].SYMBOL == "NBR2""#, row).unwrap());
+        assert!(!engine.evaluate(r#"CSQ[*].IMPACT == "HIGH""#, row).unwrap());
+        assert!(
+            engine
+                .check_filter(r#"CSQ[0].SYMBOL == "BRCA1""#)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_register_structured_field() {
+        let header = r#"##INFO=<ID=HITS,Number=.,Type=String,Description="Database hits">"#;
+        let mut engine = FilterEngine::new(header).unwrap();
+        engine
+            .register_structured_field("HITS", &["Source", "Score"], Delimiters::new('&', ':'))
+            .unwrap();
+        engine
+            .register_structured_field("SPLICEAI", &["ALLELE", "SYMBOL", "DS_AG"], '|')
+            .unwrap();
+
+        let row =
+            "chr1\t100\t.\tA\tG\t50\tPASS\tHITS=db1:7&db2:3,x;SPLICEAI=G|BRCA1|0.91,T|BRCA1|0.02";
+        assert!(engine.evaluate(r#"HITS[1].Score == "3,x""#, row).unwrap());
+        assert!(engine.evaluate(r#"SPLICEAI[*].DS_AG > 0.5"#, row).unwrap());
+        assert!(
+            engine
+                .evaluate(r#"SPLICEAI[1].ALLELE == "T""#, row)
+                .unwrap()
+        );
+        assert!(engine.check_filter("HITS[0].Source").is_empty());
+
+        for delimiters in [
+            Delimiters::new('|', '|'),
+            Delimiters::new(';', '|'),
+            Delimiters::new(',', '='),
+        ] {
+            assert!(matches!(
+                engine.register_structured_field("X", &["A"], delimiters),
+                Err(VcfFilterError::ConfigError(_))
+            ));
         }
+        assert!(engine.register_structured_field("X", &[], '|').is_err());
     }
 
     #[test]
-    fn test_doctest_scenario() {
-        // This matches the doctest example exactly
-        let header = r#"
-##INFO=<ID=DP,Number=1,Type=Integer,Description="Total depth">
-##INFO=<ID=ANN,Number=.,Type=String,Description="Functional annotations: 'Allele | Annotation | Annotation_Impact | Gene_Name | Gene_ID'">
-"#;
+    fn test_fileformat_version_rules() {
+        let header = r#"##fileformat=VCFv4.1
+##INFO=<ID=AD,Number=R,Type=Integer,Description="Allelic depths">
+##INFO=<ID=NOTE,Number=1,Type=String,Description="Note">
+##FORMAT=<ID=AD,Number=R,Type=Integer,Description="Allelic depths">"#;
         let engine = FilterEngine::new(header).unwrap();
+        assert_eq!(engine.vcf_version(), Some(VcfVersion::new(4, 1)));
+        let messages: Vec<String> = engine
+            .header_diagnostics()
+            .into_iter()
+            .map(|d| d.message)
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "##FORMAT AD declares Number=R, which VCFv4.1 does not define",
+                "##INFO AD declares Number=R, which VCFv4.1 does not define",
+            ]
+        );
 
-        // Check that ANN subfields were parsed
-        let ann_field = engine.info_map().get("ANN").unwrap();
-        let subfields = ann_field.subfields.as_ref().unwrap();
-        assert_eq!(subfields[0], "Allele");
-        assert_eq!(subfields[1], "Annotation");
-        assert_eq!(subfields[2], "Annotation_Impact");
-        assert_eq!(subfields[3], "Gene_Name");
-        assert_eq!(subfields[4], "Gene_ID");
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\tAD=3,4;NOTE=50%25";
+        assert!(
+            engine
+                .evaluate(r#"AD[1] == 4 && NOTE == "50%25""#, row)
+                .unwrap()
+        );
 
-        let row = "chr1\t100\t.\tA\tG\t50\tPASS\tDP=30;ANN=G|missense|HIGH|BRCA1|ENSG123";
+        // Explicit options and the builder override the version's default
+        let decoding = FilterEngine::builder(header)
+            .parse_options(ParseOptions {
+                percent_decoding: Some(true),
+                ..ParseOptions::default()
+            })
+            .build()
+            .unwrap();
+        assert!(decoding.evaluate(r#"NOTE == "50%""#, row).unwrap());
+        let built = FilterEngine::builder(header).build().unwrap();
+        assert_eq!(built.parse_options().percent_decoding, Some(false));
 
-        assert!(engine.evaluate("QUAL > 30", row).unwrap());
-        assert!(engine.evaluate(r#"FILTER == "PASS""#, row).unwrap());
-        assert!(engine.evaluate("DP >= 30", row).unwrap());
+        let unversioned = FilterEngine::new("").unwrap();
+        assert_eq!(unversioned.vcf_version(), None);
+        assert!(unversioned.header_diagnostics().is_empty());
+        assert!(unversioned.evaluate(r#"NOTE == "50%""#, row).unwrap());
+    }
+
+    #[test]
+    fn test_from_headers() {
+        let shard1 = "##FILTER=<ID=q10,Description=\"Low quality\">\n\
+                      ##contig=<ID=chr1,length=1000>\n\
+                      ##INFO=<ID=DP,Number=1,Type=Integer,Description=\"Depth\">\n\
+                      ##FORMAT=<ID=GQ,Number=1,Type=Integer,Description=\"Genotype quality\">\n\
+                      #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\ta";
+        let shard2 = "##FILTER=<ID=s50,Description=\"Few samples\">\n\
+                      ##contig=<ID=chr2,length=2000>\n\
+                      ##INFO=<ID=DP,Number=1,Type=Integer,Description=\"Read depth\">\n\
+                      ##INFO=<ID=AF,Number=A,Type=Float,Description=\"Allele frequency\">\n\
+                      ##FORMAT=<ID=GQ,Number=1,Type=Float,Description=\"Genotype quality\">\n\
+                      #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tb";
+        let (engine, warnings) = FilterEngine::from_headers(&[shard1, shard2]).unwrap();
+
+        let filters: Vec<&str> = engine.filters().iter().map(|f| f.id.as_str()).collect();
+        assert_eq!(filters, ["q10", "s50"]);
+        assert_eq!(engine.contigs().rank("chr2"), Some(2));
+        assert_eq!(engine.format_map()["GQ"].field_type, InfoType::Integer);
+        let messages: Vec<&str> = warnings.iter().map(|w| w.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "##FORMAT GQ is declared Number=1,Type=Integer and Number=1,Type=Float; keeping the first",
+                "samples [b] differ from the first header's [a]",
+            ]
+        );
+        let row = "chr2\t100\t.\tA\tG\t50\tPASS\tAF=0.5;DP=10";
+        assert!(engine.evaluate("AF[0] == 0.5 && DP == 10", row).unwrap());
+
+        assert!(matches!(
+            FilterEngine::from_headers(&[]),
+            Err(VcfFilterError::ConfigError(_))
+        ));
+    }
+
+    #[test]
+    fn test_aligned_prefix_fields() {
+        let header = r#"##INFO=<ID=dbNSFP_SIFT_pred,Number=A,Type=String,Description="SIFT">
+##INFO=<ID=dbNSFP_CADD_phred,Number=A,Type=Float,Description="CADD">"#;
+        let engine = FilterEngine::builder(header)
+            .strict(true)
+            .aligned_prefix("dbNSFP_")
+            .build()
+            .unwrap();
+        assert_eq!(
+            engine.info_map()["dbNSFP_SIFT_pred"].number,
+            InfoNumber::Variable
+        );
+
+        // Three transcripts on a single-ALT record are not a Number=A mismatch
+        let row =
+            "chr1\t100\t.\tA\tG\t50\tPASS\tdbNSFP_SIFT_pred=T,.,D;dbNSFP_CADD_phred=12.5,.,31";
         assert!(
             engine
-                .evaluate(r#"ANN[0].Gene_Name == "BRCA1""#, row)
+                .evaluate(r#"dbNSFP_SIFT_pred[*] == "D""#, row)
                 .unwrap()
         );
+        assert!(
+            engine
+                .evaluate(
+                    r#"dbNSFP_SIFT_pred[2] == "D" && dbNSFP_CADD_phred[2] > 30"#,
+                    row
+                )
+                .unwrap()
+        );
+        assert!(!engine.evaluate("dbNSFP_CADD_phred[1] > 0", row).unwrap());
+    }
+
+    #[test]
+    fn test_undeclared_format_lists() {
+        let engine = FilterEngine::new("").unwrap();
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\t.\tGT:AD:PL\t0/1:20,8:45,0,120";
+        assert!(engine.evaluate("AD[1] > 5", row).unwrap());
+        assert!(engine.evaluate("min(PL) == 0 && PL[2] > 100", row).unwrap());
+        assert!(engine.evaluate("vaf() > 0.25", row).unwrap());
+    }
+
+    #[test]
+    fn test_spanning_deletion_alleles() {
+        let header = r#"##INFO=<ID=AF,Number=A,Type=Float,Description="Allele frequency">"#;
+        let engine = FilterEngine::new(header).unwrap();
+        let row = "chr1\t101\t.\tA\tG,*\t50\tPASS\tAF=0.001,0.4";
+        // The `*` allele's AF is not tested per allele
+        assert!(!engine.evaluate("AF[alt] > 0.1", row).unwrap());
+        assert!(engine.evaluate("AF[alt] < 0.01", row).unwrap());
+        assert_eq!(
+            engine.evaluate_value("AF[alt]", row).unwrap(),
+            Value::Array(vec![Value::Number(0.001)])
+        );
+        assert_eq!(
+            engine.evaluate_value("alt_count()", row).unwrap(),
+            Value::Number(1.0)
+        );
+        assert!(engine.evaluate("is_snp() && !is_indel()", row).unwrap());
+
+        let star_only = "chr1\t101\t.\tA\t*\t50\tPASS\tAF=0.4";
+        assert!(
+            !engine
+                .evaluate("is_snp() || is_indel()", star_only)
+                .unwrap()
+        );
+        assert!(!engine.evaluate("AF[alt] > 0.1", star_only).unwrap());
+        assert!(engine.evaluate("QUAL > 30", star_only).unwrap());
+
+        let dropping = FilterEngine::builder(header)
+            .drop_spanning_deletions(true)
+            .build()
+            .unwrap();
+        let compiled = dropping.compile("QUAL > 30").unwrap();
+        assert!(!compiled.matches(star_only).unwrap());
+        assert!(!dropping.evaluate("QUAL > 30", star_only).unwrap());
+        assert!(compiled.matches(row).unwrap());
+    }
+
+    #[test]
+    fn test_allele_modes() {
+        let header = r#"##INFO=<ID=AF,Number=A,Type=Float,Description="Allele frequency">
+##INFO=<ID=AD,Number=R,Type=Integer,Description="Allelic depths">"#;
+        let row = "chr1\t100\t.\tA\tG,T,*\t50\tPASS\tAF=0.2,0.05,0.4;AD=10,8,6,1";
+        let site = FilterEngine::new(header).unwrap();
+        let any = FilterEngine::builder(header)
+            .allele_mode(AlleleMode::Any)
+            .build()
+            .unwrap();
+        let all = FilterEngine::new(header)
+            .unwrap()
+            .with_allele_mode(AlleleMode::All);
+        assert_eq!(site.allele_mode(), AlleleMode::Site);
+        assert_eq!(any.allele_mode(), AlleleMode::Any);
+
+        // (filter, site, any, all); `*` is never evaluated
+        for (filter, expected) in [
+            (r#"ALT == "T""#, [true, true, false]),
+            (r#"ALT == "*""#, [true, false, false]),
+            ("AF[alt] > 0.01", [true, true, true]),
+            ("AF[alt] > 0.1 && AD[alt] > 5", [true, true, false]),
+            // Site mode matches ALT and AF[alt] against different alleles
+            (r#"ALT == "G" && AF[alt] < 0.1"#, [true, false, false]),
+            ("QUAL > 30", [true, true, true]),
+        ] {
+            for (engine, expected) in [&site, &any, &all].into_iter().zip(expected) {
+                assert_eq!(
+                    engine.evaluate(filter, row).unwrap(),
+                    expected,
+                    "{}",
+                    filter
+                );
+                let compiled = engine.compile(filter).unwrap();
+                assert_eq!(compiled.matches(row).unwrap(), expected, "{}", filter);
+                let expr = engine.parse_filter(filter).unwrap();
+                let parsed = engine.parse_row(row).unwrap();
+                assert_eq!(
+                    engine.explain_parsed(&expr, &parsed).unwrap().passed(),
+                    expected,
+                    "{}",
+                    filter
+                );
+            }
+        }
+
+        assert_eq!(
+            all.evaluate_value("ALT", row).unwrap(),
+            Value::Array(vec![Value::String("G".into()), Value::String("T".into())])
+        );
+        let no_alt = "chr1\t100\t.\tA\t.\t50\tPASS\t.";
+        assert!(!all.evaluate("QUAL > 30", no_alt).unwrap());
+        assert!(site.evaluate("QUAL > 30", no_alt).unwrap());
+
+        // Three-valued: a failing allele decides All, an unknown one leaves it unknown
+        let all = all.with_three_valued(true);
+        let partial = "chr1\t100\t.\tA\tG,T\t50\tPASS\tAF=0.2,.";
+        assert_eq!(
+            all.evaluate_outcome("AF[alt] > 0.1", partial).unwrap(),
+            Outcome::Unknown
+        );
+        assert_eq!(
+            all.evaluate_outcome("AF[alt] > 0.5", partial).unwrap(),
+            Outcome::Fail
+        );
     }
 }
@@ -0,0 +1,224 @@
+//! Sandbox limits for untrusted filter expressions.
+//!
+//! Services that accept filter strings from users can bound the size and
+//! nesting of expressions and the time spent evaluating each row, so that
+//! pathological inputs cannot stall a pipeline.
+//!
+//! The filter language has no regex operator (`contains` is a plain
+//! substring test), so there is no pattern-size or backtracking limit.
+
+use std::time::Duration;
+
+use crate::error::{Result, VcfFilterError};
+use crate::filter::Expr;
+
+/// Configurable resource limits. `None` disables a limit.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EvalLimits {
+    /// Maximum length of a filter string in bytes.
+    pub max_filter_len: Option<usize>,
+    /// Maximum nesting depth of the expression (and of brackets and prefix
+    /// operators in the filter text).
+    pub max_depth: Option<usize>,
+    /// Maximum number of nodes in the expression tree.
+    pub max_nodes: Option<usize>,
+    /// Maximum nesting of built-in and registered function calls during evaluation.
+    pub max_call_depth: Option<usize>,
+    /// Wall-clock budget for evaluating a filter against one row.
+    pub time_budget: Option<Duration>,
+}
+
+impl EvalLimits {
+    /// Limits suitable for filters submitted by untrusted users.
+    pub fn untrusted() -> Self {
+        Self {
+            max_filter_len: Some(16 * 1024),
+            max_depth: Some(64),
+            max_nodes: Some(2048),
+            max_call_depth: Some(16),
+            time_budget: Some(Duration::from_millis(50)),
+        }
+    }
+
+    /// Check a filter string before it is handed to the parser.
+    ///
+    /// Nesting of brackets and runs of prefix operators (`!!!x`) is checked
+    /// here because deeply nested input can exhaust the stack of the parser
+    /// itself, or of the recursive passes over the parsed expression.
+    pub fn check_filter_text(&self, filter: &str) -> Result<()> {
+        if let Some(max) = self.max_filter_len
+            && filter.len() > max
+        {
+            return Err(VcfFilterError::LimitExceeded(format!(
+                "filter length {} exceeds maximum of {}",
+                filter.len(),
+                max
+            )));
+        }
+
+        if let Some(max) = self.max_depth {
+            let depth = nesting_depth(filter);
+            if depth > max {
+                return Err(VcfFilterError::LimitExceeded(format!(
+                    "filter nesting depth {} exceeds maximum of {}",
+                    depth, max
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check the size and depth of a parsed expression.
+    pub fn check_expr(&self, expr: &Expr) -> Result<()> {
+        if let Some(max) = self.max_depth {
+            let depth = expr_depth(expr);
+            if depth > max {
+                return Err(VcfFilterError::LimitExceeded(format!(
+                    "expression depth {} exceeds maximum of {}",
+                    depth, max
+                )));
+            }
+        }
+
+        if let Some(max) = self.max_nodes {
+            let nodes = expr_nodes(expr);
+            if nodes > max {
+                return Err(VcfFilterError::LimitExceeded(format!(
+                    "expression has {} nodes, maximum is {}",
+                    nodes, max
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Maximum nesting of `(`, `[` and prefix operators outside string literals.
+///
+/// Each `!` (other than in `!=`), and each `-` where an operand is expected,
+/// nests what follows it one level deeper, so `!!(!DP)` has depth 4.
+fn nesting_depth(filter: &str) -> usize {
+    // Depth of the enclosing brackets, and of those outside them
+    let mut base = 0usize;
+    let mut enclosing = Vec::new();
+    // Prefix operators applying to the operand being read
+    let mut prefix = 0usize;
+    let mut max_depth = 0usize;
+    let mut in_string = false;
+    // The previous significant character, to tell a prefix `-` from subtraction
+    let mut previous = None;
+
+    let mut chars = filter.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_string {
+            in_string = c != '"';
+            continue;
+        }
+        let operand_expected = previous.is_none_or(|p| "([,!+-*/<>=&|".contains(p));
+        match c {
+            '"' => {
+                in_string = true;
+                prefix = 0;
+            }
+            '!' if chars.peek() != Some(&'=') => prefix += 1,
+            '-' if operand_expected => prefix += 1,
+            '(' | '[' => {
+                enclosing.push(base);
+                base += prefix + 1;
+                prefix = 0;
+            }
+            ')' | ']' => {
+                base = enclosing.pop().unwrap_or(0);
+                prefix = 0;
+            }
+            c if c.is_whitespace() => continue,
+            _ => prefix = 0,
+        }
+        max_depth = max_depth.max(base + prefix);
+        previous = Some(c);
+    }
+
+    max_depth
+}
+
+/// Depth of the expression tree (a leaf has depth 1).
+fn expr_depth(expr: &Expr) -> usize {
+    1 + expr
+        .children()
+        .into_iter()
+        .map(expr_depth)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Number of nodes in the expression tree.
+fn expr_nodes(expr: &Expr) -> usize {
+    1 + expr.children().into_iter().map(expr_nodes).sum::<usize>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::parse_filter;
+
+    #[test]
+    fn test_default_limits_allow_everything() {
+        let limits = EvalLimits::default();
+        let filter = "((((QUAL > 1))))";
+        assert!(limits.check_filter_text(filter).is_ok());
+        assert!(limits.check_expr(&parse_filter(filter).unwrap()).is_ok());
+    }
+
+    #[test]
+    fn test_filter_length_limit() {
+        let limits = EvalLimits {
+            max_filter_len: Some(8),
+            ..Default::default()
+        };
+        assert!(limits.check_filter_text("QUAL > 1").is_ok());
+        assert!(limits.check_filter_text("QUAL > 10").is_err());
+    }
+
+    #[test]
+    fn test_bracket_depth_ignores_strings() {
+        assert_eq!(nesting_depth(r#"((A == "((("))"#), 2);
+        assert_eq!(nesting_depth("ANN[0].X == 1"), 1);
+    }
+
+    #[test]
+    fn test_prefix_operators_count_as_nesting() {
+        assert_eq!(nesting_depth("!!(!DP)"), 4);
+        assert_eq!(nesting_depth("!DP && !(QUAL > 1)"), 2);
+        assert_eq!(nesting_depth(r#"DP != 1 && A == "!!!""#), 0);
+        assert_eq!(nesting_depth("DP - 1 > -2"), 1);
+        assert_eq!(nesting_depth(&"!".repeat(5000)), 5000);
+
+        let limits = EvalLimits::untrusted();
+        let filter = format!("{}QUAL > 1", "!".repeat(5000));
+        assert!(matches!(
+            limits.check_filter_text(&filter),
+            Err(VcfFilterError::LimitExceeded(_))
+        ));
+    }
+
+    #[test]
+    fn test_expression_depth_and_nodes() {
+        let expr = parse_filter("QUAL > 1 && DP > 2").unwrap();
+        assert_eq!(expr_depth(&expr), 3);
+        assert_eq!(expr_nodes(&expr), 7);
+
+        let limits = EvalLimits {
+            max_depth: Some(2),
+            ..Default::default()
+        };
+        assert!(limits.check_expr(&expr).is_err());
+
+        let limits = EvalLimits {
+            max_nodes: Some(6),
+            ..Default::default()
+        };
+        assert!(limits.check_expr(&expr).is_err());
+    }
+}
@@ -0,0 +1,219 @@
+//! Value lists for set membership: `ANN[*].Gene_Name in_file("acmg.txt")`.
+//!
+//! Gene panels with hundreds of entries can't reasonably be inlined into a
+//! filter. `in_file("path")` names a list of values instead, and
+//! `x in_file("path")` is shorthand for `x in in_file("path")`. A list file
+//! holds one value per line; surrounding whitespace is trimmed, and blank
+//! lines and `#` comments are ignored:
+//!
+//! ```text
+//! # ACMG secondary findings
+//! BRCA1
+//! BRCA2
+//! ```
+//!
+//! Applications can also register lists up front under a name, which takes
+//! precedence over the file system:
+//!
+//! ```rust
+//! use vcf_filter::FilterEngine;
+//!
+//! let engine = FilterEngine::new("")
+//!     .unwrap()
+//!     .with_list("panel", ["BRCA1", "BRCA2"]);
+//!
+//! let row = "chr1\t100\t.\tA\tG\t50\tPASS\tGENE=BRCA2";
+//! assert!(engine.evaluate(r#"GENE in_file("panel")"#, row).unwrap());
+//! ```
+//!
+//! The engine replaces each `in_file(...)` call with a list literal when it
+//! parses a filter, so the file is read once rather than per row. A file
+//! that cannot be read is a `ConfigError`.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::error::{Result, VcfFilterError};
+use crate::filter::{AccessPart, Expr};
+use crate::value::Value;
+
+/// Name of the list membership function.
+const IN_FILE: &str = "in_file";
+
+/// Named value lists that take precedence over `in_file` paths.
+#[derive(Debug, Clone, Default)]
+pub struct ListLibrary {
+    lists: HashMap<String, Arc<Vec<String>>>,
+}
+
+impl ListLibrary {
+    /// Create an empty library.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a list under `name`, replacing any previous definition.
+    pub fn define<I, S>(&mut self, name: &str, values: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let values = values.into_iter().map(Into::into).collect();
+        self.lists.insert(name.to_string(), Arc::new(values));
+    }
+
+    /// Read a list file and register it under `name`.
+    pub fn load(&mut self, name: &str, path: impl AsRef<Path>) -> Result<()> {
+        let values = read_list(path.as_ref())?;
+        self.lists.insert(name.to_string(), Arc::new(values));
+        Ok(())
+    }
+
+    /// Get a registered list by name.
+    pub fn get(&self, name: &str) -> Option<&[String]> {
+        self.lists.get(name).map(|values| values.as_slice())
+    }
+
+    /// Iterate over the registered list names.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.lists.keys().map(String::as_str)
+    }
+
+    /// Get the values `in_file(name)` refers to: a registered list, or else a file.
+    pub fn resolve(&self, name: &str) -> Result<Vec<String>> {
+        match self.get(name) {
+            Some(values) => Ok(values.to_vec()),
+            None => read_list(Path::new(name)),
+        }
+    }
+
+    /// Replace every `in_file("name")` call in an expression with a list literal.
+    pub fn expand(&self, expr: &Expr) -> Result<Expr> {
+        let expand_all = |exprs: &[Expr]| {
+            exprs
+                .iter()
+                .map(|e| self.expand(e))
+                .collect::<Result<Vec<_>>>()
+        };
+        let boxed = |e: &Expr| self.expand(e).map(Box::new);
+
+        Ok(match expr {
+            Expr::Call(name, args) if name == IN_FILE => {
+                let values = self.resolve(list_name(args)?)?;
+                Expr::List(values.into_iter().map(Expr::String).collect())
+            }
+            Expr::Var(parts) => Expr::Var(self.expand_parts(parts)?),
+            Expr::Exists(parts) => Expr::Exists(self.expand_parts(parts)?),
+            Expr::Binary(left, op, right) => Expr::Binary(boxed(left)?, op.clone(), boxed(right)?),
+            Expr::Unary(op, inner) => Expr::Unary(op.clone(), boxed(inner)?),
+            Expr::All(inner) => Expr::All(boxed(inner)?),
+            Expr::List(items) => Expr::List(expand_all(items)?),
+            Expr::Call(name, args) => Expr::Call(name.clone(), expand_all(args)?),
+            Expr::Let(name, value, body) => Expr::Let(name.clone(), boxed(value)?, boxed(body)?),
+            Expr::Preset(_) | Expr::Number(_) | Expr::String(_) | Expr::Bool(_) => expr.clone(),
+        })
+    }
+
+    /// Expand calls inside `[?(...)]` predicates of an access path.
+    fn expand_parts(&self, parts: &[AccessPart]) -> Result<Vec<AccessPart>> {
+        parts
+            .iter()
+            .map(|part| match part {
+                AccessPart::Predicate(pred) => {
+                    Ok(AccessPart::Predicate(Box::new(self.expand(pred)?)))
+                }
+                other => Ok(other.clone()),
+            })
+            .collect()
+    }
+}
+
+/// Get the list name from `in_file` arguments, which must be one string literal.
+pub(crate) fn list_name(args: &[Expr]) -> Result<&str> {
+    match args {
+        [Expr::String(name)] => Ok(name),
+        _ => Err(VcfFilterError::ConfigError(
+            "in_file() expects a single string literal".to_string(),
+        )),
+    }
+}
+
+/// Evaluate an unexpanded `in_file("path")` call by reading the file.
+///
+/// Used when an expression bypasses the engine's parser; every call reads
+/// the file again.
+pub(crate) fn in_file(args: &[Expr]) -> Result<Value> {
+    let values = read_list(Path::new(list_name(args)?))?;
+    Ok(Value::Array(
+        values.into_iter().map(Value::String).collect(),
+    ))
+}
+
+/// Read one value per line, skipping blank lines and `#` comments.
+fn read_list(path: &Path) -> Result<Vec<String>> {
+    let text = fs::read_to_string(path).map_err(|e| {
+        VcfFilterError::ConfigError(format!("in_file(\"{}\"): {}", path.display(), e))
+    })?;
+    Ok(text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::parse_filter;
+
+    fn parse(filter: &str) -> Expr {
+        parse_filter(filter).unwrap()
+    }
+
+    #[test]
+    fn test_expand_registered_list() {
+        let mut library = ListLibrary::new();
+        library.define("panel", ["BRCA1", "BRCA2"]);
+        assert_eq!(
+            library
+                .expand(&parse(
+                    r#"ANN[?(Gene_Name in_file("panel"))].Annotation == "x""#
+                ))
+                .unwrap(),
+            parse(r#"ANN[?(Gene_Name in ["BRCA1", "BRCA2"])].Annotation == "x""#)
+        );
+    }
+
+    #[test]
+    fn test_read_list_file() {
+        let path = std::env::temp_dir().join(format!("vcf-filter-list-{}.txt", std::process::id()));
+        fs::write(&path, "# panel\nBRCA1\n\n  TP53  \n").unwrap();
+        let name = path.to_str().unwrap();
+
+        let mut library = ListLibrary::new();
+        library.load("panel", &path).unwrap();
+        assert_eq!(library.get("panel").unwrap(), ["BRCA1", "TP53"]);
+        assert_eq!(
+            in_file(&[Expr::String(name.to_string())]).unwrap(),
+            Value::Array(vec![
+                Value::String("BRCA1".to_string()),
+                Value::String("TP53".to_string())
+            ])
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_lists() {
+        let library = ListLibrary::new();
+        assert!(matches!(
+            library.expand(&parse(r#"GENE in_file("/nonexistent/genes.txt")"#)),
+            Err(VcfFilterError::ConfigError(_))
+        ));
+        assert!(library.expand(&parse("GENE in_file(GENE)")).is_err());
+        assert!(library.expand(&parse("GENE in in_file()")).is_err());
+    }
+}
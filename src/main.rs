@@ -1,21 +1,85 @@
 //! Command-line VCF filter tool.
 //!
-//! Usage: vcf-filter -filter <expression>
+//! Usage:
+//!   vcf-filter -filter <expression> [--pedigree <family.ped>] [--stats] [--select <fields>]
+//!              [--annotate <ID=EXPR>]... [--soft-filter <name> [--mode +|x|+x]]
+//!   vcf-filter filter -f <expression> [-i <in.vcf.gz>] [-o <out.vcf.gz>] [-O v|z]
+//!              [--regions <chr1:1-1000,chr2>] [--targets-file <targets.bed>] [--threads <n>]
+//!              [options]
+//!   vcf-filter batch <jobs.tsv> [--config <presets.ini>] [--threads <n>]
+//!   vcf-filter validate
+//!
+//! Inputs (files or stdin) may be gzip- or bgzip-compressed; outputs named
+//! `*.gz` or `*.bgz`, or any output with `-O z`, are written as bgzip.
+//! `--regions` reads only the records in those regions through the input's
+//! `.tbi` or `.csi` index; `--targets-file` streams any input and keeps only
+//! the records overlapping a BED file's intervals.
 //!
 //! Example:
-//!   zcat test.vcf.gz | vcf-filter -filter "QUAL > 30 && exists(CLNSIG)" | bgzip -c > out.vcf.gz
+//!   vcf-filter filter -i test.vcf.gz -o out.vcf.gz -f "QUAL > 30 && exists(CLNSIG)"
 
-use std::io::{self, BufRead, Write};
+use std::io::{self, BufRead, BufReader, Write};
 use vcf_filter::FilterEngine;
+use vcf_filter::batch;
+use vcf_filter::compression::{self, BgzfWriter, Compression};
+use vcf_filter::config::FilterConfig;
+use vcf_filter::header::VcfHeader;
+use vcf_filter::intervals::{Region, RegionSet};
+use vcf_filter::pedigree::{INHERITANCE_HEADER, Trio, write_inheritance};
+use vcf_filter::soft_filter::SoftFilter;
+use vcf_filter::tabix;
+use vcf_filter::validate::Severity;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// Options for filtering stdin to stdout.
+struct FilterArgs {
+    filter: String,
+    /// VCF file to read instead of stdin.
+    input: Option<String>,
+    /// VCF file to write instead of stdout.
+    output: Option<String>,
+    /// Output format from `-O`; otherwise taken from the output's name.
+    output_type: Option<Compression>,
+    /// Regions to read from the indexed input instead of every record.
+    regions: Option<Vec<Region>>,
+    /// BED file of intervals outside which records are dropped.
+    targets_file: Option<String>,
+    /// Evaluation threads; all cores when unset.
+    #[cfg_attr(not(feature = "parallel"), allow(dead_code))]
+    threads: Option<usize>,
+    pedigree: Option<String>,
+    stats: bool,
+    /// Fields to write as TSV columns instead of the passing VCF lines.
+    select: Option<Vec<String>>,
+    /// `ID=EXPR` definitions of INFO fields to add to passing lines.
+    annotate: Vec<String>,
+    /// FILTER name to tag failing lines with instead of dropping them.
+    soft_filter: Option<String>,
+    /// How `soft_filter` treats existing filters, as in `bcftools filter -m`.
+    mode: Option<String>,
+}
+
+/// What the command line asked for.
+enum Command {
+    /// Filter stdin to stdout with a single expression.
+    Filter(Box<FilterArgs>),
+    /// Run every job in a manifest file.
+    Batch {
+        manifest: String,
+        config: Option<String>,
+        threads: usize,
+    },
+    /// Check stdin's data rows against its header.
+    Validate,
+}
+
 fn main() {
     let args: Vec<String> = std::env::args().collect();
 
     // Parse arguments
-    let filter_expr = match parse_args(&args) {
-        Ok(Some(expr)) => expr,
+    let command = match parse_args(&args) {
+        Ok(Some(command)) => command,
         Ok(None) => return, // Version was printed, exit successfully
         Err(msg) => {
             eprintln!("{}", msg);
@@ -23,22 +87,51 @@ fn main() {
         }
     };
 
-    if let Err(e) = run_filter(&filter_expr) {
+    let result = match command {
+        Command::Filter(args) => run_filter(&args),
+        Command::Batch {
+            manifest,
+            config,
+            threads,
+        } => run_batch(&manifest, config.as_deref(), threads),
+        Command::Validate => run_validate(),
+    };
+
+    if let Err(e) = result {
         eprintln!("Error: {}", e);
         std::process::exit(1);
     }
 }
 
-fn parse_args(args: &[String]) -> Result<Option<String>, String> {
+fn parse_args(args: &[String]) -> Result<Option<Command>, String> {
     if args.len() < 2 {
         return Err(format!(
-            "Usage: {} -filter <expression>\n\n\
+            "Usage: {} -filter <expression>\n       \
+             {} filter -f <expression> [-i <input>] [-o <output>] [-O v|z] [--regions <list>]\n       \
+             {} batch <jobs.tsv> [--config <file>] [--threads <n>]\n       \
+             {} validate < in.vcf\n\n\
              Options:\n  \
              -filter, --filter <expr>  Filter expression\n  \
+             -f <expr>                 Filter expression (filter subcommand)\n  \
+             -i, --input <file>        Read this VCF instead of stdin (gzip/bgzip detected)\n  \
+             -o, --output <file>       Write this VCF instead of stdout (bgzip if *.gz)\n  \
+             -O, --output-type <v|z>   Write plain (v) or bgzip (z) VCF, even to stdout\n  \
+             --regions <list>          Read only these regions (chr1:1-1000,chr2) via -i's index\n  \
+             --targets-file <bed>      Keep only records overlapping these intervals (no index)\n  \
+             --threads <n>             Evaluation threads (default: available CPUs)\n  \
+             --pedigree <file>         PED trio; adds INHERITANCE to output INFO\n  \
+             --stats                   Report rows rejected by each && clause\n  \
+             --select <fields>         Write these comma-separated fields as TSV\n  \
+             --annotate <ID=EXPR>      Add a computed INFO field (repeatable)\n  \
+             --soft-filter <name>      Keep failing rows, setting FILTER to <name>\n  \
+             -m, --mode <+|x|+x>       Soft filter: + appends to FILTER, x resets passing rows\n  \
              -V, --version             Print version\n\n\
+             Batch options:\n  \
+             --config <file>           Preset config for @name filters\n  \
+             --threads <n>             Worker threads (default: available CPUs)\n\n\
              Example:\n  \
-             zcat test.vcf.gz | {} -filter \"QUAL > 30 && exists(CLNSIG)\" | bgzip -c > out.vcf.gz",
-            args[0], args[0]
+             {} filter -i test.vcf.gz -o out.vcf.gz -f \"QUAL > 30 && exists(CLNSIG)\"",
+            args[0], args[0], args[0], args[0], args[0]
         ));
     }
 
@@ -48,55 +141,500 @@ fn parse_args(args: &[String]) -> Result<Option<String>, String> {
             Ok(None)
         }
         "-filter" | "--filter" => {
-            if args.len() < 3 {
-                return Err("Missing filter expression after -filter".to_string());
-            }
-            Ok(Some(args[2].clone()))
+            let filter = args
+                .get(2)
+                .ok_or("Missing filter expression after -filter")?;
+            parse_filter_args(Some(filter.clone()), &args[3..])
+                .map(|args| Some(Command::Filter(Box::new(args))))
+        }
+        "filter" => {
+            parse_filter_args(None, &args[2..]).map(|args| Some(Command::Filter(Box::new(args))))
         }
+        "batch" => parse_batch_args(&args[2..]).map(Some),
+        "validate" => match args.get(2) {
+            Some(arg) => Err(format!("Unexpected validate argument: {}", arg)),
+            None => Ok(Some(Command::Validate)),
+        },
         _ => Err(format!(
-            "Unknown option: {}. Use -filter <expression> or --version",
+            "Unknown option: {}. Use -filter <expression>, filter -f <expression>, batch <jobs.tsv>, validate or --version",
             args[1]
         )),
     }
 }
 
-fn run_filter(filter_expr: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let stdin = io::stdin();
-    let stdout = io::stdout();
-    let mut stdout_lock = stdout.lock();
+/// Parse the options of a filter run; `filter` is the expression given after `-filter`.
+fn parse_filter_args(mut filter: Option<String>, args: &[String]) -> Result<FilterArgs, String> {
+    let mut input = None;
+    let mut output = None;
+    let mut output_type = None;
+    let mut regions = None;
+    let mut targets_file = None;
+    let mut threads = None;
+    let mut pedigree = None;
+    let mut stats = false;
+    let mut select = None;
+    let mut annotate = Vec::new();
+    let mut soft_filter = None;
+    let mut mode = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-f" | "--filter" if filter.is_none() => {
+                filter = Some(
+                    iter.next()
+                        .ok_or("Missing filter expression after -f")?
+                        .clone(),
+                );
+            }
+            "-i" | "--input" => {
+                input = Some(iter.next().ok_or("Missing file after --input")?.clone());
+            }
+            "-o" | "--output" => {
+                output = Some(iter.next().ok_or("Missing file after --output")?.clone());
+            }
+            "-O" | "--output-type" => {
+                output_type = Some(match iter.next().map(String::as_str) {
+                    Some("v") => Compression::Plain,
+                    Some("z") => Compression::Bgzip,
+                    _ => return Err("Expected v or z after --output-type".to_string()),
+                });
+            }
+            "--regions" => {
+                let list = iter.next().ok_or("Missing regions after --regions")?;
+                regions = Some(
+                    list.split(',')
+                        .filter(|region| !region.trim().is_empty())
+                        .map(Region::parse)
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(|e| e.to_string())?,
+                );
+            }
+            "--threads" => {
+                threads = Some(
+                    iter.next()
+                        .and_then(|n| n.parse().ok())
+                        .filter(|&n: &usize| n > 0)
+                        .ok_or("Expected a positive number after --threads")?,
+                );
+            }
+            "--targets-file" => {
+                targets_file = Some(
+                    iter.next()
+                        .ok_or("Missing BED file after --targets-file")?
+                        .clone(),
+                );
+            }
+            "--pedigree" => {
+                pedigree = Some(
+                    iter.next()
+                        .ok_or("Missing PED file after --pedigree")?
+                        .clone(),
+                );
+            }
+            "--stats" => stats = true,
+            "--select" => {
+                let fields = iter.next().ok_or("Missing fields after --select")?;
+                select = Some(split_fields(fields));
+            }
+            "--annotate" => {
+                annotate.push(
+                    iter.next()
+                        .ok_or("Missing ID=EXPR after --annotate")?
+                        .clone(),
+                );
+            }
+            "--soft-filter" => {
+                soft_filter = Some(
+                    iter.next()
+                        .ok_or("Missing FILTER name after --soft-filter")?
+                        .clone(),
+                );
+            }
+            "-m" | "--mode" => {
+                mode = Some(iter.next().ok_or("Missing mode after --mode")?.clone());
+            }
+            other => return Err(format!("Unexpected argument: {}", other)),
+        }
+    }
+    if mode.is_some() && soft_filter.is_none() {
+        return Err("--mode needs --soft-filter <name>".to_string());
+    }
+    if cfg!(not(feature = "parallel")) && threads.is_some_and(|n| n > 1) {
+        return Err("--threads needs vcf-filter built with the parallel feature".to_string());
+    }
+    if regions.is_some() && input.is_none() {
+        return Err("--regions needs an indexed input file; use -i <file>".to_string());
+    }
+    if soft_filter.is_some() && select.is_some() {
+        return Err("--soft-filter cannot be combined with --select".to_string());
+    }
+    Ok(FilterArgs {
+        filter: filter.ok_or("Missing filter expression; use -f <expression>")?,
+        input,
+        output,
+        output_type,
+        regions,
+        targets_file,
+        threads,
+        pedigree,
+        stats,
+        select,
+        annotate,
+        soft_filter,
+        mode,
+    })
+}
+
+/// Split a `--select` list on commas outside quotes, brackets and parentheses.
+fn split_fields(list: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0usize;
+    let mut quoted = false;
+    for c in list.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            '[' | '(' if !quoted => depth += 1,
+            ']' | ')' if !quoted => depth = depth.saturating_sub(1),
+            ',' if !quoted && depth == 0 => {
+                fields.push(current.trim().to_string());
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    fields.push(current.trim().to_string());
+    fields.retain(|field| !field.is_empty());
+    fields
+}
+
+fn parse_batch_args(args: &[String]) -> Result<Command, String> {
+    let mut manifest = None;
+    let mut config = None;
+    let mut threads = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--config" => {
+                config = Some(iter.next().ok_or("Missing file after --config")?.clone());
+            }
+            "--threads" => {
+                threads = iter
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .filter(|&n| n > 0)
+                    .ok_or("Expected a positive number after --threads")?;
+            }
+            _ if manifest.is_none() => manifest = Some(arg.clone()),
+            _ => return Err(format!("Unexpected batch argument: {}", arg)),
+        }
+    }
+
+    Ok(Command::Batch {
+        manifest: manifest.ok_or("Missing manifest file after batch")?,
+        config,
+        threads,
+    })
+}
+
+fn run_batch(
+    manifest: &str,
+    config: Option<&str>,
+    threads: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let jobs = batch::parse_manifest(&std::fs::read_to_string(manifest)?)?;
+    let config = match config {
+        Some(path) => Some(FilterConfig::parse(&std::fs::read_to_string(path)?)?),
+        None => None,
+    };
+
+    for summary in batch::run(&jobs, config.as_ref(), threads)? {
+        eprintln!(
+            "vcf-filter: {} -> {}: {}/{} variants passed filter",
+            summary.job.input.display(),
+            summary.job.output.display(),
+            summary.passed,
+            summary.total
+        );
+    }
+    Ok(())
+}
+
+fn run_filter(args: &FilterArgs) -> Result<(), Box<dyn std::error::Error>> {
+    // Creating the output would truncate the input before it is read
+    if let (Some(input), Some(output)) = (&args.input, &args.output)
+        && compression::resolve_path(input) == compression::resolve_path(output)
+    {
+        return Err(format!("output {} is the same file as the input", output).into());
+    }
+    let filter_expr = args.filter.as_str();
+    let trio = match &args.pedigree {
+        Some(path) => {
+            let trios = Trio::from_ped(&std::fs::read_to_string(path)?)?;
+            let trio = trios
+                .into_iter()
+                .next()
+                .ok_or("PED file has no child with both parents")?;
+            Some(trio)
+        }
+        None => None,
+    };
+    let targets = match &args.targets_file {
+        Some(path) => Some(RegionSet::read_bed(std::path::Path::new(path))?),
+        None => None,
+    };
+
+    let mut input: Box<dyn BufRead + Send> = match (&args.input, &args.regions) {
+        (Some(path), Some(regions)) => Box::new(tabix::open_regions(path, regions)?),
+        (Some(path), None) => compression::open_input(path)?,
+        (None, _) => compression::decompress(BufReader::new(io::stdin()))?,
+    };
+    let mut output: Box<dyn Write> = match (&args.output, args.output_type) {
+        (Some(path), Some(compression)) => compression::create_output_as(path, compression)?,
+        (Some(path), None) => compression::create_output(path)?,
+        (None, Some(Compression::Gzip | Compression::Bgzip)) => {
+            Box::new(BgzfWriter::new(io::stdout().lock()))
+        }
+        (None, _) => Box::new(io::stdout().lock()),
+    };
+
+    let (mut eng, header_lines) = FilterEngine::from_reader(&mut input)?;
+    if let Some(trio) = &trio {
+        eng = eng.with_pedigree(trio)?;
+    }
+    let compiled = eng.compile(filter_expr)?;
+    for diagnostic in eng
+        .header_diagnostics()
+        .into_iter()
+        .chain(eng.check_filter(filter_expr))
+    {
+        eprintln!("vcf-filter: {}", diagnostic);
+    }
+
+    let selected = match &args.select {
+        Some(fields) => Some(
+            fields
+                .iter()
+                .map(|field| eng.parse_filter(field))
+                .collect::<Result<Vec<_>, _>>()?,
+        ),
+        None => None,
+    };
+    let definitions: Vec<&str> = args.annotate.iter().map(String::as_str).collect();
+    let annotations = eng.annotations(&definitions)?;
+    let soft_filter = match &args.soft_filter {
+        Some(name) => {
+            let soft = SoftFilter::new(name)?
+                .with_description(&format!("Set if not true: {}", filter_expr));
+            // Like bcftools, replace existing filters unless --mode has `+`
+            Some(soft.with_mode(args.mode.as_deref().unwrap_or(""))?)
+        }
+        None => None,
+    };
+
+    if let Some(fields) = &args.select {
+        writeln!(output, "#{}", fields.join("\t"))?;
+    } else {
+        // FILTER, INHERITANCE and computed field declarations join their kind of line
+        let mut header = VcfHeader::from_lines(&header_lines);
+        if let Some(soft) = &soft_filter
+            && !eng.filters().iter().any(|filter| filter.id == soft.name())
+        {
+            header.add_meta_line(&soft.header_line());
+        }
+        if trio.is_some() {
+            header.add_meta_line(INHERITANCE_HEADER);
+        }
+        for line in annotations.header_lines() {
+            header.add_meta_line(&line);
+        }
+        writeln!(output, "{}", header)?;
+    }
+
+    // Rows are evaluated on all cores unless rows are rewritten, counted or targeted;
+    // input is read and decompressed on a thread of its own meanwhile
+    #[cfg(feature = "parallel")]
+    if args.threads != Some(1)
+        && trio.is_none()
+        && targets.is_none()
+        && !args.stats
+        && selected.is_none()
+        && args.annotate.is_empty()
+        && soft_filter.is_none()
+    {
+        let input = compression::read_ahead(input);
+        let (passed, total) = match args.threads {
+            Some(threads) => {
+                vcf_filter::parallel::filter_chunks_with(&compiled, input, &mut output, threads)?
+            }
+            None => vcf_filter::parallel::filter_chunks(&compiled, input, &mut output)?,
+        };
+        output.flush()?;
+        eprintln!("vcf-filter: {}/{} variants passed filter", passed, total);
+        return Ok(());
+    }
 
-    let mut header_lines = Vec::new();
-    let mut engine: Option<FilterEngine> = None;
     let mut passed = 0u64;
     let mut total = 0u64;
+    let mut clause_stats = if args.stats {
+        Some(eng.stats(filter_expr)?)
+    } else {
+        None
+    };
 
-    for line_result in stdin.lock().lines() {
+    for line_result in input.lines() {
         let line = line_result?;
 
         if line.starts_with('#') {
-            // Accumulate header lines
-            header_lines.push(line.clone());
-            writeln!(stdout_lock, "{}", line)?;
+            if selected.is_none() {
+                writeln!(output, "{}", line)?;
+            }
+            continue;
+        }
 
-            // When we hit #CHROM, we have the full header
-            if line.starts_with("#CHROM") {
-                let header_str = header_lines.join("\n");
-                engine = Some(FilterEngine::new(&header_str)?);
+        // Records outside the targets are dropped before they are counted
+        let on_target = match &targets {
+            Some(targets) => {
+                let row = eng.parse_row(&line).map_err(|e| quote_row(e, &line))?;
+                if !targets.overlaps(&row) {
+                    continue;
+                }
+                Some(row)
             }
-        } else {
-            // Data row
-            let eng = engine
-                .as_ref()
-                .ok_or("No VCF header found before data rows")?;
+            None => None,
+        };
 
-            total += 1;
-            if eng.evaluate(filter_expr, &line)? {
-                passed += 1;
-                writeln!(stdout_lock, "{}", line)?;
+        total += 1;
+        // Statistics need every row evaluated, so lines are not pre-screened
+        let screened_out = on_target.is_none()
+            && clause_stats.is_none()
+            && eng.quick_reject(&compiled, line.as_bytes()) == Some(false);
+        if screened_out && soft_filter.is_none() {
+            continue;
+        }
+        let row = match on_target {
+            Some(row) => row,
+            None => eng.parse_row(&line).map_err(|e| quote_row(e, &line))?,
+        };
+        let pass = !screened_out
+            && match &mut clause_stats {
+                Some(stats) => eng.evaluate_counted(stats, &row),
+                None => eng.evaluate_compiled(&compiled, &row),
+            }
+            .map_err(|e| quote_row(e, row.raw_line()))?;
+        if pass {
+            passed += 1;
+        }
+        // Soft filtering writes failing lines too, tagged in FILTER
+        if pass || soft_filter.is_some() {
+            if let Some(exprs) = &selected {
+                let values = eng.select_parsed(exprs, &row)?;
+                let columns: Vec<String> = values.iter().map(|v| v.to_vcf_string()).collect();
+                writeln!(output, "{}", columns.join("\t"))?;
+            } else {
+                let line = if trio.is_some() {
+                    write_inheritance(&line, eng.inheritance(&row))
+                } else {
+                    line
+                };
+                let line = if annotations.fields().is_empty() {
+                    line
+                } else {
+                    eng.annotate_parsed_line(&annotations, &line, &row)?
+                };
+                let line = match &soft_filter {
+                    Some(soft) => soft.apply(&line, pass),
+                    None => line,
+                };
+                writeln!(output, "{}", line)?;
             }
         }
     }
 
+    output.flush()?;
     eprintln!("vcf-filter: {}/{} variants passed filter", passed, total);
+    if let Some(stats) = clause_stats {
+        for clause in stats.clauses() {
+            eprintln!(
+                "vcf-filter: {} rejected {} variants ({} by this clause alone)",
+                clause.clause, clause.rejected, clause.rejected_alone
+            );
+        }
+    }
     Ok(())
 }
+
+/// Report every data row of stdin that does not conform to its header.
+///
+/// Diagnostics go to stdout, one per line, prefixed with the line number
+/// and the row's CHROM:POS; the run fails if any row has an error.
+fn run_validate() -> Result<(), Box<dyn std::error::Error>> {
+    let stdout = io::stdout();
+    let mut stdout_lock = stdout.lock();
+    let mut input = compression::decompress(BufReader::new(io::stdin()))?;
+    let (eng, header_lines) = FilterEngine::from_reader(&mut input)?;
+    for diagnostic in eng.header_diagnostics() {
+        writeln!(stdout_lock, "header: {}", diagnostic)?;
+    }
+
+    let mut total = 0u64;
+    let mut failed = 0u64;
+    let mut warnings = 0u64;
+    for (i, line_result) in input.lines().enumerate() {
+        let line = line_result?;
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        total += 1;
+        let number = header_lines.len() + i + 1;
+        let diagnostics = match eng.validate_row(&line) {
+            Ok(diagnostics) => diagnostics,
+            Err(e) => {
+                failed += 1;
+                writeln!(stdout_lock, "line {}: error: {}", number, e)?;
+                continue;
+            }
+        };
+        let mut columns = line.splitn(3, '\t');
+        let (chrom, pos) = (
+            columns.next().unwrap_or_default(),
+            columns.next().unwrap_or_default(),
+        );
+        for diagnostic in &diagnostics {
+            writeln!(
+                stdout_lock,
+                "line {}: {}:{}: {}",
+                number, chrom, pos, diagnostic
+            )?;
+        }
+        if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+            failed += 1;
+        }
+        warnings += diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Warning)
+            .count() as u64;
+    }
+
+    eprintln!(
+        "vcf-filter: {}/{} rows have errors, {} warnings",
+        failed, total, warnings
+    );
+    if failed > 0 {
+        return Err(format!("{} rows failed validation", failed).into());
+    }
+    Ok(())
+}
+
+/// Add the offending data line to a row error.
+fn quote_row(error: vcf_filter::VcfFilterError, line: &str) -> vcf_filter::VcfFilterError {
+    vcf_filter::VcfFilterError::InRow {
+        line: line.to_string(),
+        source: Box::new(error),
+    }
+}
@@ -0,0 +1,321 @@
+//! Engine configuration in one place: [`EngineOptions`] and [`FilterEngineBuilder`].
+//!
+//! Each behavior can also be set with the matching `FilterEngine::with_*`
+//! method; the builder collects them so an engine's configuration can be
+//! stored, passed around and applied in one step.
+//!
+//! ```rust
+//! use vcf_filter::FilterEngine;
+//! use vcf_filter::row::MissingValueMode;
+//!
+//! let header = "##INFO=<ID=CSQ,Number=.,Type=String,Description=\"VEP consequences\">\n\
+//!               #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tnormal\ttumor";
+//! let engine = FilterEngine::builder(header)
+//!     .strict(true)
+//!     .missing_values(MissingValueMode::Strict)
+//!     .case_insensitive(true)
+//!     .float_tolerance(1e-9)
+//!     .samples(["tumor"])
+//!     .structured_field("CSQ", ["Allele", "Consequence", "SYMBOL"])
+//!     .build()
+//!     .unwrap();
+//!
+//! let row = "chr1\t100\t.\tA\tG\t50\tPASS\tCSQ=G|missense_variant|brca1\tGT\t0/0\t0/1";
+//! assert!(engine.evaluate(r#"CSQ[0].SYMBOL == "BRCA1" && is_het()"#, row).unwrap());
+//! ```
+
+use std::collections::HashMap;
+
+use crate::FilterEngine;
+use crate::error::Result;
+use crate::eval::AlleleMode;
+use crate::limits::EvalLimits;
+use crate::row::{MissingValueMode, ParseOptions};
+
+/// Behavior options for a [`FilterEngine`].
+///
+/// The default matches [`FilterEngine::new`].
+#[derive(Debug, Clone, Default)]
+pub struct EngineOptions {
+    /// Fail on unknown fields, bad indexes and type mismatches; see
+    /// [`FilterEngine::with_strict`].
+    pub strict: bool,
+    /// Treat missing values as unknown; see [`FilterEngine::with_three_valued`].
+    pub three_valued: bool,
+    /// Row parsing options, including how `.` inside values is handled.
+    pub parse_options: ParseOptions,
+    /// Sandbox limits on filters and evaluation time.
+    pub limits: EvalLimits,
+    /// Compare strings ignoring case; see [`FilterEngine::with_case_insensitive`].
+    pub case_insensitive: bool,
+    /// Fail records whose only ALT is `*`; see
+    /// [`FilterEngine::with_drop_spanning_deletions`].
+    pub drop_spanning_deletions: bool,
+    /// Evaluate filters per ALT allele; see [`FilterEngine::with_allele_mode`].
+    pub allele_mode: AlleleMode,
+    /// Distance within which numbers compare equal; see
+    /// [`FilterEngine::with_float_tolerance`].
+    pub float_tolerance: f64,
+    /// Samples to keep, or `None` for all; see [`FilterEngine::with_samples`].
+    pub samples: Option<Vec<String>>,
+    /// Subfield names of structured INFO fields, by field ID; see
+    /// [`FilterEngine::with_structured_field`].
+    pub structured_fields: HashMap<String, Vec<String>>,
+    /// Subfield renames (`from`, `to`) of structured INFO fields, by field
+    /// ID, applied after `structured_fields`; see
+    /// [`FilterEngine::with_subfield_renames`].
+    pub subfield_renames: HashMap<String, Vec<(String, String)>>,
+}
+
+impl EngineOptions {
+    /// Create an engine from a VCF header with these options.
+    pub fn build(&self, header: &str) -> Result<FilterEngine> {
+        let mut engine = FilterEngine::new(header)?
+            .with_strict(self.strict)
+            .with_three_valued(self.three_valued)
+            .with_parse_options(self.parse_options.clone())
+            .with_limits(self.limits.clone())
+            .with_case_insensitive(self.case_insensitive)
+            .with_drop_spanning_deletions(self.drop_spanning_deletions)
+            .with_allele_mode(self.allele_mode)
+            .with_float_tolerance(self.float_tolerance)?;
+        if let Some(samples) = &self.samples {
+            engine = engine.with_samples(samples)?;
+        }
+        for (id, subfields) in &self.structured_fields {
+            engine = engine.with_structured_field(id, subfields.iter().cloned());
+        }
+        for (id, renames) in &self.subfield_renames {
+            engine = engine.with_subfield_renames(id, renames.iter().cloned())?;
+        }
+        Ok(engine)
+    }
+}
+
+/// Builder for a [`FilterEngine`]; create one with [`FilterEngine::builder`].
+#[derive(Debug, Clone)]
+pub struct FilterEngineBuilder {
+    header: String,
+    options: EngineOptions,
+}
+
+impl FilterEngineBuilder {
+    /// Start building an engine for a VCF header.
+    pub fn new(header: &str) -> Self {
+        Self {
+            header: header.to_string(),
+            options: EngineOptions::default(),
+        }
+    }
+
+    /// Replace all options at once.
+    pub fn options(mut self, options: EngineOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Fail on unknown fields, bad indexes and type mismatches.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.options.strict = strict;
+        self
+    }
+
+    /// Treat missing values as unknown (three-valued logic).
+    pub fn three_valued(mut self, three_valued: bool) -> Self {
+        self.options.three_valued = three_valued;
+        self
+    }
+
+    /// Set how `.` elements and unparseable numbers in typed values are handled.
+    pub fn missing_values(mut self, mode: MissingValueMode) -> Self {
+        self.options.parse_options.missing_values = mode;
+        self
+    }
+
+    /// Replace the row parsing options.
+    pub fn parse_options(mut self, parse_options: ParseOptions) -> Self {
+        self.options.parse_options = parse_options;
+        self
+    }
+
+    /// Apply sandbox limits.
+    pub fn limits(mut self, limits: EvalLimits) -> Self {
+        self.options.limits = limits;
+        self
+    }
+
+    /// Compare strings ignoring case.
+    pub fn case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.options.case_insensitive = case_insensitive;
+        self
+    }
+
+    /// Fail records whose only ALT allele is the spanning deletion `*`.
+    pub fn drop_spanning_deletions(mut self, drop: bool) -> Self {
+        self.options.drop_spanning_deletions = drop;
+        self
+    }
+
+    /// Evaluate filters once per ALT allele, passing rows where any or all alleles pass.
+    pub fn allele_mode(mut self, mode: AlleleMode) -> Self {
+        self.options.allele_mode = mode;
+        self
+    }
+
+    /// Treat numbers within `tolerance` of each other as equal.
+    pub fn float_tolerance(mut self, tolerance: f64) -> Self {
+        self.options.float_tolerance = tolerance;
+        self
+    }
+
+    /// Keep only the named samples, in this order.
+    pub fn samples<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.options.samples = Some(names.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Parse INFO keys starting with `prefix` as aligned per-transcript arrays.
+    pub fn aligned_prefix(mut self, prefix: &str) -> Self {
+        self.options.parse_options = self.options.parse_options.with_aligned_prefix(prefix);
+        self
+    }
+
+    /// Declare the subfields of a structured INFO field.
+    pub fn structured_field<I, S>(mut self, id: &str, subfields: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.options.structured_fields.insert(
+            id.to_string(),
+            subfields.into_iter().map(Into::into).collect(),
+        );
+        self
+    }
+
+    /// Rename subfields of a structured INFO field, from header detection
+    /// or [`structured_field`](Self::structured_field).
+    pub fn rename_subfields<I, S, T>(mut self, id: &str, renames: I) -> Self
+    where
+        I: IntoIterator<Item = (S, T)>,
+        S: Into<String>,
+        T: Into<String>,
+    {
+        self.options
+            .subfield_renames
+            .entry(id.to_string())
+            .or_default()
+            .extend(
+                renames
+                    .into_iter()
+                    .map(|(from, to)| (from.into(), to.into())),
+            );
+        self
+    }
+
+    /// Create the engine.
+    ///
+    /// Fails if the header does not parse, a selected sample is not in the
+    /// header, the float tolerance is negative or not finite, or a subfield
+    /// rename does not apply.
+    pub fn build(self) -> Result<FilterEngine> {
+        self.options.build(&self.header)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VcfFilterError;
+
+    const HEADER: &str = "##INFO=<ID=AF,Number=A,Type=Float,Description=\"Allele frequency\">\n\
+                          #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\ta\tb\tc";
+
+    #[test]
+    fn test_builder_applies_options() {
+        let engine = FilterEngine::builder(HEADER)
+            .strict(true)
+            .three_valued(true)
+            .case_insensitive(true)
+            .float_tolerance(0.5)
+            .samples(["c", "a"])
+            .build()
+            .unwrap();
+        assert!(engine.is_strict());
+        assert!(engine.is_three_valued());
+        assert!(engine.is_case_insensitive());
+        assert_eq!(engine.float_tolerance(), 0.5);
+        assert_eq!(engine.sample_names(), ["c", "a"]);
+
+        let row = "chr1\t100\t.\tA\tG\t50.3\tPASS\tAF=0.1\tGT\t0/0\t0/1\t1/1";
+        assert!(engine.evaluate("QUAL == 50", row).unwrap());
+        assert!(engine.evaluate(r#"FILTER == "pass""#, row).unwrap());
+        assert!(
+            engine
+                .evaluate("is_hom_alt() && is_hom_ref(1)", row)
+                .unwrap()
+        );
+        assert!(engine.evaluate("n_het() == 0", row).unwrap());
+
+        // Options can be kept and applied as a whole
+        let options = EngineOptions {
+            samples: Some(vec!["b".to_string()]),
+            ..EngineOptions::default()
+        };
+        let engine = FilterEngineBuilder::new(HEADER)
+            .options(options.clone())
+            .build()
+            .unwrap();
+        assert!(engine.evaluate("is_het()", row).unwrap());
+        assert_eq!(options.build(HEADER).unwrap().sample_names(), ["b"]);
+    }
+
+    #[test]
+    fn test_invalid_options() {
+        let unknown_sample = FilterEngine::builder(HEADER).samples(["d"]).build();
+        assert!(matches!(
+            unknown_sample,
+            Err(VcfFilterError::ConfigError(_))
+        ));
+
+        let negative = FilterEngine::builder(HEADER).float_tolerance(-1.0).build();
+        assert!(matches!(negative, Err(VcfFilterError::ConfigError(_))));
+
+        let not_structured = FilterEngine::builder(HEADER)
+            .rename_subfields("AF", [("a", "b")])
+            .build();
+        assert!(matches!(
+            not_structured,
+            Err(VcfFilterError::ConfigError(_))
+        ));
+    }
+
+    #[test]
+    fn test_subfield_renames() {
+        let header = "##INFO=<ID=ANN,Number=.,Type=String,Description=\"Functional annotations: 'Allele | HGVS.c | HGVS.p'\">";
+        let engine = FilterEngine::builder(header)
+            .structured_field("CSQ", ["Allele", "SYMBOL"])
+            .rename_subfields("ANN", [("HGVS_c", "c"), ("HGVS_p", "p")])
+            .rename_subfields("CSQ", [("SYMBOL", "Gene")])
+            .build()
+            .unwrap();
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\tANN=G|c.1A>G|p.M1V;CSQ=G|BRCA1";
+        assert!(
+            engine
+                .evaluate(r#"ANN[0].p == "p.M1V" && CSQ[0].Gene == "BRCA1""#, row)
+                .unwrap()
+        );
+        assert!(!engine.evaluate(r#"ANN[0].HGVS_p == "p.M1V""#, row).unwrap());
+
+        for renames in [[("HGVS_c", "HGVS_p")], [("Gene_Name", "Gene")]] {
+            let result = FilterEngine::builder(header)
+                .rename_subfields("ANN", renames)
+                .build();
+            assert!(matches!(result, Err(VcfFilterError::ConfigError(_))));
+        }
+    }
+}
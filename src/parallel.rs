@@ -0,0 +1,205 @@
+//! Multi-threaded filtering with ordered output (`parallel` feature).
+//!
+//! Data lines are read in chunks; each chunk is evaluated on the rayon
+//! thread pool while the next one is read, and passing lines are written
+//! in their original order. [`filter_chunks_with`] evaluates on a pool of
+//! its own with a given number of threads; otherwise run inside a custom
+//! [`rayon::ThreadPool`] with `install` to limit them.
+//!
+//! ```rust
+//! use std::io::Cursor;
+//! use vcf_filter::FilterEngine;
+//!
+//! let vcf = "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+//!            chr1\t100\t.\tA\tG\t50\tPASS\t.\n\
+//!            chr1\t200\t.\tA\tG\t5\tPASS\t.\n";
+//! let mut reader = Cursor::new(vcf);
+//! let (engine, _header) = FilterEngine::from_reader(&mut reader).unwrap();
+//!
+//! let mut output = Vec::new();
+//! let (passed, total) = engine.filter_parallel(reader, &mut output, "QUAL > 30").unwrap();
+//! assert_eq!((passed, total), (1, 2));
+//! assert_eq!(output, b"chr1\t100\t.\tA\tG\t50\tPASS\t.\n");
+//! ```
+
+use std::io::{BufRead, Write};
+
+use rayon::ThreadPool;
+use rayon::prelude::*;
+
+use crate::compiled::CompiledFilter;
+use crate::compression::io_error;
+use crate::error::{Result, VcfFilterError};
+
+/// Number of data lines evaluated per chunk.
+const CHUNK_LINES: usize = 8192;
+
+/// Write the data lines of `reader` that pass `compiled` to `writer`, in order.
+///
+/// Header (`#`) and empty lines are skipped. Returns `(passed, total)`. An
+/// evaluation error stops filtering at that line, after all earlier passing
+/// lines have been written.
+pub fn filter_chunks<R: BufRead + Send, W: Write>(
+    compiled: &CompiledFilter,
+    reader: R,
+    writer: &mut W,
+) -> Result<(u64, u64)> {
+    filter_in(None, compiled, reader, writer)
+}
+
+/// Like [`filter_chunks`], evaluating on a new pool of `threads` threads.
+///
+/// The writer stays on the calling thread, so it need not be `Send`.
+pub fn filter_chunks_with<R: BufRead + Send, W: Write>(
+    compiled: &CompiledFilter,
+    reader: R,
+    writer: &mut W,
+    threads: usize,
+) -> Result<(u64, u64)> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .map_err(|e| {
+            VcfFilterError::IoError(format!("failed to start {} threads: {}", threads, e))
+        })?;
+    filter_in(Some(&pool), compiled, reader, writer)
+}
+
+/// Filter on `pool`, or on the current pool when `None`.
+fn filter_in<R: BufRead + Send, W: Write>(
+    pool: Option<&ThreadPool>,
+    compiled: &CompiledFilter,
+    mut reader: R,
+    writer: &mut W,
+) -> Result<(u64, u64)> {
+    let mut passed = 0u64;
+    let mut total = 0u64;
+    let mut chunk = read_chunk(&mut reader)?;
+
+    while !chunk.is_empty() {
+        let evaluate = || {
+            chunk
+                .par_iter()
+                .map(|line| compiled.matches(line))
+                .collect::<Vec<_>>()
+        };
+        let read = || read_chunk(&mut reader);
+        let (results, next) = match pool {
+            Some(pool) => pool.join(evaluate, read),
+            None => rayon::join(evaluate, read),
+        };
+        for (line, result) in chunk.iter().zip(results) {
+            total += 1;
+            let pass = result.map_err(|e| VcfFilterError::InRow {
+                line: line.clone(),
+                source: Box::new(e),
+            })?;
+            if pass {
+                passed += 1;
+                writeln!(writer, "{}", line).map_err(io_error("<output>"))?;
+            }
+        }
+        chunk = next?;
+    }
+
+    writer.flush().map_err(io_error("<output>"))?;
+    Ok((passed, total))
+}
+
+/// Read up to `CHUNK_LINES` data lines.
+fn read_chunk(reader: &mut impl BufRead) -> Result<Vec<String>> {
+    let mut chunk = Vec::with_capacity(CHUNK_LINES);
+    let mut line = String::new();
+    while chunk.len() < CHUNK_LINES {
+        line.clear();
+        let read = reader
+            .read_line(&mut line)
+            .map_err(|e| VcfFilterError::RowParseError(format!("failed to read line: {}", e)))?;
+        if read == 0 {
+            break;
+        }
+        let trimmed = line.trim_end_matches(['\n', '\r']);
+        if !trimmed.is_empty() && !trimmed.starts_with('#') {
+            chunk.push(trimmed.to_string());
+        }
+    }
+    Ok(chunk)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::FilterEngine;
+
+    #[test]
+    fn test_ordered_output() {
+        let header = r#"##INFO=<ID=DP,Number=1,Type=Integer,Description="Depth">"#;
+        let engine = FilterEngine::new(header).unwrap();
+        // Several chunks, with every third row passing
+        let lines: Vec<String> = (0..20_000)
+            .map(|i| format!("chr1\t{}\t.\tA\tG\t50\tPASS\tDP={}", i + 1, i % 3))
+            .collect();
+        let input = format!("#CHROM\tPOS\n{}\n", lines.join("\n"));
+
+        let mut output = Vec::new();
+        let (passed, total) = engine
+            .filter_parallel(input.as_bytes(), &mut output, "DP == 0")
+            .unwrap();
+        assert_eq!((passed, total), (6667, 20_000));
+
+        let expected: Vec<&str> = lines.iter().step_by(3).map(String::as_str).collect();
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.lines().collect::<Vec<_>>(), expected);
+
+        let serial: Vec<String> = engine
+            .filter_lines(input.as_bytes(), "DP == 0")
+            .unwrap()
+            .collect::<crate::Result<_>>()
+            .unwrap();
+        assert_eq!(output.lines().collect::<Vec<_>>(), serial);
+    }
+
+    #[test]
+    fn test_error_stops_in_order() {
+        let engine = FilterEngine::new("").unwrap();
+        let input =
+            "chr1\t100\t.\tA\tG\t50\tPASS\t.\nchr1\tnot_a_pos\nchr1\t300\t.\tA\tG\t50\tPASS\t.\n";
+        let mut output = Vec::new();
+        assert!(
+            engine
+                .filter_parallel(input.as_bytes(), &mut output, "QUAL > 30")
+                .is_err()
+        );
+        assert_eq!(output, b"chr1\t100\t.\tA\tG\t50\tPASS\t.\n");
+    }
+
+    #[test]
+    fn test_error_quotes_row() {
+        let engine = FilterEngine::new("").unwrap();
+        let input = "chr1\t100\t.\tA\tG\t50\tPASS\t.\nchr1\tnot_a_pos\n";
+        let error = engine
+            .filter_parallel(input.as_bytes(), &mut Vec::new(), "QUAL > 30")
+            .unwrap_err();
+        assert!(matches!(error, crate::VcfFilterError::InRow { .. }));
+        assert!(error.to_string().ends_with("\n  in row: chr1\tnot_a_pos"));
+    }
+
+    #[test]
+    fn test_fixed_thread_count() {
+        let engine = FilterEngine::new("").unwrap();
+        let compiled = engine.compile("QUAL > 30").unwrap();
+        let input: String = (0..10_000)
+            .map(|i| format!("chr1\t{}\t.\tA\tG\t{}\tPASS\t.\n", i + 1, i % 60))
+            .collect();
+
+        let mut expected = Vec::new();
+        super::filter_chunks(&compiled, input.as_bytes(), &mut expected).unwrap();
+        for threads in [1, 3] {
+            let mut output = Vec::new();
+            let (passed, total) =
+                super::filter_chunks_with(&compiled, input.as_bytes(), &mut output, threads)
+                    .unwrap();
+            assert_eq!((passed, total), (4823, 10_000));
+            assert_eq!(output, expected);
+        }
+    }
+}
@@ -0,0 +1,309 @@
+//! Trio pedigrees and per-record inheritance calls.
+//!
+//! With a [`Trio`] configured on the [`FilterEngine`](crate::FilterEngine),
+//! every parsed row gains an `INHERITANCE` virtual field computed from the
+//! child's and parents' GT values:
+//!
+//! | Value          | Meaning                                                  |
+//! |----------------|----------------------------------------------------------|
+//! | `"denovo"`     | The child's alleles cannot all be inherited              |
+//! | `"paternal"`   | The child's alt alleles came from the father             |
+//! | `"maternal"`   | The child's alt alleles came from the mother             |
+//! | `"biparental"` | The child received an alt allele from each parent        |
+//! | `"ambiguous"`  | Either parent could have passed on the alt allele        |
+//!
+//! A diploid child receives one allele from each parent, so a `1/1` child of
+//! `0/1` and `0/0` parents is `"denovo"`: the mother cannot supply an alt. A
+//! haploid allele may come from either parent. The field is missing when the
+//! child carries no alt allele, is neither haploid nor diploid, or any of the
+//! three genotypes is missing. [`write_inheritance`] adds the call to a
+//! record's INFO column so filtered output carries it for reviewers.
+
+use std::fmt;
+
+use crate::error::{Result, VcfFilterError};
+use crate::row::{Genotype, VcfRow, percent_encode, set_info};
+
+/// Name of the virtual field holding the inheritance call.
+pub const INHERITANCE_FIELD: &str = "INHERITANCE";
+
+/// Header line declaring the `INHERITANCE` INFO field in output VCFs.
+pub const INHERITANCE_HEADER: &str = "##INFO=<ID=INHERITANCE,Number=1,Type=String,Description=\"Inheritance of the child's alt alleles: denovo, paternal, maternal, biparental or ambiguous\">";
+
+/// A child with both parents, identified by VCF sample names.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trio {
+    /// Sample name of the child.
+    pub child: String,
+    /// Sample name of the father.
+    pub father: String,
+    /// Sample name of the mother.
+    pub mother: String,
+}
+
+impl Trio {
+    /// Create a trio from sample names.
+    pub fn new(child: &str, father: &str, mother: &str) -> Self {
+        Self {
+            child: child.to_string(),
+            father: father.to_string(),
+            mother: mother.to_string(),
+        }
+    }
+
+    /// Read every complete trio (child with both parents) from a PED file.
+    ///
+    /// PED columns are family, individual, father, mother, sex, phenotype;
+    /// a parent of `0` means unknown. Columns may be tab- or space-separated.
+    pub fn from_ped(text: &str) -> Result<Vec<Trio>> {
+        let mut trios = Vec::new();
+
+        for (line_no, line) in text.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let columns: Vec<&str> = line.split_whitespace().collect();
+            if columns.len() < 4 {
+                return Err(VcfFilterError::ConfigError(format!(
+                    "PED line {}: expected at least 4 columns",
+                    line_no + 1
+                )));
+            }
+            let (child, father, mother) = (columns[1], columns[2], columns[3]);
+            if father != "0" && mother != "0" {
+                trios.push(Trio::new(child, father, mother));
+            }
+        }
+
+        Ok(trios)
+    }
+}
+
+/// The inheritance call for a record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Inheritance {
+    DeNovo,
+    Paternal,
+    Maternal,
+    Biparental,
+    Ambiguous,
+}
+
+impl Inheritance {
+    /// The value written to `INHERITANCE`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Inheritance::DeNovo => "denovo",
+            Inheritance::Paternal => "paternal",
+            Inheritance::Maternal => "maternal",
+            Inheritance::Biparental => "biparental",
+            Inheritance::Ambiguous => "ambiguous",
+        }
+    }
+}
+
+impl fmt::Display for Inheritance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A trio resolved to sample column indices for one VCF header.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrioColumns {
+    child: usize,
+    father: usize,
+    mother: usize,
+}
+
+impl TrioColumns {
+    /// Locate the trio's samples among the header's sample names.
+    pub fn resolve(trio: &Trio, sample_names: &[String]) -> Result<Self> {
+        let find = |name: &str| {
+            sample_names.iter().position(|s| s == name).ok_or_else(|| {
+                VcfFilterError::ConfigError(format!("pedigree sample not in VCF header: {}", name))
+            })
+        };
+        Ok(Self {
+            child: find(&trio.child)?,
+            father: find(&trio.father)?,
+            mother: find(&trio.mother)?,
+        })
+    }
+
+    /// Call the inheritance of the child's alt alleles on a record.
+    pub fn inheritance(&self, row: &VcfRow) -> Option<Inheritance> {
+        let genotype = |column: usize| row.genotype(column).and_then(Genotype::called);
+        let child = genotype(self.child)?;
+        let father = genotype(self.father)?;
+        let mother = genotype(self.mother)?;
+        call_inheritance(&child, &father, &mother)
+    }
+}
+
+/// Parse a GT string (e.g., `0/1`, `1|1`) into allele indices.
+///
+/// Returns `None` if any allele is missing (`.`).
+pub fn parse_genotype(gt: &str) -> Option<Vec<usize>> {
+    Genotype::parse(gt)?.called()
+}
+
+/// Call inheritance from the child's and parents' allele indices.
+fn call_inheritance(child: &[usize], father: &[usize], mother: &[usize]) -> Option<Inheritance> {
+    if child.iter().all(|&a| a == 0) {
+        return None;
+    }
+
+    // The alleles each parent may have passed on: one each for a diploid
+    // child, one from either parent for a haploid one
+    let assignments = match *child {
+        [a, b] => vec![(Some(a), Some(b)), (Some(b), Some(a))],
+        [a] => vec![(Some(a), None), (None, Some(a))],
+        _ => return None,
+    };
+    let mut calls: Vec<Inheritance> = assignments
+        .into_iter()
+        .filter(|(from_father, from_mother)| {
+            from_father.is_none_or(|a| father.contains(&a))
+                && from_mother.is_none_or(|a| mother.contains(&a))
+        })
+        .map(|(from_father, from_mother)| {
+            match (from_father.unwrap_or(0) != 0, from_mother.unwrap_or(0) != 0) {
+                (true, true) => Inheritance::Biparental,
+                (true, false) => Inheritance::Paternal,
+                _ => Inheritance::Maternal,
+            }
+        })
+        .collect();
+    calls.dedup();
+
+    Some(match calls.as_slice() {
+        [] => Inheritance::DeNovo,
+        [call] => *call,
+        _ => Inheritance::Ambiguous,
+    })
+}
+
+/// Add or replace the `INHERITANCE` entry in a VCF data line's INFO column.
+///
+/// With no call, any existing `INHERITANCE` entry is removed and the line is
+/// otherwise unchanged.
+pub fn write_inheritance(line: &str, inheritance: Option<Inheritance>) -> String {
+    let value = inheritance.map(|inheritance| inheritance.to_string());
+    set_info(
+        line,
+        INHERITANCE_FIELD,
+        value
+            .as_deref()
+            .map(|value| percent_encode(value))
+            .as_deref(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::parse_header;
+    use crate::row::parse_row;
+
+    #[test]
+    fn test_call_inheritance() {
+        assert_eq!(
+            call_inheritance(&[0, 1], &[0, 0], &[0, 0]),
+            Some(Inheritance::DeNovo)
+        );
+        assert_eq!(
+            call_inheritance(&[0, 1], &[0, 1], &[0, 0]),
+            Some(Inheritance::Paternal)
+        );
+        assert_eq!(
+            call_inheritance(&[1, 0], &[0, 0], &[1, 1]),
+            Some(Inheritance::Maternal)
+        );
+        assert_eq!(
+            call_inheritance(&[1, 1], &[0, 1], &[0, 1]),
+            Some(Inheritance::Biparental)
+        );
+        assert_eq!(
+            call_inheritance(&[0, 1], &[0, 1], &[0, 1]),
+            Some(Inheritance::Ambiguous)
+        );
+        // Multiallelic: allele 2 is carried by neither parent
+        assert_eq!(
+            call_inheritance(&[1, 2], &[0, 1], &[0, 1]),
+            Some(Inheritance::DeNovo)
+        );
+        assert_eq!(call_inheritance(&[0, 0], &[0, 1], &[0, 1]), None);
+        // Each alt copy needs its own parent: the mother has none to give
+        assert_eq!(
+            call_inheritance(&[1, 1], &[0, 1], &[0, 0]),
+            Some(Inheritance::DeNovo)
+        );
+        // The father cannot supply the reference allele, so he gave the alt
+        assert_eq!(
+            call_inheritance(&[0, 1], &[1, 1], &[0, 1]),
+            Some(Inheritance::Paternal)
+        );
+        // A haploid allele comes from either parent
+        assert_eq!(
+            call_inheritance(&[1], &[0], &[0, 1]),
+            Some(Inheritance::Maternal)
+        );
+        assert_eq!(
+            call_inheritance(&[1], &[0], &[0, 0]),
+            Some(Inheritance::DeNovo)
+        );
+    }
+
+    #[test]
+    fn test_trio_columns_inheritance() {
+        let samples = vec!["dad".to_string(), "kid".to_string(), "mom".to_string()];
+        let columns = TrioColumns::resolve(&Trio::new("kid", "dad", "mom"), &samples).unwrap();
+        let info_map = parse_header("").unwrap();
+
+        let row = parse_row(
+            "chr1\t100\t.\tA\tG\t50\tPASS\t.\tGT:DP\t0/0:30\t0/1:25\t0/1:28",
+            &info_map,
+        )
+        .unwrap();
+        assert_eq!(columns.inheritance(&row), Some(Inheritance::Maternal));
+
+        let row = parse_row(
+            "chr1\t100\t.\tA\tG\t50\tPASS\t.\tGT\t0/0\t0/1\t./.",
+            &info_map,
+        )
+        .unwrap();
+        assert_eq!(columns.inheritance(&row), None);
+
+        assert!(TrioColumns::resolve(&Trio::new("kid", "dad", "nope"), &samples).is_err());
+    }
+
+    #[test]
+    fn test_from_ped() {
+        let ped = "#family\tid\tfather\tmother\tsex\tpheno\nfam1 kid dad mom 1 2\nfam1 dad 0 0 1 1\nfam1 mom 0 0 2 1\n";
+        assert_eq!(
+            Trio::from_ped(ped).unwrap(),
+            vec![Trio::new("kid", "dad", "mom")]
+        );
+        assert!(Trio::from_ped("fam1 kid\n").is_err());
+    }
+
+    #[test]
+    fn test_write_inheritance() {
+        let line = "chr1\t100\t.\tA\tG\t50\tPASS\tDP=30\tGT\t0/1";
+        assert_eq!(
+            write_inheritance(line, Some(Inheritance::DeNovo)),
+            "chr1\t100\t.\tA\tG\t50\tPASS\tDP=30;INHERITANCE=denovo\tGT\t0/1"
+        );
+
+        let line = "chr1\t100\t.\tA\tG\t50\tPASS\tINHERITANCE=paternal";
+        assert_eq!(
+            write_inheritance(line, Some(Inheritance::Maternal)),
+            "chr1\t100\t.\tA\tG\t50\tPASS\tINHERITANCE=maternal"
+        );
+        assert_eq!(
+            write_inheritance(line, None),
+            "chr1\t100\t.\tA\tG\t50\tPASS\t."
+        );
+    }
+}
@@ -0,0 +1,677 @@
+//! Pre-resolved evaluation plans for compiled filters.
+//!
+//! The tree-walking evaluator re-resolves every field path on every row:
+//! it clones the field's value out of the row, looks the annotation field up
+//! in the `InfoMap` and searches its subfield names, and allocates a fresh
+//! `Value` for each literal. A [`Plan`] does that work once, when the filter
+//! is compiled:
+//!
+//! - literals, including constant lists, are built once and compared by
+//!   reference;
+//! - INFO/FORMAT fields are read in place instead of cloned;
+//! - annotation subfield names (`ANN[*].Gene_Name`, and bare names such as
+//!   `Annotation_Impact` inside `[?(...)]` predicates) are bound to their
+//!   column index, and wildcard comparisons iterate the annotations without
+//!   collecting them into an array.
+//!
+//! The boolean structure of a filter (`&&`, `||`, `!`, comparisons and
+//! `exists()`), arithmetic, plain field paths and predicate paths are
+//! planned. Everything else, such as function calls, `let` and `all()`, is
+//! kept as an expression and handed to [`evaluate_with`], so a plan always
+//! produces the same result as evaluating the expression it came from.
+
+use std::borrow::Cow;
+
+use crate::error::Result;
+use crate::eval::{
+    AnnotationScope, EvalContext, Quantifier, arithmetic, column_value, compare, compare_elements,
+    evaluate_with, is_flag, is_list_field, orders_chromosomes, subfield_path, uses_all_access,
+    value_present, value_to_bool,
+};
+use crate::filter::{AccessPart, BinaryOp, Expr, UnaryOp};
+use crate::functions::BUILTIN_FIELDS;
+use crate::header::InfoMap;
+use crate::row::VcfRow;
+use crate::value::Value;
+
+static MISSING: Value = Value::Missing;
+static FALSE: Value = Value::Bool(false);
+
+/// A filter's boolean structure with pre-resolved operands.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Plan {
+    /// A boolean literal.
+    Literal(bool),
+    /// Short-circuiting `&&`.
+    And(Box<Plan>, Box<Plan>),
+    /// Short-circuiting `||`.
+    Or(Box<Plan>, Box<Plan>),
+    /// `!`
+    Not(Box<Plan>),
+    /// A comparison operator applied to two operands.
+    Compare {
+        left: Operand,
+        op: BinaryOp,
+        right: Operand,
+        quantifier: Quantifier,
+    },
+    /// `exists(path)`; `every` is set for `[all]` paths, and `subfield` when
+    /// empty strings count as absent.
+    Exists {
+        operand: Operand,
+        every: bool,
+        subfield: bool,
+    },
+    /// Any other value, tested for truthiness.
+    Truthy(Operand),
+}
+
+/// Where a field is looked up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Namespace {
+    /// INFO first, then FORMAT.
+    Any,
+    /// `INFO.<field>`
+    Info,
+    /// `FORMAT.<field>`
+    Format,
+}
+
+impl Namespace {
+    /// The namespace prefix as the tree-walker names it.
+    fn label(self) -> Option<&'static str> {
+        match self {
+            Namespace::Any => None,
+            Namespace::Info => Some("INFO"),
+            Namespace::Format => Some("FORMAT"),
+        }
+    }
+}
+
+/// Which annotations a subfield path selects.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Select {
+    /// `[i]`
+    Index(usize),
+    /// `[*]` or `[all]`
+    Each,
+}
+
+/// A comparison operand with its field path resolved.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Operand {
+    /// A literal value.
+    Literal(Value),
+    /// A built-in column (`QUAL`, `FILTER`, ...).
+    Column(String),
+    /// An INFO or FORMAT field.
+    Field { namespace: Namespace, name: String },
+    /// An INFO Flag field, false when absent.
+    Flag { namespace: Namespace, name: String },
+    /// One element of a multi-valued field (`AD[1]`); `per_allele` is set for
+    /// `Number=A`/`Number=R` fields, whose single value is a one-element list.
+    Element {
+        namespace: Namespace,
+        name: String,
+        index: usize,
+        per_allele: bool,
+    },
+    /// An annotation subfield; `subfield` is `None` if the header lacks it.
+    Subfield {
+        name: String,
+        subfield: Option<usize>,
+        select: Select,
+    },
+    /// A subfield of the annotation a predicate is evaluated against.
+    Scoped(usize),
+    /// Annotations kept by a `[?(...)]` predicate, optionally indexed and
+    /// projected to a subfield.
+    Matching {
+        namespace: Namespace,
+        name: String,
+        /// The field's subfield names, for predicate fallbacks.
+        subfields: Vec<String>,
+        predicate: Box<Plan>,
+        index: Option<usize>,
+        projection: Projection,
+    },
+    /// `+`, `-`, `*` or `/`.
+    Arithmetic(Box<Operand>, BinaryOp, Box<Operand>),
+    /// Any other expression, evaluated by the tree-walker.
+    Expr(Expr),
+}
+
+/// What a predicate path yields for each matching annotation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Projection {
+    /// The whole annotation.
+    Annotation,
+    /// One subfield; `None` if the header lacks it.
+    Subfield(Option<usize>),
+}
+
+impl Plan {
+    /// Plan an expression, resolving subfield names against `info_map`.
+    pub(crate) fn new(expr: &Expr, info_map: &InfoMap) -> Self {
+        Planner {
+            info_map,
+            scope: None,
+        }
+        .plan(expr)
+    }
+
+    /// Evaluate the plan against a row.
+    ///
+    /// `ctx` carries the annotation scope inside predicates; plans are never
+    /// built below `let` or `all()`, so it has no bindings and quantifies with
+    /// `Any`.
+    pub(crate) fn evaluate(&self, row: &VcfRow, ctx: &EvalContext) -> Result<bool> {
+        ctx.check_deadline()?;
+
+        match self {
+            Plan::Literal(b) => Ok(*b),
+            Plan::And(left, right) => Ok(left.evaluate(row, ctx)? && right.evaluate(row, ctx)?),
+            Plan::Or(left, right) => Ok(left.evaluate(row, ctx)? || right.evaluate(row, ctx)?),
+            Plan::Not(inner) => Ok(!inner.evaluate(row, ctx)?),
+            Plan::Compare {
+                left,
+                op,
+                right,
+                quantifier,
+            } => {
+                if let Some(elements) = left.elements(row) {
+                    let right = right.resolve(row, ctx)?;
+                    return Ok(compare_elements(
+                        elements,
+                        op,
+                        &right,
+                        *quantifier,
+                        ctx.collation,
+                    ));
+                }
+                let left = left.resolve(row, ctx)?;
+                let right = right.resolve(row, ctx)?;
+                compare(&left, op, &right, *quantifier, ctx.collation)
+            }
+            Plan::Exists {
+                operand,
+                every,
+                subfield,
+            } => {
+                if !subfield {
+                    return Ok(!operand.resolve(row, ctx)?.is_missing());
+                }
+                if let Some(mut elements) = operand.elements(row) {
+                    return Ok(if *every {
+                        let mut seen = false;
+                        elements.all(|v| {
+                            seen = true;
+                            value_present(v)
+                        }) && seen
+                    } else {
+                        elements.any(value_present)
+                    });
+                }
+                let value = operand.resolve(row, ctx)?;
+                Ok(match value.as_ref() {
+                    Value::Array(arr) if *every => !arr.is_empty() && arr.iter().all(value_present),
+                    value => value_present(value),
+                })
+            }
+            Plan::Truthy(operand) => value_to_bool(&*operand.resolve(row, ctx)?),
+        }
+    }
+}
+
+/// Builds plans, tracking the subfields in scope inside predicates.
+struct Planner<'a> {
+    info_map: &'a InfoMap,
+    /// Subfield names of the annotation an enclosing predicate iterates over.
+    scope: Option<&'a [String]>,
+}
+
+impl Planner<'_> {
+    fn plan(&self, expr: &Expr) -> Plan {
+        match expr {
+            Expr::Bool(b) => Plan::Literal(*b),
+            Expr::Binary(left, BinaryOp::And, right) => {
+                Plan::And(Box::new(self.plan(left)), Box::new(self.plan(right)))
+            }
+            Expr::Binary(left, BinaryOp::Or, right) => {
+                Plan::Or(Box::new(self.plan(left)), Box::new(self.plan(right)))
+            }
+            Expr::Binary(_, BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div, _) => {
+                Plan::Truthy(self.operand(expr))
+            }
+            // Chromosome ordering maps both sides to ranks first
+            Expr::Binary(left, op, right) if orders_chromosomes(left, op, right) => {
+                Plan::Truthy(Operand::Expr(expr.clone()))
+            }
+            Expr::Binary(left, op, right) => Plan::Compare {
+                left: self.operand(left),
+                op: op.clone(),
+                right: self.operand(right),
+                quantifier: if uses_all_access(left) {
+                    Quantifier::All
+                } else {
+                    Quantifier::Any
+                },
+            },
+            Expr::Unary(UnaryOp::Not, inner) => Plan::Not(Box::new(self.plan(inner))),
+            Expr::Exists(parts) => match self.path(parts) {
+                Some(operand) => Plan::Exists {
+                    operand,
+                    every: parts.contains(&AccessPart::All),
+                    subfield: subfield_path(parts),
+                },
+                None => Plan::Truthy(Operand::Expr(expr.clone())),
+            },
+            _ => Plan::Truthy(self.operand(expr)),
+        }
+    }
+
+    fn operand(&self, expr: &Expr) -> Operand {
+        match expr {
+            Expr::Number(n) => Operand::Literal(Value::Number(*n)),
+            Expr::String(s) => Operand::Literal(Value::String(s.clone())),
+            Expr::Bool(b) => Operand::Literal(Value::Bool(*b)),
+            // Constant lists (`in ["HIGH", "MODERATE"]`) are built once
+            Expr::List(items) => {
+                let values: Option<Vec<Value>> = items
+                    .iter()
+                    .map(|item| match self.operand(item) {
+                        Operand::Literal(value) => Some(value),
+                        _ => None,
+                    })
+                    .collect();
+                values
+                    .map(|values| Operand::Literal(Value::Array(values)))
+                    .unwrap_or_else(|| Operand::Expr(expr.clone()))
+            }
+            Expr::Binary(
+                left,
+                op @ (BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div),
+                right,
+            ) => Operand::Arithmetic(
+                Box::new(self.operand(left)),
+                op.clone(),
+                Box::new(self.operand(right)),
+            ),
+            Expr::Var(parts) => match self.path(parts) {
+                Some(Operand::Field { namespace, name })
+                    if is_flag(namespace.label(), &name, self.info_map) =>
+                {
+                    Operand::Flag { namespace, name }
+                }
+                Some(operand) => operand,
+                None => Operand::Expr(expr.clone()),
+            },
+            _ => Operand::Expr(expr.clone()),
+        }
+    }
+
+    /// Resolve a field path, or `None` if it needs the tree-walker.
+    fn path(&self, parts: &[AccessPart]) -> Option<Operand> {
+        // Inside a predicate, bare subfield names refer to the current annotation
+        if let ([AccessPart::Field(name)], Some(scope)) = (parts, self.scope)
+            && let Some(i) = scope.iter().position(|s| s == name)
+        {
+            return Some(Operand::Scoped(i));
+        }
+
+        let (namespace, name, rest) = match parts {
+            [AccessPart::Field(ns), AccessPart::Field(name), rest @ ..] if ns == "INFO" => {
+                (Namespace::Info, name, rest)
+            }
+            [AccessPart::Field(ns), AccessPart::Field(name), rest @ ..] if ns == "FORMAT" => {
+                (Namespace::Format, name, rest)
+            }
+            [AccessPart::Field(ns), ..] if ns == "INFO" || ns == "FORMAT" || ns == "SAMPLE" => {
+                return None;
+            }
+            [AccessPart::Field(name)] if BUILTIN_FIELDS.contains(&name.as_str()) => {
+                return Some(Operand::Column(name.clone()));
+            }
+            [AccessPart::Field(name), ..] if BUILTIN_FIELDS.contains(&name.as_str()) => {
+                return None;
+            }
+            [AccessPart::Field(name), rest @ ..] => (Namespace::Any, name, rest),
+            _ => return None,
+        };
+
+        let name = name.clone();
+        let subfields = self
+            .info_map
+            .get(&name)
+            .and_then(|field| field.subfields.as_deref());
+        let position = |subfield: &String| subfields?.iter().position(|s| s == subfield);
+
+        let select = match rest {
+            [] => return Some(Operand::Field { namespace, name }),
+            [AccessPart::Index(index)] => {
+                let per_allele = is_list_field(namespace.label(), &name, self.info_map);
+                return Some(Operand::Element {
+                    namespace,
+                    name,
+                    index: *index,
+                    per_allele,
+                });
+            }
+            [AccessPart::Predicate(predicate), tail @ ..] => {
+                let (index, projection) = match tail {
+                    [] => (None, Projection::Annotation),
+                    [AccessPart::Index(i)] => (Some(*i), Projection::Annotation),
+                    [AccessPart::Field(sub)] => (None, Projection::Subfield(position(sub))),
+                    [AccessPart::Index(i), AccessPart::Field(sub)] => {
+                        (Some(*i), Projection::Subfield(position(sub)))
+                    }
+                    _ => return None,
+                };
+                // Predicates never match on FORMAT fields or unstructured fields
+                let Some(subfields) = subfields.filter(|_| namespace != Namespace::Format) else {
+                    return Some(Operand::Literal(Value::Missing));
+                };
+                let scoped = Planner {
+                    info_map: self.info_map,
+                    scope: Some(subfields),
+                };
+                return Some(Operand::Matching {
+                    namespace,
+                    name,
+                    subfields: subfields.to_vec(),
+                    predicate: Box::new(scoped.plan(predicate)),
+                    index,
+                    projection,
+                });
+            }
+            [AccessPart::Index(index), AccessPart::Field(_)] => Select::Index(*index),
+            [AccessPart::Wildcard | AccessPart::All, AccessPart::Field(_)] => Select::Each,
+            _ => return None,
+        };
+        // FORMAT fields have no annotation subfields
+        if namespace == Namespace::Format {
+            return None;
+        }
+        let Some(AccessPart::Field(subfield)) = rest.last() else {
+            return None;
+        };
+        Some(Operand::Subfield {
+            subfield: position(subfield),
+            name,
+            select,
+        })
+    }
+}
+
+impl Operand {
+    /// The selected subfield values of a wildcard subfield path, in place.
+    fn elements<'r>(&self, row: &'r VcfRow) -> Option<impl Iterator<Item = &'r Value>> {
+        let Operand::Subfield {
+            name,
+            subfield,
+            select: Select::Each,
+        } = self
+        else {
+            return None;
+        };
+        let annotations = match (subfield, row.info.get(name)) {
+            (Some(_), Some(Value::Array(annotations))) => annotations.as_slice(),
+            _ => &[],
+        };
+        let subfield = *subfield;
+        Some(
+            annotations
+                .iter()
+                .filter_map(move |annotation| match annotation {
+                    Value::Array(values) => values.get(subfield?),
+                    _ => None,
+                }),
+        )
+    }
+
+    /// Resolve the operand's value, borrowing from the row where possible.
+    fn resolve<'r>(&'r self, row: &'r VcfRow, ctx: &EvalContext<'r>) -> Result<Cow<'r, Value>> {
+        Ok(match self {
+            Operand::Literal(value) => Cow::Borrowed(value),
+            Operand::Column(name) => Cow::Owned(column_value(name, row, ctx)),
+            Operand::Field { namespace, name } => Cow::Borrowed(field(row, *namespace, name)),
+            Operand::Flag { namespace, name } => {
+                Cow::Borrowed(match field(row, *namespace, name) {
+                    Value::Missing => &FALSE,
+                    value => value,
+                })
+            }
+            Operand::Element {
+                namespace,
+                name,
+                index,
+                per_allele,
+            } => Cow::Borrowed(match field(row, *namespace, name) {
+                Value::Array(values) => values.get(*index).unwrap_or(&MISSING),
+                value if *per_allele && *index == 0 => value,
+                _ => &MISSING,
+            }),
+            Operand::Subfield {
+                name,
+                subfield,
+                select: Select::Index(index),
+            } => Cow::Borrowed(
+                match (subfield, row.info.get(name)) {
+                    (Some(subfield), Some(Value::Array(annotations))) => {
+                        match annotations.get(*index) {
+                            Some(Value::Array(values)) => values.get(*subfield),
+                            _ => None,
+                        }
+                    }
+                    _ => None,
+                }
+                .unwrap_or(&MISSING),
+            ),
+            Operand::Subfield { .. } => Cow::Owned(Value::Array(
+                self.elements(row).into_iter().flatten().cloned().collect(),
+            )),
+            Operand::Scoped(i) => Cow::Borrowed(
+                ctx.annotation
+                    .and_then(|scope| scope.values.get(*i))
+                    .unwrap_or(&MISSING),
+            ),
+            Operand::Matching {
+                namespace,
+                name,
+                subfields,
+                predicate,
+                index,
+                projection,
+            } => {
+                let Value::Array(annotations) = field(row, *namespace, name) else {
+                    return Ok(Cow::Borrowed(&MISSING));
+                };
+                let mut matches = Vec::new();
+                for annotation in annotations {
+                    let Value::Array(values) = annotation else {
+                        continue;
+                    };
+                    let scoped = EvalContext {
+                        annotation: Some(AnnotationScope { subfields, values }),
+                        ..*ctx
+                    };
+                    if predicate.evaluate(row, &scoped)? {
+                        matches.push(values);
+                    }
+                }
+                let project = |values: &Vec<Value>| match projection {
+                    Projection::Subfield(Some(i)) => {
+                        values.get(*i).cloned().unwrap_or(Value::Missing)
+                    }
+                    _ => Value::Array(values.clone()),
+                };
+                Cow::Owned(match (projection, index) {
+                    (Projection::Subfield(None), _) => Value::Missing,
+                    (_, Some(i)) => matches
+                        .get(*i)
+                        .map(|v| project(v))
+                        .unwrap_or(Value::Missing),
+                    (Projection::Annotation, None) if matches.is_empty() => Value::Missing,
+                    (_, None) => Value::Array(matches.into_iter().map(project).collect()),
+                })
+            }
+            Operand::Arithmetic(left, op, right) => {
+                let left = left.resolve(row, ctx)?;
+                let right = right.resolve(row, ctx)?;
+                Cow::Owned(arithmetic(op, &left, &right)?)
+            }
+            Operand::Expr(expr) => Cow::Owned(evaluate_with(expr, row, ctx)?),
+        })
+    }
+}
+
+/// Look up an INFO/FORMAT field in place.
+fn field<'r>(row: &'r VcfRow, namespace: Namespace, name: &str) -> &'r Value {
+    match namespace {
+        Namespace::Any => row.info.get(name).or_else(|| row.format.get(name)),
+        Namespace::Info => row.info.get(name),
+        Namespace::Format => row.format.get(name),
+    }
+    .unwrap_or(&MISSING)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::parse_filter;
+    use crate::header::parse_header;
+    use crate::row::parse_row;
+
+    const HEADER: &str = r#"##INFO=<ID=DP,Number=1,Type=Integer,Description="Total depth">
+##INFO=<ID=AF,Number=A,Type=Float,Description="Allele frequency">
+##INFO=<ID=DB,Number=0,Type=Flag,Description="dbSNP membership">
+##INFO=<ID=CLNSIG,Number=.,Type=String,Description="Clinical significance">
+##INFO=<ID=ANN,Number=.,Type=String,Description="Functional annotations: 'Allele | Annotation | Annotation_Impact | Gene_Name'">"#;
+
+    const ROWS: &[&str] = &[
+        "chr1\t100\trs1\tA\tG,T\t50\tPASS\tDP=30;AF=0.1,0.6;DB;CLNSIG=Pathogenic;ANN=G|missense|HIGH|BRCA1,T|synonymous|LOW|BRCA2\tGT:DP:AD\t0/1:25:10,15",
+        "chr2\t200\t.\tC\tT\t.\tLowQual;q10\tDP=5;ANN=T|intron|MODIFIER|TP53,T||LOW|\tGT:DP\t0/0:.",
+        "chr3\t300\t.\tG\tA\t20\t.\tCLNSIG=Benign,Likely_benign;ANN=A|stop_gained|HIGH|",
+        "chrX\t400\trs4\tT\tC\t99\tPASS\t.",
+    ];
+
+    #[test]
+    fn test_plan_agrees_with_evaluator() {
+        let info_map = parse_header(HEADER).unwrap();
+        let ctx = EvalContext::new(&info_map);
+        let filters = [
+            "DP > 10 && QUAL >= 30",
+            r#"FILTER == "PASS" || FILTER != "LowQual""#,
+            r#"ANN[*].Gene_Name == "BRCA1""#,
+            r#"ANN[*].Gene_Name != "BRCA1""#,
+            r#"ANN[all].Annotation_Impact in ["HIGH", "LOW"]"#,
+            r#"ANN[1].Gene_Name contains "BRCA""#,
+            r#"ANN[*].Missing_Subfield == "x" || ANN[0].Nope != "y""#,
+            "exists(ANN[*].Gene_Name) && !exists(ANN[all].Gene_Name)",
+            "exists(INFO.DP) || exists(FORMAT.AD[1])",
+            "AF[1] > 0.5 || AF > 0.05",
+            "AF[*] > 0.5",
+            "FORMAT.DP >= 25 && INFO.DB",
+            r#"CLNSIG == "Benign" || CLNSIG in ["Pathogenic"]"#,
+            r#"CLNSIG in ["Likely_benign", DP, [1, "x"]] || ALT in ["T"]"#,
+            r#"ALT == "G" || ID == "rs4" || POS > 250"#,
+            "AD[1] / DP > 0.3",
+            "AD[1] * 2 - 1 > DP / 3 || AF[*] * 2 > 1 || DP / 0 > 1",
+            r#"CLNSIG + 1 > 0"#,
+            r#"ANN[?(Annotation_Impact == "HIGH" && Gene_Name != "")].Gene_Name == "BRCA1""#,
+            r#"exists(ANN[?(Annotation == "intron")]) || exists(ANN[?(DP > 10)][0])"#,
+            r#"ANN[?(Annotation_Impact in ["HIGH", "LOW"])][1].Gene_Name == "BRCA2""#,
+            r#"ANN[?(Impact == "HIGH")].Gene_Name == "BRCA1" || ANN[?(Gene_Name)].Nope == "x""#,
+            "exists(FORMAT.DP[?(true)]) || exists(DP[?(true)]) || exists(ANN[?(DP + 1)])",
+            r#"ANN[?(len(Gene_Name) > 4)].Annotation != "x""#,
+            "all(ANN[*].Annotation_Impact == \"HIGH\")",
+            r#"ANN[?(Annotation_Impact == "HIGH")].Gene_Name == "BRCA1""#,
+            "let d = DP in d > 10",
+            "!DB && true",
+            "DB == false || INFO.DB != true",
+            "DB == DB && !exists(DB)",
+            "DP",
+            "DP + 1",
+            "len(ANN) == 2",
+        ];
+        for filter in filters {
+            let expr = parse_filter(filter).unwrap();
+            let plan = Plan::new(&expr, &info_map);
+            for line in ROWS {
+                let row = parse_row(line, &info_map).unwrap();
+                let expected = evaluate_with(&expr, &row, &ctx).map(|v| value_to_bool(&v).unwrap());
+                let actual = plan.evaluate(&row, &ctx);
+                match (expected, actual) {
+                    (Ok(expected), Ok(actual)) => {
+                        assert_eq!(actual, expected, "{} on {}", filter, line)
+                    }
+                    (Err(_), Err(_)) => {}
+                    (expected, actual) => {
+                        panic!("{} on {}: {:?} vs {:?}", filter, line, expected, actual)
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_plan_resolves_paths_once() {
+        let info_map = parse_header(HEADER).unwrap();
+        let plan = Plan::new(
+            &parse_filter(r#"ANN[*].Gene_Name == "BRCA1" && DP + 1 > 2"#).unwrap(),
+            &info_map,
+        );
+        let Plan::And(left, right) = plan else {
+            panic!("expected &&");
+        };
+        assert_eq!(
+            *left,
+            Plan::Compare {
+                left: Operand::Subfield {
+                    name: "ANN".to_string(),
+                    subfield: Some(3),
+                    select: Select::Each,
+                },
+                op: BinaryOp::Eq,
+                right: Operand::Literal(Value::String("BRCA1".to_string())),
+                quantifier: Quantifier::Any,
+            }
+        );
+        assert!(matches!(
+            *right,
+            Plan::Compare {
+                left: Operand::Arithmetic(..),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_predicate_names_bind_to_subfield_indexes() {
+        let info_map = parse_header(HEADER).unwrap();
+        let plan = Plan::new(
+            &parse_filter(r#"ANN[?(Annotation_Impact == "HIGH")].Gene_Name == "BRCA1""#).unwrap(),
+            &info_map,
+        );
+        let Plan::Compare {
+            left:
+                Operand::Matching {
+                    predicate,
+                    projection,
+                    ..
+                },
+            ..
+        } = plan
+        else {
+            panic!("expected a predicate path");
+        };
+        assert_eq!(projection, Projection::Subfield(Some(3)));
+        assert!(matches!(
+            *predicate,
+            Plan::Compare {
+                left: Operand::Scoped(2),
+                ..
+            }
+        ));
+    }
+}
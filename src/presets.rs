@@ -0,0 +1,197 @@
+//! Named filters referenced as `@name` inside other expressions.
+//!
+//! Clinical pipelines reuse the same building blocks constantly. A
+//! [`PresetLibrary`] holds named filters that any expression can embed:
+//!
+//! ```text
+//! # Indented lines continue the previous definition
+//! @rare := AF < 0.01
+//! @coding := ANN[*].Annotation_Impact in ["HIGH", "MODERATE"]
+//! @rare_coding := @rare && @coding
+//!     && QUAL >= 30
+//! ```
+//!
+//! ```rust
+//! use vcf_filter::FilterEngine;
+//!
+//! let engine = FilterEngine::new("")
+//!     .unwrap()
+//!     .with_preset("deep", "DP >= 30")
+//!     .unwrap();
+//!
+//! let row = "chr1\t100\t.\tA\tG\t50\tPASS\tDP=40";
+//! assert!(engine.evaluate("@deep && QUAL > 30", row).unwrap());
+//! ```
+//!
+//! References are expanded when a preset is defined, so a preset may only
+//! refer to presets defined before it, which rules out cycles. Filters are
+//! expanded when parsed by the engine; an unknown `@name` is a
+//! `ConfigError`.
+//!
+//! The `[presets]` section of a [`FilterConfig`](crate::config::FilterConfig)
+//! is a library too, and accepts the same `@name := expression` lines.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::config::logical_lines;
+use crate::error::{Result, VcfFilterError};
+use crate::filter::{AccessPart, Expr, parse_error, parse_filter};
+
+/// A set of named, fully expanded filters.
+#[derive(Debug, Clone, Default)]
+pub struct PresetLibrary {
+    presets: HashMap<String, Arc<Expr>>,
+}
+
+impl PresetLibrary {
+    /// Create an empty library.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse `@name := expression` definitions, one per line.
+    ///
+    /// Blank lines and `#` comments are ignored, and indented lines continue
+    /// the previous definition.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut library = Self::new();
+        for (line_no, line) in logical_lines(text) {
+            let (name, body) = line
+                .strip_prefix('@')
+                .and_then(|rest| rest.split_once(":="))
+                .ok_or_else(|| {
+                    VcfFilterError::ConfigError(format!(
+                        "line {}: expected `@name := expression`",
+                        line_no + 1
+                    ))
+                })?;
+            library.define(name.trim(), body.trim())?;
+        }
+        Ok(library)
+    }
+
+    /// Parse a filter and register it under `name`, replacing any previous definition.
+    pub fn define(&mut self, name: &str, filter: &str) -> Result<()> {
+        let expr = parse_filter(filter).map_err(parse_error)?;
+        self.define_expr(name, &expr)
+    }
+
+    /// Register an already parsed filter under `name`.
+    pub fn define_expr(&mut self, name: &str, expr: &Expr) -> Result<()> {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(VcfFilterError::ConfigError(format!(
+                "invalid preset name: @{}",
+                name
+            )));
+        }
+        let expanded = self.expand(expr)?;
+        self.presets.insert(name.to_string(), Arc::new(expanded));
+        Ok(())
+    }
+
+    /// Get an expanded preset by name (without the `@`).
+    pub fn get(&self, name: &str) -> Option<Arc<Expr>> {
+        self.presets.get(name).cloned()
+    }
+
+    /// Iterate over the preset names.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.presets.keys().map(String::as_str)
+    }
+
+    /// Number of presets in the library.
+    pub fn len(&self) -> usize {
+        self.presets.len()
+    }
+
+    /// Returns true if the library holds no presets.
+    pub fn is_empty(&self) -> bool {
+        self.presets.is_empty()
+    }
+
+    /// Replace every `@name` reference in an expression with its definition.
+    pub fn expand(&self, expr: &Expr) -> Result<Expr> {
+        let expand_all = |exprs: &[Expr]| {
+            exprs
+                .iter()
+                .map(|e| self.expand(e))
+                .collect::<Result<Vec<_>>>()
+        };
+        let boxed = |e: &Expr| self.expand(e).map(Box::new);
+
+        Ok(match expr {
+            Expr::Preset(name) => self
+                .get(name)
+                .map(|preset| (*preset).clone())
+                .ok_or_else(|| VcfFilterError::ConfigError(format!("unknown preset: @{}", name)))?,
+            Expr::Var(parts) => Expr::Var(self.expand_parts(parts)?),
+            Expr::Exists(parts) => Expr::Exists(self.expand_parts(parts)?),
+            Expr::Binary(left, op, right) => Expr::Binary(boxed(left)?, op.clone(), boxed(right)?),
+            Expr::Unary(op, inner) => Expr::Unary(op.clone(), boxed(inner)?),
+            Expr::All(inner) => Expr::All(boxed(inner)?),
+            Expr::List(items) => Expr::List(expand_all(items)?),
+            Expr::Call(name, args) => Expr::Call(name.clone(), expand_all(args)?),
+            Expr::Let(name, value, body) => Expr::Let(name.clone(), boxed(value)?, boxed(body)?),
+            Expr::Number(_) | Expr::String(_) | Expr::Bool(_) => expr.clone(),
+        })
+    }
+
+    /// Expand references inside `[?(...)]` predicates of an access path.
+    fn expand_parts(&self, parts: &[AccessPart]) -> Result<Vec<AccessPart>> {
+        parts
+            .iter()
+            .map(|part| match part {
+                AccessPart::Predicate(pred) => {
+                    Ok(AccessPart::Predicate(Box::new(self.expand(pred)?)))
+                }
+                other => Ok(other.clone()),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(filter: &str) -> Expr {
+        parse_filter(filter).unwrap()
+    }
+
+    #[test]
+    fn test_parse_library() {
+        let library = PresetLibrary::parse(
+            "# building blocks\n@rare := AF < 0.01\n@high := ANN[*].Annotation_Impact == \"HIGH\"\n@rare_high := @rare && @high\n    && QUAL >= 30\n",
+        )
+        .unwrap();
+        assert_eq!(library.len(), 3);
+        assert_eq!(
+            *library.get("rare_high").unwrap(),
+            parse(r#"(AF < 0.01 && ANN[*].Annotation_Impact == "HIGH") && QUAL >= 30"#)
+        );
+    }
+
+    #[test]
+    fn test_expand_nested_references() {
+        let mut library = PresetLibrary::new();
+        library.define("deep", "DP >= 30").unwrap();
+        assert_eq!(
+            library
+                .expand(&parse("!@deep || ANN[?(@deep)].Gene_Name == \"X\""))
+                .unwrap(),
+            parse("!(DP >= 30) || ANN[?(DP >= 30)].Gene_Name == \"X\"")
+        );
+    }
+
+    #[test]
+    fn test_unknown_and_invalid_presets() {
+        let mut library = PresetLibrary::new();
+        assert!(matches!(
+            library.define("a", "@b && QUAL > 1"),
+            Err(VcfFilterError::ConfigError(_))
+        ));
+        assert!(library.define("bad name", "QUAL > 1").is_err());
+        assert!(PresetLibrary::parse("rare = AF < 0.01").is_err());
+    }
+}
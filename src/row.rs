@@ -3,10 +3,13 @@
 //! Parses individual VCF data rows into structured `VcfRow` objects,
 //! including parsing of INFO fields and structured annotations like ANN.
 
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::fmt;
+use std::sync::LazyLock;
 
 use crate::error::{Result, VcfFilterError};
-use crate::header::{InfoField, InfoMap, InfoNumber, InfoType};
+use crate::header::{FormatMap, InfoField, InfoMap, InfoNumber, InfoType, parse_format_header};
 use crate::value::Value;
 
 /// A parsed VCF data row.
@@ -16,7 +19,7 @@ pub struct VcfRow {
     pub chrom: String,
     /// Position (POS column).
     pub pos: u64,
-    /// Variant ID (ID column).
+    /// Variant ID (ID column), as written; see [`VcfRow::ids`] for the list.
     pub id: Option<String>,
     /// Reference allele (REF column).
     pub ref_allele: String,
@@ -24,17 +27,167 @@ pub struct VcfRow {
     pub alt_alleles: Vec<String>,
     /// Quality score (QUAL column).
     pub qual: Option<f64>,
-    /// Filter status (FILTER column).
+    /// Filter status (FILTER column), one entry per `;`-separated name; empty for `.`.
     pub filter: Vec<String>,
     /// INFO fields parsed into values.
+    ///
+    /// Rows parsed for a [`CompiledFilter`](crate::CompiledFilter) only hold
+    /// the keys the filter reads; see [`VcfRow::unparsed_info`].
     pub info: HashMap<String, Value>,
-    /// FORMAT fields (sample genotype data like GT, DP, GQ).
+    /// FORMAT fields of the first sample (genotype data like GT, DP, GQ).
     pub format: HashMap<String, Value>,
+    /// FORMAT fields of every sample, in header column order.
+    pub samples: Vec<HashMap<String, Value>>,
+    /// GT of every sample parsed with the row, in header column order;
+    /// `None` where a sample has no GT or it cannot be parsed.
+    pub genotypes: Vec<Option<Genotype>>,
+    /// Column text as read, for [`VcfRow::to_vcf_line`].
+    raw: RawColumns,
+}
+
+/// An INFO key with its encoded value (`None` for flags).
+type RawInfoEntry = (String, Option<String>);
+
+/// The text of the columns a parsed value cannot reproduce exactly.
+#[derive(Debug, Clone, Default)]
+struct RawColumns {
+    /// The line as read.
+    line: String,
+    /// Byte offset of the FORMAT column in `line`, if it has one.
+    samples: Option<usize>,
+    /// QUAL as written.
+    qual: String,
+    /// INFO entries in order.
+    info: Vec<RawInfoEntry>,
+    /// INFO keys left unparsed, whose values are only in `info` above.
+    deferred: Vec<String>,
 }
 
 /// A single annotation from a structured field like ANN.
 pub type Annotation = HashMap<String, String>;
 
+/// How `.` tokens inside multi-valued or typed values are handled.
+///
+/// A whole value of `.` (e.g. `CADD=.`) is always parsed as `Value::Missing`.
+/// This setting controls `.` elements mixed into a list (e.g. `AD=10,.`)
+/// and values that do not match their declared numeric type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MissingValueMode {
+    /// `.` elements become `Value::Missing`; unparseable numbers are kept as strings.
+    #[default]
+    Lenient,
+    /// `.` elements and unparseable numbers in Integer/Float fields are a row parse error.
+    Strict,
+}
+
+/// How `nan`, `inf` and `-inf` in QUAL and numeric INFO values are handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NonFiniteMode {
+    /// Keep them as numbers. Infinities order as usual; every comparison
+    /// involving NaN, including `!=`, is false.
+    #[default]
+    Propagate,
+    /// Parse them as `Value::Missing`, like `.`.
+    Missing,
+}
+
+/// Options controlling how VCF rows are parsed.
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    /// Handling of `.` tokens inside typed values.
+    pub missing_values: MissingValueMode,
+    /// Handling of NaN and infinite numbers.
+    pub non_finite: NonFiniteMode,
+    /// Characters that separate the values of a String INFO field, by field ID.
+    ///
+    /// Values are split on any of the characters, trimmed of surrounding
+    /// spaces and underscores (`Pathogenic|_risk_factor`), and become an
+    /// array when there is more than one. Defaults to `,`, `/` and `|` for
+    /// ClinVar's `CLNSIG` and `CLNSIGCONF`.
+    pub value_separators: HashMap<String, String>,
+    /// Whether `%XX` escapes in String values are decoded.
+    ///
+    /// `None` follows the header: VCFv4.3 introduced percent-encoding, so
+    /// the engine decodes for 4.3 and later, and for headers without a
+    /// `##fileformat` line, but not for 4.2 and earlier. Row parsing
+    /// without an engine decodes unless this is `Some(false)`.
+    pub percent_decoding: Option<bool>,
+    /// Key prefixes of INFO fields holding aligned per-transcript lists,
+    /// such as SnpSift's `dbNSFP_`.
+    ///
+    /// Matching values always parse as arrays split on `,`, with `.`
+    /// elements kept in place as missing, so index `i` of one field pairs
+    /// with index `i` of another (`dbNSFP_SIFT_pred=D,.,T`). The engine also
+    /// treats their declared `Number=A` as `.`, since the lists are per
+    /// transcript rather than per allele.
+    pub aligned_prefixes: Vec<String>,
+}
+
+/// ClinVar fields split into their classifications by default.
+const CLINVAR_SEPARATORS: &[(&str, &str)] = &[("CLNSIG", ",/|"), ("CLNSIGCONF", ",/|")];
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            missing_values: MissingValueMode::default(),
+            non_finite: NonFiniteMode::default(),
+            value_separators: CLINVAR_SEPARATORS
+                .iter()
+                .map(|(field, separators)| (field.to_string(), separators.to_string()))
+                .collect(),
+            percent_decoding: None,
+            aligned_prefixes: Vec::new(),
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Decode `%XX` escapes unless percent decoding is turned off.
+    fn decode<'a>(&self, raw: &'a str) -> Cow<'a, str> {
+        if self.percent_decoding == Some(false) {
+            Cow::Borrowed(raw)
+        } else {
+            percent_decode(raw)
+        }
+    }
+
+    /// Get the value of a parsed number under the NaN/infinity policy.
+    fn number(&self, n: f64) -> Value {
+        if !n.is_finite() && self.non_finite == NonFiniteMode::Missing {
+            Value::Missing
+        } else {
+            Value::Number(n)
+        }
+    }
+
+    /// Split a String INFO field on any of `separators`; an empty string
+    /// restores the default `,`-separated parsing.
+    pub fn with_separators(mut self, field: &str, separators: &str) -> Self {
+        if separators.is_empty() {
+            self.value_separators.remove(field);
+        } else {
+            self.value_separators
+                .insert(field.to_string(), separators.to_string());
+        }
+        self
+    }
+
+    /// Parse INFO keys starting with `prefix` as aligned arrays.
+    pub fn with_aligned_prefix(mut self, prefix: &str) -> Self {
+        if !self.aligned_prefixes.iter().any(|p| p == prefix) {
+            self.aligned_prefixes.push(prefix.to_string());
+        }
+        self
+    }
+
+    /// Whether an INFO key holds an aligned per-transcript list.
+    pub(crate) fn is_aligned(&self, key: &str) -> bool {
+        self.aligned_prefixes
+            .iter()
+            .any(|prefix| key.starts_with(prefix.as_str()))
+    }
+}
+
 impl VcfRow {
     /// Get a value from the row by field name.
     ///
@@ -44,11 +197,7 @@ impl VcfRow {
         match field {
             "CHROM" => Value::String(self.chrom.clone()),
             "POS" => Value::Number(self.pos as f64),
-            "ID" => self
-                .id
-                .as_ref()
-                .map(|s| Value::String(s.clone()))
-                .unwrap_or(Value::Missing),
+            "ID" => names_value(&self.ids()),
             "REF" => Value::String(self.ref_allele.clone()),
             "ALT" => {
                 if self.alt_alleles.len() == 1 {
@@ -63,148 +212,1420 @@ impl VcfRow {
                 }
             }
             "QUAL" => self.qual.map(Value::Number).unwrap_or(Value::Missing),
-            "FILTER" => {
-                if self.filter.len() == 1 {
-                    Value::String(self.filter[0].clone())
-                } else {
-                    Value::Array(
-                        self.filter
-                            .iter()
-                            .map(|s| Value::String(s.clone()))
-                            .collect(),
-                    )
+            "FILTER" => names_value(&self.filter),
+            // INFO is the primary namespace for filter fields and should
+            // not be shadowed by FORMAT fields with the same name (e.g., DP).
+            _ => self.get_ref(field).cloned().unwrap_or(Value::Missing),
+        }
+    }
+
+    /// Borrow the value of an INFO field, or else a FORMAT field, by name.
+    ///
+    /// Looks fields up like [`get`](Self::get) without copying the value.
+    /// The built-in columns are not stored as values, so they give `None`;
+    /// read them from the row's fields or with `get`.
+    pub fn get_ref(&self, field: &str) -> Option<&Value> {
+        self.info.get(field).or_else(|| self.format.get(field))
+    }
+
+    /// Iterate over the parsed INFO entries, borrowing their values.
+    ///
+    /// Entries come in line order, followed by values added directly to
+    /// `info` (engine virtual fields) in no particular order. Keys left
+    /// unparsed (see [`unparsed_info`](Self::unparsed_info)) are skipped.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use vcf_filter::{FilterEngine, Value};
+    ///
+    /// let engine = FilterEngine::new("").unwrap();
+    /// let row = engine.parse_row("chr1\t100\t.\tA\tG\t50\tPASS\tDP=30;DB;AF=0.5").unwrap();
+    /// let keys: Vec<&str> = row.info_iter().map(|(key, _)| key).collect();
+    /// assert_eq!(keys, ["DP", "DB", "AF"]);
+    /// assert_eq!(row.get_ref("DP"), Some(&Value::Number(30.0)));
+    /// ```
+    pub fn info_iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        let written = self
+            .raw
+            .info
+            .iter()
+            .filter_map(|(key, _)| self.info.get_key_value(key));
+        let added = self
+            .info
+            .iter()
+            .filter(|(key, _)| !self.raw.info.iter().any(|(written, _)| written == *key));
+        written
+            .chain(added)
+            .map(|(key, value)| (key.as_str(), value))
+    }
+}
+
+impl VcfRow {
+    /// Set an INFO value, keeping the key's position or appending a new key.
+    ///
+    /// `Value::Bool(true)` is written as a flag and `Value::Bool(false)`
+    /// removes the key. Strings are percent-encoded when serialized.
+    pub fn set_info(&mut self, key: &str, value: Value) {
+        let encoded = match &value {
+            Value::Bool(false) => {
+                self.remove_info(key);
+                return;
+            }
+            Value::Bool(true) => None,
+            value => Some(encode_info_value(value)),
+        };
+        match self.raw.info.iter_mut().find(|(k, _)| k == key) {
+            Some(entry) => entry.1 = encoded,
+            None => self.raw.info.push((key.to_string(), encoded)),
+        }
+        self.raw.deferred.retain(|k| k != key);
+        self.info.insert(key.to_string(), value);
+    }
+
+    /// Remove an INFO key, returning its value; `None` for an unparsed key.
+    pub fn remove_info(&mut self, key: &str) -> Option<Value> {
+        self.raw.info.retain(|(k, _)| k != key);
+        self.raw.deferred.retain(|k| k != key);
+        self.info.remove(key)
+    }
+
+    /// The line the row was parsed from, unaffected by later edits.
+    ///
+    /// Write it out to pass a record through untouched after evaluating the
+    /// parsed row, or quote it in an error message.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use vcf_filter::FilterEngine;
+    ///
+    /// let engine = FilterEngine::new("").unwrap();
+    /// let line = "chr1\t100\t.\tA\tG\t50.00\tPASS\tDP=30";
+    /// let mut row = engine.parse_row(line).unwrap();
+    /// row.add_filter("LowQual");
+    /// assert_eq!(row.raw_line(), line);
+    /// assert_ne!(row.to_vcf_line(), line);
+    /// ```
+    pub fn raw_line(&self) -> &str {
+        &self.raw.line
+    }
+
+    /// The INFO keys present in the line but not parsed into `info`.
+    ///
+    /// Empty unless the row was parsed for a compiled filter that reads
+    /// only some keys. Parse them with
+    /// [`FilterEngine::parse_info_key`](crate::FilterEngine::parse_info_key)
+    /// or [`FilterEngine::parse_all_info`](crate::FilterEngine::parse_all_info).
+    pub fn unparsed_info(&self) -> impl Iterator<Item = &str> {
+        self.raw.deferred.iter().map(String::as_str)
+    }
+
+    /// Parse an unparsed INFO key into `info`, keeping the value for later reads.
+    ///
+    /// Does nothing for keys already parsed or absent from the line.
+    pub(crate) fn parse_deferred(
+        &mut self,
+        key: &str,
+        info_map: &InfoMap,
+        options: &ParseOptions,
+    ) -> Result<()> {
+        let Some(index) = self.raw.deferred.iter().position(|k| k == key) else {
+            return Ok(());
+        };
+        let value = match self.raw.info.iter().find(|(k, _)| k == key) {
+            Some((_, Some(value))) => parse_info_entry(key, value, info_map, options)?,
+            Some((_, None)) => Value::Bool(true),
+            None => return Ok(()),
+        };
+        self.raw.deferred.remove(index);
+        self.info.insert(key.to_string(), value);
+        Ok(())
+    }
+
+    /// Replace the FILTER names; an empty list is written as `.`.
+    pub fn set_filter<I, S>(&mut self, names: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.filter = names.into_iter().map(Into::into).collect();
+    }
+
+    /// Add a FILTER name unless already present, replacing `PASS`.
+    pub fn add_filter(&mut self, name: &str) {
+        self.filter.retain(|f| f != "PASS");
+        if !self.filter.iter().any(|f| f == name) {
+            self.filter.push(name.to_string());
+        }
+    }
+
+    /// Replace the ID column; `None` is written as `.`.
+    pub fn set_id(&mut self, id: Option<&str>) {
+        self.id = id.map(str::to_string);
+    }
+
+    /// Serialize the row back into a tab-separated VCF data line.
+    ///
+    /// Unchanged columns are written as they were read, and INFO keeps its
+    /// key order with new keys appended. INFO is written from the entries
+    /// read or set with [`set_info`](Self::set_info): a key removed from
+    /// `info` is left out, but values inserted into `info` directly (such
+    /// as engine virtual fields) are not written. FORMAT and sample columns
+    /// are written as read.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use vcf_filter::{FilterEngine, Value};
+    ///
+    /// let engine = FilterEngine::new("").unwrap();
+    /// let mut row = engine
+    ///     .parse_row("chr1\t100\t.\tA\tG\t50.00\tPASS\tDP=30;DB;AF=0.10\tGT\t0/1")
+    ///     .unwrap();
+    /// row.set_info("DP", Value::Number(31.0));
+    /// row.set_info("NOTE", Value::String("a;b".to_string()));
+    /// row.remove_info("DB");
+    /// row.add_filter("LowQual");
+    /// row.set_id(Some("rs1"));
+    /// assert_eq!(
+    ///     row.to_vcf_line(),
+    ///     "chr1\t100\trs1\tA\tG\t50.00\tLowQual\tDP=31;AF=0.10;NOTE=a%3Bb\tGT\t0/1"
+    /// );
+    /// ```
+    pub fn to_vcf_line(&self) -> String {
+        let alt = if self.alt_alleles.is_empty() {
+            ".".to_string()
+        } else {
+            self.alt_alleles.join(",")
+        };
+        let raw_qual = match self.raw.qual.as_str() {
+            "." => None,
+            text => text.parse::<f64>().ok(),
+        };
+        // Compare bits so an unchanged NaN keeps its text
+        let qual = match self.qual {
+            _ if !self.raw.qual.is_empty()
+                && raw_qual.map(f64::to_bits) == self.qual.map(f64::to_bits) =>
+            {
+                self.raw.qual.clone()
+            }
+            Some(qual) => qual.to_string(),
+            None => ".".to_string(),
+        };
+        let filter = if self.filter.is_empty() {
+            ".".to_string()
+        } else {
+            self.filter.join(";")
+        };
+        let entries: Vec<String> = self
+            .raw
+            .info
+            .iter()
+            .filter(|(key, _)| self.info.contains_key(key) || self.raw.deferred.contains(key))
+            .map(|(key, value)| match value {
+                Some(value) => format!("{}={}", key, value),
+                None => key.clone(),
+            })
+            .collect();
+        let info = if entries.is_empty() {
+            ".".to_string()
+        } else {
+            entries.join(";")
+        };
+
+        let columns = [
+            self.chrom.clone(),
+            self.pos.to_string(),
+            self.id.clone().unwrap_or_else(|| ".".to_string()),
+            self.ref_allele.clone(),
+            alt,
+            qual,
+            filter,
+            info,
+        ];
+        let mut line = columns.join("\t");
+        if let Some(samples) = self
+            .raw
+            .samples
+            .and_then(|start| self.raw.line.get(start..))
+        {
+            line.push('\t');
+            line.push_str(samples);
+        }
+        line
+    }
+}
+
+/// Write a value as INFO text: strings percent-encoded, missing as `.` and
+/// arrays comma-separated.
+pub(crate) fn encode_info_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => percent_encode(s).into_owned(),
+        Value::Array(values) => values
+            .iter()
+            .map(encode_info_value)
+            .collect::<Vec<_>>()
+            .join(","),
+        other => other.to_vcf_string(),
+    }
+}
+
+/// A gene named by a LOF/NMD annotation that has no matching ANN annotation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnnotationMismatch {
+    /// The structured field the gene came from ("LOF" or "NMD").
+    pub field: String,
+    /// The gene name that was not found among ANN gene names.
+    pub gene: String,
+}
+
+/// Default tolerance for comparing a stored AF against AC/AN.
+///
+/// Callers usually round AF to three decimals, which is off by at most 0.0005.
+pub const DEFAULT_AF_TOLERANCE: f64 = 0.001;
+
+/// An alternate allele whose stored AF disagrees with AC/AN.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlleleFrequencyMismatch {
+    /// Zero-based index of the alternate allele.
+    pub allele: usize,
+    /// The AF value stored in the record, if the allele has one.
+    pub stored: Option<f64>,
+    /// AF recomputed as AC/AN, if the allele has an AC value.
+    pub computed: Option<f64>,
+}
+
+/// The kind of change an alternate allele makes to the reference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VariantClass {
+    /// One base replaced by another (`A>G`).
+    Snp,
+    /// Several adjacent bases replaced by as many others (`AC>GT`).
+    Mnp,
+    /// Bases inserted (`A>AT`).
+    Ins,
+    /// Bases deleted (`AT>A`).
+    Del,
+    /// A symbolic allele (`<DEL>`) or a record with a non-BND `SVTYPE`.
+    Sv,
+    /// A breakend (`G]chr2:100]`) or a record with `SVTYPE=BND`.
+    Bnd,
+    /// No change: a missing (`.`), overlapping-deletion (`*`) or reference allele.
+    Other,
+}
+
+impl VariantClass {
+    /// Classify an alternate allele against the reference allele.
+    pub fn of(ref_allele: &str, alt: &str) -> Self {
+        if alt.contains('[') || alt.contains(']') {
+            return VariantClass::Bnd;
+        }
+        if alt.starts_with('<') {
+            return VariantClass::Sv;
+        }
+        if alt == "." || alt == "*" || alt.eq_ignore_ascii_case(ref_allele) {
+            return VariantClass::Other;
+        }
+        match (ref_allele.len(), alt.len()) {
+            (1, 1) => VariantClass::Snp,
+            (r, a) if r == a => VariantClass::Mnp,
+            (r, a) if r < a => VariantClass::Ins,
+            _ => VariantClass::Del,
+        }
+    }
+
+    /// The normalized name returned by `variant_type()` (`"SNP"`, `"DEL"`, ...).
+    pub fn name(&self) -> &'static str {
+        match self {
+            VariantClass::Snp => "SNP",
+            VariantClass::Mnp => "MNP",
+            VariantClass::Ins => "INS",
+            VariantClass::Del => "DEL",
+            VariantClass::Sv => "SV",
+            VariantClass::Bnd => "BND",
+            VariantClass::Other => "OTHER",
+        }
+    }
+
+    /// Returns true for insertions and deletions.
+    pub fn is_indel(&self) -> bool {
+        matches!(self, VariantClass::Ins | VariantClass::Del)
+    }
+
+    /// Returns true for symbolic alleles and breakends.
+    pub fn is_sv(&self) -> bool {
+        matches!(self, VariantClass::Sv | VariantClass::Bnd)
+    }
+}
+
+/// A breakend ALT allele joining the record's position to a mate position.
+///
+/// The four VCF forms are `t[p[`, `t]p]`, `]p]t` and `[p[t`, where `t` is
+/// the replacement bases and `p` the mate's `chrom:pos`.
+///
+/// ```rust
+/// use vcf_filter::Breakend;
+///
+/// let bnd = Breakend::parse("G]chr17:198982]").unwrap();
+/// assert_eq!(bnd.bases, "G");
+/// assert_eq!((bnd.mate_chrom.as_str(), bnd.mate_pos), ("chr17", 198982));
+/// assert!(bnd.bases_first);
+/// assert!(!bnd.mate_extends_right);
+/// assert_eq!(Breakend::parse("<DEL>"), None);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Breakend {
+    /// The replacement bases (`G` in `G]chr17:198982]`).
+    pub bases: String,
+    /// The mate's chromosome, which may be a symbolic `<contig>` name.
+    pub mate_chrom: String,
+    /// The mate's 1-based position.
+    pub mate_pos: u64,
+    /// True when the bases precede the bracket (`t[p[`, `t]p]`), so the
+    /// joined sequence continues after this position.
+    pub bases_first: bool,
+    /// True for `[` brackets: the joined sequence extends to the right of
+    /// the mate position; `]` brackets join the sequence ending there.
+    pub mate_extends_right: bool,
+}
+
+impl Breakend {
+    /// Parse a breakend ALT allele; `None` for any other allele.
+    pub fn parse(alt: &str) -> Option<Self> {
+        let bracket = alt.chars().find(|c| matches!(c, '[' | ']'))?;
+        let parts: Vec<&str> = alt.split(bracket).collect();
+        let [before, mate, after] = parts.as_slice() else {
+            return None;
+        };
+        let (bases, bases_first) = match (before.is_empty(), after.is_empty()) {
+            (false, true) => (before, true),
+            (true, false) => (after, false),
+            _ => return None,
+        };
+        let (chrom, pos) = mate.rsplit_once(':')?;
+        Some(Self {
+            bases: bases.to_string(),
+            mate_chrom: chrom.to_string(),
+            mate_pos: pos.parse().ok()?,
+            bases_first,
+            mate_extends_right: bracket == '[',
+        })
+    }
+}
+
+/// The type of a symbolic ALT allele: `DUP` for `<DUP:TANDEM>`.
+fn symbolic_type(alt: &str) -> Option<&str> {
+    let inner = alt.strip_prefix('<')?.strip_suffix('>')?;
+    inner.split(':').next()
+}
+
+/// A sample's parsed GT value.
+///
+/// ```rust
+/// use vcf_filter::Genotype;
+///
+/// let gt = Genotype::parse(".|1").unwrap();
+/// assert_eq!(gt.alleles, [None, Some(1)]);
+/// assert!(gt.phased);
+/// assert_eq!(gt.ploidy(), 2);
+/// assert_eq!(gt.is_het(), None);
+/// assert!(!gt.is_missing());
+/// assert!(Genotype::parse("./.").unwrap().is_missing());
+/// assert_eq!(gt.to_string(), ".|1");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Genotype {
+    /// Allele indices in GT order; `None` for a missing allele (`.`).
+    pub alleles: Vec<Option<usize>>,
+    /// True if the alleles are separated by `|`, or the GT starts with the
+    /// VCFv4.4 phasing prefix `|` (`|1` for a phased haploid call).
+    pub phased: bool,
+}
+
+impl Genotype {
+    /// Parse a GT string such as `0/1`, `1|0`, `./.`, `.|1` or a haploid `1`.
+    ///
+    /// Returns `None` if an allele is neither a number nor `.`.
+    pub fn parse(gt: &str) -> Option<Self> {
+        let alleles = gt
+            .strip_prefix(['/', '|'])
+            .unwrap_or(gt)
+            .split(['/', '|'])
+            .map(|allele| match allele {
+                "." => Some(None),
+                _ => allele.parse().ok().map(Some),
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self {
+            alleles,
+            phased: gt.contains('|'),
+        })
+    }
+
+    /// Number of alleles: 1 for haploid calls, 2 for diploid ones.
+    pub fn ploidy(&self) -> usize {
+        self.alleles.len()
+    }
+
+    /// Every allele is missing (`.`, `./.`); `.|1` is only partly missing.
+    pub fn is_missing(&self) -> bool {
+        self.alleles.iter().all(Option::is_none)
+    }
+
+    /// Get the allele indices if every allele is called.
+    pub fn called(&self) -> Option<Vec<usize>> {
+        self.alleles.iter().copied().collect()
+    }
+
+    /// Two or more different alleles (`0/1`, `1|0`, `1/2`).
+    pub fn is_het(&self) -> Option<bool> {
+        let alleles = self.called()?;
+        Some(alleles.iter().any(|&a| a != alleles[0]))
+    }
+
+    /// Every allele is the reference (`0/0`, haploid `0`).
+    pub fn is_hom_ref(&self) -> Option<bool> {
+        Some(self.called()?.iter().all(|&a| a == 0))
+    }
+
+    /// Every allele is the same alternate allele (`1/1`, `2|2`, haploid `1`).
+    pub fn is_hom_alt(&self) -> Option<bool> {
+        let alleles = self.called()?;
+        Some(alleles[0] != 0 && alleles.iter().all(|&a| a == alleles[0]))
+    }
+}
+
+impl fmt::Display for Genotype {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let separator = if self.phased { "|" } else { "/" };
+        if self.phased && self.alleles.len() == 1 {
+            f.write_str(separator)?;
+        }
+        for (i, allele) in self.alleles.iter().enumerate() {
+            if i > 0 {
+                f.write_str(separator)?;
+            }
+            match allele {
+                Some(allele) => write!(f, "{}", allele)?,
+                None => f.write_str(".")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The element indexes of a per-allele value kept for one ALT allele.
+///
+/// `None` unless the value has as many elements as its `Number` implies
+/// for a site with `alts` ALT alleles. `Number=G` values are diploid, or
+/// haploid with one element per allele.
+fn allele_indexes(number: &InfoNumber, alt: usize, alts: usize, len: usize) -> Option<Vec<usize>> {
+    let allele = alt + 1;
+    // Index of the diploid genotype j/k (j <= k) in VCF genotype order
+    let genotype = |j: usize, k: usize| k * (k + 1) / 2 + j;
+    match number {
+        InfoNumber::PerAltAllele if len == alts => Some(vec![alt]),
+        InfoNumber::PerAllele if len == alts + 1 => Some(vec![0, allele]),
+        InfoNumber::PerGenotype if len == (alts + 1) * (alts + 2) / 2 => Some(vec![
+            genotype(0, 0),
+            genotype(0, allele),
+            genotype(allele, allele),
+        ]),
+        InfoNumber::PerGenotype if len == alts + 1 => Some(vec![0, allele]),
+        _ => None,
+    }
+}
+
+/// Pick the elements at `indexes`.
+fn pick<T: Clone>(items: &[T], indexes: &[usize]) -> Vec<T> {
+    indexes.iter().map(|&i| items[i].clone()).collect()
+}
+
+/// Slice a parsed per-allele value; `Number=A` values become a single value.
+fn slice_value(value: &mut Value, indexes: &[usize], number: &InfoNumber) {
+    let Value::Array(items) = value else {
+        return;
+    };
+    if indexes.iter().any(|&i| i >= items.len()) {
+        return;
+    }
+    let mut picked = pick(items, indexes);
+    *value = match number {
+        InfoNumber::PerAltAllele => picked.remove(0),
+        _ => Value::Array(picked),
+    };
+}
+
+/// Rewrite a GT for the record of one ALT allele: that allele becomes `1`
+/// and other ALT alleles `0`. Unparseable GTs are kept.
+fn split_genotype(gt: &str, alt: usize) -> String {
+    let Some(mut genotype) = Genotype::parse(gt) else {
+        return gt.to_string();
+    };
+    for allele in genotype.alleles.iter_mut().flatten() {
+        *allele = usize::from(*allele == alt + 1);
+    }
+    genotype.to_string()
+}
+
+/// Get every element of a value as a number, or `None` if any is missing.
+fn numeric_elements(value: Option<&Value>) -> Option<Vec<f64>> {
+    match value? {
+        Value::Array(arr) => arr.iter().map(Value::as_number).collect(),
+        other => other.as_number().map(|n| vec![n]),
+    }
+}
+
+/// Get the value of a `;`-separated column (ID, FILTER): missing when empty,
+/// a string for one name and an array for several.
+fn names_value<S: AsRef<str>>(names: &[S]) -> Value {
+    match names {
+        [] => Value::Missing,
+        [name] => Value::String(name.as_ref().to_string()),
+        names => Value::Array(
+            names
+                .iter()
+                .map(|name| Value::String(name.as_ref().to_string()))
+                .collect(),
+        ),
+    }
+}
+
+impl VcfRow {
+    /// Get the record's identifiers (`rs123;COSV456` is two), skipping `.` entries.
+    pub fn ids(&self) -> Vec<&str> {
+        self.id
+            .as_deref()
+            .unwrap_or_default()
+            .split(';')
+            .map(str::trim)
+            .filter(|id| !id.is_empty() && *id != ".")
+            .collect()
+    }
+
+    /// Get the bases the record covers as a zero-based, half-open span.
+    ///
+    /// The span covers the REF allele, extended to [`sv_end`](Self::sv_end)
+    /// when that lies beyond it: INFO `END` (structural variants, gVCF
+    /// blocks), or POS + |SVLEN| for a symbolic `<DEL>`, `<DUP>`, `<INV>`
+    /// or `<CNV>` without END. Region overlap (`region()`, `in_bed()` and
+    /// interval annotations) is tested against this span.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use vcf_filter::FilterEngine;
+    ///
+    /// let engine = FilterEngine::new("").unwrap();
+    /// let snv = engine.parse_row("chr1\t100\t.\tA\tG\t50\tPASS\t.").unwrap();
+    /// assert_eq!(snv.span(), (99, 100));
+    /// let cnv = engine.parse_row("chr1\t100\t.\tA\t<DEL>\t50\tPASS\tSVLEN=-500").unwrap();
+    /// assert_eq!(cnv.span(), (99, 600));
+    /// ```
+    pub fn span(&self) -> (u64, u64) {
+        let start = self.pos.saturating_sub(1);
+        let ref_end = start + self.ref_allele.len().max(1) as u64;
+        (start, self.sv_end().max(ref_end))
+    }
+
+    /// Get the indexes of the ALT alleles other than the spanning deletion `*`.
+    ///
+    /// `*` stands for a deletion described by another record, so per-allele
+    /// filters (`AF[alt] > 0.01`) skip it and allele counts leave it out.
+    pub fn alt_indexes(&self) -> Vec<usize> {
+        (0..self.alt_alleles.len())
+            .filter(|&i| self.alt_alleles[i] != "*")
+            .collect()
+    }
+
+    /// Whether every ALT allele is the spanning deletion `*`.
+    pub fn is_spanning_deletion_only(&self) -> bool {
+        !self.alt_alleles.is_empty() && self.alt_alleles.iter().all(|alt| alt == "*")
+    }
+
+    /// Get the structural variant type of the record.
+    ///
+    /// Taken from INFO `SVTYPE` when present, else from the first symbolic
+    /// ALT allele (`DUP` for `<DUP:TANDEM>`) or breakend (`BND`). `None`
+    /// for records without structural alleles.
+    pub fn sv_type(&self) -> Option<&str> {
+        if let Some(Value::String(svtype)) = self.info.get("SVTYPE") {
+            return Some(svtype);
+        }
+        self.alt_alleles
+            .iter()
+            .find_map(|alt| match Breakend::parse(alt) {
+                Some(_) => Some("BND"),
+                None => symbolic_type(alt),
+            })
+    }
+
+    /// Parse the breakend of each alternate allele, in ALT order.
+    pub fn breakends(&self) -> Vec<Option<Breakend>> {
+        self.alt_alleles
+            .iter()
+            .map(|alt| Breakend::parse(alt))
+            .collect()
+    }
+
+    /// Get the 1-based position of the record's last affected base.
+    ///
+    /// INFO `END` when set; otherwise a symbolic deletion, duplication,
+    /// inversion or copy-number allele ends at POS + |SVLEN|, and any other
+    /// record at the last base of REF.
+    pub fn sv_end(&self) -> u64 {
+        if let Some(end) = self.info.get("END").and_then(Value::as_number) {
+            return end as u64;
+        }
+        let svlen = match self.info.get("SVLEN") {
+            Some(Value::Array(items)) => items.first().and_then(Value::as_number),
+            Some(value) => value.as_number(),
+            None => None,
+        };
+        let spans_bases = self
+            .alt_alleles
+            .iter()
+            .filter_map(|alt| symbolic_type(alt))
+            .any(|svtype| matches!(svtype, "DEL" | "DUP" | "INV" | "CNV"));
+        match svlen {
+            Some(len) if spans_bases => self.pos + len.abs() as u64,
+            _ => self.pos + self.ref_allele.len().max(1) as u64 - 1,
+        }
+    }
+
+    /// Split a multiallelic record into one record per ALT allele.
+    ///
+    /// Values of `Number=A`, `R` and `G` INFO fields are sliced to the
+    /// allele, as are the reserved FORMAT lists (AD, ADF, ADR, EC, GL, GP,
+    /// PL). In GT, the allele becomes `1` and other ALT alleles `0`, as
+    /// `bcftools norm -m-` writes them. Values whose length does not match
+    /// the site are kept whole. Records with one ALT allele are returned
+    /// as they are; each split record's [`raw_line`](Self::raw_line) is
+    /// its serialized text.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use vcf_filter::FilterEngine;
+    ///
+    /// let header = r#"##INFO=<ID=AF,Number=A,Type=Float,Description="Allele frequency">"#;
+    /// let engine = FilterEngine::new(header).unwrap();
+    /// let row = engine
+    ///     .parse_row("chr1\t100\t.\tA\tG,T\t50\tPASS\tAF=0.1,0.2;DP=30\tGT:AD\t1/2:3,10,12")
+    ///     .unwrap();
+    /// let lines: Vec<String> = row
+    ///     .split_multiallelic(engine.info_map())
+    ///     .iter()
+    ///     .map(|split| split.to_vcf_line())
+    ///     .collect();
+    /// assert_eq!(
+    ///     lines,
+    ///     [
+    ///         "chr1\t100\t.\tA\tG\t50\tPASS\tAF=0.1;DP=30\tGT:AD\t1/0:3,10",
+    ///         "chr1\t100\t.\tA\tT\t50\tPASS\tAF=0.2;DP=30\tGT:AD\t0/1:3,12",
+    ///     ]
+    /// );
+    /// ```
+    pub fn split_multiallelic(&self, info_map: &InfoMap) -> Vec<VcfRow> {
+        self.split_with_format(info_map, &FormatMap::new())
+    }
+
+    /// Split a record, slicing FORMAT keys by their `format_map` numbers
+    /// and the reserved definitions of undeclared ones.
+    pub(crate) fn split_with_format(
+        &self,
+        info_map: &InfoMap,
+        format_map: &FormatMap,
+    ) -> Vec<VcfRow> {
+        if self.alt_alleles.len() < 2 {
+            return vec![self.clone()];
+        }
+        (0..self.alt_alleles.len())
+            .map(|alt| self.split_allele(alt, info_map, format_map))
+            .collect()
+    }
+
+    /// The record restricted to one ALT allele; see [`split_multiallelic`](Self::split_multiallelic).
+    fn split_allele(&self, alt: usize, info_map: &InfoMap, format_map: &FormatMap) -> VcfRow {
+        let alts = self.alt_alleles.len();
+        let mut row = self.clone();
+        row.alt_alleles = vec![self.alt_alleles[alt].clone()];
+
+        for (key, text) in row.raw.info.iter_mut() {
+            let (Some(field), Some(value)) = (info_map.get(key), text.as_mut()) else {
+                continue;
+            };
+            let elements: Vec<&str> = value.split(',').collect();
+            let Some(indexes) = allele_indexes(&field.number, alt, alts, elements.len()) else {
+                continue;
+            };
+            *value = pick(&elements, &indexes).join(",");
+            if let Some(parsed) = row.info.get_mut(key) {
+                slice_value(parsed, &indexes, &field.number);
+            }
+        }
+
+        let format_number = |key: &str| {
+            format_map
+                .get(key)
+                .or_else(|| RESERVED_FORMAT.get(key))
+                .map(|field| &field.number)
+        };
+        for sample in &mut row.samples {
+            for (key, value) in sample.iter_mut() {
+                match (key.as_str(), &*value) {
+                    ("GT", Value::String(gt)) => *value = Value::String(split_genotype(gt, alt)),
+                    (_, Value::Array(items)) => {
+                        if let Some(number) = format_number(key)
+                            && let Some(indexes) = allele_indexes(number, alt, alts, items.len())
+                        {
+                            slice_value(value, &indexes, number);
+                        }
+                    }
+                    _ => {}
                 }
             }
-            _ => {
-                // Check INFO fields first, then FORMAT fields.
-                // INFO is the primary namespace for filter fields and should
-                // not be shadowed by FORMAT fields with the same name (e.g., DP).
-                if let Some(val) = self.info.get(field) {
-                    val.clone()
-                } else {
-                    self.format.get(field).cloned().unwrap_or(Value::Missing)
+        }
+        row.format = row.samples.first().cloned().unwrap_or_default();
+        row.genotypes = row
+            .samples
+            .iter()
+            .map(|sample| Genotype::parse(sample.get("GT")?.as_string()?))
+            .collect();
+
+        // Rewrite the sample text from the original columns
+        let genotype_columns = self.raw.samples.map(|start| {
+            let mut columns = self.raw.line[start..].split('\t');
+            let keys: Vec<&str> = columns.next().unwrap_or_default().split(':').collect();
+            let samples = columns.map(|sample| {
+                sample
+                    .split(':')
+                    .zip(&keys)
+                    .map(|(token, key)| match *key {
+                        "GT" => split_genotype(token, alt),
+                        _ => {
+                            let elements: Vec<&str> = token.split(',').collect();
+                            format_number(key)
+                                .and_then(|number| {
+                                    allele_indexes(number, alt, alts, elements.len())
+                                })
+                                .map_or_else(
+                                    || token.to_string(),
+                                    |indexes| pick(&elements, &indexes).join(","),
+                                )
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join(":")
+            });
+            std::iter::once(keys.join(":"))
+                .chain(samples)
+                .collect::<Vec<_>>()
+                .join("\t")
+        });
+        row.raw.samples = None;
+        let mut line = row.to_vcf_line();
+        if let Some(columns) = genotype_columns {
+            line.push('\t');
+            row.raw.samples = Some(line.len());
+            line.push_str(&columns);
+        }
+        row.raw.line = line;
+        row
+    }
+
+    /// Classify each alternate allele, in ALT order.
+    ///
+    /// Every allele of a record with an INFO `SVTYPE` is [`VariantClass::Bnd`]
+    /// for `SVTYPE=BND` and [`VariantClass::Sv`] otherwise.
+    pub fn variant_classes(&self) -> Vec<VariantClass> {
+        let svtype = self.info.get("SVTYPE").map(|svtype| match svtype {
+            Value::String(s) if s == "BND" => VariantClass::Bnd,
+            _ => VariantClass::Sv,
+        });
+        self.alt_alleles
+            .iter()
+            .map(|alt| svtype.unwrap_or_else(|| VariantClass::of(&self.ref_allele, alt)))
+            .collect()
+    }
+
+    /// Get the signed length change of each alternate allele, in ALT order.
+    ///
+    /// Insertions are positive and deletions negative; SNPs and MNPs are 0.
+    /// Symbolic alleles take their length from SVLEN (negated for `<DEL>`
+    /// alleles, which VCF 4.4+ writes as positive), falling back to END for
+    /// deletions. Breakends and `*`/`.` alleles have no length.
+    pub fn indel_lengths(&self) -> Vec<Option<f64>> {
+        let svlens = match self.info.get("SVLEN") {
+            Some(Value::Array(items)) => items.iter().map(Value::as_number).collect(),
+            Some(value) => vec![value.as_number()],
+            None => Vec::new(),
+        };
+        self.alt_alleles
+            .iter()
+            .enumerate()
+            .map(|(i, alt)| {
+                let class = VariantClass::of(&self.ref_allele, alt);
+                match class {
+                    VariantClass::Sv => {
+                        let is_deletion = alt.starts_with("<DEL");
+                        let svlen = svlens.get(i).or(svlens.first()).copied().flatten();
+                        match svlen {
+                            Some(len) if is_deletion => Some(-len.abs()),
+                            Some(len) => Some(len),
+                            None if is_deletion => {
+                                let (start, end) = self.span();
+                                Some(-((end - start - 1) as f64)).filter(|&len| len < 0.0)
+                            }
+                            None => None,
+                        }
+                    }
+                    VariantClass::Bnd | VariantClass::Other => None,
+                    _ => Some(alt.len() as f64 - self.ref_allele.len() as f64),
+                }
+            })
+            .collect()
+    }
+
+    /// Get the parsed GT of a sample (zero-based, in header column order).
+    ///
+    /// Returns `None` if the sample has no GT or it cannot be parsed.
+    pub fn genotype(&self, sample: usize) -> Option<&Genotype> {
+        self.genotypes.get(sample)?.as_ref()
+    }
+
+    /// Number of sample columns in the record.
+    pub fn sample_count(&self) -> usize {
+        self.samples.len().max(usize::from(!self.format.is_empty()))
+    }
+
+    /// Get a FORMAT value of a sample (zero-based, in header column order).
+    pub fn sample_value(&self, sample: usize, key: &str) -> Option<&Value> {
+        match self.samples.get(sample) {
+            Some(sample) => sample.get(key),
+            None if sample == 0 => self.format.get(key),
+            None => None,
+        }
+    }
+
+    /// Get a sample's FORMAT value as numbers (`AD=10,5` gives `[10, 5]`).
+    ///
+    /// Returns `None` if the field is absent or any element is not a number.
+    pub fn sample_numbers(&self, sample: usize, key: &str) -> Option<Vec<f64>> {
+        value_numbers(self.sample_value(sample, key)?)
+    }
+
+    /// Compute a sample's variant allele fraction for each alternate allele.
+    ///
+    /// Uses the AD allele depths (alt depth / total depth), falling back to
+    /// the sample's AF and then to AO/DP. Returns `None` if none of these is
+    /// available or the total depth is zero.
+    pub fn variant_allele_fractions(&self, sample: usize) -> Option<Vec<f64>> {
+        if let Some(depths) = self.sample_numbers(sample, "AD")
+            && depths.len() >= 2
+        {
+            let total: f64 = depths.iter().sum();
+            return (total > 0.0).then(|| depths[1..].iter().map(|d| d / total).collect());
+        }
+        if let Some(fractions) = self.sample_numbers(sample, "AF") {
+            return Some(fractions);
+        }
+        let observed = self.sample_numbers(sample, "AO")?;
+        match self.sample_numbers(sample, "DP")?.as_slice() {
+            [depth] if *depth > 0.0 => Some(observed.iter().map(|ao| ao / depth).collect()),
+            _ => None,
+        }
+    }
+
+    /// Recompute per-allele frequencies as AC/AN.
+    ///
+    /// Returns `None` if AC or AN is missing or non-numeric, or AN is zero.
+    pub fn computed_allele_frequencies(&self) -> Option<Vec<f64>> {
+        let counts = numeric_elements(self.info.get("AC"))?;
+        let total = numeric_elements(self.info.get("AN"))?;
+        match total.as_slice() {
+            [an] if *an > 0.0 => Some(counts.iter().map(|ac| ac / an).collect()),
+            _ => None,
+        }
+    }
+
+    /// Compare the stored AF against AC/AN for every alternate allele.
+    ///
+    /// Returns `None` when AF, AC or AN is unavailable, so the record cannot
+    /// be checked. Otherwise returns the alleles whose AF differs from AC/AN
+    /// by more than `tolerance`, or whose AF and AC counts do not line up;
+    /// an empty list means the record is consistent.
+    pub fn allele_frequency_mismatches(
+        &self,
+        tolerance: f64,
+    ) -> Option<Vec<AlleleFrequencyMismatch>> {
+        let stored = numeric_elements(self.info.get("AF"))?;
+        let computed = self.computed_allele_frequencies()?;
+
+        let mismatches = (0..stored.len().max(computed.len()))
+            .filter_map(|allele| {
+                let (stored, computed) = (stored.get(allele), computed.get(allele));
+                let consistent = matches!(
+                    (stored, computed),
+                    (Some(s), Some(c)) if (s - c).abs() <= tolerance
+                );
+                (!consistent).then(|| AlleleFrequencyMismatch {
+                    allele,
+                    stored: stored.copied(),
+                    computed: computed.copied(),
+                })
+            })
+            .collect();
+        Some(mismatches)
+    }
+}
+
+impl VcfRow {
+    /// Get the annotations of a structured INFO field, by subfield name.
+    ///
+    /// Each annotation maps the field's subfield names (from the header, or
+    /// declared with `with_structured_field`) to their text; subfields an
+    /// annotation leaves out are absent from its map. Returns an empty list
+    /// when the field is absent, `.`, or not structured.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use vcf_filter::FilterEngine;
+    ///
+    /// let header = r#"##INFO=<ID=ANN,Number=.,Type=String,Description="Functional annotations: 'Allele | Annotation | Gene_Name'">"#;
+    /// let engine = FilterEngine::new(header).unwrap();
+    /// let row = engine
+    ///     .parse_row("chr1\t100\t.\tA\tG\t50\tPASS\tANN=G|missense_variant|BRCA1,G|intron_variant")
+    ///     .unwrap();
+    ///
+    /// let annotations = row.annotations("ANN", engine.info_map());
+    /// assert_eq!(annotations.len(), 2);
+    /// assert_eq!(annotations[0]["Gene_Name"], "BRCA1");
+    /// assert_eq!(annotations[1]["Annotation"], "intron_variant");
+    /// assert!(!annotations[1].contains_key("Gene_Name"));
+    /// ```
+    pub fn annotations(&self, field: &str, info_map: &InfoMap) -> Vec<Annotation> {
+        let Some(meta) = info_map.get(field) else {
+            return Vec::new();
+        };
+        let Some(subfield_names) = &meta.subfields else {
+            return Vec::new();
+        };
+        // Keys left unparsed by a compiled filter are parsed here
+        let deferred = match self.info.get(field) {
+            Some(_) => None,
+            None if self.raw.deferred.iter().any(|key| key == field) => self
+                .raw
+                .info
+                .iter()
+                .find(|(key, _)| key == field)
+                .and_then(|(_, value)| value.as_deref())
+                .and_then(|raw| parse_info_value(raw, meta, &ParseOptions::default()).ok()),
+            None => None,
+        };
+        let Some(Value::Array(annotations)) = deferred.as_ref().or(self.info.get(field)) else {
+            return Vec::new();
+        };
+        annotations
+            .iter()
+            .filter_map(|annotation| match annotation {
+                Value::Array(values) => Some(
+                    subfield_names
+                        .iter()
+                        .zip(values)
+                        .filter(|(_, value)| !value.is_missing())
+                        .map(|(name, value)| (name.clone(), value.to_vcf_string()))
+                        .collect(),
+                ),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Cross-check LOF/NMD gene names against the ANN gene names of this record.
+    ///
+    /// SnpEff derives LOF and NMD from the same transcripts it reports in ANN,
+    /// so a gene present in LOF/NMD but absent from ANN indicates an
+    /// inconsistent or partially-updated annotation. Returns an empty list
+    /// when the record is consistent or has no ANN field.
+    pub fn lof_gene_mismatches(&self, info_map: &InfoMap) -> Vec<AnnotationMismatch> {
+        let ann_genes = get_all_annotation_subfields(self, "ANN", "Gene_Name", info_map);
+        if ann_genes.is_empty() {
+            return Vec::new();
+        }
+
+        let mut mismatches = Vec::new();
+        for field in ["LOF", "NMD"] {
+            for gene in get_all_annotation_subfields(self, field, "Gene_Name", info_map) {
+                let Value::String(gene) = gene else {
+                    continue;
+                };
+                if !ann_genes.iter().any(|g| g.as_string() == Some(&gene)) {
+                    mismatches.push(AnnotationMismatch {
+                        field: field.to_string(),
+                        gene,
+                    });
                 }
             }
         }
+
+        mismatches
+    }
+
+    /// Get the phred-scaled genotype likelihoods of the first sample.
+    ///
+    /// Uses the `PL` FORMAT field when present, otherwise converts `GL`
+    /// (log10 likelihoods) to phred scale. Returns `None` if neither field is
+    /// present or any element is missing or non-numeric.
+    pub fn genotype_likelihoods(&self) -> Option<Vec<f64>> {
+        let (value, scale) = match (self.format.get("PL"), self.format.get("GL")) {
+            (Some(pl), _) if *pl != Value::Missing => (pl, 1.0),
+            (_, Some(gl)) if *gl != Value::Missing => (gl, -10.0),
+            _ => return None,
+        };
+
+        let values = value_numbers(value)?;
+        Some(values.into_iter().map(|v| v * scale).collect())
+    }
+}
+
+/// Get the numbers of a FORMAT value, typed or as comma-separated text.
+///
+/// Returns `None` if any element is missing or not a number.
+fn value_numbers(value: &Value) -> Option<Vec<f64>> {
+    match value {
+        Value::Number(n) => Some(vec![*n]),
+        Value::Array(values) => values.iter().map(Value::as_number).collect(),
+        Value::String(raw) => raw
+            .split(',')
+            .map(|element| element.trim().parse::<f64>().ok())
+            .collect(),
+        _ => None,
+    }
+}
+
+/// Parse a single numeric element of an Integer/Float INFO or FORMAT field.
+fn parse_number_element(raw: &str, field: &InfoField, options: &ParseOptions) -> Result<Value> {
+    if raw == "." {
+        return match options.missing_values {
+            MissingValueMode::Lenient => Ok(Value::Missing),
+            MissingValueMode::Strict => Err(VcfFilterError::RowParseError(format!(
+                "Missing element '.' in {} field {}",
+                type_label(&field.field_type),
+                field.id
+            ))),
+        };
+    }
+
+    let parsed = match field.field_type {
+        InfoType::Integer => raw.parse::<i64>().map(|n| n as f64).ok(),
+        _ => raw.parse::<f64>().ok(),
+    };
+
+    match (parsed, options.missing_values) {
+        (Some(n), _) => Ok(options.number(n)),
+        (None, MissingValueMode::Lenient) => Ok(Value::String(raw.to_string())),
+        (None, MissingValueMode::Strict) => Err(VcfFilterError::RowParseError(format!(
+            "Invalid {} value '{}' for field {}",
+            type_label(&field.field_type),
+            raw,
+            field.id
+        ))),
+    }
+}
+
+/// Human-readable name of a numeric INFO type for error messages.
+fn type_label(field_type: &InfoType) -> &'static str {
+    match field_type {
+        InfoType::Integer => "Integer",
+        _ => "Float",
+    }
+}
+
+/// Parse a string list element, mapping `.` to `Value::Missing`.
+fn parse_string_element(raw: &str, options: &ParseOptions) -> Value {
+    if raw == "." {
+        Value::Missing
+    } else {
+        Value::String(options.decode(raw).into_owned())
+    }
+}
+
+/// Characters VCF 4.3 requires to be percent-encoded in INFO values.
+const ENCODED_CHARS: &[char] = &['%', ':', ';', '=', ',', '\r', '\n', '\t'];
+
+/// Decode the `%XX` escapes VCF 4.3 reserves in an INFO value (`a%3Bb` is `a;b`).
+///
+/// Only escapes of the characters [`percent_encode`] writes are decoded, so
+/// the two are inverses; other escapes (`%20`) and malformed ones are kept
+/// as written.
+pub fn percent_decode(raw: &str) -> Cow<'_, str> {
+    if !raw.contains('%') {
+        return Cow::Borrowed(raw);
+    }
+    let bytes = raw.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| raw.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            .filter(|byte| ENCODED_CHARS.contains(&char::from(*byte)));
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    Cow::Owned(String::from_utf8_lossy(&decoded).into_owned())
+}
+
+/// Encode the characters VCF 4.3 reserves in INFO values (`a;b` is `a%3Bb`).
+pub fn percent_encode(value: &str) -> Cow<'_, str> {
+    if !value.contains(ENCODED_CHARS) {
+        return Cow::Borrowed(value);
+    }
+    let mut encoded = String::with_capacity(value.len() + 8);
+    for c in value.chars() {
+        if ENCODED_CHARS.contains(&c) {
+            encoded.push_str(&format!("%{:02X}", c as u32));
+        } else {
+            encoded.push(c);
+        }
+    }
+    Cow::Owned(encoded)
+}
+
+/// Set, replace or remove one INFO entry of a VCF data line.
+///
+/// `Some("")` writes `id` as a flag, `Some(value)` writes `id=value` (the
+/// value must already be encoded) and `None` removes the entry. Any existing
+/// entry for `id` is dropped, and an empty INFO column is written as `.`.
+/// Lines with fewer than 8 columns are returned unchanged.
+pub fn set_info(line: &str, id: &str, value: Option<&str>) -> String {
+    let mut fields: Vec<&str> = line.split('\t').collect();
+    if fields.len() < 8 {
+        return line.to_string();
+    }
+
+    let mut entries: Vec<String> = fields[7]
+        .split(';')
+        .filter(|entry| *entry != "." && entry.split('=').next() != Some(id))
+        .map(String::from)
+        .collect();
+    match value {
+        Some("") => entries.push(id.to_string()),
+        Some(value) => entries.push(format!("{}={}", id, value)),
+        None => {}
+    }
+
+    let info = if entries.is_empty() {
+        ".".to_string()
+    } else {
+        entries.join(";")
+    };
+    fields[7] = &info;
+    fields.join("\t")
+}
+
+/// Whether a string could be written percent-encoded in an INFO value.
+pub(crate) fn has_encoded_chars(value: &str) -> bool {
+    value.contains(ENCODED_CHARS)
+}
+
+/// Structured subfields that always hold numbers (SnpEff LOF/NMD).
+const NUMERIC_SUBFIELDS: &[&str] = &[
+    "Number_of_transcripts_in_gene",
+    "Percent_of_transcripts_affected",
+];
+
+/// Parse a single subfield of a structured annotation.
+fn parse_subfield_value(name: &str, raw: &str, options: &ParseOptions) -> Value {
+    if NUMERIC_SUBFIELDS.contains(&name)
+        && let Ok(n) = raw.parse::<f64>()
+    {
+        return Value::Number(n);
     }
+    Value::String(options.decode(raw).into_owned())
 }
 
 /// Parse INFO field values based on their type.
-fn parse_info_value(raw: &str, field: &InfoField) -> Value {
+fn parse_info_value(raw: &str, field: &InfoField, options: &ParseOptions) -> Result<Value> {
+    if raw == "." {
+        return Ok(Value::Missing);
+    }
+
     // Handle structured fields with subfields (like ANN)
     if let Some(ref subfield_names) = field.subfields {
-        // Split by comma for multiple annotations
+        // Split into annotations, then each annotation into subfields
         let annotations: Vec<Value> = raw
-            .split(',')
+            .split(field.delimiters.record)
             .map(|ann| {
-                let parts: Vec<&str> = ann.split('|').collect();
-                let mut map = HashMap::new();
-                for (i, name) in subfield_names.iter().enumerate() {
-                    if let Some(val) = parts.get(i) {
-                        map.insert(name.clone(), val.to_string());
-                    }
-                }
-                // Convert to a nested Value structure
+                // SnpEff wraps each LOF/NMD entry in parentheses: `(PRG4|ENSG...|4|1.00)`
+                let ann = ann
+                    .strip_prefix('(')
+                    .and_then(|inner| inner.strip_suffix(')'))
+                    .unwrap_or(ann);
+                let parts: Vec<&str> = ann.split(field.delimiters.subfield).collect();
+                // Convert to a nested Value structure indexed by subfield position
                 Value::Array(
                     subfield_names
                         .iter()
-                        .map(|name| {
-                            map.get(name)
-                                .map(|v| Value::String(v.clone()))
-                                .unwrap_or(Value::Missing)
+                        .enumerate()
+                        .map(|(i, name)| match parts.get(i) {
+                            Some(val) => parse_subfield_value(name, val, options),
+                            None => Value::Missing,
                         })
                         .collect(),
                 )
             })
             .collect();
 
-        // Store as array of arrays, but also keep the raw parsed data accessible
-        return Value::Array(annotations);
+        return Ok(Value::Array(annotations));
     }
 
     // Handle based on type and number
     match (&field.number, &field.field_type) {
-        (InfoNumber::Count(1), InfoType::Integer) => raw
-            .parse::<i64>()
-            .map(|n| Value::Number(n as f64))
-            .unwrap_or(Value::String(raw.to_string())),
-        (InfoNumber::Count(1), InfoType::Float) => raw
-            .parse::<f64>()
-            .map(Value::Number)
-            .unwrap_or(Value::String(raw.to_string())),
-        (InfoNumber::Flag, _) => Value::Bool(true),
-        (_, InfoType::Integer) => {
-            // Multiple integers
-            let values: Vec<Value> = raw
-                .split(',')
-                .map(|s| {
-                    s.parse::<i64>()
-                        .map(|n| Value::Number(n as f64))
-                        .unwrap_or(Value::String(s.to_string()))
-                })
-                .collect();
-            if values.len() == 1 {
-                values.into_iter().next().unwrap()
-            } else {
-                Value::Array(values)
-            }
-        }
-        (_, InfoType::Float) => {
-            // Multiple floats
-            let values: Vec<Value> = raw
+        (InfoNumber::Flag, _) => Ok(Value::Bool(true)),
+        (_, InfoType::Integer | InfoType::Float) => {
+            let values = raw
                 .split(',')
-                .map(|s| {
-                    s.parse::<f64>()
-                        .map(Value::Number)
-                        .unwrap_or(Value::String(s.to_string()))
-                })
-                .collect();
+                .map(|s| parse_number_element(s, field, options))
+                .collect::<Result<Vec<Value>>>()?;
             if values.len() == 1 {
-                values.into_iter().next().unwrap()
+                Ok(values.into_iter().next().unwrap())
             } else {
-                Value::Array(values)
+                Ok(Value::Array(values))
             }
         }
         _ => {
             // String or unknown - check for multiple values
             if raw.contains(',') && !raw.contains('|') {
-                Value::Array(
+                Ok(Value::Array(
                     raw.split(',')
-                        .map(|s| Value::String(s.to_string()))
+                        .map(|element| parse_string_element(element, options))
                         .collect(),
-                )
+                ))
             } else {
-                Value::String(raw.to_string())
+                Ok(Value::String(options.decode(raw).into_owned()))
             }
         }
     }
 }
 
 /// Parse INFO field when no metadata is available.
-fn parse_info_value_unknown(raw: &str) -> Value {
+fn parse_info_value_unknown(raw: &str, options: &ParseOptions) -> Value {
+    if raw == "." {
+        return Value::Missing;
+    }
+
     // Try to parse as number
     if let Ok(n) = raw.parse::<f64>() {
-        return Value::Number(n);
+        return options.number(n);
     }
 
     // Check for multiple values
     if raw.contains(',') && !raw.contains('|') {
         return Value::Array(
             raw.split(',')
-                .map(|s| Value::String(s.to_string()))
+                .map(|element| parse_string_element(element, options))
                 .collect(),
         );
     }
 
-    Value::String(raw.to_string())
+    Value::String(options.decode(raw).into_owned())
+}
+
+/// Split a String INFO value on any of `separators`.
+fn split_info_value(raw: &str, separators: &str, options: &ParseOptions) -> Value {
+    let mut values: Vec<Value> = raw
+        .split(|c| separators.contains(c))
+        .map(|element| element.trim_matches(|c| c == ' ' || c == '_'))
+        .filter(|element| !element.is_empty())
+        .map(|element| parse_string_element(element, options))
+        .collect();
+    match values.len() {
+        0 => Value::Missing,
+        1 => values.remove(0),
+        _ => Value::Array(values),
+    }
+}
+
+/// Parse an aligned per-transcript list, keeping `.` elements in place.
+///
+/// Elements are numbers when the field is declared Integer or Float, or
+/// when an undeclared element parses as one.
+fn parse_aligned_value(
+    raw: &str,
+    meta: Option<&InfoField>,
+    options: &ParseOptions,
+) -> Result<Value> {
+    if raw == "." {
+        return Ok(Value::Missing);
+    }
+    let values = raw
+        .split(',')
+        .map(|element| match meta {
+            _ if element == "." => Ok(Value::Missing),
+            Some(field) if matches!(field.field_type, InfoType::Integer | InfoType::Float) => {
+                parse_number_element(element, field, options)
+            }
+            Some(_) => Ok(parse_string_element(element, options)),
+            None => Ok(match element.parse::<f64>() {
+                Ok(n) => options.number(n),
+                Err(_) => parse_string_element(element, options),
+            }),
+        })
+        .collect::<Result<Vec<Value>>>()?;
+    Ok(Value::Array(values))
+}
+
+/// Parse the value of one `key=value` INFO entry.
+fn parse_info_entry(
+    key: &str,
+    value: &str,
+    info_map: &InfoMap,
+    options: &ParseOptions,
+) -> Result<Value> {
+    let meta = info_map.get(key);
+    let splittable =
+        meta.is_none_or(|meta| meta.subfields.is_none() && meta.field_type == InfoType::String);
+    Ok(match (meta, options.value_separators.get(key)) {
+        _ if options.is_aligned(key) => parse_aligned_value(value, meta, options)?,
+        (_, Some(separators)) if splittable => split_info_value(value, separators, options),
+        (Some(meta), _) => parse_info_value(value, meta, options)?,
+        (None, _) => parse_info_value_unknown(value, options),
+    })
 }
 
+/// The parsed INFO column: values by key, the entries as written, and the
+/// keys left unparsed.
+type InfoColumn = (HashMap<String, Value>, Vec<RawInfoEntry>, Vec<String>);
+
 /// Parse the INFO column into a map of field names to values.
-fn parse_info_column(info_str: &str, info_map: &InfoMap) -> HashMap<String, Value> {
+///
+/// With `keys`, only those keys are parsed; the others are recorded as
+/// unparsed.
+fn parse_info_column(
+    info_str: &str,
+    info_map: &InfoMap,
+    options: &ParseOptions,
+    keys: Option<&[String]>,
+) -> Result<InfoColumn> {
     let mut result = HashMap::new();
+    let mut raw = Vec::new();
+    let mut deferred = Vec::new();
 
     if info_str == "." {
-        return result;
+        return Ok((result, raw, deferred));
     }
 
     for field in info_str.split(';') {
@@ -212,20 +1633,24 @@ fn parse_info_column(info_str: &str, info_map: &InfoMap) -> HashMap<String, Valu
             continue;
         }
 
-        if let Some((key, value)) = field.split_once('=') {
-            let parsed_value = if let Some(field_meta) = info_map.get(key) {
-                parse_info_value(value, field_meta)
-            } else {
-                parse_info_value_unknown(value)
+        let (key, value) = match field.split_once('=') {
+            Some((key, value)) => (key, Some(value)),
+            // Flag field (no value)
+            None => (field, None),
+        };
+        if keys.is_some_and(|keys| !keys.iter().any(|k| k == key)) {
+            deferred.push(key.to_string());
+        } else {
+            let parsed_value = match value {
+                Some(value) => parse_info_entry(key, value, info_map, options)?,
+                None => Value::Bool(true),
             };
             result.insert(key.to_string(), parsed_value);
-        } else {
-            // Flag field (no value)
-            result.insert(field.to_string(), Value::Bool(true));
         }
+        raw.push((key.to_string(), value.map(str::to_string)));
     }
 
-    result
+    Ok((result, raw, deferred))
 }
 
 /// Parse a single VCF data row.
@@ -239,6 +1664,65 @@ fn parse_info_column(info_str: &str, info_map: &InfoMap) -> HashMap<String, Valu
 ///
 /// A parsed `VcfRow` structure.
 pub fn parse_row(row: &str, info_map: &InfoMap) -> Result<VcfRow> {
+    parse_row_with_options(row, info_map, &ParseOptions::default())
+}
+
+/// Parse a single VCF data row with explicit parsing options.
+///
+/// # Arguments
+///
+/// * `row` - A single line from the VCF file (tab-separated)
+/// * `info_map` - The INFO field metadata from the header
+/// * `options` - Controls handling of `.` tokens and typed values
+///
+/// # Returns
+///
+/// A parsed `VcfRow` structure.
+pub fn parse_row_with_options(
+    row: &str,
+    info_map: &InfoMap,
+    options: &ParseOptions,
+) -> Result<VcfRow> {
+    parse_row_with_format(row, info_map, &FormatMap::new(), options)
+}
+
+/// Parse a single VCF data row, typing FORMAT values from header metadata.
+///
+/// FORMAT keys declared `Type=Integer` or `Type=Float` are parsed as numbers
+/// (arrays for comma-separated values) like INFO values, as are undeclared
+/// list-typed keys reserved by the VCF specification, such as AD and PL;
+/// other keys stay strings.
+///
+/// # Arguments
+///
+/// * `row` - A single line from the VCF file (tab-separated)
+/// * `info_map` - The INFO field metadata from the header
+/// * `format_map` - The FORMAT field metadata from the header
+/// * `options` - Controls handling of `.` tokens and typed values
+///
+/// # Returns
+///
+/// A parsed `VcfRow` structure.
+pub fn parse_row_with_format(
+    row: &str,
+    info_map: &InfoMap,
+    format_map: &FormatMap,
+    options: &ParseOptions,
+) -> Result<VcfRow> {
+    parse_row_with_keys(row, info_map, format_map, options, None)
+}
+
+/// Parse a single VCF data row, parsing only the given INFO keys when `keys` is set.
+///
+/// Other INFO keys are kept as written: [`VcfRow::to_vcf_line`] writes them
+/// back unchanged and [`VcfRow::unparsed_info`] lists them.
+pub(crate) fn parse_row_with_keys(
+    row: &str,
+    info_map: &InfoMap,
+    format_map: &FormatMap,
+    options: &ParseOptions,
+    keys: Option<&[String]>,
+) -> Result<VcfRow> {
     let fields: Vec<&str> = row.split('\t').collect();
 
     if fields.len() < 8 {
@@ -271,7 +1755,10 @@ pub fn parse_row(row: &str, info_map: &InfoMap) -> Result<VcfRow> {
     let qual = if fields[5] == "." {
         None
     } else {
-        fields[5].parse::<f64>().ok()
+        fields[5]
+            .parse::<f64>()
+            .ok()
+            .filter(|qual| qual.is_finite() || options.non_finite == NonFiniteMode::Propagate)
     };
 
     let filter: Vec<String> = if fields[6] == "." {
@@ -280,14 +1767,22 @@ pub fn parse_row(row: &str, info_map: &InfoMap) -> Result<VcfRow> {
         fields[6].split(';').map(|s| s.to_string()).collect()
     };
 
-    let info = parse_info_column(fields[7], info_map);
+    let (info, raw_info, deferred) = parse_info_column(fields[7], info_map, options, keys)?;
 
     // Parse FORMAT and sample columns if present (columns 9 and 10+)
-    let format = if fields.len() >= 10 {
-        parse_format_columns(fields[8], fields[9])
+    let samples: Vec<HashMap<String, Value>> = if fields.len() >= 10 {
+        fields[9..]
+            .iter()
+            .map(|sample| parse_format_columns(fields[8], sample, format_map, options))
+            .collect::<Result<_>>()?
     } else {
-        HashMap::new()
+        Vec::new()
     };
+    let format = samples.first().cloned().unwrap_or_default();
+    let genotypes = samples
+        .iter()
+        .map(|sample| Genotype::parse(sample.get("GT")?.as_string()?))
+        .collect();
 
     Ok(VcfRow {
         chrom,
@@ -299,14 +1794,50 @@ pub fn parse_row(row: &str, info_map: &InfoMap) -> Result<VcfRow> {
         filter,
         info,
         format,
+        samples,
+        genotypes,
+        raw: RawColumns {
+            qual: fields[5].to_string(),
+            info: raw_info,
+            deferred,
+            // The first 8 columns and their tabs come before FORMAT
+            samples: (fields.len() > 8)
+                .then(|| fields[..8].iter().map(|column| column.len() + 1).sum()),
+            line: row.to_string(),
+        },
     })
 }
 
+/// The list-typed numeric FORMAT keys reserved by the VCF specification.
+const RESERVED_FORMAT_LINES: &str = concat!(
+    "##FORMAT=<ID=AD,Number=R,Type=Integer,Description=\"Read depth for each allele\">\n",
+    "##FORMAT=<ID=ADF,Number=R,Type=Integer,Description=\"Read depth for each allele on the forward strand\">\n",
+    "##FORMAT=<ID=ADR,Number=R,Type=Integer,Description=\"Read depth for each allele on the reverse strand\">\n",
+    "##FORMAT=<ID=EC,Number=A,Type=Integer,Description=\"Expected alternate allele counts\">\n",
+    "##FORMAT=<ID=GL,Number=G,Type=Float,Description=\"Genotype likelihoods\">\n",
+    "##FORMAT=<ID=GP,Number=G,Type=Float,Description=\"Genotype posterior probabilities\">\n",
+    "##FORMAT=<ID=HQ,Number=2,Type=Integer,Description=\"Haplotype quality\">\n",
+    "##FORMAT=<ID=PL,Number=G,Type=Integer,Description=\"Phred-scaled genotype likelihoods\">",
+);
+
+/// Definitions of the reserved list-typed FORMAT keys, for headers that omit them.
+static RESERVED_FORMAT: LazyLock<FormatMap> = LazyLock::new(|| {
+    parse_format_header(RESERVED_FORMAT_LINES).expect("reserved FORMAT lines parse")
+});
+
 /// Parse FORMAT and sample columns into a HashMap.
 ///
 /// FORMAT column contains colon-separated field names (e.g., "GT:DP:GQ"),
 /// and sample column contains corresponding colon-separated values (e.g., "0/1:30:99").
-fn parse_format_columns(format_str: &str, sample_str: &str) -> HashMap<String, Value> {
+/// Values of numeric keys in `format_map` are parsed as numbers, as are the
+/// list-typed keys the VCF specification reserves (AD, ADF, ADR, EC, GL, GP,
+/// HQ, PL) when `format_map` does not declare them.
+fn parse_format_columns(
+    format_str: &str,
+    sample_str: &str,
+    format_map: &FormatMap,
+    options: &ParseOptions,
+) -> Result<HashMap<String, Value>> {
     let mut result = HashMap::new();
 
     let format_keys: Vec<&str> = format_str.split(':').collect();
@@ -314,16 +1845,27 @@ fn parse_format_columns(format_str: &str, sample_str: &str) -> HashMap<String, V
 
     for (i, key) in format_keys.iter().enumerate() {
         if let Some(value) = sample_values.get(i) {
-            let val = if *value == "." {
-                Value::Missing
-            } else {
-                Value::String(value.to_string())
+            let meta = format_map.get(*key).or_else(|| RESERVED_FORMAT.get(*key));
+            let val = match meta {
+                _ if *value == "." => Value::Missing,
+                Some(meta) if matches!(meta.field_type, InfoType::Integer | InfoType::Float) => {
+                    let mut values = value
+                        .split(',')
+                        .map(|element| parse_number_element(element, meta, options))
+                        .collect::<Result<Vec<Value>>>()?;
+                    if values.len() == 1 {
+                        values.remove(0)
+                    } else {
+                        Value::Array(values)
+                    }
+                }
+                _ => Value::String(value.to_string()),
             };
             result.insert(key.to_string(), val);
         }
     }
 
-    result
+    Ok(result)
 }
 
 /// Helper to access a subfield from a structured annotation.
@@ -482,6 +2024,30 @@ mod tests {
         assert_eq!(parsed.get("POS"), Value::Number(12345.0));
         assert_eq!(parsed.get("QUAL"), Value::Number(30.5));
         assert_eq!(parsed.get("FILTER"), Value::String("PASS".to_string()));
+        assert_eq!(parsed.get("ID"), Value::String("rs123".to_string()));
+
+        let row = "chr1\t12345\trs123;COSV456\tA\tG\t30.5\tq10;s50\t.";
+        let parsed = parse_row(row, &info_map).unwrap();
+        assert_eq!(parsed.ids(), ["rs123", "COSV456"]);
+        assert_eq!(
+            parsed.get("FILTER"),
+            Value::Array(vec![
+                Value::String("q10".to_string()),
+                Value::String("s50".to_string())
+            ])
+        );
+        assert_eq!(
+            parsed.get("ID"),
+            Value::Array(vec![
+                Value::String("rs123".to_string()),
+                Value::String("COSV456".to_string())
+            ])
+        );
+
+        let row = "chr1\t12345\t.\tA\tG\t30.5\t.\t.";
+        let parsed = parse_row(row, &info_map).unwrap();
+        assert_eq!(parsed.get("ID"), Value::Missing);
+        assert_eq!(parsed.get("FILTER"), Value::Missing);
     }
 
     #[test]
@@ -502,4 +2068,567 @@ mod tests {
 
         assert_eq!(parsed.get("DP"), Value::String("15".to_string()));
     }
+
+    #[test]
+    fn test_dot_elements_parse_as_missing() {
+        let header = r#"##INFO=<ID=AD,Number=R,Type=Integer,Description="Allele depths">
+##INFO=<ID=CADD,Number=1,Type=Float,Description="CADD score">"#;
+        let info_map = parse_header(header).unwrap();
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\tAD=10,.;CADD=.;OTHER=a,.";
+        let parsed = parse_row(row, &info_map).unwrap();
+
+        assert_eq!(
+            parsed.info.get("AD"),
+            Some(&Value::Array(vec![Value::Number(10.0), Value::Missing]))
+        );
+        assert_eq!(parsed.info.get("CADD"), Some(&Value::Missing));
+        assert_eq!(
+            parsed.info.get("OTHER"),
+            Some(&Value::Array(vec![
+                Value::String("a".to_string()),
+                Value::Missing
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_strict_missing_value_mode() {
+        let header = r#"##INFO=<ID=AD,Number=R,Type=Integer,Description="Allele depths">
+##INFO=<ID=CADD,Number=1,Type=Float,Description="CADD score">"#;
+        let info_map = parse_header(header).unwrap();
+        let options = ParseOptions {
+            missing_values: MissingValueMode::Strict,
+            ..ParseOptions::default()
+        };
+
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\tAD=10,.";
+        assert!(parse_row_with_options(row, &info_map, &options).is_err());
+
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\tCADD=high";
+        assert!(parse_row_with_options(row, &info_map, &options).is_err());
+
+        // A whole-value `.` is always a valid missing marker
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\tCADD=.";
+        let parsed = parse_row_with_options(row, &info_map, &options).unwrap();
+        assert_eq!(parsed.info.get("CADD"), Some(&Value::Missing));
+    }
+
+    #[test]
+    fn test_aligned_prefix_values() {
+        let header = r#"##INFO=<ID=dbNSFP_CADD_phred,Number=A,Type=Float,Description="CADD phred">
+##INFO=<ID=CLNDN,Number=.,Type=String,Description="Disease name">"#;
+        let info_map = parse_header(header).unwrap();
+        let options = ParseOptions {
+            missing_values: MissingValueMode::Strict,
+            ..ParseOptions::default().with_aligned_prefix("dbNSFP_")
+        };
+
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\tdbNSFP_CADD_phred=23.1,.;dbNSFP_SIFT_pred=D;dbNSFP_GERP=.;CLNDN=x";
+        let parsed = parse_row_with_options(row, &info_map, &options).unwrap();
+        assert_eq!(
+            parsed.info["dbNSFP_CADD_phred"],
+            Value::Array(vec![Value::Number(23.1), Value::Missing])
+        );
+        assert_eq!(
+            parsed.info["dbNSFP_SIFT_pred"],
+            Value::Array(vec![Value::String("D".to_string())])
+        );
+        assert_eq!(parsed.info["dbNSFP_GERP"], Value::Missing);
+        assert_eq!(parsed.info["CLNDN"], Value::String("x".to_string()));
+    }
+
+    #[test]
+    fn test_clinvar_value_separators() {
+        let info_map = parse_header(HEADER).unwrap();
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\tCLNSIG=Pathogenic/Likely_pathogenic|_risk_factor;CLNSIGCONF=Benign(1)|Likely_benign(2);CLNDN=a/b";
+        let parsed = parse_row(row, &info_map).unwrap();
+        let strings = |values: &[&str]| {
+            Value::Array(
+                values
+                    .iter()
+                    .map(|s| Value::String(s.to_string()))
+                    .collect(),
+            )
+        };
+        assert_eq!(
+            parsed.info.get("CLNSIG"),
+            Some(&strings(&[
+                "Pathogenic",
+                "Likely_pathogenic",
+                "risk_factor"
+            ]))
+        );
+        assert_eq!(
+            parsed.info.get("CLNSIGCONF"),
+            Some(&strings(&["Benign(1)", "Likely_benign(2)"]))
+        );
+        assert_eq!(
+            parsed.info.get("CLNDN"),
+            Some(&Value::String("a/b".to_string()))
+        );
+
+        let options = ParseOptions::default()
+            .with_separators("CLNSIG", "")
+            .with_separators("CLNDN", "/");
+        let parsed = parse_row_with_options(row, &info_map, &options).unwrap();
+        assert_eq!(
+            parsed.info.get("CLNSIG"),
+            Some(&Value::String(
+                "Pathogenic/Likely_pathogenic|_risk_factor".to_string()
+            ))
+        );
+        assert_eq!(parsed.info.get("CLNDN"), Some(&strings(&["a", "b"])));
+    }
+
+    #[test]
+    fn test_percent_encoded_info_values() {
+        let info_map = parse_header(HEADER).unwrap();
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\tCLNDN=Long%3B_QT%2Csyndrome,x%3Dy;NOTE=100%25%20sure%2;ANN=G%2CT|missense_variant";
+        let parsed = parse_row(row, &info_map).unwrap();
+        assert_eq!(
+            parsed.info.get("CLNDN"),
+            Some(&Value::Array(vec![
+                Value::String("Long;_QT,syndrome".to_string()),
+                Value::String("x=y".to_string())
+            ]))
+        );
+        assert_eq!(
+            parsed.info.get("NOTE"),
+            Some(&Value::String("100%%20sure%2".to_string()))
+        );
+        assert_eq!(
+            get_annotation_subfield(&parsed, "ANN", 0, "Allele", &info_map),
+            Value::String("G,T".to_string())
+        );
+        assert_eq!(percent_encode("a;b=c%"), "a%3Bb%3Dc%25");
+        assert_eq!(percent_decode(&percent_encode("x:y,\tz")), "x:y,\tz");
+        // Escapes of characters VCF does not reserve are kept as written
+        assert_eq!(percent_decode("a%20b%3bc"), "a%20b;c");
+        assert_eq!(percent_decode(&percent_encode("a%20b")), "a%20b");
+    }
+
+    #[test]
+    fn test_non_finite_numbers() {
+        let header = r#"##INFO=<ID=CADD,Number=1,Type=Float,Description="CADD score">"#;
+        let info_map = parse_header(header).unwrap();
+        let row = "chr1\t100\t.\tA\tG\tnan\tPASS\tCADD=-Inf;SCORE=Infinity";
+
+        let parsed = parse_row(row, &info_map).unwrap();
+        assert!(parsed.qual.is_some_and(f64::is_nan));
+        assert_eq!(
+            parsed.info.get("CADD"),
+            Some(&Value::Number(f64::NEG_INFINITY))
+        );
+        assert_eq!(
+            parsed.info.get("SCORE"),
+            Some(&Value::Number(f64::INFINITY))
+        );
+
+        let options = ParseOptions {
+            non_finite: NonFiniteMode::Missing,
+            ..ParseOptions::default()
+        };
+        let parsed = parse_row_with_options(row, &info_map, &options).unwrap();
+        assert_eq!(parsed.qual, None);
+        assert_eq!(parsed.info.get("CADD"), Some(&Value::Missing));
+        assert_eq!(parsed.info.get("SCORE"), Some(&Value::Missing));
+    }
+
+    const LOF_HEADER: &str = r#"##INFO=<ID=ANN,Number=.,Type=String,Description="Functional annotations: 'Allele | Annotation | Annotation_Impact | Gene_Name | Gene_ID'">
+##INFO=<ID=LOF,Number=.,Type=String,Description="Predicted loss of function effects for this variant. Format: 'Gene_Name | Gene_ID | Number_of_transcripts_in_gene | Percent_of_transcripts_affected'">
+##INFO=<ID=NMD,Number=.,Type=String,Description="Predicted nonsense mediated decay effects for this variant. Format: 'Gene_Name | Gene_ID | Number_of_transcripts_in_gene | Percent_of_transcripts_affected'">"#;
+
+    #[test]
+    fn test_lof_numeric_subfields() {
+        let info_map = parse_header(LOF_HEADER).unwrap();
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\tLOF=PRG4|ENSG00000116690|4|0.75";
+        let parsed = parse_row(row, &info_map).unwrap();
+
+        assert_eq!(
+            get_annotation_subfield(
+                &parsed,
+                "LOF",
+                0,
+                "Number_of_transcripts_in_gene",
+                &info_map
+            ),
+            Value::Number(4.0)
+        );
+        assert_eq!(
+            get_annotation_subfield(
+                &parsed,
+                "LOF",
+                0,
+                "Percent_of_transcripts_affected",
+                &info_map
+            ),
+            Value::Number(0.75)
+        );
+        assert_eq!(
+            get_annotation_subfield(&parsed, "LOF", 0, "Gene_ID", &info_map),
+            Value::String("ENSG00000116690".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parenthesized_lof_entries() {
+        let info_map = parse_header(LOF_HEADER).unwrap();
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\tLOF=(PRG4|ENSG00000116690|4|1.00),(CAP1|ENSG00000131236|2|0.50)";
+        let parsed = parse_row(row, &info_map).unwrap();
+
+        let subfield =
+            |index, name| get_annotation_subfield(&parsed, "LOF", index, name, &info_map);
+        assert_eq!(subfield(0, "Gene_Name"), Value::String("PRG4".to_string()));
+        assert_eq!(
+            subfield(0, "Percent_of_transcripts_affected"),
+            Value::Number(1.0)
+        );
+        assert_eq!(subfield(1, "Gene_Name"), Value::String("CAP1".to_string()));
+        assert_eq!(
+            subfield(1, "Percent_of_transcripts_affected"),
+            Value::Number(0.5)
+        );
+    }
+
+    #[test]
+    fn test_lof_gene_mismatches() {
+        let info_map = parse_header(LOF_HEADER).unwrap();
+
+        let row =
+            "chr1\t100\t.\tA\tG\t50\tPASS\tANN=G|stop_gained|HIGH|PRG4|E1;LOF=(PRG4|E1|4|1.00)";
+        let parsed = parse_row(row, &info_map).unwrap();
+        assert!(parsed.lof_gene_mismatches(&info_map).is_empty());
+
+        let row =
+            "chr1\t100\t.\tA\tG\t50\tPASS\tANN=G|stop_gained|HIGH|PRG4|E1;NMD=(TPR|E2|1|1.00)";
+        let parsed = parse_row(row, &info_map).unwrap();
+        assert_eq!(
+            parsed.lof_gene_mismatches(&info_map),
+            vec![AnnotationMismatch {
+                field: "NMD".to_string(),
+                gene: "TPR".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_typed_format_values() {
+        let info_map = parse_header(HEADER).unwrap();
+        let format_map = crate::header::parse_format_header(
+            "##FORMAT=<ID=GQ,Number=1,Type=Integer,Description=\"Genotype quality\">\n\
+             ##FORMAT=<ID=PL,Number=G,Type=Integer,Description=\"Likelihoods\">\n\
+             ##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">",
+        )
+        .unwrap();
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\t.\tGT:GQ:PL:XX\t0/1:35:40,0,.:7\t./.:.:x";
+        let parsed =
+            parse_row_with_format(row, &info_map, &format_map, &ParseOptions::default()).unwrap();
+
+        assert_eq!(parsed.format.get("GT"), Some(&Value::String("0/1".into())));
+        assert_eq!(parsed.format.get("GQ"), Some(&Value::Number(35.0)));
+        assert_eq!(
+            parsed.format.get("PL"),
+            Some(&Value::Array(vec![
+                Value::Number(40.0),
+                Value::Number(0.0),
+                Value::Missing
+            ]))
+        );
+        // Undeclared keys stay strings; unparseable numbers are kept as text
+        assert_eq!(parsed.format.get("XX"), Some(&Value::String("7".into())));
+        assert_eq!(parsed.sample_value(1, "GQ"), Some(&Value::Missing));
+        assert_eq!(
+            parsed.sample_value(1, "PL"),
+            Some(&Value::String("x".into()))
+        );
+        assert_eq!(parsed.sample_numbers(0, "GQ"), Some(vec![35.0]));
+        assert_eq!(parsed.genotype_likelihoods(), None);
+        assert_eq!(parsed.to_vcf_line(), row);
+
+        let strict = ParseOptions {
+            missing_values: MissingValueMode::Strict,
+            ..ParseOptions::default()
+        };
+        assert!(parse_row_with_format(row, &info_map, &format_map, &strict).is_err());
+    }
+
+    #[test]
+    fn test_genotype_likelihoods() {
+        let info_map = parse_header(HEADER).unwrap();
+
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\t.\tGT:PL\t0/1:40,0,250";
+        let parsed = parse_row(row, &info_map).unwrap();
+        assert_eq!(parsed.genotype_likelihoods(), Some(vec![40.0, 0.0, 250.0]));
+
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\t.\tGT:GL\t0/1:-4,0,-25";
+        let parsed = parse_row(row, &info_map).unwrap();
+        assert_eq!(parsed.genotype_likelihoods(), Some(vec![40.0, 0.0, 250.0]));
+
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\t.\tGT:PL\t0/1:.";
+        let parsed = parse_row(row, &info_map).unwrap();
+        assert_eq!(parsed.genotype_likelihoods(), None);
+    }
+
+    #[test]
+    fn test_allele_frequency_mismatches() {
+        let header = r#"##INFO=<ID=AC,Number=A,Type=Integer,Description="Allele count">
+##INFO=<ID=AF,Number=A,Type=Float,Description="Allele frequency">
+##INFO=<ID=AN,Number=1,Type=Integer,Description="Total alleles">"#;
+        let info_map = parse_header(header).unwrap();
+        let parse = |info: &str| {
+            parse_row(
+                &format!("chr1\t100\t.\tA\tG,T\t50\tPASS\t{}", info),
+                &info_map,
+            )
+            .unwrap()
+        };
+
+        let row = parse("AC=1,2;AF=0.167,0.333;AN=6");
+        assert_eq!(
+            row.computed_allele_frequencies(),
+            Some(vec![1.0 / 6.0, 2.0 / 6.0])
+        );
+        assert_eq!(
+            row.allele_frequency_mismatches(DEFAULT_AF_TOLERANCE),
+            Some(vec![])
+        );
+
+        let row = parse("AC=1,2;AF=0.167,0.5;AN=6");
+        assert_eq!(
+            row.allele_frequency_mismatches(DEFAULT_AF_TOLERANCE),
+            Some(vec![AlleleFrequencyMismatch {
+                allele: 1,
+                stored: Some(0.5),
+                computed: Some(2.0 / 6.0),
+            }])
+        );
+
+        // AF with fewer values than AC
+        let row = parse("AC=1,2;AF=0.167;AN=6");
+        assert_eq!(
+            row.allele_frequency_mismatches(DEFAULT_AF_TOLERANCE)
+                .unwrap()
+                .len(),
+            1
+        );
+
+        // Not checkable
+        assert_eq!(
+            parse("AC=1;AN=0;AF=0.5").allele_frequency_mismatches(0.1),
+            None
+        );
+        assert_eq!(parse("AF=0.5").allele_frequency_mismatches(0.1), None);
+    }
+
+    #[test]
+    fn test_to_vcf_line_round_trip() {
+        let info_map = parse_header(HEADER).unwrap();
+        for line in [
+            "chr1\t12345\trs123\tA\tG,T\t30.50\tq10;s50\tEND=12400;DB;CLNSIG=Pathogenic/Likely_pathogenic;X=1e-5\tGT:DP\t0/1:.\t1/1:30",
+            "chr1\t1\t.\tN\t.\t.\t.\t.",
+            "chr1\t1\t.\tA\tG\tnan\tPASS\tANN=G|missense_variant|MODERATE|BRCA1|ENSG1|||||||||||,T|x|LOW|||||||||||||",
+        ] {
+            assert_eq!(parse_row(line, &info_map).unwrap().to_vcf_line(), line);
+        }
+    }
+
+    #[test]
+    fn test_row_mutation() {
+        let info_map = parse_header(HEADER).unwrap();
+        let mut row = parse_row(
+            "chr1\t100\t.\tA\tG\t30.50\tPASS\tEND=200;DB;CLNSIG=Benign",
+            &info_map,
+        )
+        .unwrap();
+
+        row.set_info("END", Value::Number(250.0));
+        row.set_info("DB", Value::Bool(false));
+        row.set_info("SOMATIC", Value::Bool(true));
+        row.set_info(
+            "GENES",
+            Value::Array(vec![Value::String("A B".into()), Value::Missing]),
+        );
+        row.info.remove("CLNSIG");
+        row.add_filter("q10");
+        row.add_filter("q10");
+        row.set_id(Some("rs1"));
+        row.qual = Some(40.0);
+        assert_eq!(
+            row.to_vcf_line(),
+            "chr1\t100\trs1\tA\tG\t40\tq10\tEND=250;SOMATIC;GENES=A B,."
+        );
+        assert_eq!(row.info.get("DB"), None);
+
+        row.set_filter(Vec::<String>::new());
+        for key in ["END", "SOMATIC", "GENES"] {
+            row.remove_info(key);
+        }
+        // Fields the row was not read with are not written
+        row.info.insert("VIRTUAL".into(), Value::Number(1.0));
+        assert_eq!(row.to_vcf_line(), "chr1\t100\trs1\tA\tG\t40\t.\t.");
+    }
+
+    #[test]
+    fn test_raw_line() {
+        let info_map = parse_header(HEADER).unwrap();
+        for line in [
+            "chr1\t100\t.\tA\tG\t30\tPASS\tDB\tGT",
+            "chr1\t100\t.\tA\tG\t30\tPASS\tDB\t",
+        ] {
+            let mut row = parse_row(line, &info_map).unwrap();
+            assert_eq!(row.to_vcf_line(), line);
+            row.set_id(Some("rs1"));
+            row.remove_info("DB");
+            assert_eq!(row.raw_line(), line);
+            assert!(row.to_vcf_line().starts_with("chr1\t100\trs1\t"));
+        }
+    }
+
+    #[test]
+    fn test_row_genotypes() {
+        let info_map = parse_header(HEADER).unwrap();
+        let row = parse_row(
+            "chr1\t100\t.\tA\tG\t30\tPASS\t.\tDP:GT\t10:0|1\t5\t3:.|1\t2:x",
+            &info_map,
+        )
+        .unwrap();
+        assert_eq!(row.genotypes.len(), 4);
+        assert_eq!(row.genotype(0).unwrap().called(), Some(vec![0, 1]));
+        assert_eq!(row.genotype(1), None);
+        assert_eq!(row.genotype(2).unwrap().alleles, [None, Some(1)]);
+        assert_eq!(row.genotype(3), None);
+        assert_eq!(row.genotype(4), None);
+    }
+
+    #[test]
+    fn test_reserved_format_lists() {
+        let info_map = parse_header(HEADER).unwrap();
+        let format_map = crate::header::parse_format_header(
+            "##FORMAT=<ID=PL,Number=G,Type=String,Description=\"Declared as text\">",
+        )
+        .unwrap();
+        let row =
+            "chr1\t100\t.\tA\tG\t50\tPASS\t.\tGT:AD:GL:PL:DP\t0/1:10,5:-0.1,-2.5,-9:0,3,30:15";
+        let parsed =
+            parse_row_with_format(row, &info_map, &format_map, &ParseOptions::default()).unwrap();
+
+        let numbers =
+            |values: &[f64]| Value::Array(values.iter().map(|&n| Value::Number(n)).collect());
+        assert_eq!(parsed.format["AD"], numbers(&[10.0, 5.0]));
+        assert_eq!(parsed.format["GL"], numbers(&[-0.1, -2.5, -9.0]));
+        // Header declarations win over the reserved definitions
+        assert_eq!(parsed.format["PL"], Value::String("0,3,30".into()));
+        assert_eq!(parsed.format["DP"], Value::String("15".into()));
+        assert_eq!(parsed.variant_allele_fractions(0), Some(vec![5.0 / 15.0]));
+        assert_eq!(parsed.to_vcf_line(), row);
+    }
+
+    #[test]
+    fn test_split_multiallelic() {
+        let header = concat!(
+            "##INFO=<ID=AF,Number=A,Type=Float,Description=\"AF\">\n",
+            "##INFO=<ID=RC,Number=R,Type=Integer,Description=\"Read counts\">\n",
+            "##INFO=<ID=DP,Number=1,Type=Integer,Description=\"Depth\">",
+        );
+        let info_map = parse_header(header).unwrap();
+        let line = "chr1\t100\t.\tA\tG,T\t50\tPASS\tAF=0.1,0.2;RC=5,6,7;DP=30\tGT:PL:DP\t1/2:0,1,2,3,4,5:9\t0|0:.:4";
+        let row = parse_row(line, &info_map).unwrap();
+        let split = row.split_multiallelic(&info_map);
+        assert_eq!(split.len(), 2);
+
+        assert_eq!(split[0].alt_alleles, ["G"]);
+        assert_eq!(split[0].info["AF"], Value::Number(0.1));
+        assert_eq!(
+            split[0].info["RC"],
+            Value::Array(vec![Value::Number(5.0), Value::Number(6.0)])
+        );
+        assert_eq!(split[1].info["DP"], Value::Number(30.0));
+        assert_eq!(
+            split[1].to_vcf_line(),
+            "chr1\t100\t.\tA\tT\t50\tPASS\tAF=0.2;RC=5,7;DP=30\tGT:PL:DP\t0/1:0,3,5:9\t0|0:.:4"
+        );
+        assert_eq!(split[1].raw_line(), split[1].to_vcf_line());
+        assert_eq!(split[0].genotype(0).unwrap().to_string(), "1/0");
+        assert_eq!(
+            split[1].samples[0]["PL"],
+            Value::Array(vec![
+                Value::Number(0.0),
+                Value::Number(3.0),
+                Value::Number(5.0)
+            ])
+        );
+
+        // Biallelic rows, and values of the wrong length, are kept
+        let biallelic = parse_row("chr1\t5\t.\tA\tG\t.\t.\tAF=0.5", &info_map).unwrap();
+        let kept = biallelic.split_multiallelic(&info_map);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].to_vcf_line(), biallelic.to_vcf_line());
+        let odd = parse_row("chr1\t5\t.\tA\tG,T\t.\t.\tAF=0.5", &info_map).unwrap();
+        assert_eq!(
+            odd.split_multiallelic(&info_map)[1].info["AF"],
+            Value::Number(0.5)
+        );
+    }
+
+    #[test]
+    fn test_annotations_by_name() {
+        let info_map = parse_header(LOF_HEADER).unwrap();
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\tANN=G|stop_gained|HIGH|PRG4|ENSG1,G|intron_variant;LOF=(PRG4|ENSG1|4|0.75);NMD=.";
+        let parsed = parse_row(row, &info_map).unwrap();
+
+        let annotations = parsed.annotations("ANN", &info_map);
+        assert_eq!(annotations.len(), 2);
+        assert_eq!(annotations[0]["Annotation_Impact"], "HIGH");
+        assert_eq!(annotations[0].len(), 5);
+        assert_eq!(annotations[1].len(), 2);
+        let lof = parsed.annotations("LOF", &info_map);
+        assert_eq!(lof[0]["Gene_Name"], "PRG4");
+        assert_eq!(lof[0]["Percent_of_transcripts_affected"], "0.75");
+        assert!(parsed.annotations("NMD", &info_map).is_empty());
+        assert!(parsed.annotations("CSQ", &info_map).is_empty());
+
+        // Keys left unparsed are parsed on demand
+        let lazy = parse_row_with_keys(
+            row,
+            &info_map,
+            &FormatMap::new(),
+            &ParseOptions::default(),
+            Some(&[]),
+        )
+        .unwrap();
+        assert!(lazy.info.is_empty());
+        assert_eq!(lazy.annotations("ANN", &info_map), annotations);
+    }
+
+    #[test]
+    fn test_borrowed_info_access() {
+        let info_map = parse_header(HEADER).unwrap();
+        let row = "chr1\t100\t.\tA\tG\t50\tPASS\tDP=30;DB;AF=0.1\tGT:DP:GQ\t0/1:12:40";
+        let mut parsed = parse_row(row, &info_map).unwrap();
+        parsed.info.insert("VIRTUAL".into(), Value::Number(1.0));
+        parsed.set_info("NEW", Value::String("x".into()));
+
+        let entries: Vec<(&str, &Value)> = parsed.info_iter().collect();
+        assert_eq!(entries[0], ("DP", &Value::Number(30.0)));
+        assert_eq!(entries[1], ("DB", &Value::Bool(true)));
+        let keys: Vec<&str> = entries.iter().map(|(key, _)| *key).collect();
+        assert_eq!(keys, ["DP", "DB", "AF", "NEW", "VIRTUAL"]);
+
+        // INFO shadows FORMAT; built-in columns are not stored
+        assert_eq!(parsed.get_ref("DP"), Some(&Value::Number(30.0)));
+        assert_eq!(parsed.get_ref("GQ"), Some(&Value::String("40".into())));
+        assert_eq!(parsed.get_ref("CHROM"), None);
+        assert_eq!(parsed.get_ref("MQ"), None);
+
+        let lazy = parse_row_with_keys(
+            row,
+            &info_map,
+            &FormatMap::new(),
+            &ParseOptions::default(),
+            Some(&["AF".to_string()]),
+        )
+        .unwrap();
+        let keys: Vec<&str> = lazy.info_iter().map(|(key, _)| key).collect();
+        assert_eq!(keys, ["AF"]);
+    }
 }
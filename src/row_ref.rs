@@ -0,0 +1,230 @@
+//! Borrowed VCF rows: column access without allocation.
+//!
+//! [`VcfRow`](crate::VcfRow) owns its data: parsing a line allocates a
+//! `String` per column and a map per INFO column and sample. On
+//! whole-genome files that allocation dominates the time spent reading
+//! rows. A [`VcfRowRef`] instead keeps `&str` slices into the line and
+//! finds INFO keys and sample values by scanning them when asked, so
+//! reading a few columns costs no allocation at all.
+//!
+//! Values are returned as written, without type conversion or
+//! percent-decoding. Parse the line with
+//! [`FilterEngine::parse_row`](crate::FilterEngine::parse_row) (see
+//! [`VcfRowRef::line`]) when typed values, filter evaluation or editing are
+//! needed.
+//!
+//! ```rust
+//! use vcf_filter::VcfRowRef;
+//!
+//! let line = "chr1\t100\trs1\tA\tG,T\t50\tPASS\tDP=30;DB\tGT:DP\t0/1:12\t1/1:.";
+//! let row = VcfRowRef::parse(line).unwrap();
+//!
+//! assert_eq!(row.chrom(), "chr1");
+//! assert_eq!(row.pos(), 100);
+//! assert_eq!(row.alt_alleles().collect::<Vec<_>>(), ["G", "T"]);
+//! assert_eq!(row.info("DP"), Some(Some("30")));
+//! assert_eq!(row.info("DB"), Some(None));
+//! assert_eq!(row.info("AF"), None);
+//! assert_eq!(row.sample_value(1, "GT"), Some("1/1"));
+//! assert_eq!(row.sample_value(1, "DP"), None);
+//! ```
+
+use crate::error::{Result, VcfFilterError};
+
+/// A VCF data row borrowing its columns from the line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VcfRowRef<'a> {
+    /// The whole line.
+    line: &'a str,
+    /// The eight fixed columns, CHROM to INFO.
+    columns: [&'a str; 8],
+    /// POS, parsed.
+    pos: u64,
+    /// The FORMAT and sample columns, tab-separated; empty when absent.
+    genotypes: &'a str,
+}
+
+impl<'a> VcfRowRef<'a> {
+    /// Split a data line into its columns.
+    ///
+    /// Fails with `RowParseError` if the line has fewer than 8 columns or
+    /// POS is not a number, like [`parse_row`](crate::row::parse_row).
+    pub fn parse(line: &'a str) -> Result<Self> {
+        let mut columns = [""; 8];
+        let mut rest = line;
+        for (i, column) in columns.iter_mut().enumerate() {
+            match rest.split_once('\t') {
+                Some((value, tail)) => {
+                    *column = value;
+                    rest = tail;
+                }
+                None if i == 7 => {
+                    *column = rest;
+                    rest = "";
+                }
+                None => {
+                    return Err(VcfFilterError::RowParseError(format!(
+                        "Expected at least 8 columns, got {}",
+                        i + 1
+                    )));
+                }
+            }
+        }
+        let pos = columns[1]
+            .parse::<u64>()
+            .map_err(|e| VcfFilterError::RowParseError(format!("Invalid POS: {}", e)))?;
+        Ok(Self {
+            line,
+            columns,
+            pos,
+            genotypes: rest,
+        })
+    }
+
+    /// The line the row was read from, for parsing into an owned row.
+    pub fn line(&self) -> &'a str {
+        self.line
+    }
+
+    /// Chromosome (CHROM column).
+    pub fn chrom(&self) -> &'a str {
+        self.columns[0]
+    }
+
+    /// Position (POS column).
+    pub fn pos(&self) -> u64 {
+        self.pos
+    }
+
+    /// Variant IDs (ID column); empty for `.`.
+    pub fn ids(&self) -> impl Iterator<Item = &'a str> + use<'a> {
+        list(self.columns[2], ';')
+    }
+
+    /// Reference allele (REF column).
+    pub fn ref_allele(&self) -> &'a str {
+        self.columns[3]
+    }
+
+    /// Alternate alleles (ALT column); empty for `.`.
+    pub fn alt_alleles(&self) -> impl Iterator<Item = &'a str> + use<'a> {
+        list(self.columns[4], ',')
+    }
+
+    /// Quality score (QUAL column); `None` for `.` or unparseable text.
+    pub fn qual(&self) -> Option<f64> {
+        match self.columns[5] {
+            "." => None,
+            qual => qual.parse().ok(),
+        }
+    }
+
+    /// Filter names (FILTER column); empty for `.`.
+    pub fn filters(&self) -> impl Iterator<Item = &'a str> + use<'a> {
+        list(self.columns[6], ';')
+    }
+
+    /// The INFO entries in order, with their raw values (`None` for flags).
+    pub fn info_entries(&self) -> impl Iterator<Item = (&'a str, Option<&'a str>)> + use<'a> {
+        list(self.columns[7], ';').map(|entry| match entry.split_once('=') {
+            Some((key, value)) => (key, Some(value)),
+            None => (entry, None),
+        })
+    }
+
+    /// Look up an INFO key: `Some(Some(value))` as written, `Some(None)`
+    /// for a flag, `None` when absent.
+    pub fn info(&self, key: &str) -> Option<Option<&'a str>> {
+        self.info_entries()
+            .find(|(k, _)| *k == key)
+            .map(|(_, value)| value)
+    }
+
+    /// The FORMAT keys (FORMAT column); empty without genotype columns.
+    pub fn format_keys(&self) -> impl Iterator<Item = &'a str> + use<'a> {
+        let format = self.genotypes.split('\t').next().unwrap_or_default();
+        format.split(':').filter(|key| !key.is_empty())
+    }
+
+    /// Number of sample columns.
+    pub fn sample_count(&self) -> usize {
+        match self.genotypes {
+            "" => 0,
+            genotypes => genotypes.split('\t').count().saturating_sub(1),
+        }
+    }
+
+    /// The text of a sample column, by 0-based sample index.
+    pub fn sample(&self, sample: usize) -> Option<&'a str> {
+        self.genotypes.split('\t').nth(sample + 1)
+    }
+
+    /// A sample's value of a FORMAT key as written; `None` when the key is
+    /// absent, or the value is `.` or left out of the column.
+    pub fn sample_value(&self, sample: usize, key: &str) -> Option<&'a str> {
+        let index = self.format_keys().position(|k| k == key)?;
+        self.sample(sample)?
+            .split(':')
+            .nth(index)
+            .filter(|value| *value != "." && !value.is_empty())
+    }
+}
+
+/// Split a column into its elements; `.` is an empty list.
+fn list(column: &str, separator: char) -> impl Iterator<Item = &str> {
+    let column = if column == "." { "" } else { column };
+    column.split(separator).filter(|item| !item.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FilterEngine;
+
+    #[test]
+    fn test_row_ref_columns() {
+        let line = "chr1\t100\t.\tA\t.\t.\tq10;LowDP\t.";
+        let row = VcfRowRef::parse(line).unwrap();
+        assert_eq!(row.ids().count(), 0);
+        assert_eq!(row.alt_alleles().count(), 0);
+        assert_eq!(row.qual(), None);
+        assert_eq!(row.filters().collect::<Vec<_>>(), ["q10", "LowDP"]);
+        assert_eq!(row.info_entries().count(), 0);
+        assert_eq!(row.sample_count(), 0);
+        assert_eq!(row.format_keys().count(), 0);
+        assert_eq!(row.sample(0), None);
+
+        assert!(matches!(
+            VcfRowRef::parse("chr1\t100\t.\tA\tG\t50\tPASS"),
+            Err(VcfFilterError::RowParseError(_))
+        ));
+        assert!(matches!(
+            VcfRowRef::parse("chr1\tx\t.\tA\tG\t50\tPASS\t."),
+            Err(VcfFilterError::RowParseError(_))
+        ));
+    }
+
+    #[test]
+    fn test_row_ref_matches_owned_row() {
+        let header = r#"##INFO=<ID=DP,Number=1,Type=Integer,Description="Depth">"#;
+        let engine = FilterEngine::new(header).unwrap();
+        let line = "chr2\t5\trs1;rs2\tAC\tA\t12.5\tPASS\tDP=7;AF=0.1,0.2\tGT:AD\t0/1:3,4";
+        let borrowed = VcfRowRef::parse(line).unwrap();
+        let owned = engine.parse_row(borrowed.line()).unwrap();
+
+        assert_eq!(borrowed.chrom(), owned.chrom);
+        assert_eq!(borrowed.pos(), owned.pos);
+        assert_eq!(borrowed.ids().collect::<Vec<_>>(), owned.ids());
+        assert_eq!(borrowed.ref_allele(), owned.ref_allele);
+        assert_eq!(
+            borrowed.alt_alleles().collect::<Vec<_>>(),
+            owned.alt_alleles
+        );
+        assert_eq!(borrowed.qual(), owned.qual);
+        assert_eq!(borrowed.filters().collect::<Vec<_>>(), owned.filter);
+        assert_eq!(borrowed.info("AF"), Some(Some("0.1,0.2")));
+        assert_eq!(borrowed.sample_count(), owned.sample_count());
+        assert_eq!(borrowed.sample_value(0, "AD"), Some("3,4"));
+        assert_eq!(borrowed.sample(0), Some("0/1:3,4"));
+    }
+}
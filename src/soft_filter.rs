@@ -0,0 +1,218 @@
+//! Soft filtering: tag failing records in the FILTER column instead of dropping them.
+//!
+//! Follows `bcftools filter -s NAME -m +`: a failing record's FILTER becomes
+//! `NAME` when it was `PASS` or `.`, and `NAME` is appended to any other
+//! existing filters (`q10;NAME`). A passing record keeps its FILTER, except
+//! that `.` becomes `PASS`. [`SoftFilter::with_mode`] selects the other
+//! `bcftools filter -m` behaviours. Declare the name in the output header
+//! with [`SoftFilter::header_line`].
+//!
+//! ```rust
+//! use std::io::Cursor;
+//! use vcf_filter::FilterEngine;
+//! use vcf_filter::soft_filter::SoftFilter;
+//!
+//! let vcf = "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+//!            chr1\t100\t.\tA\tG\t50\t.\t.\n\
+//!            chr1\t200\t.\tA\tG\t5\tPASS\t.\n\
+//!            chr1\t300\t.\tA\tG\t5\tq10\t.\n";
+//! let mut reader = Cursor::new(vcf);
+//! let (engine, _header) = FilterEngine::from_reader(&mut reader).unwrap();
+//!
+//! let soft = SoftFilter::new("LowQual").unwrap().with_description("QUAL <= 30");
+//! assert_eq!(soft.header_line(), r#"##FILTER=<ID=LowQual,Description="QUAL <= 30">"#);
+//!
+//! let lines: Vec<String> = engine
+//!     .soft_filter_lines(reader, "QUAL > 30", &soft)
+//!     .unwrap()
+//!     .collect::<Result<_, _>>()
+//!     .unwrap();
+//! assert_eq!(
+//!     lines,
+//!     [
+//!         "chr1\t100\t.\tA\tG\t50\tPASS\t.",
+//!         "chr1\t200\t.\tA\tG\t5\tLowQual\t.",
+//!         "chr1\t300\t.\tA\tG\t5\tq10;LowQual\t.",
+//!     ]
+//! );
+//! ```
+
+use crate::error::{Result, VcfFilterError};
+use crate::header::FilterField;
+
+/// A FILTER name to set on records that fail a filter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SoftFilter {
+    name: String,
+    description: String,
+    /// Append the name to failing records' filters instead of replacing them.
+    append: bool,
+    /// Set passing records' FILTER to `PASS`.
+    reset: bool,
+}
+
+impl SoftFilter {
+    /// Create a soft filter setting `name`.
+    ///
+    /// Fails with `ConfigError` if the name is empty, `PASS` or `0`, or
+    /// contains whitespace, `;`, `,` or `=`.
+    pub fn new(name: &str) -> Result<Self> {
+        let invalid = name.is_empty()
+            || name == "PASS"
+            || name == "0"
+            || name
+                .chars()
+                .any(|c| c.is_whitespace() || matches!(c, ';' | ',' | '='));
+        if invalid {
+            return Err(VcfFilterError::ConfigError(format!(
+                "'{}' is not a valid FILTER name",
+                name
+            )));
+        }
+        Ok(Self {
+            name: name.to_string(),
+            description: format!("Set by vcf-filter as {}", name),
+            append: true,
+            reset: false,
+        })
+    }
+
+    /// Set how existing filters are treated, as `bcftools filter -m MODE` does.
+    ///
+    /// Without `+` the name replaces a failing record's filters, as bcftools
+    /// does when `-m` is not given; `+` appends it instead (the default of
+    /// [`new`](Self::new)). `x` resets passing records to `PASS`. So `""`
+    /// replaces, `"+"` appends, `"x"` replaces and resets, `"+x"` appends
+    /// and resets. Fails with `ConfigError` for any other mode.
+    pub fn with_mode(mut self, mode: &str) -> Result<Self> {
+        if !matches!(mode, "" | "+" | "x" | "+x" | "x+") {
+            return Err(VcfFilterError::ConfigError(format!(
+                "'{}' is not a soft filter mode; expected +, x, +x or an empty mode",
+                mode
+            )));
+        }
+        self.append = mode.contains('+');
+        self.reset = mode.contains('x');
+        Ok(self)
+    }
+
+    /// Set the description written in the `##FILTER` header line.
+    pub fn with_description(mut self, description: &str) -> Self {
+        self.description = description.to_string();
+        self
+    }
+
+    /// The FILTER name set on failing records.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The `##FILTER` header line declaring the name, to insert before `#CHROM`.
+    pub fn header_line(&self) -> String {
+        FilterField {
+            id: self.name.clone(),
+            description: self.description.clone(),
+        }
+        .header_line()
+    }
+
+    /// Whether a header line already declares this FILTER name.
+    pub fn is_declared_by(&self, header_line: &str) -> bool {
+        header_line
+            .strip_prefix("##FILTER=<ID=")
+            .and_then(|rest| rest.strip_prefix(self.name.as_str()))
+            .is_some_and(|rest| rest.starts_with([',', '>']))
+    }
+
+    /// Rewrite the FILTER column of a VCF data line for a record that passed or failed.
+    ///
+    /// Lines with fewer than 7 columns are returned unchanged.
+    pub fn apply(&self, line: &str, passed: bool) -> String {
+        let mut fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 7 {
+            return line.to_string();
+        }
+
+        let current = fields[6];
+        let filter = match (passed, current) {
+            (true, "." | "") => "PASS".to_string(),
+            (true, _) if self.reset => "PASS".to_string(),
+            (true, _) => return line.to_string(),
+            (false, "PASS" | "." | "") => self.name.clone(),
+            (false, _) if !self.append => self.name.clone(),
+            (false, _) if current.split(';').any(|name| name == self.name) => {
+                return line.to_string();
+            }
+            (false, _) => format!("{};{}", current, self.name),
+        };
+        fields[6] = &filter;
+        fields.join("\t")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let soft = SoftFilter::new("LowQual").unwrap();
+        let line = |filter: &str| format!("chr1\t100\t.\tA\tG\t50\t{}\tDP=5", filter);
+
+        assert_eq!(soft.apply(&line("PASS"), false), line("LowQual"));
+        assert_eq!(soft.apply(&line("."), false), line("LowQual"));
+        assert_eq!(soft.apply(&line("q10"), false), line("q10;LowQual"));
+        assert_eq!(soft.apply(&line("q10;LowQual"), false), line("q10;LowQual"));
+        assert_eq!(soft.apply(&line("."), true), line("PASS"));
+        assert_eq!(soft.apply(&line("q10"), true), line("q10"));
+        assert_eq!(soft.apply("chr1\t100", false), "chr1\t100");
+    }
+
+    #[test]
+    fn test_modes() {
+        let line = |filter: &str| format!("chr1\t100\t.\tA\tG\t50\t{}\tDP=5", filter);
+        let soft = SoftFilter::new("LowQual").unwrap();
+        assert_eq!(soft.clone().with_mode("+").unwrap(), soft);
+
+        let replace = soft.clone().with_mode("").unwrap();
+        assert_eq!(replace.apply(&line("PASS"), false), line("LowQual"));
+        assert_eq!(replace.apply(&line("q10"), false), line("LowQual"));
+        assert_eq!(replace.apply(&line("q10"), true), line("q10"));
+
+        let reset = soft.clone().with_mode("x").unwrap();
+        assert_eq!(reset.apply(&line("q10"), true), line("PASS"));
+        assert_eq!(reset.apply(&line("q10"), false), line("LowQual"));
+
+        let both = soft.clone().with_mode("+x").unwrap();
+        assert_eq!(both.apply(&line("q10"), true), line("PASS"));
+        assert_eq!(both.apply(&line("q10"), false), line("q10;LowQual"));
+
+        for mode in ["-", "+y", "++x"] {
+            assert!(matches!(
+                soft.clone().with_mode(mode),
+                Err(VcfFilterError::ConfigError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_names_and_header() {
+        for name in ["", "PASS", "0", "Low Qual", "a;b"] {
+            assert!(matches!(
+                SoftFilter::new(name),
+                Err(VcfFilterError::ConfigError(_))
+            ));
+        }
+
+        let soft = SoftFilter::new("LowQual")
+            .unwrap()
+            .with_description(r#"FILTER != "PASS""#);
+        assert_eq!(
+            soft.header_line(),
+            r#"##FILTER=<ID=LowQual,Description="FILTER != \"PASS\"">"#
+        );
+        assert!(soft.is_declared_by(r#"##FILTER=<ID=LowQual,Description="Low quality">"#));
+        assert!(!soft.is_declared_by(r#"##FILTER=<ID=LowQualSNP,Description="x">"#));
+        assert!(!soft.is_declared_by(r#"##INFO=<ID=LowQual,Number=0,Type=Flag>"#));
+    }
+}
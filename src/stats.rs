@@ -0,0 +1,197 @@
+//! Per-clause rejection statistics for tuning filters.
+//!
+//! A filter's top-level clauses are the operands of its outermost `&&`
+//! chain; a filter without one is a single clause. For every failing row,
+//! each clause is evaluated on its own (no short-circuiting), so a row
+//! failing two clauses counts against both. A clause that is the *only*
+//! failing one for a row is also counted separately: those rows would pass
+//! if the clause were dropped.
+//!
+//! ```rust
+//! use vcf_filter::FilterEngine;
+//!
+//! let header = r#"##INFO=<ID=DP,Number=1,Type=Integer,Description="Depth">"#;
+//! let engine = FilterEngine::new(header).unwrap();
+//! let mut stats = engine.stats("DP > 20 && QUAL >= 30").unwrap();
+//!
+//! for row in [
+//!     "chr1\t100\t.\tA\tG\t50\tPASS\tDP=30",
+//!     "chr1\t200\t.\tA\tG\t50\tPASS\tDP=5",
+//!     "chr1\t300\t.\tA\tG\t10\tPASS\tDP=5",
+//! ] {
+//!     engine.evaluate_counted(&mut stats, &engine.parse_row(row).unwrap()).unwrap();
+//! }
+//! assert_eq!(stats.passed(), 1);
+//! assert_eq!(
+//!     stats.to_string(),
+//!     "1/3 rows passed\n\
+//!      DP > 20 rejected 2 rows (1 by this clause alone)\n\
+//!      QUAL >= 30 rejected 1 rows (0 by this clause alone)"
+//! );
+//! ```
+
+use std::fmt;
+
+use crate::filter::{BinaryOp, Expr};
+
+/// Rejection counts for one top-level clause.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClauseStats {
+    /// Canonical text of the clause.
+    pub clause: String,
+    /// Rows for which the clause did not pass.
+    pub rejected: u64,
+    /// Rows for which this was the only clause that did not pass.
+    pub rejected_alone: u64,
+}
+
+/// Pass/fail counts of a filter and its top-level clauses.
+///
+/// Create one with [`FilterEngine::stats`](crate::FilterEngine::stats) and
+/// record rows with
+/// [`FilterEngine::evaluate_counted`](crate::FilterEngine::evaluate_counted).
+#[derive(Debug, Clone)]
+pub struct FilterStats {
+    pub(crate) expr: Expr,
+    pub(crate) clauses: Vec<Expr>,
+    counts: Vec<ClauseStats>,
+    rows: u64,
+    passed: u64,
+}
+
+impl FilterStats {
+    /// Start collecting statistics for a parsed filter.
+    pub(crate) fn new(expr: Expr) -> Self {
+        let clauses: Vec<Expr> = conjuncts(&expr).into_iter().cloned().collect();
+        let counts = clauses
+            .iter()
+            .map(|clause| ClauseStats {
+                clause: clause.to_string(),
+                rejected: 0,
+                rejected_alone: 0,
+            })
+            .collect();
+        Self {
+            expr,
+            clauses,
+            counts,
+            rows: 0,
+            passed: 0,
+        }
+    }
+
+    /// Count a row, given whether it passed and which clauses failed.
+    pub(crate) fn record(&mut self, passed: bool, failed: &[usize]) {
+        self.rows += 1;
+        if passed {
+            self.passed += 1;
+        }
+        for &i in failed {
+            self.counts[i].rejected += 1;
+            if failed.len() == 1 {
+                self.counts[i].rejected_alone += 1;
+            }
+        }
+    }
+
+    /// Add the counts of another collector for the same filter, e.g. from another thread.
+    ///
+    /// # Panics
+    ///
+    /// If `other` was created for a different filter.
+    pub fn merge(&mut self, other: &FilterStats) {
+        assert_eq!(self.expr, other.expr, "merging stats of different filters");
+        self.rows += other.rows;
+        self.passed += other.passed;
+        for (counts, other) in self.counts.iter_mut().zip(&other.counts) {
+            counts.rejected += other.rejected;
+            counts.rejected_alone += other.rejected_alone;
+        }
+    }
+
+    /// Number of rows recorded.
+    pub fn rows(&self) -> u64 {
+        self.rows
+    }
+
+    /// Number of recorded rows that passed the whole filter.
+    pub fn passed(&self) -> u64 {
+        self.passed
+    }
+
+    /// Counts per top-level clause, in filter order.
+    pub fn clauses(&self) -> &[ClauseStats] {
+        &self.counts
+    }
+}
+
+impl fmt::Display for FilterStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{} rows passed", self.passed, self.rows)?;
+        for counts in &self.counts {
+            write!(
+                f,
+                "\n{} rejected {} rows ({} by this clause alone)",
+                counts.clause, counts.rejected, counts.rejected_alone
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Split the outermost `&&` chain of an expression into its operands.
+fn conjuncts(expr: &Expr) -> Vec<&Expr> {
+    match expr {
+        Expr::Binary(left, BinaryOp::And, right) => {
+            let mut clauses = conjuncts(left);
+            clauses.extend(conjuncts(right));
+            clauses
+        }
+        other => vec![other],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::parse_filter;
+
+    #[test]
+    fn test_clauses() {
+        let stats = FilterStats::new(parse_filter("DP > 20 && (A || B) && C").unwrap());
+        let clauses: Vec<&str> = stats.clauses().iter().map(|c| c.clause.as_str()).collect();
+        assert_eq!(clauses, ["DP > 20", "A || B", "C"]);
+
+        let stats = FilterStats::new(parse_filter("A || B && C").unwrap());
+        assert_eq!(stats.clauses().len(), 1);
+    }
+
+    #[test]
+    fn test_record_and_merge() {
+        let expr = parse_filter("A && B").unwrap();
+        let mut stats = FilterStats::new(expr.clone());
+        stats.record(true, &[]);
+        stats.record(false, &[0, 1]);
+        stats.record(false, &[1]);
+
+        let mut other = FilterStats::new(expr);
+        other.record(false, &[0]);
+        stats.merge(&other);
+
+        assert_eq!((stats.rows(), stats.passed()), (4, 1));
+        assert_eq!(
+            (
+                stats.clauses()[0].rejected,
+                stats.clauses()[0].rejected_alone
+            ),
+            (2, 1)
+        );
+        assert_eq!(
+            (
+                stats.clauses()[1].rejected,
+                stats.clauses()[1].rejected_alone
+            ),
+            (2, 1)
+        );
+    }
+}
@@ -0,0 +1,617 @@
+//! Random access to bgzip-compressed VCFs through tabix and CSI indexes.
+//!
+//! `tabix -p vcf calls.vcf.gz` writes `calls.vcf.gz.tbi`; `bcftools index`
+//! writes `calls.vcf.gz.csi`. Both map each chromosome's bins of the genome
+//! to the BGZF virtual offsets of the records in them, so the records of a
+//! region can be read without decompressing the rest of the file.
+//!
+//! [`open_regions`] reads the header and then only the records overlapping
+//! the requested regions, as one stream:
+//!
+//! ```rust,no_run
+//! use std::io::BufRead;
+//! use vcf_filter::intervals::Region;
+//! use vcf_filter::tabix::open_regions;
+//!
+//! let regions = [Region::parse("chr1:1-1000000").unwrap(), Region::parse("chr2").unwrap()];
+//! for line in open_regions("calls.vcf.gz", &regions).unwrap().lines() {
+//!     println!("{}", line.unwrap());
+//! }
+//! ```
+//!
+//! Records come out in file order, each once even when it overlaps several
+//! regions. As in tabix, a record spans its REF allele, or up to its INFO
+//! `END`; regions on chromosomes missing from the index select nothing.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use crate::compression::{BgzfReader, io_error, open_input};
+use crate::error::{Result, VcfFilterError};
+use crate::intervals::Region;
+
+/// Bin size shift and depth of the binning scheme of `.tbi` indexes.
+const TBI_MIN_SHIFT: u32 = 14;
+const TBI_DEPTH: u32 = 5;
+
+/// A chunk of records between two virtual offsets.
+type Chunk = (u64, u64);
+
+/// One chromosome's bins and smallest record offsets.
+#[derive(Debug, Clone, Default)]
+struct ReferenceIndex {
+    /// Chunks of each bin.
+    bins: HashMap<u32, Vec<Chunk>>,
+    /// Offset of the first record in each bin (CSI only).
+    bin_offsets: HashMap<u32, u64>,
+    /// Offset of the first record in each 16 kbp window (tabix only).
+    linear: Vec<u64>,
+}
+
+/// A parsed `.tbi` or `.csi` index.
+#[derive(Debug, Clone)]
+pub struct TabixIndex {
+    min_shift: u32,
+    depth: u32,
+    /// Chromosome names, in index order.
+    names: Vec<String>,
+    references: Vec<ReferenceIndex>,
+}
+
+impl TabixIndex {
+    /// Read an index file, detecting tabix or CSI by its magic bytes.
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut bytes = Vec::new();
+        open_input(path)?
+            .read_to_end(&mut bytes)
+            .map_err(io_error(path))?;
+        Self::parse(&bytes).map_err(|message| {
+            VcfFilterError::IoError(format!("{}: invalid index: {}", path.display(), message))
+        })
+    }
+
+    /// Find and read the index of a VCF: `<path>.tbi`, else `<path>.csi`.
+    pub fn find(vcf: impl AsRef<Path>) -> Result<Self> {
+        let vcf = vcf.as_ref();
+        let candidate = |ext: &str| {
+            let mut path = vcf.as_os_str().to_owned();
+            path.push(ext);
+            PathBuf::from(path)
+        };
+        let path = [candidate(".tbi"), candidate(".csi")]
+            .into_iter()
+            .find(|path| path.exists())
+            .ok_or_else(|| {
+                VcfFilterError::IoError(format!("{}: no .tbi or .csi index found", vcf.display()))
+            })?;
+        Self::read(path)
+    }
+
+    /// Parse the decompressed bytes of an index.
+    pub fn parse(bytes: &[u8]) -> std::result::Result<Self, String> {
+        let mut input = Input(bytes);
+        let magic = input.take(4)?;
+        let csi = match magic {
+            b"TBI\x01" => false,
+            b"CSI\x01" => true,
+            _ => return Err("not a tabix or CSI index".to_string()),
+        };
+
+        let (min_shift, depth, names) = if csi {
+            let min_shift = input.u32()?;
+            let depth = input.u32()?;
+            let aux_len = input.u32()? as usize;
+            let mut aux = Input(input.take(aux_len)?);
+            // The auxiliary data of a VCF index is the tabix header
+            let names = if aux_len >= 28 {
+                read_names(&mut aux)?
+            } else {
+                Vec::new()
+            };
+            if min_shift > 32 || depth > 10 {
+                return Err("unsupported CSI binning scheme".to_string());
+            }
+            (min_shift, depth, names)
+        } else {
+            (TBI_MIN_SHIFT, TBI_DEPTH, Vec::new())
+        };
+
+        let n_ref = input.u32()? as usize;
+        let names = if csi { names } else { read_names(&mut input)? };
+        if names.len() != n_ref {
+            return Err(format!(
+                "{} references but {} sequence names",
+                n_ref,
+                names.len()
+            ));
+        }
+
+        let references = (0..n_ref)
+            .map(|_| {
+                let mut reference = ReferenceIndex::default();
+                for _ in 0..input.u32()? {
+                    let bin = input.u32()?;
+                    if csi {
+                        reference.bin_offsets.insert(bin, input.u64()?);
+                    }
+                    let chunks = (0..input.u32()?)
+                        .map(|_| Ok((input.u64()?, input.u64()?)))
+                        .collect::<std::result::Result<Vec<_>, String>>()?;
+                    reference.bins.insert(bin, chunks);
+                }
+                if !csi {
+                    reference.linear = (0..input.u32()?)
+                        .map(|_| input.u64())
+                        .collect::<std::result::Result<_, _>>()?;
+                }
+                Ok(reference)
+            })
+            .collect::<std::result::Result<_, String>>()?;
+
+        Ok(Self {
+            min_shift,
+            depth,
+            names,
+            references,
+        })
+    }
+
+    /// Get the chromosome names, in index order.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Get the merged chunks that may hold records of `chrom:[start, end)`.
+    ///
+    /// Empty when the chromosome is not in the index.
+    pub fn chunks(&self, chrom: &str, start: u64, end: u64) -> Vec<(u64, u64)> {
+        let Some(tid) = self.names.iter().position(|name| name == chrom) else {
+            return Vec::new();
+        };
+        let reference = &self.references[tid];
+        let limit = 1u64 << (self.min_shift + 3 * self.depth);
+        let (start, end) = (start.min(limit - 1), end.clamp(start + 1, limit));
+
+        let min_offset = self.min_offset(reference, start);
+        merge(
+            reg2bins(start, end, self.min_shift, self.depth)
+                .into_iter()
+                .filter_map(|bin| reference.bins.get(&bin))
+                .flatten()
+                .filter(|&&(_, chunk_end)| chunk_end > min_offset)
+                .copied()
+                .collect(),
+        )
+    }
+
+    /// Get the offset below which no record can reach `start`.
+    fn min_offset(&self, reference: &ReferenceIndex, start: u64) -> u64 {
+        if !reference.linear.is_empty() {
+            let window = (start >> self.min_shift) as usize;
+            return reference.linear[window.min(reference.linear.len() - 1)];
+        }
+        // CSI: the offset of the smallest bin holding `start`, or of a parent
+        let mut bin = bin_level_start(self.depth) + (start >> self.min_shift) as u32;
+        loop {
+            if let Some(&offset) = reference.bin_offsets.get(&bin) {
+                return offset;
+            }
+            if bin == 0 {
+                return 0;
+            }
+            bin = (bin - 1) >> 3;
+        }
+    }
+}
+
+/// Sort chunks and merge those that overlap or touch.
+fn merge(mut chunks: Vec<Chunk>) -> Vec<Chunk> {
+    chunks.sort_unstable();
+    let mut merged: Vec<Chunk> = Vec::with_capacity(chunks.len());
+    for (start, end) in chunks {
+        match merged.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = (*last_end).max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// Get the first bin number of a level of the binning scheme.
+fn bin_level_start(level: u32) -> u32 {
+    ((1 << (3 * level)) - 1) / 7
+}
+
+/// List the bins that may hold records overlapping `[start, end)`, per the SAM spec.
+fn reg2bins(start: u64, end: u64, min_shift: u32, depth: u32) -> Vec<u32> {
+    let end = end - 1;
+    let mut bins = Vec::new();
+    for level in 0..=depth {
+        let shift = min_shift + 3 * (depth - level);
+        let first = bin_level_start(level);
+        bins.extend((start >> shift) as u32 + first..=(end >> shift) as u32 + first);
+    }
+    bins
+}
+
+/// Read the tabix header fields and the sequence names that follow them.
+fn read_names(input: &mut Input) -> std::result::Result<Vec<String>, String> {
+    // format, col_seq, col_beg, col_end, meta, skip
+    input.take(24)?;
+    let names_len = input.u32()? as usize;
+    input
+        .take(names_len)?
+        .split(|&b| b == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            String::from_utf8(name.to_vec()).map_err(|_| "sequence name is not UTF-8".to_string())
+        })
+        .collect()
+}
+
+/// A cursor over little-endian index bytes.
+struct Input<'a>(&'a [u8]);
+
+impl<'a> Input<'a> {
+    fn take(&mut self, n: usize) -> std::result::Result<&'a [u8], String> {
+        if self.0.len() < n {
+            return Err("unexpected end of index".to_string());
+        }
+        let (taken, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> std::result::Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> std::result::Result<u64, String> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+/// The regions requested on one chromosome and the chunks left to read.
+struct Query {
+    chrom: String,
+    /// Zero-based, half-open `(start, end)` of each region.
+    regions: Vec<(u64, u64)>,
+    /// Chunks still to read, last first.
+    chunks: Vec<Chunk>,
+}
+
+/// A VCF stream of the header and the records overlapping some regions.
+///
+/// Built by [`open_regions`]; reads lines as a [`BufRead`].
+pub struct RegionReader<R> {
+    bgzf: BgzfReader<R>,
+    /// Queries left, in index order.
+    queries: Vec<Query>,
+    /// End of the chunk being read.
+    chunk_end: Option<u64>,
+    in_header: bool,
+    /// The line being handed out and the read position in it.
+    line: Vec<u8>,
+    pos: usize,
+}
+
+/// Open a bgzip-compressed VCF through its index, reading only the records
+/// that overlap `regions` after the header.
+pub fn open_regions(
+    path: impl AsRef<Path>,
+    regions: &[Region],
+) -> Result<RegionReader<BufReader<File>>> {
+    let path = path.as_ref();
+    let index = TabixIndex::find(path)?;
+    let file = File::open(path).map_err(io_error(path))?;
+    Ok(RegionReader::new(
+        BgzfReader::new(BufReader::new(file)),
+        &index,
+        regions,
+    ))
+}
+
+impl<R: Read + io::Seek> RegionReader<R> {
+    /// Query a BGZF stream positioned at its start with its index.
+    pub fn new(bgzf: BgzfReader<R>, index: &TabixIndex, regions: &[Region]) -> Self {
+        let mut queries: Vec<(usize, Query)> = Vec::new();
+        for region in regions {
+            let Some(tid) = index.names().iter().position(|name| *name == region.chrom) else {
+                continue;
+            };
+            let i = match queries.iter().position(|(t, _)| *t == tid) {
+                Some(i) => i,
+                None => {
+                    queries.push((
+                        tid,
+                        Query {
+                            chrom: region.chrom.clone(),
+                            regions: Vec::new(),
+                            chunks: Vec::new(),
+                        },
+                    ));
+                    queries.len() - 1
+                }
+            };
+            let query = &mut queries[i].1;
+            query.regions.push((region.start, region.end));
+            query
+                .chunks
+                .extend(index.chunks(&region.chrom, region.start, region.end));
+        }
+
+        // Read chromosomes in file order, each chunk once
+        queries.sort_by_key(|(tid, _)| *tid);
+        let mut queries: Vec<Query> = queries.into_iter().map(|(_, query)| query).collect();
+        for query in &mut queries {
+            query.chunks = merge(std::mem::take(&mut query.chunks));
+            query.chunks.reverse();
+        }
+        queries.reverse();
+
+        Self {
+            bgzf,
+            queries,
+            chunk_end: None,
+            in_header: true,
+            line: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    /// Load the next header line or overlapping record into `line`, or
+    /// leave it empty at the end.
+    fn next_line(&mut self) -> io::Result<()> {
+        self.pos = 0;
+        while self.in_header {
+            self.line.clear();
+            self.bgzf.read_until(b'\n', &mut self.line)?;
+            if self.line.starts_with(b"#") {
+                return Ok(());
+            }
+            self.in_header = false;
+        }
+
+        loop {
+            self.line.clear();
+            let Some(query) = self.queries.last_mut() else {
+                return Ok(());
+            };
+            let in_chunk = self
+                .chunk_end
+                .is_some_and(|end| self.bgzf.virtual_offset() < end);
+            if !in_chunk {
+                match query.chunks.pop() {
+                    Some((start, end)) => {
+                        self.bgzf.seek_virtual(start)?;
+                        self.chunk_end = Some(end);
+                    }
+                    None => {
+                        self.queries.pop();
+                        self.chunk_end = None;
+                    }
+                }
+                continue;
+            }
+
+            if self.bgzf.read_until(b'\n', &mut self.line)? == 0 {
+                self.chunk_end = None;
+                continue;
+            }
+            let Some((chrom, start, end)) = record_span(&self.line) else {
+                continue;
+            };
+            if chrom != query.chrom.as_bytes() {
+                continue;
+            }
+            // Records are sorted by start, so none later can overlap
+            if query
+                .regions
+                .iter()
+                .all(|&(_, region_end)| start >= region_end)
+            {
+                self.queries.pop();
+                self.chunk_end = None;
+                continue;
+            }
+            if query
+                .regions
+                .iter()
+                .any(|&(region_start, region_end)| start < region_end && end > region_start)
+            {
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl<R: Read + io::Seek> Read for RegionReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: Read + io::Seek> BufRead for RegionReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos >= self.line.len() {
+            self.next_line()?;
+        }
+        Ok(&self.line[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.line.len());
+    }
+}
+
+/// Get the CHROM and zero-based, half-open span of a data line, as tabix
+/// computes it: the REF allele, or up to INFO `END`.
+fn record_span(line: &[u8]) -> Option<(&[u8], u64, u64)> {
+    let line = std::str::from_utf8(line)
+        .ok()?
+        .trim_end_matches(['\n', '\r']);
+    if line.starts_with('#') {
+        return None;
+    }
+    let columns: Vec<&str> = line.splitn(9, '\t').collect();
+    let start = columns.get(1)?.parse::<u64>().ok()?.saturating_sub(1);
+    let ref_end = start + columns.get(3)?.len().max(1) as u64;
+    let info_end = columns
+        .get(7)
+        .into_iter()
+        .flat_map(|info| info.split(';'))
+        .find_map(|entry| entry.strip_prefix("END="))
+        .and_then(|end| end.parse::<u64>().ok());
+    let end = info_end.map_or(ref_end, |end| end.max(ref_end));
+    Some((columns[0].as_bytes(), start, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compression::BgzfWriter;
+    use std::io::{Cursor, Write};
+
+    const HEADER: &str = "##fileformat=VCFv4.2\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n";
+
+    /// Write one BGZF block per record and index the records as tabix would.
+    fn indexed(records: &[&str]) -> (Vec<u8>, Vec<u8>) {
+        let mut writer = BgzfWriter::new(Vec::new());
+        writer.write_all(HEADER.as_bytes()).unwrap();
+        writer.flush().unwrap();
+        for record in records {
+            writeln!(writer, "{}", record).unwrap();
+            writer.flush().unwrap();
+        }
+        let vcf = writer.finish().unwrap();
+
+        let mut reader = BgzfReader::new(Cursor::new(vcf.clone()));
+        let mut names: Vec<String> = Vec::new();
+        let mut references: Vec<HashMap<u32, Vec<Chunk>>> = Vec::new();
+        let mut line = Vec::new();
+        loop {
+            let start = reader.virtual_offset();
+            line.clear();
+            if reader.read_until(b'\n', &mut line).unwrap() == 0 {
+                break;
+            }
+            let Some((chrom, pos, end)) = record_span(&line) else {
+                continue;
+            };
+            let chrom = String::from_utf8(chrom.to_vec()).unwrap();
+            if names.last() != Some(&chrom) {
+                names.push(chrom);
+                references.push(HashMap::new());
+            }
+            // The smallest bin holding the whole record
+            let bin = (0..=TBI_DEPTH)
+                .rev()
+                .find_map(|level| {
+                    let shift = TBI_MIN_SHIFT + 3 * (TBI_DEPTH - level);
+                    (pos >> shift == (end - 1) >> shift)
+                        .then(|| bin_level_start(level) + (pos >> shift) as u32)
+                })
+                .unwrap();
+            references
+                .last_mut()
+                .unwrap()
+                .entry(bin)
+                .or_default()
+                .push((start, reader.virtual_offset()));
+        }
+
+        let mut index = b"TBI\x01".to_vec();
+        index.extend((names.len() as u32).to_le_bytes());
+        for field in [2u32, 1, 2, 0, b'#' as u32, 0] {
+            index.extend(field.to_le_bytes());
+        }
+        let names_bytes: Vec<u8> = names
+            .iter()
+            .flat_map(|name| name.bytes().chain([0]))
+            .collect();
+        index.extend((names_bytes.len() as u32).to_le_bytes());
+        index.extend(names_bytes);
+        for bins in &references {
+            index.extend((bins.len() as u32).to_le_bytes());
+            for (bin, chunks) in bins {
+                index.extend(bin.to_le_bytes());
+                index.extend((chunks.len() as u32).to_le_bytes());
+                for (start, end) in chunks {
+                    index.extend(start.to_le_bytes());
+                    index.extend(end.to_le_bytes());
+                }
+            }
+            // An empty linear index
+            index.extend(0u32.to_le_bytes());
+        }
+        (vcf, index)
+    }
+
+    fn query(vcf: &[u8], index: &TabixIndex, regions: &[&str]) -> Vec<String> {
+        let regions: Vec<Region> = regions.iter().map(|r| Region::parse(r).unwrap()).collect();
+        RegionReader::new(BgzfReader::new(Cursor::new(vcf.to_vec())), index, &regions)
+            .lines()
+            .map(|line| line.unwrap())
+            .filter(|line| !line.starts_with('#'))
+            .map(|line| {
+                let columns: Vec<&str> = line.split('\t').collect();
+                format!("{}:{}", columns[0], columns[1])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_reg2bins() {
+        assert_eq!(reg2bins(0, 1, 14, 5), vec![0, 1, 9, 73, 585, 4681]);
+        assert_eq!(
+            reg2bins(16_384, 16_385, 14, 5),
+            vec![0, 1, 9, 73, 585, 4682]
+        );
+        assert_eq!(reg2bins(16_000, 16_500, 14, 5).len(), 7);
+    }
+
+    #[test]
+    fn test_region_queries() {
+        let (vcf, index) = indexed(&[
+            "chr1\t100\t.\tA\tG\t50\tPASS\t.",
+            "chr1\t20000\t.\tA\tG\t50\tPASS\t.",
+            "chr1\t40000\t.\tA\t<DEL>\t50\tPASS\tEND=60000",
+            "chr2\t50\t.\tA\tG\t50\tPASS\t.",
+        ]);
+        let index = TabixIndex::parse(&index).unwrap();
+        assert_eq!(index.names(), ["chr1", "chr2"]);
+
+        assert_eq!(query(&vcf, &index, &["chr1:15000-30000"]), ["chr1:20000"]);
+        assert_eq!(
+            query(&vcf, &index, &["chr1"]),
+            ["chr1:100", "chr1:20000", "chr1:40000"]
+        );
+        // The deletion reaches into the region through END
+        assert_eq!(query(&vcf, &index, &["chr1:50000-55000"]), ["chr1:40000"]);
+        // File order, and records overlapping two regions once
+        assert_eq!(
+            query(&vcf, &index, &["chr2", "chr1:1-200", "chr1:50-150"]),
+            ["chr1:100", "chr2:50"]
+        );
+        assert!(query(&vcf, &index, &["chrX", "chr1:300-400"]).is_empty());
+
+        // The header comes first even when no record matches
+        let reader = RegionReader::new(BgzfReader::new(Cursor::new(vcf)), &index, &[]);
+        let text: String = reader.lines().map(|line| line.unwrap() + "\n").collect();
+        assert_eq!(text, HEADER);
+    }
+
+    #[test]
+    fn test_parse_invalid_index() {
+        assert!(TabixIndex::parse(b"BAI\x01").is_err());
+        assert!(TabixIndex::parse(b"TBI\x01\x01\x00").is_err());
+        assert!(TabixIndex::find("/nonexistent/calls.vcf.gz").is_err());
+    }
+}
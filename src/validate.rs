@@ -0,0 +1,935 @@
+//! Parse-time validation of filters against the header schema.
+//!
+//! Mistakes such as `ANN[0].GeneName` (the subfield is `Gene_Name`) or
+//! `DB > 0` (`DB` is a Flag) do not fail evaluation: they silently match
+//! nothing. [`FilterEngine::check_filter`](crate::FilterEngine::check_filter)
+//! reports them before any rows are processed:
+//!
+//! ```rust
+//! use vcf_filter::FilterEngine;
+//! use vcf_filter::validate::Severity;
+//!
+//! let header = concat!(
+//!     "##INFO=<ID=DB,Number=0,Type=Flag,Description=\"dbSNP membership\">\n",
+//!     "##INFO=<ID=ANN,Number=.,Type=String,Description=\"Functional annotations: 'Allele | Annotation | Gene_Name'\">"
+//! );
+//! let engine = FilterEngine::new(header).unwrap();
+//!
+//! let diagnostics = engine.check_filter(r#"ANN[0].GeneName == "BRCA1" && DB > 0"#);
+//! assert_eq!(diagnostics.len(), 2);
+//! assert!(diagnostics.iter().all(|d| d.severity == Severity::Error));
+//! assert!(diagnostics[0].message.contains("did you mean Gene_Name"));
+//! ```
+//!
+//! Errors are filters that cannot work as written. Warnings are fields the
+//! header does not declare, which may still be FORMAT fields.
+//!
+//! [`FilterEngine::validate_row`](crate::FilterEngine::validate_row) checks
+//! data rows against the same header: value counts against `Number`, values
+//! against `Type`, and FILTER names and CHROM against the declared filters
+//! and contigs. Values are checked as written, before type conversion:
+//!
+//! ```rust
+//! use vcf_filter::FilterEngine;
+//!
+//! let header = concat!(
+//!     "##INFO=<ID=AF,Number=A,Type=Float,Description=\"Allele frequency\">\n",
+//!     "##FILTER=<ID=q10,Description=\"Quality below 10\">\n",
+//!     "##contig=<ID=chr1,length=1000>"
+//! );
+//! let engine = FilterEngine::new(header).unwrap();
+//!
+//! let diagnostics = engine.validate_row("chr1\t100\t.\tA\tG,T\t5\tLowDP\tAF=0.1,x").unwrap();
+//! let messages: Vec<String> = diagnostics.iter().map(ToString::to_string).collect();
+//! assert_eq!(
+//!     messages,
+//!     [
+//!         "error: FILTER: LowDP is not declared in the header",
+//!         "error: INFO/AF: x is not a Float",
+//!     ]
+//! );
+//! ```
+
+use std::fmt;
+
+use crate::chromosome::ContigOrder;
+use crate::consequence;
+use crate::eval::orders_chromosomes;
+use crate::filter::{AccessPart, BinaryOp, Expr};
+use crate::functions::{BUILTIN_FIELDS, BUILTIN_FUNCTIONS, FunctionRegistry};
+use crate::header::{FilterField, FormatMap, InfoMap, InfoNumber, InfoType};
+use crate::intervals::Region;
+use crate::row::Genotype;
+use crate::row_ref::VcfRowRef;
+
+/// How serious a diagnostic is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The filter cannot work as written.
+    Error,
+    /// The filter may not do what was intended.
+    Warning,
+}
+
+/// A problem found in a filter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// How serious the problem is.
+    pub severity: Severity,
+    /// Human-readable description, with a suggestion where one is known.
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn error(message: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            message,
+        }
+    }
+
+    fn warning(message: String) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.severity {
+            Severity::Error => write!(f, "error: {}", self.message),
+            Severity::Warning => write!(f, "warning: {}", self.message),
+        }
+    }
+}
+
+/// The part of a data row a [`RowDiagnostic`] is about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RowLocation {
+    /// The CHROM column.
+    Chrom,
+    /// The POS column.
+    Pos,
+    /// A name in the FILTER column.
+    Filter(String),
+    /// An INFO entry, by key.
+    Info(String),
+    /// A FORMAT key, in one sample (by 0-based index) or the FORMAT column.
+    Format {
+        /// The FORMAT key.
+        key: String,
+        /// The sample, or `None` for the key itself.
+        sample: Option<usize>,
+    },
+}
+
+impl fmt::Display for RowLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RowLocation::Chrom => f.write_str("CHROM"),
+            RowLocation::Pos => f.write_str("POS"),
+            RowLocation::Filter(_) => f.write_str("FILTER"),
+            RowLocation::Info(key) => write!(f, "INFO/{}", key),
+            RowLocation::Format { key, sample: None } => write!(f, "FORMAT/{}", key),
+            RowLocation::Format {
+                key,
+                sample: Some(sample),
+            } => write!(f, "FORMAT/{} in sample {}", key, sample + 1),
+        }
+    }
+}
+
+/// A problem found in a data row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowDiagnostic {
+    /// How serious the problem is.
+    pub severity: Severity,
+    /// Where in the row the problem is.
+    pub location: RowLocation,
+    /// Human-readable description.
+    pub message: String,
+}
+
+impl RowDiagnostic {
+    fn error(location: RowLocation, message: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            location,
+            message,
+        }
+    }
+
+    fn warning(location: RowLocation, message: String) -> Self {
+        Self {
+            severity: Severity::Warning,
+            location,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for RowDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}: {}: {}", severity, self.location, self.message)
+    }
+}
+
+/// What a data row is validated against.
+pub(crate) struct RowSchema<'a> {
+    /// INFO field metadata from the header.
+    pub info_map: &'a InfoMap,
+    /// FORMAT field metadata from the header.
+    pub format_map: &'a FormatMap,
+    /// Filters from the header's `##FILTER` lines.
+    pub filters: &'a [FilterField],
+    /// Contigs from the header's `##contig` lines.
+    pub contigs: &'a ContigOrder,
+}
+
+/// Check a data row against a header, in column order.
+///
+/// Undeclared INFO and FORMAT keys are warnings and their values are not
+/// checked. FILTER names must be declared (or `PASS`), and CHROM must be a
+/// declared contig when the header declares any.
+pub(crate) fn check_row(row: &VcfRowRef, schema: &RowSchema) -> Vec<RowDiagnostic> {
+    let mut out = Vec::new();
+    if !schema.contigs.is_empty() {
+        match schema.contigs.get(row.chrom()) {
+            None => out.push(RowDiagnostic::error(
+                RowLocation::Chrom,
+                format!("contig {} is not declared in the header", row.chrom()),
+            )),
+            Some(contig) => {
+                if let Some(length) = contig.length.filter(|&length| row.pos() > length) {
+                    out.push(RowDiagnostic::error(
+                        RowLocation::Pos,
+                        format!(
+                            "{} is past the end of {} (length {})",
+                            row.pos(),
+                            contig.id,
+                            length
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+
+    for name in row.filters() {
+        if name != "PASS" && !schema.filters.iter().any(|filter| filter.id == name) {
+            out.push(RowDiagnostic::error(
+                RowLocation::Filter(name.to_string()),
+                format!("{} is not declared in the header", name),
+            ));
+        }
+    }
+
+    let alts = row.alt_alleles().count();
+    for (key, value) in row.info_entries() {
+        let location = || RowLocation::Info(key.to_string());
+        let Some(field) = schema.info_map.get(key) else {
+            out.push(RowDiagnostic::warning(
+                location(),
+                "not declared in the header".to_string(),
+            ));
+            continue;
+        };
+        let is_flag = field.number == InfoNumber::Flag || field.field_type == InfoType::Flag;
+        match value {
+            Some(_) if is_flag => out.push(RowDiagnostic::error(
+                location(),
+                "a Flag cannot have a value".to_string(),
+            )),
+            None if !is_flag => out.push(RowDiagnostic::error(
+                location(),
+                format!("a {} field needs a value", field.field_type),
+            )),
+            Some(value) => {
+                if let Some(message) =
+                    check_value(value, &field.number, &field.field_type, alts, None)
+                {
+                    out.push(RowDiagnostic::error(location(), message));
+                }
+            }
+            None => {}
+        }
+    }
+
+    let keys: Vec<&str> = row.format_keys().collect();
+    for key in &keys {
+        if !schema.format_map.contains_key(*key) {
+            out.push(RowDiagnostic::warning(
+                RowLocation::Format {
+                    key: key.to_string(),
+                    sample: None,
+                },
+                "not declared in the header".to_string(),
+            ));
+        }
+    }
+    for sample in 0..row.sample_count() {
+        let ploidy = row
+            .sample_value(sample, "GT")
+            .and_then(Genotype::parse)
+            .map(|genotype| genotype.ploidy());
+        let values = row.sample(sample).unwrap_or_default().split(':');
+        for (key, value) in keys.iter().zip(values) {
+            let Some(field) = schema.format_map.get(*key).filter(|_| *key != "GT") else {
+                continue;
+            };
+            if let Some(message) =
+                check_value(value, &field.number, &field.field_type, alts, ploidy)
+            {
+                out.push(RowDiagnostic::error(
+                    RowLocation::Format {
+                        key: key.to_string(),
+                        sample: Some(sample),
+                    },
+                    message,
+                ));
+            }
+        }
+    }
+    out
+}
+
+/// Check a value's element count against `Number` and its elements against
+/// `Type`; a whole value of `.` and `.` elements are missing and accepted.
+fn check_value(
+    value: &str,
+    number: &InfoNumber,
+    field_type: &InfoType,
+    alts: usize,
+    ploidy: Option<usize>,
+) -> Option<String> {
+    if value == "." || value.is_empty() {
+        return None;
+    }
+    let elements: Vec<&str> = value.split(',').collect();
+    let expected = match number {
+        InfoNumber::Count(n) => vec![*n],
+        InfoNumber::PerAltAllele => vec![alts],
+        InfoNumber::PerAllele => vec![alts + 1],
+        // Unordered genotypes of `ploidy` alleles, diploid or haploid when unknown
+        InfoNumber::PerGenotype => match ploidy {
+            Some(ploidy) => vec![genotype_count(alts + 1, ploidy)],
+            None => vec![genotype_count(alts + 1, 2), alts + 1],
+        },
+        InfoNumber::Variable | InfoNumber::Flag => Vec::new(),
+    };
+    if !expected.is_empty() && !expected.contains(&elements.len()) {
+        return Some(format!(
+            "expected {} value{} for Number={}, found {}",
+            expected[0],
+            if expected[0] == 1 { "" } else { "s" },
+            number,
+            elements.len()
+        ));
+    }
+    elements
+        .into_iter()
+        .filter(|element| *element != ".")
+        .find(|element| !conforms(element, field_type))
+        .map(|element| format!("{} is not {} {}", element, article(field_type), field_type))
+}
+
+/// Whether a single value element is of the declared type.
+fn conforms(element: &str, field_type: &InfoType) -> bool {
+    match field_type {
+        InfoType::Integer => element.parse::<i64>().is_ok(),
+        InfoType::Float => element.parse::<f64>().is_ok(),
+        InfoType::Character => element.chars().count() == 1,
+        InfoType::String | InfoType::Flag => true,
+    }
+}
+
+/// The indefinite article for a type name.
+fn article(field_type: &InfoType) -> &'static str {
+    match field_type {
+        InfoType::Integer => "an",
+        _ => "a",
+    }
+}
+
+/// The number of unordered genotypes of `ploidy` alleles drawn from `alleles`.
+fn genotype_count(alleles: usize, ploidy: usize) -> usize {
+    // C(alleles + ploidy - 1, ploidy), built up one factor at a time
+    (1..=ploidy).fold(1, |count, k| count * (alleles + k - 1) / k)
+}
+
+/// What a filter is validated against.
+pub(crate) struct Schema<'a> {
+    /// INFO field metadata from the header.
+    pub info_map: &'a InfoMap,
+    /// Fields added to rows by the engine (interval columns, `INHERITANCE`).
+    pub virtual_fields: &'a [String],
+    /// User-registered functions.
+    pub functions: &'a FunctionRegistry,
+    /// Sample names from the `#CHROM` header line.
+    pub sample_names: &'a [String],
+    /// Contigs from the header's `##contig` lines.
+    pub contigs: &'a ContigOrder,
+}
+
+/// Names in scope at a point of the expression.
+#[derive(Clone, Copy)]
+struct Scope<'a> {
+    /// Names bound by enclosing `let`s.
+    bindings: &'a [String],
+    /// Subfields of the annotation an enclosing predicate iterates over.
+    subfields: Option<&'a [String]>,
+}
+
+/// Check an expression against a schema, in expression order.
+pub(crate) fn check_expr(expr: &Expr, schema: &Schema) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let scope = Scope {
+        bindings: &[],
+        subfields: None,
+    };
+    check(expr, schema, scope, &mut diagnostics);
+    diagnostics
+}
+
+fn check(expr: &Expr, schema: &Schema, scope: Scope, out: &mut Vec<Diagnostic>) {
+    match expr {
+        Expr::Var(parts) | Expr::Exists(parts) => check_path(parts, schema, scope, out),
+        Expr::Binary(left, op, right) => {
+            check(left, schema, scope, out);
+            check(right, schema, scope, out);
+            check_operand_types(left, op, right, schema, scope, out);
+            if *op == BinaryOp::SeverityAtLeast {
+                check_consequence_term(right, out);
+            }
+            if orders_chromosomes(left, op, right) {
+                check_chromosome_name(left, schema, out);
+                check_chromosome_name(right, schema, out);
+            }
+        }
+        Expr::Call(name, args) => {
+            if !BUILTIN_FUNCTIONS.contains(&name.as_str()) && schema.functions.get(name).is_none() {
+                let known = BUILTIN_FUNCTIONS
+                    .iter()
+                    .copied()
+                    .chain(schema.functions.names());
+                out.push(Diagnostic::error(format!(
+                    "unknown function {}(){}",
+                    name,
+                    suggestion(name, known)
+                )));
+            }
+            if name == "region" {
+                args.iter().for_each(|arg| check_region(arg, schema, out));
+            }
+            args.iter().for_each(|arg| check(arg, schema, scope, out));
+        }
+        Expr::Let(name, value, body) => {
+            check(value, schema, scope, out);
+            let mut bindings = scope.bindings.to_vec();
+            bindings.push(name.clone());
+            let inner = Scope {
+                bindings: &bindings,
+                ..scope
+            };
+            check(body, schema, inner, out);
+        }
+        Expr::Preset(name) => out.push(Diagnostic::error(format!("unknown preset @{}", name))),
+        _ => expr
+            .children()
+            .into_iter()
+            .for_each(|child| check(child, schema, scope, out)),
+    }
+}
+
+/// Check the field and subfield names of an access path.
+fn check_path(parts: &[AccessPart], schema: &Schema, scope: Scope, out: &mut Vec<Diagnostic>) {
+    let Some(AccessPart::Field(first)) = parts.first() else {
+        return;
+    };
+
+    for part in parts {
+        if let AccessPart::Sample(sample) = part
+            && !schema.sample_names.contains(sample)
+        {
+            out.push(Diagnostic::error(format!(
+                "unknown sample \"{}\"{}",
+                sample,
+                suggestion(sample, schema.sample_names.iter().map(String::as_str))
+            )));
+        }
+    }
+
+    let in_scope = parts.len() == 1
+        && (scope.bindings.contains(first)
+            || scope
+                .subfields
+                .is_some_and(|subfields| subfields.contains(first)));
+    let (namespace, name, rest) = match parts {
+        _ if in_scope => return,
+        [_, AccessPart::Field(name), rest @ ..] if first == "INFO" || first == "FORMAT" => {
+            (Some(first.as_str()), name, rest)
+        }
+        // `INFO` alone, an INFO key glob, or a per-sample FORMAT field
+        _ if first == "INFO" || first == "FORMAT" || first == "SAMPLE" => return,
+        [_, rest @ ..] => (None, first, rest),
+        [] => return,
+    };
+    // FORMAT fields are not declared in the INFO header
+    if namespace == Some("FORMAT")
+        || (namespace.is_none() && BUILTIN_FIELDS.contains(&name.as_str()))
+        || schema.virtual_fields.contains(name)
+    {
+        return;
+    }
+
+    let Some(field) = schema.info_map.get(name) else {
+        let mut known: Vec<&str> = schema.info_map.keys().map(String::as_str).collect();
+        known.extend(
+            scope
+                .subfields
+                .unwrap_or_default()
+                .iter()
+                .map(String::as_str),
+        );
+        out.push(Diagnostic::warning(format!(
+            "field {} is not declared in the header{}",
+            name,
+            suggestion(name, known)
+        )));
+        return;
+    };
+
+    if rest.contains(&AccessPart::Alt)
+        && !matches!(
+            field.number,
+            InfoNumber::PerAltAllele | InfoNumber::PerAllele | InfoNumber::Variable
+        )
+    {
+        out.push(Diagnostic::error(format!(
+            "{} is not declared Number=A or Number=R, so {}[alt] has no per-allele values",
+            name, name
+        )));
+    }
+
+    let subfield = rest.iter().rev().find_map(|part| match part {
+        AccessPart::Field(sub) => Some(sub),
+        _ => None,
+    });
+    match (&field.subfields, subfield) {
+        (Some(subfields), Some(sub)) if !subfields.contains(sub) => {
+            out.push(Diagnostic::error(format!(
+                "{} has no subfield {}{}",
+                name,
+                sub,
+                suggestion(sub, subfields.iter().map(String::as_str))
+            )));
+        }
+        (None, Some(sub)) => out.push(Diagnostic::error(format!(
+            "{} has no subfields, so .{} is always missing",
+            name, sub
+        ))),
+        _ => {}
+    }
+
+    // Bare names inside predicates refer to this field's subfields
+    for part in rest {
+        if let AccessPart::Predicate(pred) = part {
+            let inner = Scope {
+                subfields: field.subfields.as_deref(),
+                ..scope
+            };
+            check(pred, schema, inner, out);
+        }
+    }
+}
+
+/// Flag comparisons whose operand types can never match.
+fn check_operand_types(
+    left: &Expr,
+    op: &BinaryOp,
+    right: &Expr,
+    schema: &Schema,
+    scope: Scope,
+    out: &mut Vec<Diagnostic>,
+) {
+    let pairs = [(left, right), (right, left)];
+    for (field_side, other) in pairs {
+        let Some((name, field_type)) = plain_info_field(field_side, schema, scope) else {
+            continue;
+        };
+        match (field_type, op, other) {
+            (InfoType::Flag, BinaryOp::And | BinaryOp::Or, _) => {}
+            (InfoType::Flag, _, Expr::Number(_)) => out.push(Diagnostic::error(format!(
+                "{} is a Flag and never equals or orders against a number; use `{}` or `!{}`",
+                name, name, name
+            ))),
+            (InfoType::Integer | InfoType::Float, BinaryOp::Contains, _)
+                if std::ptr::eq(field_side, left) =>
+            {
+                out.push(Diagnostic::error(format!(
+                    "{} is numeric; `contains` only matches strings",
+                    name
+                )))
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Flag `severity_at_least` thresholds that are not ranked consequence terms.
+fn check_consequence_term(threshold: &Expr, out: &mut Vec<Diagnostic>) {
+    if let Expr::String(term) = threshold
+        && !consequence::is_known(term)
+    {
+        out.push(Diagnostic::error(format!(
+            "unknown consequence term \"{}\"{}",
+            term,
+            suggestion(term, consequence::SEVERITY_ORDER.iter().copied())
+        )));
+    }
+}
+
+/// Warn about chromosome names that have no rank in `CHROM` orderings.
+fn check_chromosome_name(operand: &Expr, schema: &Schema, out: &mut Vec<Diagnostic>) {
+    let Expr::String(name) = operand else {
+        return;
+    };
+    if schema.contigs.rank(name).is_some() {
+        return;
+    }
+    if schema.contigs.is_empty() {
+        out.push(Diagnostic::warning(format!(
+            "\"{}\" is not a numbered or sex/mitochondrial chromosome; ordering comparisons with it are always false",
+            name
+        )));
+    } else {
+        out.push(Diagnostic::warning(format!(
+            "\"{}\" is not a ##contig in the header; ordering comparisons with it are always false",
+            name
+        )));
+    }
+}
+
+/// Check literal `region("chr:start-end")` arguments against the header contigs.
+fn check_region(arg: &Expr, schema: &Schema, out: &mut Vec<Diagnostic>) {
+    let Expr::String(text) = arg else {
+        return;
+    };
+    let region = match Region::parse(text) {
+        Ok(region) => region,
+        Err(e) => {
+            out.push(Diagnostic::error(e.to_string()));
+            return;
+        }
+    };
+    if schema.contigs.is_empty() {
+        return;
+    }
+    match schema.contigs.get(&region.chrom) {
+        None => out.push(Diagnostic::warning(format!(
+            "region \"{}\" is on {}, which is not a ##contig in the header{}",
+            text,
+            region.chrom,
+            suggestion(
+                &region.chrom,
+                schema.contigs.contigs().iter().map(|c| c.id.as_str())
+            )
+        ))),
+        Some(contig) => {
+            if let Some(length) = contig.length
+                && region.start >= length
+            {
+                out.push(Diagnostic::warning(format!(
+                    "region \"{}\" starts past the end of {} (length {})",
+                    text, region.chrom, length
+                )));
+            }
+        }
+    }
+}
+
+/// The INFO field (and its type) a plain variable refers to, if any.
+fn plain_info_field<'a>(
+    expr: &'a Expr,
+    schema: &'a Schema,
+    scope: Scope,
+) -> Option<(&'a str, &'a InfoType)> {
+    let Expr::Var(parts) = expr else {
+        return None;
+    };
+    let name = match parts.as_slice() {
+        [AccessPart::Field(ns), AccessPart::Field(name)] if ns == "INFO" => name,
+        [AccessPart::Field(name)]
+            if !scope.bindings.contains(name)
+                && !scope.subfields.is_some_and(|s| s.contains(name)) =>
+        {
+            name
+        }
+        _ => return None,
+    };
+    if schema.virtual_fields.contains(name) {
+        return None;
+    }
+    schema
+        .info_map
+        .get(name)
+        .map(|field| (name.as_str(), &field.field_type))
+}
+
+/// Format a "did you mean" hint for the closest known name, if any is close.
+fn suggestion<'a>(name: &str, known: impl IntoIterator<Item = &'a str>) -> String {
+    let lower = name.to_lowercase();
+    known
+        .into_iter()
+        .map(|candidate| (edit_distance(&lower, &candidate.to_lowercase()), candidate))
+        .filter(|(distance, _)| *distance <= (name.len() / 3).max(2))
+        .min()
+        .map(|(_, candidate)| format!("; did you mean {}?", candidate))
+        .unwrap_or_default()
+}
+
+/// Levenshtein distance between two strings, by characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::parse_filter;
+    use crate::header::{parse_contig_header, parse_header};
+
+    const HEADER: &str = r#"##INFO=<ID=DP,Number=1,Type=Integer,Description="Total depth">
+##INFO=<ID=DB,Number=0,Type=Flag,Description="dbSNP membership">
+##INFO=<ID=CLNSIG,Number=.,Type=String,Description="Clinical significance">
+##INFO=<ID=AF,Number=A,Type=Float,Description="Allele frequency">
+##INFO=<ID=AD,Number=R,Type=Integer,Description="Allelic depths">
+##INFO=<ID=ANN,Number=.,Type=String,Description="Functional annotations: 'Allele | Annotation | Annotation_Impact | Gene_Name'">"#;
+
+    fn check_filter(filter: &str) -> Vec<Diagnostic> {
+        check_filter_with_contigs(filter, &ContigOrder::default())
+    }
+
+    fn check_filter_with_contigs(filter: &str, contigs: &ContigOrder) -> Vec<Diagnostic> {
+        let info_map = parse_header(HEADER).unwrap();
+        let functions = FunctionRegistry::new();
+        let schema = Schema {
+            info_map: &info_map,
+            virtual_fields: &["ROH_REGION".to_string()],
+            functions: &functions,
+            sample_names: &["tumor".to_string(), "normal".to_string()],
+            contigs,
+        };
+        check_expr(&parse_filter(filter).unwrap(), &schema)
+    }
+
+    #[test]
+    fn test_valid_filters_have_no_diagnostics() {
+        for filter in [
+            r#"ANN[*].Gene_Name == "BRCA1" && DP > 10 && QUAL > 30"#,
+            r#"ANN[?(Annotation_Impact == "HIGH")].Gene_Name == "X""#,
+            "DB && !exists(INFO.DP)",
+            "let ab = DP / 2 in ab > 1",
+            r#"ROH_REGION == "roh_1" && FORMAT.GQ > 20"#,
+            "max(INFO.gnomAD_*) > 0.01",
+            r#"SAMPLE["tumor"].DP > 50 && FORMAT.DP["normal"] > 20"#,
+            r#"AF[alt] < 0.01 && AD[alt] > 5 && ALT[alt] == "T""#,
+        ] {
+            assert_eq!(check_filter(filter), vec![], "{}", filter);
+        }
+    }
+
+    #[test]
+    fn test_unknown_subfield_suggests_closest() {
+        let diagnostics = check_filter(r#"ANN[0].GeneName == "BRCA1""#);
+        assert_eq!(
+            diagnostics,
+            vec![Diagnostic::error(
+                "ANN has no subfield GeneName; did you mean Gene_Name?".to_string()
+            )]
+        );
+
+        let diagnostics = check_filter(r#"ANN[?(Impact == "HIGH")].Gene_Name == "X""#);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert!(diagnostics[0].message.contains("Impact"));
+
+        assert_eq!(check_filter("DP[0].Value > 1")[0].severity, Severity::Error);
+    }
+
+    #[test]
+    fn test_unknown_fields_and_functions() {
+        let diagnostics = check_filter("CLNSGI == \"Benign\" && mystery(DP)");
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert!(diagnostics[0].message.contains("did you mean CLNSIG?"));
+        assert_eq!(diagnostics[1].severity, Severity::Error);
+        assert!(diagnostics[1].message.contains("mystery()"));
+    }
+
+    #[test]
+    fn test_unknown_sample() {
+        assert_eq!(
+            check_filter(r#"SAMPLE["tumour"].GT == "0/1""#),
+            vec![Diagnostic::error(
+                "unknown sample \"tumour\"; did you mean tumor?".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_alt_access_needs_allele_field() {
+        assert_eq!(
+            check_filter("DP[alt] > 10"),
+            vec![Diagnostic::error(
+                "DP is not declared Number=A or Number=R, so DP[alt] has no per-allele values"
+                    .to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_unknown_consequence_term() {
+        assert!(
+            check_filter(r#"ANN[*].Annotation severity_at_least "missense_variant""#).is_empty()
+        );
+        assert_eq!(
+            check_filter(r#"ANN[*].Annotation severity_at_least "missense_varaint""#),
+            vec![Diagnostic::error(
+                "unknown consequence term \"missense_varaint\"; did you mean missense_variant?"
+                    .to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_unranked_chromosome() {
+        assert!(check_filter(r#"CHROM <= "chr9" && CHROM == "chrUn""#).is_empty());
+        let diagnostics = check_filter(r#"CHROM < "chrUn_gl000220""#);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn test_contigs_and_regions() {
+        let contigs = ContigOrder::new(
+            parse_contig_header("##contig=<ID=chr1,length=1000>\n##contig=<ID=chrUn_gl000220>")
+                .unwrap(),
+        );
+        let check = |filter| check_filter_with_contigs(filter, &contigs);
+
+        assert!(check(r#"CHROM < "chrUn_gl000220" && region("chr1:1-999")"#).is_empty());
+        assert_eq!(check(r#"CHROM < "chr2""#)[0].severity, Severity::Warning);
+        assert_eq!(
+            check(r#"region("chr1:2000-3000", "chr01")"#),
+            vec![
+                Diagnostic::warning(
+                    "region \"chr1:2000-3000\" starts past the end of chr1 (length 1000)"
+                        .to_string()
+                ),
+                Diagnostic::warning(
+                    "region \"chr01\" is on chr01, which is not a ##contig in the header; did you mean chr1?"
+                        .to_string()
+                ),
+            ]
+        );
+        assert_eq!(check(r#"region("chr1:9-1")"#)[0].severity, Severity::Error);
+        assert!(check_filter(r#"region("chr01")"#).is_empty());
+    }
+
+    #[test]
+    fn test_operand_type_mismatches() {
+        assert_eq!(check_filter("DB > 0")[0].severity, Severity::Error);
+        assert_eq!(check_filter("1 == INFO.DB")[0].severity, Severity::Error);
+        assert_eq!(
+            check_filter(r#"DP contains "3""#)[0].severity,
+            Severity::Error
+        );
+        assert!(check_filter("DB == true").is_empty());
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("genename", "gene_name"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn test_validate_row() {
+        let header = concat!(
+            "##INFO=<ID=AF,Number=A,Type=Float,Description=\"AF\">\n",
+            "##INFO=<ID=AD,Number=R,Type=Integer,Description=\"AD\">\n",
+            "##INFO=<ID=DB,Number=0,Type=Flag,Description=\"dbSNP\">\n",
+            "##INFO=<ID=STR,Number=1,Type=Character,Description=\"Strand\">\n",
+            "##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">\n",
+            "##FORMAT=<ID=PL,Number=G,Type=Integer,Description=\"Likelihoods\">\n",
+            "##FILTER=<ID=q10,Description=\"Low quality\">\n",
+            "##contig=<ID=chr1,length=1000>",
+        );
+        let engine = crate::FilterEngine::new(header).unwrap();
+        let valid = "chr1\t100\t.\tA\tG,T\t50\tq10\tAF=0.1,.;AD=5,3,2;DB;STR=+\tGT:PL\t0/1:0,1,2,3,4,5\t1:0,7,9\t.:.";
+        assert_eq!(engine.validate_row(valid).unwrap(), []);
+
+        let invalid = "chr1\t1200\t.\tA\tG\t50\tPASS;LowDP\tAF=0.1,0.2;AD=5,x;DB=1;STR=ab;XX=1\tGT:PL:ZZ\t0/0/1:0,1,2\t.:.:.";
+        let diagnostics = engine.validate_row(invalid).unwrap();
+        let locations: Vec<String> = diagnostics.iter().map(|d| d.location.to_string()).collect();
+        assert_eq!(
+            locations,
+            [
+                "POS",
+                "FILTER",
+                "INFO/AF",
+                "INFO/AD",
+                "INFO/DB",
+                "INFO/STR",
+                "INFO/XX",
+                "FORMAT/ZZ",
+                "FORMAT/PL in sample 1",
+            ]
+        );
+        assert_eq!(diagnostics[1].location, RowLocation::Filter("LowDP".into()));
+        assert_eq!(diagnostics[3].message, "x is not an Integer");
+        assert_eq!(
+            diagnostics[8].message,
+            "expected 4 values for Number=G, found 3"
+        );
+        let warnings: Vec<_> = diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Warning)
+            .map(|d| &d.location)
+            .collect();
+        assert_eq!(
+            warnings,
+            [
+                &RowLocation::Info("XX".into()),
+                &RowLocation::Format {
+                    key: "ZZ".into(),
+                    sample: None
+                }
+            ]
+        );
+
+        let undeclared = engine.validate_row("chrX\t5\t.\tA\tG\t.\t.\t.").unwrap();
+        assert_eq!(
+            undeclared[0].to_string(),
+            "error: CHROM: contig chrX is not declared in the header"
+        );
+        assert!(engine.validate_row("chr1\t5\t.").is_err());
+    }
+}
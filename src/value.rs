@@ -59,6 +59,22 @@ impl Value {
             Value::Missing => "missing",
         }
     }
+
+    /// Formats the value as VCF-style text: strings unquoted, missing as
+    /// `.`, and array elements separated by commas.
+    pub fn to_vcf_string(&self) -> String {
+        match self {
+            Value::String(s) => s.clone(),
+            Value::Number(n) => n.to_string(),
+            Value::Bool(b) => b.to_string(),
+            Value::Array(arr) => arr
+                .iter()
+                .map(Value::to_vcf_string)
+                .collect::<Vec<_>>()
+                .join(","),
+            Value::Missing => ".".to_string(),
+        }
+    }
 }
 
 impl fmt::Display for Value {